@@ -0,0 +1,176 @@
+//!
+//! Annotations (comments) of a cell.
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use spreadsheet_ods::annotation::Annotation;
+//! use spreadsheet_ods::{Length, Sheet};
+//!
+//! let mut sheet = Sheet::new("1");
+//! let mut note = Annotation::new("Please check this value.");
+//! note.set_author("reviewer");
+//! note.set_date(NaiveDate::from_ymd_opt(2022, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap());
+//! note.set_display(true);
+//! note.set_size(Length::Cm(4.0), Length::Cm(2.0));
+//! sheet.set_annotation(0, 0, note);
+//! ```
+//!
+
+use chrono::NaiveDateTime;
+
+use crate::attrmap2::AttrMap2;
+use crate::style::units::Length;
+use crate::text::{TextP, TextTag};
+
+/// An annotation attached to a cell.
+///
+/// The text of the annotation is a list of text:p paragraphs.
+/// Any other attributes like the graphic style are kept in the attrmap.
+#[derive(Debug, Clone, Default)]
+pub struct Annotation {
+    name: String,
+    display: bool,
+    author: Option<String>,
+    date: Option<NaiveDateTime>,
+    text: Vec<TextTag>,
+    attr: AttrMap2,
+}
+
+impl Annotation {
+    /// Annotation with one paragraph of text.
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        let mut annotation = Self::new_empty();
+        annotation.push_text_str(text);
+        annotation
+    }
+
+    /// Empty annotation.
+    pub fn new_empty() -> Self {
+        Self {
+            name: Default::default(),
+            display: false,
+            author: None,
+            date: None,
+            text: Vec::new(),
+            attr: Default::default(),
+        }
+    }
+
+    /// Name of the annotation.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name of the annotation.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    /// Show the annotation permanently, not only when hovering over the cell.
+    pub fn display(&self) -> bool {
+        self.display
+    }
+
+    /// Show the annotation permanently, not only when hovering over the cell.
+    pub fn set_display(&mut self, display: bool) {
+        self.display = display;
+    }
+
+    /// Author.
+    pub fn author(&self) -> Option<&String> {
+        self.author.as_ref()
+    }
+
+    /// Author.
+    pub fn set_author<S: Into<String>>(&mut self, author: S) {
+        self.author = Some(author.into());
+    }
+
+    /// Removes the author.
+    pub fn clear_author(&mut self) {
+        self.author = None;
+    }
+
+    /// Creation date.
+    pub fn date(&self) -> Option<NaiveDateTime> {
+        self.date
+    }
+
+    /// Creation date.
+    pub fn set_date(&mut self, date: NaiveDateTime) {
+        self.date = Some(date);
+    }
+
+    /// Removes the date.
+    pub fn clear_date(&mut self) {
+        self.date = None;
+    }
+
+    /// Text paragraphs.
+    pub fn text(&self) -> &Vec<TextTag> {
+        &self.text
+    }
+
+    /// Text paragraphs.
+    pub fn text_mut(&mut self) -> &mut Vec<TextTag> {
+        &mut self.text
+    }
+
+    /// Appends a paragraph.
+    pub fn push_text(&mut self, text: TextTag) {
+        self.text.push(text);
+    }
+
+    /// Appends a paragraph with plain text.
+    pub fn push_text_str<S: Into<String>>(&mut self, text: S) {
+        self.text.push(TextP::new().text(text).into_xmltag());
+    }
+
+    /// The text without markup. Paragraphs are separated by \n.
+    pub fn text_str(&self) -> String {
+        let mut buf = String::new();
+        for (i, t) in self.text.iter().enumerate() {
+            if i > 0 {
+                buf.push('\n');
+            }
+            t.extract_text(&mut buf);
+        }
+        buf
+    }
+
+    /// Position of the annotation box relative to the sheet.
+    pub fn set_position(&mut self, x: Length, y: Length) {
+        self.attr.set_attr("svg:x", x.to_string());
+        self.attr.set_attr("svg:y", y.to_string());
+    }
+
+    /// Position of the annotation box relative to the sheet.
+    pub fn position(&self) -> Option<(Length, Length)> {
+        Some((self.length("svg:x")?, self.length("svg:y")?))
+    }
+
+    /// Size of the annotation box.
+    pub fn set_size(&mut self, width: Length, height: Length) {
+        self.attr.set_attr("svg:width", width.to_string());
+        self.attr.set_attr("svg:height", height.to_string());
+    }
+
+    /// Size of the annotation box.
+    pub fn size(&self) -> Option<(Length, Length)> {
+        Some((self.length("svg:width")?, self.length("svg:height")?))
+    }
+
+    fn length(&self, name: &str) -> Option<Length> {
+        self.attr.attr(name).and_then(|v| v.parse().ok())
+    }
+
+    /// Other attributes.
+    pub fn attrmap(&self) -> &AttrMap2 {
+        &self.attr
+    }
+
+    /// Other attributes.
+    pub fn attrmap_mut(&mut self) -> &mut AttrMap2 {
+        &mut self.attr
+    }
+}
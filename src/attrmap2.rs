@@ -4,13 +4,13 @@
 //! related families of attributes.
 //!
 
-use std::collections::{hash_map, HashMap};
+use std::collections::{btree_map, BTreeMap};
 use string_cache::DefaultAtom;
 
-/// Container type for attributes.
+/// Container type for attributes. They are kept sorted by name.
 #[derive(Default, Clone, Debug)]
 pub struct AttrMap2 {
-    map: Option<HashMap<DefaultAtom, String>>,
+    map: Option<BTreeMap<DefaultAtom, String>>,
 }
 
 impl AttrMap2 {
@@ -27,7 +27,7 @@ impl AttrMap2 {
 
     /// Add from Slice
     pub fn add_all(&mut self, data: &[(&str, String)]) {
-        let attr = self.map.get_or_insert_with(HashMap::new);
+        let attr = self.map.get_or_insert_with(BTreeMap::new);
         for (name, value) in data {
             attr.insert(DefaultAtom::from(*name), value.to_string());
         }
@@ -36,7 +36,7 @@ impl AttrMap2 {
     /// Adds an attribute.
     pub fn set_attr(&mut self, name: &str, value: String) {
         self.map
-            .get_or_insert_with(HashMap::new)
+            .get_or_insert_with(BTreeMap::new)
             .insert(DefaultAtom::from(name), value);
     }
 
@@ -82,7 +82,7 @@ impl AttrMap2 {
 /// Iterator for an AttrMap.
 #[derive(Debug)]
 pub struct AttrMapIter<'a> {
-    it: Option<hash_map::Iter<'a, DefaultAtom, String>>,
+    it: Option<btree_map::Iter<'a, DefaultAtom, String>>,
 }
 
 impl<'a> From<&'a AttrMap2> for AttrMapIter<'a> {
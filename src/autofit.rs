@@ -0,0 +1,296 @@
+//!
+//! Estimates the size of the cell content for the optimal column width
+//! and row height. There is no font rendering, the character widths come
+//! from a table of metrics for common fonts.
+//!
+
+use crate::style::units::Length;
+use crate::{CellStyle, WorkBook};
+use std::str::FromStr;
+
+// LibreOffice default font size.
+const DEFAULT_SIZE: f64 = 10.0;
+// Inner margins of the cell and a little extra, in pt.
+const COL_PADDING: f64 = 5.0;
+// Line height relative to the font size.
+const LINE_HEIGHT: f64 = 1.28;
+// LibreOffice default column width in pt.
+const DEFAULT_COL_WIDTH: f64 = 64.0;
+
+/// Average character widths of a font in 1/1000 em.
+struct FontMetrics {
+    names: &'static [&'static str],
+    space: u16,
+    digit: u16,
+    narrow: u16,
+    lower: u16,
+    upper: u16,
+    wide: u16,
+    other: u16,
+    // Bold is wider by this factor.
+    bold: f64,
+}
+
+// The first one is the fallback.
+const FONTS: [FontMetrics; 6] = [
+    FontMetrics {
+        names: &["liberation sans", "arial", "helvetica"],
+        space: 278,
+        digit: 556,
+        narrow: 250,
+        lower: 520,
+        upper: 680,
+        wide: 850,
+        other: 580,
+        bold: 1.07,
+    },
+    FontMetrics {
+        names: &["liberation serif", "times new roman", "times"],
+        space: 250,
+        digit: 500,
+        narrow: 278,
+        lower: 460,
+        upper: 680,
+        wide: 800,
+        other: 520,
+        bold: 1.05,
+    },
+    FontMetrics {
+        names: &[
+            "liberation mono",
+            "courier new",
+            "courier",
+            "dejavu sans mono",
+            "consolas",
+        ],
+        space: 600,
+        digit: 600,
+        narrow: 600,
+        lower: 600,
+        upper: 600,
+        wide: 600,
+        other: 600,
+        bold: 1.0,
+    },
+    FontMetrics {
+        names: &["dejavu sans", "verdana"],
+        space: 318,
+        digit: 636,
+        narrow: 300,
+        lower: 600,
+        upper: 720,
+        wide: 950,
+        other: 640,
+        bold: 1.1,
+    },
+    FontMetrics {
+        names: &["carlito", "calibri"],
+        space: 226,
+        digit: 507,
+        narrow: 240,
+        lower: 470,
+        upper: 590,
+        wide: 780,
+        other: 500,
+        bold: 1.04,
+    },
+    FontMetrics {
+        names: &["caladea", "cambria", "georgia"],
+        space: 220,
+        digit: 556,
+        narrow: 280,
+        lower: 500,
+        upper: 680,
+        wide: 850,
+        other: 560,
+        bold: 1.06,
+    },
+];
+
+impl FontMetrics {
+    // Longest matching name wins, "DejaVu Sans Mono" is no "DejaVu Sans".
+    fn find(name: &str) -> &'static FontMetrics {
+        let name = name.to_lowercase();
+        FONTS
+            .iter()
+            .flat_map(|f| f.names.iter().map(move |n| (f, n)))
+            .filter(|(_, n)| name.starts_with(*n))
+            .max_by_key(|(_, n)| n.len())
+            .map(|(f, _)| f)
+            .unwrap_or(&FONTS[0])
+    }
+
+    fn char_width(&self, c: char) -> u16 {
+        match c {
+            ' ' => self.space,
+            '0'..='9' => self.digit,
+            'i' | 'l' | 'j' | 'I' | 'f' | 't' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' | '('
+            | ')' | '[' | ']' | '{' | '}' | '-' | '`' | '"' => self.narrow,
+            'm' | 'w' | 'M' | 'W' | '@' | '%' => self.wide,
+            'a'..='z' => self.lower,
+            'A'..='Z' => self.upper,
+            // CJK and others are full width.
+            c if c as u32 >= 0x1100 && c.is_alphabetic() => 1000,
+            _ => self.other,
+        }
+    }
+}
+
+/// Font of a cell as far as it matters for the size.
+pub(crate) struct CellFont {
+    metrics: &'static FontMetrics,
+    size: f64,
+    bold: bool,
+    wrap: bool,
+}
+
+impl Default for CellFont {
+    fn default() -> Self {
+        Self {
+            metrics: &FONTS[0],
+            size: DEFAULT_SIZE,
+            bold: false,
+            wrap: false,
+        }
+    }
+}
+
+impl CellFont {
+    /// Font of the cell style, follows the parent styles. Styles without
+    /// a font use the "Default" style.
+    pub(crate) fn from_style(book: &WorkBook, style: Option<&String>) -> Self {
+        let mut chain = Vec::new();
+        let mut next = style.cloned();
+        while let Some(name) = next {
+            // guard against cycles.
+            if chain.len() > 16 || chain.iter().any(|v: &&CellStyle| v.name() == name) {
+                break;
+            }
+            match book.cellstyle(&name) {
+                Some(style) => {
+                    next = style.parent_style().cloned();
+                    chain.push(style);
+                }
+                None => break,
+            }
+        }
+        if let Some(style) = book.cellstyle("Default") {
+            chain.push(style);
+        }
+
+        let mut font = CellFont::default();
+        if let Some(name) = chain
+            .iter()
+            .find_map(|v| v.textstyle().attr("style:font-name"))
+        {
+            font.metrics = FontMetrics::find(name);
+        }
+        if let Some(weight) = chain
+            .iter()
+            .find_map(|v| v.textstyle().attr("fo:font-weight"))
+        {
+            font.bold = match weight.as_str() {
+                "bold" => true,
+                v => v.parse::<u32>().map(|v| v >= 600).unwrap_or(false),
+            };
+        }
+        if let Some(wrap) = chain
+            .iter()
+            .find_map(|v| v.cellstyle().attr("fo:wrap-option"))
+        {
+            font.wrap = wrap == "wrap";
+        }
+        // percentages are relative to the parent.
+        let mut factor = 1.0;
+        for style in &chain {
+            if let Some(size) = style.textstyle().attr("fo:font-size") {
+                if let Some(p) = size.strip_suffix('%') {
+                    factor *= p.parse::<f64>().unwrap_or(100.0) / 100.0;
+                } else if let Some(size) = Length::from_str(size).ok().and_then(length_pt) {
+                    font.size = size;
+                    break;
+                }
+            }
+        }
+        font.size *= factor;
+
+        font
+    }
+
+    fn char_width(&self, c: char) -> f64 {
+        let w = self.metrics.char_width(c) as f64 * self.size / 1000.0;
+        if self.bold {
+            w * self.metrics.bold
+        } else {
+            w
+        }
+    }
+
+    fn text_width(&self, text: &str) -> f64 {
+        text.chars().map(|c| self.char_width(c)).sum()
+    }
+
+    /// Width of the content in pt. Wrapped text only needs the longest
+    /// word.
+    pub(crate) fn width(&self, text: &str) -> f64 {
+        let width = if self.wrap {
+            text.split_whitespace()
+                .map(|v| self.text_width(v))
+                .fold(0.0, f64::max)
+        } else {
+            text.lines().map(|v| self.text_width(v)).fold(0.0, f64::max)
+        };
+        width + COL_PADDING
+    }
+
+    /// Height of the content in pt. Wrapped text is broken into lines
+    /// that fit the column width.
+    pub(crate) fn height(&self, text: &str, col_width: Length) -> f64 {
+        let lines = if self.wrap {
+            let width = length_pt(col_width).unwrap_or(DEFAULT_COL_WIDTH) - COL_PADDING;
+            text.lines()
+                .map(|v| self.wrapped_lines(v, width))
+                .sum::<usize>()
+                .max(1)
+        } else {
+            text.lines().count().max(1)
+        };
+        lines as f64 * self.size * LINE_HEIGHT
+    }
+
+    fn wrapped_lines(&self, text: &str, width: f64) -> usize {
+        let space = self.char_width(' ');
+        let mut lines = 1;
+        let mut line = 0.0;
+        for word in text.split_whitespace() {
+            let w = self.text_width(word);
+            if line > 0.0 && line + space + w > width {
+                lines += 1;
+                line = w;
+            } else if line > 0.0 {
+                line += space + w;
+            } else {
+                line = w;
+            }
+        }
+        lines
+    }
+}
+
+/// Length in pt. None for Length::Default.
+pub(crate) fn length_pt(length: Length) -> Option<f64> {
+    match length {
+        Length::Default => None,
+        Length::Cm(v) => Some(v * 72.0 / 2.54),
+        Length::Mm(v) => Some(v * 72.0 / 25.4),
+        Length::In(v) => Some(v * 72.0),
+        Length::Pt(v) => Some(v),
+        Length::Pc(v) => Some(v * 12.0),
+        Length::Em(v) => Some(v * DEFAULT_SIZE),
+    }
+}
+
+/// Converts pt to cm, rounded to 1/1000 cm.
+pub(crate) fn pt_length(pt: f64) -> Length {
+    Length::Cm((pt * 2.54 / 72.0 * 1000.0).round() / 1000.0)
+}
@@ -0,0 +1,878 @@
+//!
+//! Conditional formats as LibreOffice writes them in
+//! calcext:conditional-formats.
+//!
+//! Each ConditionalFormat applies to one or more cell ranges and holds a
+//! list of rules. A rule is a condition that applies a cell style,
+//! a color scale, a data bar or an icon set.
+//!
+//! ```
+//! use color::Rgb;
+//! use spreadsheet_ods::conditional::{
+//!     CfCondition, CfTest, ColorScale, ConditionalFormat, IconSet, IconSetType,
+//! };
+//! use spreadsheet_ods::style::CellStyle;
+//! use spreadsheet_ods::{CellRange, Sheet, WorkBook};
+//!
+//! let mut wb = WorkBook::new_empty();
+//! let mut alert = CellStyle::empty();
+//! alert.set_name("alert");
+//! alert.set_font_bold();
+//! let alert = wb.add_cellstyle(alert);
+//!
+//! let mut sheet = Sheet::new("report");
+//!
+//! let mut cf = ConditionalFormat::new(CellRange::remote("report", 1, 1, 20, 1));
+//! cf.push_condition(CfCondition::new(CfTest::greater(1000), &alert));
+//! cf.push_icon_set(IconSet::new(IconSetType::TrafficLights3));
+//! sheet.add_conditional_format(cf);
+//!
+//! let mut cf = ConditionalFormat::new(CellRange::remote("report", 1, 2, 20, 2));
+//! cf.push_color_scale(ColorScale::new_2(
+//!     Rgb::new(255, 255, 255),
+//!     Rgb::new(0, 128, 0),
+//! ));
+//! sheet.add_conditional_format(cf);
+//!
+//! wb.push_sheet(sheet);
+//! ```
+//!
+
+use std::fmt::{Display, Formatter};
+
+use color::Rgb;
+
+use crate::attrmap2::AttrMap2;
+use crate::condition::Value;
+use crate::style::CellStyleRef;
+use crate::xmltree::XmlTag;
+use crate::{CellRange, CellRef, OdsError};
+
+/// A conditional format for a set of cell ranges.
+#[derive(Debug, Clone, Default)]
+pub struct ConditionalFormat {
+    range: Vec<CellRange>,
+    rules: Vec<CfRule>,
+}
+
+impl ConditionalFormat {
+    /// Conditional format for one range.
+    pub fn new(range: CellRange) -> Self {
+        Self {
+            range: vec![range],
+            rules: Vec::new(),
+        }
+    }
+
+    /// Conditional format for multiple ranges.
+    pub fn new_ranges(range: Vec<CellRange>) -> Self {
+        Self {
+            range,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Target ranges.
+    pub fn range(&self) -> &Vec<CellRange> {
+        &self.range
+    }
+
+    /// Target ranges.
+    pub fn range_mut(&mut self) -> &mut Vec<CellRange> {
+        &mut self.range
+    }
+
+    /// Adds a target range.
+    pub fn add_range(&mut self, range: CellRange) {
+        self.range.push(range);
+    }
+
+    /// Rules in order of their priority.
+    pub fn rules(&self) -> &Vec<CfRule> {
+        &self.rules
+    }
+
+    /// Rules in order of their priority.
+    pub fn rules_mut(&mut self) -> &mut Vec<CfRule> {
+        &mut self.rules
+    }
+
+    /// Adds a rule.
+    pub fn push_rule(&mut self, rule: CfRule) {
+        self.rules.push(rule);
+    }
+
+    /// Adds a condition.
+    pub fn push_condition(&mut self, condition: CfCondition) {
+        self.rules.push(CfRule::Condition(condition));
+    }
+
+    /// Adds a color scale.
+    pub fn push_color_scale(&mut self, scale: ColorScale) {
+        self.rules.push(CfRule::ColorScale(scale));
+    }
+
+    /// Adds a data bar.
+    pub fn push_data_bar(&mut self, bar: DataBar) {
+        self.rules.push(CfRule::DataBar(bar));
+    }
+
+    /// Adds an icon set.
+    pub fn push_icon_set(&mut self, icons: IconSet) {
+        self.rules.push(CfRule::IconSet(icons));
+    }
+}
+
+/// One rule of a conditional format.
+#[derive(Debug, Clone)]
+#[allow(variant_size_differences)]
+pub enum CfRule {
+    /// calcext:condition
+    Condition(CfCondition),
+    /// calcext:color-scale
+    ColorScale(ColorScale),
+    /// calcext:data-bar
+    DataBar(DataBar),
+    /// calcext:icon-set
+    IconSet(IconSet),
+    /// Anything else, e.g. calcext:date-is. Kept as is.
+    Other(XmlTag),
+}
+
+/// Applies a cell style if the test is true.
+#[derive(Debug, Clone)]
+pub struct CfCondition {
+    test: CfTest,
+    style: String,
+    base_cell: Option<CellRef>,
+}
+
+impl CfCondition {
+    /// Applies the style when the test is true.
+    pub fn new(test: CfTest, style: &CellStyleRef) -> Self {
+        Self {
+            test,
+            style: style.to_string(),
+            base_cell: None,
+        }
+    }
+
+    /// Test.
+    pub fn test(&self) -> &CfTest {
+        &self.test
+    }
+
+    /// Test.
+    pub fn set_test(&mut self, test: CfTest) {
+        self.test = test;
+    }
+
+    /// Applied style.
+    pub fn style(&self) -> &String {
+        &self.style
+    }
+
+    /// Applied style.
+    pub fn set_style(&mut self, style: &CellStyleRef) {
+        self.style = style.to_string();
+    }
+
+    /// Relative references in the expressions are relative to this cell.
+    /// Defaults to the top left cell of the first range.
+    pub fn base_cell(&self) -> Option<&CellRef> {
+        self.base_cell.as_ref()
+    }
+
+    /// Relative references in the expressions are relative to this cell.
+    pub fn set_base_cell(&mut self, base_cell: CellRef) {
+        self.base_cell = Some(base_cell);
+    }
+
+    /// Resets the base cell.
+    pub fn clear_base_cell(&mut self) {
+        self.base_cell = None;
+    }
+}
+
+/// The test of a CfCondition.
+///
+/// The values are expressions in OpenFormula syntax, text must be quoted.
+/// The constructor functions take care of this.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfTest {
+    /// Cell value = expression.
+    Equal(String),
+    /// Cell value != expression.
+    NotEqual(String),
+    /// Cell value < expression.
+    Less(String),
+    /// Cell value > expression.
+    Greater(String),
+    /// Cell value <= expression.
+    LessEqual(String),
+    /// Cell value >= expression.
+    GreaterEqual(String),
+    /// Cell value between both expressions.
+    Between(String, String),
+    /// Cell value not between both expressions.
+    NotBetween(String, String),
+    /// The formula is true.
+    Formula(String),
+    /// The n largest values.
+    TopElements(u32),
+    /// The n smallest values.
+    BottomElements(u32),
+    /// The top n percent.
+    TopPercent(u32),
+    /// The bottom n percent.
+    BottomPercent(u32),
+    /// Above the average of the range.
+    AboveAverage,
+    /// Below the average of the range.
+    BelowAverage,
+    /// Above or equal to the average of the range.
+    AboveEqualAverage,
+    /// Below or equal to the average of the range.
+    BelowEqualAverage,
+    /// Values that occur more than once.
+    Duplicate,
+    /// Values that occur only once.
+    Unique,
+    /// Cell contains an error.
+    Error,
+    /// Cell contains no error.
+    NoError,
+    /// Text contains the expression.
+    ContainsText(String),
+    /// Text doesn't contain the expression.
+    NotContainsText(String),
+    /// Text begins with the expression.
+    BeginsWith(String),
+    /// Text ends with the expression.
+    EndsWith(String),
+    /// Unknown condition. Kept as is.
+    Other(String),
+}
+
+impl CfTest {
+    /// Cell value = value.
+    pub fn equal<V: Into<Value>>(value: V) -> Self {
+        CfTest::Equal(value.into().to_string())
+    }
+
+    /// Cell value != value.
+    pub fn not_equal<V: Into<Value>>(value: V) -> Self {
+        CfTest::NotEqual(value.into().to_string())
+    }
+
+    /// Cell value < value.
+    pub fn less<V: Into<Value>>(value: V) -> Self {
+        CfTest::Less(value.into().to_string())
+    }
+
+    /// Cell value > value.
+    pub fn greater<V: Into<Value>>(value: V) -> Self {
+        CfTest::Greater(value.into().to_string())
+    }
+
+    /// Cell value <= value.
+    pub fn less_equal<V: Into<Value>>(value: V) -> Self {
+        CfTest::LessEqual(value.into().to_string())
+    }
+
+    /// Cell value >= value.
+    pub fn greater_equal<V: Into<Value>>(value: V) -> Self {
+        CfTest::GreaterEqual(value.into().to_string())
+    }
+
+    /// Cell value between from and to.
+    pub fn between<V: Into<Value>>(from: V, to: V) -> Self {
+        CfTest::Between(from.into().to_string(), to.into().to_string())
+    }
+
+    /// Cell value not between from and to.
+    pub fn not_between<V: Into<Value>>(from: V, to: V) -> Self {
+        CfTest::NotBetween(from.into().to_string(), to.into().to_string())
+    }
+
+    /// The formula is true. Relative references are relative to the
+    /// base cell.
+    pub fn formula<S: Into<String>>(formula: S) -> Self {
+        CfTest::Formula(formula.into())
+    }
+
+    /// Text contains the text.
+    pub fn contains_text<V: Into<Value>>(text: V) -> Self {
+        CfTest::ContainsText(text.into().to_string())
+    }
+
+    /// Text doesn't contain the text.
+    pub fn not_contains_text<V: Into<Value>>(text: V) -> Self {
+        CfTest::NotContainsText(text.into().to_string())
+    }
+
+    /// Text begins with the text.
+    pub fn begins_with<V: Into<Value>>(text: V) -> Self {
+        CfTest::BeginsWith(text.into().to_string())
+    }
+
+    /// Text ends with the text.
+    pub fn ends_with<V: Into<Value>>(text: V) -> Self {
+        CfTest::EndsWith(text.into().to_string())
+    }
+
+    /// Parses the calcext:value of a calcext:condition.
+    pub(crate) fn parse(value: &str) -> CfTest {
+        if let Some(v) = func_args(value, "between") {
+            if let [from, to] = v.as_slice() {
+                return CfTest::Between(from.to_string(), to.to_string());
+            }
+        } else if let Some(v) = func_args(value, "not-between") {
+            if let [from, to] = v.as_slice() {
+                return CfTest::NotBetween(from.to_string(), to.to_string());
+            }
+        } else if let Some(v) = func_arg(value, "formula-is") {
+            return CfTest::Formula(v.to_string());
+        } else if let Some(v) = func_arg(value, "top-elements") {
+            if let Ok(v) = v.trim().parse() {
+                return CfTest::TopElements(v);
+            }
+        } else if let Some(v) = func_arg(value, "bottom-elements") {
+            if let Ok(v) = v.trim().parse() {
+                return CfTest::BottomElements(v);
+            }
+        } else if let Some(v) = func_arg(value, "top-percent") {
+            if let Ok(v) = v.trim().parse() {
+                return CfTest::TopPercent(v);
+            }
+        } else if let Some(v) = func_arg(value, "bottom-percent") {
+            if let Ok(v) = v.trim().parse() {
+                return CfTest::BottomPercent(v);
+            }
+        } else if let Some(v) = func_arg(value, "contains-text") {
+            return CfTest::ContainsText(v.to_string());
+        } else if let Some(v) = func_arg(value, "not-contains-text") {
+            return CfTest::NotContainsText(v.to_string());
+        } else if let Some(v) = func_arg(value, "begins-with") {
+            return CfTest::BeginsWith(v.to_string());
+        } else if let Some(v) = func_arg(value, "ends-with") {
+            return CfTest::EndsWith(v.to_string());
+        } else {
+            match value {
+                "above-average" => return CfTest::AboveAverage,
+                "below-average" => return CfTest::BelowAverage,
+                "above-equal-average" => return CfTest::AboveEqualAverage,
+                "below-equal-average" => return CfTest::BelowEqualAverage,
+                "duplicate" => return CfTest::Duplicate,
+                "unique" => return CfTest::Unique,
+                "error" => return CfTest::Error,
+                "no-error" => return CfTest::NoError,
+                _ => {}
+            }
+
+            if let Some(v) = value.strip_prefix("!=") {
+                return CfTest::NotEqual(v.to_string());
+            } else if let Some(v) = value.strip_prefix("<=") {
+                return CfTest::LessEqual(v.to_string());
+            } else if let Some(v) = value.strip_prefix(">=") {
+                return CfTest::GreaterEqual(v.to_string());
+            } else if let Some(v) = value.strip_prefix('<') {
+                return CfTest::Less(v.to_string());
+            } else if let Some(v) = value.strip_prefix('>') {
+                return CfTest::Greater(v.to_string());
+            } else if let Some(v) = value.strip_prefix('=') {
+                return CfTest::Equal(v.to_string());
+            }
+        }
+
+        CfTest::Other(value.to_string())
+    }
+}
+
+/// The argument of name(...).
+fn func_arg<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    value
+        .strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+/// The arguments of name(...,...). Splits at the top level commas.
+fn func_args<'a>(value: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let args = func_arg(value, name)?;
+
+    let mut res = Vec::new();
+    let mut depth = 0u32;
+    let mut quoted = false;
+    let mut start = 0;
+    for (idx, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' | ';' if !quoted && depth == 0 => {
+                res.push(&args[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    res.push(&args[start..]);
+
+    Some(res)
+}
+
+impl Display for CfTest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CfTest::Equal(v) => write!(f, "={}", v),
+            CfTest::NotEqual(v) => write!(f, "!={}", v),
+            CfTest::Less(v) => write!(f, "<{}", v),
+            CfTest::Greater(v) => write!(f, ">{}", v),
+            CfTest::LessEqual(v) => write!(f, "<={}", v),
+            CfTest::GreaterEqual(v) => write!(f, ">={}", v),
+            CfTest::Between(a, b) => write!(f, "between({},{})", a, b),
+            CfTest::NotBetween(a, b) => write!(f, "not-between({},{})", a, b),
+            CfTest::Formula(v) => write!(f, "formula-is({})", v),
+            CfTest::TopElements(v) => write!(f, "top-elements({})", v),
+            CfTest::BottomElements(v) => write!(f, "bottom-elements({})", v),
+            CfTest::TopPercent(v) => write!(f, "top-percent({})", v),
+            CfTest::BottomPercent(v) => write!(f, "bottom-percent({})", v),
+            CfTest::AboveAverage => write!(f, "above-average"),
+            CfTest::BelowAverage => write!(f, "below-average"),
+            CfTest::AboveEqualAverage => write!(f, "above-equal-average"),
+            CfTest::BelowEqualAverage => write!(f, "below-equal-average"),
+            CfTest::Duplicate => write!(f, "duplicate"),
+            CfTest::Unique => write!(f, "unique"),
+            CfTest::Error => write!(f, "error"),
+            CfTest::NoError => write!(f, "no-error"),
+            CfTest::ContainsText(v) => write!(f, "contains-text({})", v),
+            CfTest::NotContainsText(v) => write!(f, "not-contains-text({})", v),
+            CfTest::BeginsWith(v) => write!(f, "begins-with({})", v),
+            CfTest::EndsWith(v) => write!(f, "ends-with({})", v),
+            CfTest::Other(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// How the value of a color scale, data bar or icon set entry is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum CfValueType {
+    Minimum,
+    Maximum,
+    AutoMinimum,
+    AutoMaximum,
+    Percent,
+    Percentile,
+    Number,
+    Formula,
+}
+
+impl Display for CfValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CfValueType::Minimum => write!(f, "minimum"),
+            CfValueType::Maximum => write!(f, "maximum"),
+            CfValueType::AutoMinimum => write!(f, "auto-minimum"),
+            CfValueType::AutoMaximum => write!(f, "auto-maximum"),
+            CfValueType::Percent => write!(f, "percent"),
+            CfValueType::Percentile => write!(f, "percentile"),
+            CfValueType::Number => write!(f, "number"),
+            CfValueType::Formula => write!(f, "formula"),
+        }
+    }
+}
+
+impl TryFrom<&str> for CfValueType {
+    type Error = OdsError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "minimum" => Ok(CfValueType::Minimum),
+            "maximum" => Ok(CfValueType::Maximum),
+            "auto-minimum" => Ok(CfValueType::AutoMinimum),
+            "auto-maximum" => Ok(CfValueType::AutoMaximum),
+            "percent" => Ok(CfValueType::Percent),
+            "percentile" => Ok(CfValueType::Percentile),
+            "number" => Ok(CfValueType::Number),
+            "formula" => Ok(CfValueType::Formula),
+            _ => Err(OdsError::Parse(format!("Unknown calcext:type {}", s))),
+        }
+    }
+}
+
+/// A threshold for color scales, data bars and icon sets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CfValue {
+    value_type: CfValueType,
+    value: String,
+}
+
+impl CfValue {
+    /// New value.
+    pub fn new<S: Into<String>>(value_type: CfValueType, value: S) -> Self {
+        Self {
+            value_type,
+            value: value.into(),
+        }
+    }
+
+    /// Smallest value of the range.
+    pub fn minimum() -> Self {
+        Self::new(CfValueType::Minimum, "0")
+    }
+
+    /// Largest value of the range.
+    pub fn maximum() -> Self {
+        Self::new(CfValueType::Maximum, "0")
+    }
+
+    /// Automatic minimum for data bars.
+    pub fn auto_minimum() -> Self {
+        Self::new(CfValueType::AutoMinimum, "0")
+    }
+
+    /// Automatic maximum for data bars.
+    pub fn auto_maximum() -> Self {
+        Self::new(CfValueType::AutoMaximum, "0")
+    }
+
+    /// Percent between minimum and maximum.
+    pub fn percent(value: f64) -> Self {
+        Self::new(CfValueType::Percent, value.to_string())
+    }
+
+    /// Percentile of the values.
+    pub fn percentile(value: f64) -> Self {
+        Self::new(CfValueType::Percentile, value.to_string())
+    }
+
+    /// Fixed number.
+    pub fn number(value: f64) -> Self {
+        Self::new(CfValueType::Number, value.to_string())
+    }
+
+    /// Result of a formula.
+    pub fn formula<S: Into<String>>(formula: S) -> Self {
+        Self::new(CfValueType::Formula, formula)
+    }
+
+    /// Type.
+    pub fn value_type(&self) -> CfValueType {
+        self.value_type
+    }
+
+    /// Value. Depending on the type this is a number or a formula.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// Colors the cells on a scale between two or three colors.
+#[derive(Debug, Clone, Default)]
+pub struct ColorScale {
+    entries: Vec<(CfValue, Rgb<u8>)>,
+}
+
+impl ColorScale {
+    /// Empty color scale.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// From the minimum to the maximum.
+    pub fn new_2(min: Rgb<u8>, max: Rgb<u8>) -> Self {
+        let mut scale = Self::new();
+        scale.push(CfValue::minimum(), min);
+        scale.push(CfValue::maximum(), max);
+        scale
+    }
+
+    /// From the minimum over the median to the maximum.
+    pub fn new_3(min: Rgb<u8>, mid: Rgb<u8>, max: Rgb<u8>) -> Self {
+        let mut scale = Self::new();
+        scale.push(CfValue::minimum(), min);
+        scale.push(CfValue::percentile(50.0), mid);
+        scale.push(CfValue::maximum(), max);
+        scale
+    }
+
+    /// Adds an entry.
+    pub fn push(&mut self, value: CfValue, color: Rgb<u8>) {
+        self.entries.push((value, color));
+    }
+
+    /// Entries.
+    pub fn entries(&self) -> &Vec<(CfValue, Rgb<u8>)> {
+        &self.entries
+    }
+
+    /// Entries.
+    pub fn entries_mut(&mut self) -> &mut Vec<(CfValue, Rgb<u8>)> {
+        &mut self.entries
+    }
+}
+
+/// Shows a bar in the cell.
+///
+/// Attributes like calcext:axis-position or calcext:max-length are
+/// kept in the attrmap.
+#[derive(Debug, Clone)]
+pub struct DataBar {
+    min: CfValue,
+    max: CfValue,
+    positive_color: Rgb<u8>,
+    negative_color: Option<Rgb<u8>>,
+    gradient: bool,
+    attr: AttrMap2,
+}
+
+impl DataBar {
+    /// Data bar with automatic minimum and maximum.
+    pub fn new(positive_color: Rgb<u8>) -> Self {
+        Self {
+            min: CfValue::auto_minimum(),
+            max: CfValue::auto_maximum(),
+            positive_color,
+            negative_color: None,
+            gradient: true,
+            attr: Default::default(),
+        }
+    }
+
+    /// Value for the shortest bar.
+    pub fn min(&self) -> &CfValue {
+        &self.min
+    }
+
+    /// Value for the shortest bar.
+    pub fn set_min(&mut self, min: CfValue) {
+        self.min = min;
+    }
+
+    /// Value for the longest bar.
+    pub fn max(&self) -> &CfValue {
+        &self.max
+    }
+
+    /// Value for the longest bar.
+    pub fn set_max(&mut self, max: CfValue) {
+        self.max = max;
+    }
+
+    /// Bar color.
+    pub fn positive_color(&self) -> Rgb<u8> {
+        self.positive_color
+    }
+
+    /// Bar color.
+    pub fn set_positive_color(&mut self, color: Rgb<u8>) {
+        self.positive_color = color;
+    }
+
+    /// Bar color for negative values.
+    pub fn negative_color(&self) -> Option<Rgb<u8>> {
+        self.negative_color
+    }
+
+    /// Bar color for negative values.
+    pub fn set_negative_color(&mut self, color: Rgb<u8>) {
+        self.negative_color = Some(color);
+    }
+
+    /// Gradient or solid fill.
+    pub fn gradient(&self) -> bool {
+        self.gradient
+    }
+
+    /// Gradient or solid fill.
+    pub fn set_gradient(&mut self, gradient: bool) {
+        self.gradient = gradient;
+    }
+
+    /// Other attributes.
+    pub fn attrmap(&self) -> &AttrMap2 {
+        &self.attr
+    }
+
+    /// Other attributes.
+    pub fn attrmap_mut(&mut self) -> &mut AttrMap2 {
+        &mut self.attr
+    }
+}
+
+/// Icons of an icon set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum IconSetType {
+    Arrows3,
+    ArrowsGray3,
+    Flags3,
+    TrafficLights3,
+    TrafficLightsRimmed3,
+    Signs3,
+    Symbols3,
+    SymbolsUncircled3,
+    Smilies3,
+    Stars3,
+    Triangles3,
+    ColorSmilies3,
+    Arrows4,
+    ArrowsGray4,
+    RedToBlack4,
+    Rating4,
+    TrafficLights4,
+    Arrows5,
+    ArrowsGray5,
+    Rating5,
+    Quarters5,
+    Boxes5,
+}
+
+impl IconSetType {
+    /// Number of icons.
+    pub fn icon_count(&self) -> u32 {
+        match self {
+            IconSetType::Arrows3
+            | IconSetType::ArrowsGray3
+            | IconSetType::Flags3
+            | IconSetType::TrafficLights3
+            | IconSetType::TrafficLightsRimmed3
+            | IconSetType::Signs3
+            | IconSetType::Symbols3
+            | IconSetType::SymbolsUncircled3
+            | IconSetType::Smilies3
+            | IconSetType::Stars3
+            | IconSetType::Triangles3
+            | IconSetType::ColorSmilies3 => 3,
+            IconSetType::Arrows4
+            | IconSetType::ArrowsGray4
+            | IconSetType::RedToBlack4
+            | IconSetType::Rating4
+            | IconSetType::TrafficLights4 => 4,
+            IconSetType::Arrows5
+            | IconSetType::ArrowsGray5
+            | IconSetType::Rating5
+            | IconSetType::Quarters5
+            | IconSetType::Boxes5 => 5,
+        }
+    }
+}
+
+impl Display for IconSetType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            IconSetType::Arrows3 => "3Arrows",
+            IconSetType::ArrowsGray3 => "3ArrowsGray",
+            IconSetType::Flags3 => "3Flags",
+            IconSetType::TrafficLights3 => "3TrafficLights1",
+            IconSetType::TrafficLightsRimmed3 => "3TrafficLights2",
+            IconSetType::Signs3 => "3Signs",
+            IconSetType::Symbols3 => "3Symbols",
+            IconSetType::SymbolsUncircled3 => "3Symbols2",
+            IconSetType::Smilies3 => "3Smilies",
+            IconSetType::Stars3 => "3Stars",
+            IconSetType::Triangles3 => "3Triangles",
+            IconSetType::ColorSmilies3 => "3ColorSmilies",
+            IconSetType::Arrows4 => "4Arrows",
+            IconSetType::ArrowsGray4 => "4ArrowsGray",
+            IconSetType::RedToBlack4 => "4RedToBlack",
+            IconSetType::Rating4 => "4Rating",
+            IconSetType::TrafficLights4 => "4TrafficLights",
+            IconSetType::Arrows5 => "5Arrows",
+            IconSetType::ArrowsGray5 => "5ArrowsGray",
+            IconSetType::Rating5 => "5Rating",
+            IconSetType::Quarters5 => "5Quarters",
+            IconSetType::Boxes5 => "5Boxes",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl TryFrom<&str> for IconSetType {
+    type Error = OdsError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "3Arrows" => Ok(IconSetType::Arrows3),
+            "3ArrowsGray" => Ok(IconSetType::ArrowsGray3),
+            "3Flags" => Ok(IconSetType::Flags3),
+            "3TrafficLights1" => Ok(IconSetType::TrafficLights3),
+            "3TrafficLights2" => Ok(IconSetType::TrafficLightsRimmed3),
+            "3Signs" => Ok(IconSetType::Signs3),
+            "3Symbols" => Ok(IconSetType::Symbols3),
+            "3Symbols2" => Ok(IconSetType::SymbolsUncircled3),
+            "3Smilies" => Ok(IconSetType::Smilies3),
+            "3Stars" => Ok(IconSetType::Stars3),
+            "3Triangles" => Ok(IconSetType::Triangles3),
+            "3ColorSmilies" => Ok(IconSetType::ColorSmilies3),
+            "4Arrows" => Ok(IconSetType::Arrows4),
+            "4ArrowsGray" => Ok(IconSetType::ArrowsGray4),
+            "4RedToBlack" => Ok(IconSetType::RedToBlack4),
+            "4Rating" => Ok(IconSetType::Rating4),
+            "4TrafficLights" => Ok(IconSetType::TrafficLights4),
+            "5Arrows" => Ok(IconSetType::Arrows5),
+            "5ArrowsGray" => Ok(IconSetType::ArrowsGray5),
+            "5Rating" => Ok(IconSetType::Rating5),
+            "5Quarters" => Ok(IconSetType::Quarters5),
+            "5Boxes" => Ok(IconSetType::Boxes5),
+            _ => Err(OdsError::Parse(format!("Unknown icon set {}", s))),
+        }
+    }
+}
+
+/// Shows an icon depending on the value.
+#[derive(Debug, Clone)]
+pub struct IconSet {
+    icon_set_type: IconSetType,
+    show_value: bool,
+    entries: Vec<CfValue>,
+}
+
+impl IconSet {
+    /// Icon set with evenly distributed percent thresholds.
+    pub fn new(icon_set_type: IconSetType) -> Self {
+        let n = icon_set_type.icon_count();
+        let entries = (0..n)
+            .map(|i| CfValue::percent((f64::from(i) * 100.0 / f64::from(n)).round()))
+            .collect();
+        Self {
+            icon_set_type,
+            show_value: true,
+            entries,
+        }
+    }
+
+    /// Icons.
+    pub fn icon_set_type(&self) -> IconSetType {
+        self.icon_set_type
+    }
+
+    /// Icons.
+    pub fn set_icon_set_type(&mut self, icon_set_type: IconSetType) {
+        self.icon_set_type = icon_set_type;
+    }
+
+    /// Show the value next to the icon.
+    pub fn show_value(&self) -> bool {
+        self.show_value
+    }
+
+    /// Show the value next to the icon.
+    pub fn set_show_value(&mut self, show_value: bool) {
+        self.show_value = show_value;
+    }
+
+    /// The lower threshold for each icon.
+    pub fn entries(&self) -> &Vec<CfValue> {
+        &self.entries
+    }
+
+    /// The lower threshold for each icon.
+    pub fn set_entries(&mut self, entries: Vec<CfValue>) {
+        self.entries = entries;
+    }
+}
@@ -0,0 +1,511 @@
+//!
+//! Database ranges. A database range is a cell range with a header
+//! row that can be filtered and sorted.
+//!
+//! The autofilter of a sheet is the anonymous database range of the
+//! sheet, see [Sheet::set_autofilter](crate::Sheet::set_autofilter).
+//!
+//! ```
+//! use spreadsheet_ods::database::{
+//!     DatabaseRange, Filter, FilterCondition, FilterDataType, FilterOperator, SortBy, SortOrder,
+//! };
+//! use spreadsheet_ods::{CellRange, Sheet, WorkBook};
+//!
+//! let mut wb = WorkBook::new_empty();
+//!
+//! let mut sh = Sheet::new("orders");
+//! sh.set_autofilter(CellRange::local(0, 0, 99, 3));
+//! wb.push_sheet(sh);
+//!
+//! let mut amount = FilterCondition::new(3, FilterOperator::Greater, "100");
+//! amount.set_data_type(FilterDataType::Number);
+//!
+//! let mut dr = DatabaseRange::new("LargeOrders", CellRange::remote("orders", 0, 0, 99, 3));
+//! dr.set_filter(Some(Filter::Condition(amount)));
+//! dr.push_sort(SortBy::new(3, SortOrder::Descending));
+//! wb.add_database_range(dr);
+//! ```
+//!
+
+use crate::attrmap2::AttrMap2;
+use crate::xmltree::XmlTag;
+use crate::{CellRange, OdsError};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+/// A database range. Written as table:database-range.
+///
+/// Any other attributes of the range, the filter and the sort are kept in
+/// the attrmaps. A filter or sort that can't be read is kept as it is.
+#[derive(Debug, Clone)]
+pub struct DatabaseRange {
+    name: String,
+    range: CellRange,
+    contains_header: bool,
+    display_filter_buttons: bool,
+    filter: Option<Filter>,
+    sort: Vec<SortBy>,
+    attr: AttrMap2,
+    filter_attr: AttrMap2,
+    sort_attr: AttrMap2,
+    // Other content, e.g. table:subtotal-rules.
+    pub(crate) extra: Vec<XmlTag>,
+}
+
+impl DatabaseRange {
+    /// New database range with a header row. No filter buttons are
+    /// shown.
+    pub fn new<S: Into<String>>(name: S, range: CellRange) -> Self {
+        Self {
+            name: name.into(),
+            range,
+            contains_header: true,
+            display_filter_buttons: false,
+            filter: None,
+            sort: Vec::new(),
+            attr: Default::default(),
+            filter_attr: Default::default(),
+            sort_attr: Default::default(),
+            extra: Vec::new(),
+        }
+    }
+
+    /// Name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    /// Range including the header row.
+    pub fn range(&self) -> &CellRange {
+        &self.range
+    }
+
+    /// Range including the header row.
+    pub fn set_range(&mut self, range: CellRange) {
+        self.range = range;
+    }
+
+    pub(crate) fn range_mut(&mut self) -> &mut CellRange {
+        &mut self.range
+    }
+
+    /// The first row contains the column names.
+    pub fn contains_header(&self) -> bool {
+        self.contains_header
+    }
+
+    /// The first row contains the column names.
+    pub fn set_contains_header(&mut self, contains_header: bool) {
+        self.contains_header = contains_header;
+    }
+
+    /// Shows the autofilter buttons in the header row.
+    pub fn display_filter_buttons(&self) -> bool {
+        self.display_filter_buttons
+    }
+
+    /// Shows the autofilter buttons in the header row.
+    pub fn set_display_filter_buttons(&mut self, display: bool) {
+        self.display_filter_buttons = display;
+    }
+
+    /// Filter.
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    /// Filter.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+    }
+
+    /// Sort descriptors in order of precedence.
+    pub fn sort(&self) -> &[SortBy] {
+        &self.sort
+    }
+
+    /// Sort descriptors in order of precedence.
+    pub fn set_sort(&mut self, sort: Vec<SortBy>) {
+        self.sort = sort;
+    }
+
+    /// Adds a sort descriptor.
+    pub fn push_sort(&mut self, sort: SortBy) {
+        self.sort.push(sort);
+    }
+
+    /// Other attributes of table:database-range, e.g. table:is-selection
+    /// or table:refresh-delay.
+    pub fn attrmap(&self) -> &AttrMap2 {
+        &self.attr
+    }
+
+    /// Other attributes of table:database-range.
+    pub fn attrmap_mut(&mut self) -> &mut AttrMap2 {
+        &mut self.attr
+    }
+
+    /// Attributes of table:filter, e.g. table:display-duplicates or
+    /// table:condition-source.
+    pub fn filter_attrmap(&self) -> &AttrMap2 {
+        &self.filter_attr
+    }
+
+    /// Attributes of table:filter.
+    pub fn filter_attrmap_mut(&mut self) -> &mut AttrMap2 {
+        &mut self.filter_attr
+    }
+
+    /// Attributes of table:sort, e.g. table:case-sensitive or
+    /// table:language.
+    pub fn sort_attrmap(&self) -> &AttrMap2 {
+        &self.sort_attr
+    }
+
+    /// Attributes of table:sort.
+    pub fn sort_attrmap_mut(&mut self) -> &mut AttrMap2 {
+        &mut self.sort_attr
+    }
+}
+
+/// Filter of a database range. Written as table:filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// One condition.
+    Condition(FilterCondition),
+    /// All of them must match. Written as table:filter-and.
+    And(Vec<Filter>),
+    /// One of them must match. Written as table:filter-or.
+    Or(Vec<Filter>),
+}
+
+/// Condition for one column. Written as table:filter-condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterCondition {
+    field: u32,
+    operator: FilterOperator,
+    value: String,
+    data_type: FilterDataType,
+    case_sensitive: bool,
+    set_items: Vec<String>,
+}
+
+impl FilterCondition {
+    /// Condition for the field. The field is the column relative to
+    /// the start of the range.
+    pub fn new<S: Into<String>>(field: u32, operator: FilterOperator, value: S) -> Self {
+        Self {
+            field,
+            operator,
+            value: value.into(),
+            data_type: FilterDataType::Text,
+            case_sensitive: false,
+            set_items: Vec::new(),
+        }
+    }
+
+    /// Condition that matches any of the values. This is what the
+    /// autofilter uses when more than one value is selected.
+    pub fn one_of(field: u32, values: Vec<String>) -> Self {
+        Self {
+            field,
+            operator: FilterOperator::Equal,
+            value: values.first().cloned().unwrap_or_default(),
+            data_type: FilterDataType::Text,
+            case_sensitive: false,
+            set_items: values,
+        }
+    }
+
+    /// Column relative to the start of the range.
+    pub fn field(&self) -> u32 {
+        self.field
+    }
+
+    /// Column relative to the start of the range.
+    pub fn set_field(&mut self, field: u32) {
+        self.field = field;
+    }
+
+    /// Operator.
+    pub fn operator(&self) -> FilterOperator {
+        self.operator
+    }
+
+    /// Operator.
+    pub fn set_operator(&mut self, operator: FilterOperator) {
+        self.operator = operator;
+    }
+
+    /// Value to compare with.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Value to compare with.
+    pub fn set_value<S: Into<String>>(&mut self, value: S) {
+        self.value = value.into();
+    }
+
+    /// Compare as text or as number.
+    pub fn data_type(&self) -> FilterDataType {
+        self.data_type
+    }
+
+    /// Compare as text or as number.
+    pub fn set_data_type(&mut self, data_type: FilterDataType) {
+        self.data_type = data_type;
+    }
+
+    /// Text comparison is case-sensitive.
+    pub fn case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// Text comparison is case-sensitive.
+    pub fn set_case_sensitive(&mut self, case_sensitive: bool) {
+        self.case_sensitive = case_sensitive;
+    }
+
+    /// Values for an equal test with multiple values. Written as
+    /// table:filter-set-item.
+    pub fn set_items(&self) -> &[String] {
+        &self.set_items
+    }
+
+    /// Values for an equal test with multiple values.
+    pub fn set_set_items(&mut self, set_items: Vec<String>) {
+        self.set_items = set_items;
+    }
+}
+
+/// Operators for a filter condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum FilterOperator {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    /// Regular expression.
+    Match,
+    NotMatch,
+    Empty,
+    NotEmpty,
+    TopValues,
+    BottomValues,
+    TopPercent,
+    BottomPercent,
+    Contains,
+    NotContains,
+    BeginsWith,
+    NotBeginsWith,
+    EndsWith,
+    NotEndsWith,
+}
+
+impl Display for FilterOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterOperator::Equal => write!(f, "="),
+            FilterOperator::NotEqual => write!(f, "!="),
+            FilterOperator::Less => write!(f, "<"),
+            FilterOperator::Greater => write!(f, ">"),
+            FilterOperator::LessEqual => write!(f, "<="),
+            FilterOperator::GreaterEqual => write!(f, ">="),
+            FilterOperator::Match => write!(f, "match"),
+            FilterOperator::NotMatch => write!(f, "!match"),
+            FilterOperator::Empty => write!(f, "empty"),
+            FilterOperator::NotEmpty => write!(f, "!empty"),
+            FilterOperator::TopValues => write!(f, "top values"),
+            FilterOperator::BottomValues => write!(f, "bottom values"),
+            FilterOperator::TopPercent => write!(f, "top percent"),
+            FilterOperator::BottomPercent => write!(f, "bottom percent"),
+            FilterOperator::Contains => write!(f, "contains"),
+            FilterOperator::NotContains => write!(f, "!contains"),
+            FilterOperator::BeginsWith => write!(f, "begins"),
+            FilterOperator::NotBeginsWith => write!(f, "!begins"),
+            FilterOperator::EndsWith => write!(f, "ends"),
+            FilterOperator::NotEndsWith => write!(f, "!ends"),
+        }
+    }
+}
+
+impl TryFrom<&str> for FilterOperator {
+    type Error = OdsError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "=" => Ok(FilterOperator::Equal),
+            "!=" => Ok(FilterOperator::NotEqual),
+            "<" => Ok(FilterOperator::Less),
+            ">" => Ok(FilterOperator::Greater),
+            "<=" => Ok(FilterOperator::LessEqual),
+            ">=" => Ok(FilterOperator::GreaterEqual),
+            "match" => Ok(FilterOperator::Match),
+            "!match" => Ok(FilterOperator::NotMatch),
+            "empty" => Ok(FilterOperator::Empty),
+            "!empty" => Ok(FilterOperator::NotEmpty),
+            "top values" => Ok(FilterOperator::TopValues),
+            "bottom values" => Ok(FilterOperator::BottomValues),
+            "top percent" => Ok(FilterOperator::TopPercent),
+            "bottom percent" => Ok(FilterOperator::BottomPercent),
+            "contains" => Ok(FilterOperator::Contains),
+            "!contains" => Ok(FilterOperator::NotContains),
+            "begins" => Ok(FilterOperator::BeginsWith),
+            "!begins" => Ok(FilterOperator::NotBeginsWith),
+            "ends" => Ok(FilterOperator::EndsWith),
+            "!ends" => Ok(FilterOperator::NotEndsWith),
+            _ => Err(OdsError::Parse(format!("Unknown table:operator {}", s))),
+        }
+    }
+}
+
+/// How the values of a filter condition are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum FilterDataType {
+    Text,
+    Number,
+}
+
+impl Display for FilterDataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterDataType::Text => write!(f, "text"),
+            FilterDataType::Number => write!(f, "number"),
+        }
+    }
+}
+
+impl TryFrom<&str> for FilterDataType {
+    type Error = OdsError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "text" => Ok(FilterDataType::Text),
+            "number" => Ok(FilterDataType::Number),
+            _ => Err(OdsError::Parse(format!("Unknown table:data-type {}", s))),
+        }
+    }
+}
+
+/// Sort descriptor for one column. Written as table:sort-by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortBy {
+    field: u32,
+    order: SortOrder,
+    data_type: SortDataType,
+}
+
+impl SortBy {
+    /// Sort by the field. The field is the column relative to the start
+    /// of the range.
+    pub fn new(field: u32, order: SortOrder) -> Self {
+        Self {
+            field,
+            order,
+            data_type: SortDataType::Automatic,
+        }
+    }
+
+    /// Column relative to the start of the range.
+    pub fn field(&self) -> u32 {
+        self.field
+    }
+
+    /// Column relative to the start of the range.
+    pub fn set_field(&mut self, field: u32) {
+        self.field = field;
+    }
+
+    /// Order.
+    pub fn order(&self) -> SortOrder {
+        self.order
+    }
+
+    /// Order.
+    pub fn set_order(&mut self, order: SortOrder) {
+        self.order = order;
+    }
+
+    /// Compare as text, as number or depending on the value.
+    pub fn data_type(&self) -> &SortDataType {
+        &self.data_type
+    }
+
+    /// Compare as text, as number or depending on the value.
+    pub fn set_data_type(&mut self, data_type: SortDataType) {
+        self.data_type = data_type;
+    }
+}
+
+/// Sort order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortOrder::Ascending => write!(f, "ascending"),
+            SortOrder::Descending => write!(f, "descending"),
+        }
+    }
+}
+
+impl TryFrom<&str> for SortOrder {
+    type Error = OdsError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "ascending" => Ok(SortOrder::Ascending),
+            "descending" => Ok(SortOrder::Descending),
+            _ => Err(OdsError::Parse(format!("Unknown table:order {}", s))),
+        }
+    }
+}
+
+/// How the values are compared for sorting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SortDataType {
+    Automatic,
+    Text,
+    Number,
+    /// Sorts by a user defined sort list. LibreOffice uses the names
+    /// UserList0, UserList1, ...
+    UserList(String),
+}
+
+impl Display for SortDataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortDataType::Automatic => write!(f, "automatic"),
+            SortDataType::Text => write!(f, "text"),
+            SortDataType::Number => write!(f, "number"),
+            SortDataType::UserList(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl From<&str> for SortDataType {
+    fn from(s: &str) -> Self {
+        match s {
+            "automatic" => SortDataType::Automatic,
+            "text" => SortDataType::Text,
+            "number" => SortDataType::Number,
+            _ => SortDataType::UserList(s.to_string()),
+        }
+    }
+}
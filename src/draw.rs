@@ -0,0 +1,137 @@
+//!
+//! Images embedded in a sheet.
+//!
+//! The image data is stored in the package via WorkBook::add_image().
+//! The returned Image can be anchored to a cell or to the page.
+//!
+//! ```
+//! use spreadsheet_ods::style::GraphicStyle;
+//! use spreadsheet_ods::{Length, Sheet, WorkBook};
+//!
+//! let mut wb = WorkBook::new_empty();
+//! let gs = wb.add_graphicstyle(GraphicStyle::new("logo"));
+//!
+//! let png = vec![0u8; 16]; // the png data
+//! let mut logo = wb.add_image("image/png", png);
+//! logo.set_name("Logo");
+//! logo.set_style(&gs);
+//! logo.set_position(Length::Mm(2.0), Length::Mm(2.0));
+//! logo.set_size(Length::Cm(4.0), Length::Cm(2.0));
+//!
+//! let mut sheet = Sheet::new("1");
+//! sheet.add_image(0, 0, logo);
+//! wb.push_sheet(sheet);
+//! ```
+//!
+
+use crate::attrmap2::AttrMap2;
+use crate::style::units::Length;
+use crate::style::GraphicStyleRef;
+
+/// An image in a draw:frame.
+///
+/// If the image is anchored to a cell the position is relative to the
+/// top-left corner of the cell, otherwise it's relative to the sheet.
+///
+/// Any other attributes of the frame are kept in the attrmap.
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    name: String,
+    style: Option<String>,
+    href: String,
+    attr: AttrMap2,
+}
+
+impl Image {
+    /// Image that refers to a file in the package. The usual way is to
+    /// use WorkBook::add_image() which stores the data too.
+    pub fn new<S: Into<String>>(href: S) -> Self {
+        Self {
+            name: Default::default(),
+            style: None,
+            href: href.into(),
+            attr: Default::default(),
+        }
+    }
+
+    /// Name of the frame.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name of the frame.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    /// Path of the image file in the package.
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+
+    /// Path of the image file in the package.
+    pub fn set_href<S: Into<String>>(&mut self, href: S) {
+        self.href = href.into();
+    }
+
+    /// Graphic style.
+    pub fn style(&self) -> Option<&String> {
+        self.style.as_ref()
+    }
+
+    /// Graphic style.
+    pub fn set_style(&mut self, style: &GraphicStyleRef) {
+        self.style = Some(style.to_string());
+    }
+
+    /// Removes the style.
+    pub fn clear_style(&mut self) {
+        self.style = None;
+    }
+
+    /// Position of the image.
+    pub fn set_position(&mut self, x: Length, y: Length) {
+        self.attr.set_attr("svg:x", x.to_string());
+        self.attr.set_attr("svg:y", y.to_string());
+    }
+
+    /// Position of the image.
+    pub fn position(&self) -> Option<(Length, Length)> {
+        Some((self.length("svg:x")?, self.length("svg:y")?))
+    }
+
+    /// Size of the image.
+    pub fn set_size(&mut self, width: Length, height: Length) {
+        self.attr.set_attr("svg:width", width.to_string());
+        self.attr.set_attr("svg:height", height.to_string());
+    }
+
+    /// Size of the image.
+    pub fn size(&self) -> Option<(Length, Length)> {
+        Some((self.length("svg:width")?, self.length("svg:height")?))
+    }
+
+    /// Z-Order.
+    pub fn set_z_index(&mut self, z_index: u32) {
+        self.attr.set_attr("draw:z-index", z_index.to_string());
+    }
+
+    /// Z-Order.
+    pub fn z_index(&self) -> Option<u32> {
+        self.attr.attr("draw:z-index").and_then(|v| v.parse().ok())
+    }
+
+    fn length(&self, name: &str) -> Option<Length> {
+        self.attr.attr(name).and_then(|v| v.parse().ok())
+    }
+
+    /// Other attributes of the draw:frame.
+    pub fn attrmap(&self) -> &AttrMap2 {
+        &self.attr
+    }
+
+    /// Other attributes of the draw:frame.
+    pub fn attrmap_mut(&mut self) -> &mut AttrMap2 {
+        &mut self.attr
+    }
+}
@@ -20,6 +20,7 @@ pub enum OdsError {
     Chrono(chrono::format::ParseError),
     SystemTime(std::time::SystemTimeError),
     Nom(nom::error::Error<String>),
+    Csv(csv::Error),
 }
 
 impl Display for OdsError {
@@ -38,6 +39,7 @@ impl Display for OdsError {
             OdsError::Utf8(e) => write!(f, "UTF8 {}", e)?,
             OdsError::Nom(e) => write!(f, "Nom {}", e)?,
             OdsError::Escape(s) => write!(f, "Escape {}", s)?,
+            OdsError::Csv(e) => write!(f, "Csv {}", e)?,
         }
 
         Ok(())
@@ -60,10 +62,25 @@ impl std::error::Error for OdsError {
             OdsError::Utf8(e) => Some(e),
             OdsError::Nom(e) => Some(e),
             OdsError::Escape(_) => None,
+            OdsError::Csv(e) => Some(e),
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::de::Error for OdsError {
+    fn custom<T: Display>(msg: T) -> Self {
+        OdsError::Ods(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for OdsError {
+    fn custom<T: Display>(msg: T) -> Self {
+        OdsError::Ods(msg.to_string())
+    }
+}
+
 impl From<std::io::Error> for OdsError {
     fn from(err: std::io::Error) -> OdsError {
         OdsError::Io(err)
@@ -112,6 +129,12 @@ impl From<std::time::SystemTimeError> for OdsError {
     }
 }
 
+impl From<csv::Error> for OdsError {
+    fn from(err: csv::Error) -> OdsError {
+        OdsError::Csv(err)
+    }
+}
+
 impl From<std::str::Utf8Error> for OdsError {
     fn from(err: std::str::Utf8Error) -> OdsError {
         OdsError::Utf8(err)
@@ -0,0 +1,408 @@
+//!
+//! Find and replace in the cells of a workbook, see
+//! [WorkBook::find](crate::WorkBook::find) and
+//! [WorkBook::replace_all](crate::WorkBook::replace_all).
+//!
+//! ```
+//! use spreadsheet_ods::find::{FindMode, FindQuery};
+//! use spreadsheet_ods::{Sheet, WorkBook};
+//!
+//! let mut wb = WorkBook::new_empty();
+//! let mut sh = Sheet::new("parts");
+//! sh.set_value(0, 0, "PX-100 bolt");
+//! sh.set_value(1, 0, "PX-200 nut");
+//! wb.push_sheet(sh);
+//!
+//! let query = FindQuery::new("px-1");
+//! assert_eq!(wb.find(&query).unwrap(), vec![(0, 0, 0)]);
+//!
+//! let query = FindQuery::new("PX-").mode(FindMode::Literal);
+//! wb.replace_all(&query, "QX-").unwrap();
+//! assert_eq!(wb.sheet(0).value(0, 0).as_str_or(""), "QX-100 bolt");
+//! ```
+//!
+//! Regular expressions need the feature `regex`, which is on by default.
+//!
+
+use crate::text::TextTag;
+use crate::xmltree::XmlContent;
+use crate::{CellData, OdsError, Value, WorkBook};
+#[cfg(feature = "regex")]
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+
+/// How the search text is matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindMode {
+    /// Finds the text anywhere in the cell.
+    Literal,
+    /// The search text is a regular expression. The replacement can
+    /// use the groups as $1, $name etc.
+    #[cfg(feature = "regex")]
+    Regex,
+    /// The text must match the whole content of the cell.
+    WholeCell,
+}
+
+/// What is searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchIn {
+    /// The values. Text is searched as it is, other values as they are
+    /// displayed.
+    Values,
+    /// The formulas. Cells without formula are searched like values.
+    /// The formula is used as it is stored, without the "of:" prefix.
+    /// References look like [.A1] there.
+    Formulas,
+    /// The text of the annotations.
+    Annotations,
+}
+
+/// A search.
+#[derive(Debug, Clone)]
+pub struct FindQuery {
+    text: String,
+    mode: FindMode,
+    search_in: SearchIn,
+    case_sensitive: bool,
+}
+
+impl FindQuery {
+    /// Literal search in the values. Ignores the case.
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self {
+            text: text.into(),
+            mode: FindMode::Literal,
+            search_in: SearchIn::Values,
+            case_sensitive: false,
+        }
+    }
+
+    /// How the text is matched.
+    #[must_use]
+    pub fn mode(mut self, mode: FindMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// What is searched.
+    #[must_use]
+    pub fn search_in(mut self, search_in: SearchIn) -> Self {
+        self.search_in = search_in;
+        self
+    }
+
+    /// Search case sensitive.
+    #[must_use]
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub(crate) fn matcher(&self) -> Result<Matcher, OdsError> {
+        let pattern = match self.mode {
+            FindMode::Literal => Pattern::Literal(self.text.clone()),
+            FindMode::WholeCell => Pattern::WholeCell(self.text.clone()),
+            #[cfg(feature = "regex")]
+            FindMode::Regex => Pattern::Regex(
+                RegexBuilder::new(&self.text)
+                    .case_insensitive(!self.case_sensitive)
+                    .build()
+                    .map_err(|e| OdsError::Parse(format!("Invalid regex {}: {}", self.text, e)))?,
+            ),
+        };
+        Ok(Matcher {
+            pattern,
+            case_sensitive: self.case_sensitive,
+        })
+    }
+}
+
+// Literal and whole cell searches work without the regex crate.
+enum Pattern {
+    Literal(String),
+    WholeCell(String),
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+/// Compiled FindQuery.
+pub(crate) struct Matcher {
+    pattern: Pattern,
+    case_sensitive: bool,
+}
+
+impl Matcher {
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        match &self.pattern {
+            Pattern::Literal(v) => (0..=text.len())
+                .any(|p| text.is_char_boundary(p) && self.match_at(v, text, p).is_some()),
+            Pattern::WholeCell(v) => self.match_at(v, text, 0) == Some(text.len()),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// Replaces all matches. None if nothing matched.
+    pub(crate) fn replace(&self, text: &str, replacement: &str) -> Option<String> {
+        match &self.pattern {
+            Pattern::Literal(v) => self.replace_literal(v, text, replacement),
+            Pattern::WholeCell(v) => {
+                if self.match_at(v, text, 0) == Some(text.len()) {
+                    Some(replacement.to_string())
+                } else {
+                    None
+                }
+            }
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => match regex.replace_all(text, replacement) {
+                Cow::Borrowed(_) => None,
+                Cow::Owned(v) => Some(v),
+            },
+        }
+    }
+
+    // Matches the literal at the byte position and returns the end of the
+    // match.
+    fn match_at(&self, literal: &str, text: &str, pos: usize) -> Option<usize> {
+        let mut end = pos;
+        let mut it = text[pos..].chars();
+        for c in literal.chars() {
+            let t = it.next()?;
+            if !(c == t || !self.case_sensitive && c.to_lowercase().eq(t.to_lowercase())) {
+                return None;
+            }
+            end += t.len_utf8();
+        }
+        Some(end)
+    }
+
+    fn replace_literal(&self, literal: &str, text: &str, replacement: &str) -> Option<String> {
+        let mut res = String::new();
+        let mut last = 0;
+        let mut pos = 0;
+        let mut found = false;
+        loop {
+            if let Some(end) = self.match_at(literal, text, pos) {
+                res.push_str(&text[last..pos]);
+                res.push_str(replacement);
+                last = end;
+                found = true;
+                if end > pos {
+                    pos = end;
+                    continue;
+                }
+            }
+            match text[pos..].chars().next() {
+                Some(c) => pos += c.len_utf8(),
+                None => break,
+            }
+        }
+        if found {
+            res.push_str(&text[last..]);
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    fn whole_cell(&self) -> bool {
+        matches!(self.pattern, Pattern::WholeCell(_))
+    }
+
+    /// Replaces in the text nodes of the paragraphs. The markup stays as
+    /// it is, but text that spans more than one node is not found.
+    ///
+    /// A whole cell match replaces the text of the first node and drops
+    /// the paragraphs after it.
+    pub(crate) fn replace_tags(&self, tags: &mut Vec<TextTag>, replacement: &str) -> bool {
+        let mut buf = String::new();
+        for (i, t) in tags.iter().enumerate() {
+            if i > 0 {
+                buf.push('\n');
+            }
+            t.extract_text(&mut buf);
+        }
+        if !self.is_match(&buf) {
+            return false;
+        }
+
+        let mut replaced = false;
+        for t in tags.iter_mut() {
+            replaced |= self.replace_nodes(t, replacement);
+        }
+        if !replaced && self.whole_cell() {
+            if let Some(replacement) = self.replace(&buf, replacement) {
+                let mut first = true;
+                for t in tags.iter_mut() {
+                    set_text(t, &replacement, &mut first);
+                }
+                // only the paragraph with the text is left.
+                if !tags.is_empty() {
+                    let keep = tags.iter().position(has_text).unwrap_or(0);
+                    let tag = tags.swap_remove(keep);
+                    *tags = vec![tag];
+                }
+                replaced = true;
+            }
+        }
+        replaced
+    }
+
+    fn replace_nodes(&self, tag: &mut TextTag, replacement: &str) -> bool {
+        let mut replaced = false;
+        for c in tag.content_mut() {
+            match c {
+                XmlContent::Text(t) => {
+                    if let Some(v) = self.replace(t, replacement) {
+                        *t = v;
+                        replaced = true;
+                    }
+                }
+                XmlContent::Tag(t) => {
+                    replaced |= self.replace_nodes(t, replacement);
+                }
+            }
+        }
+        replaced
+    }
+}
+
+/// See WorkBook::find.
+pub(crate) fn find(book: &WorkBook, query: &FindQuery) -> Result<Vec<(usize, u32, u32)>, OdsError> {
+    let matcher = query.matcher()?;
+
+    let mut res = Vec::new();
+    for (idx, sheet) in book.sheets.iter().enumerate() {
+        for ((row, col), cell) in sheet.data.iter() {
+            let text = match query.search_in {
+                SearchIn::Values => value_text(book, idx, *row, *col, cell),
+                SearchIn::Formulas => match &cell.formula {
+                    Some(formula) => Some(Cow::from(strip_prefix(formula).1)),
+                    None => value_text(book, idx, *row, *col, cell),
+                },
+                SearchIn::Annotations => cell
+                    .extra
+                    .as_ref()
+                    .and_then(|v| v.annotation.as_ref())
+                    .map(|v| Cow::from(v.text_str())),
+            };
+            if let Some(text) = text {
+                if matcher.is_match(&text) {
+                    res.push((idx, *row, *col));
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// See WorkBook::replace_all.
+pub(crate) fn replace_all(
+    book: &mut WorkBook,
+    query: &FindQuery,
+    replacement: &str,
+) -> Result<usize, OdsError> {
+    let matcher = query.matcher()?;
+
+    let mut count = 0;
+    for sheet in book.sheets.iter_mut() {
+        let sheet = &mut **sheet;
+        for ((row, col), cell) in sheet.data.iter_mut() {
+            let replaced = match query.search_in {
+                SearchIn::Values => replace_value(&matcher, &mut cell.value, replacement),
+                SearchIn::Formulas => match &cell.formula {
+                    Some(formula) => {
+                        let (prefix, formula) = strip_prefix(formula);
+                        match matcher.replace(formula, replacement) {
+                            Some(v) => {
+                                cell.formula = Some(format!("{}{}", prefix, v));
+                                true
+                            }
+                            None => false,
+                        }
+                    }
+                    None => replace_value(&matcher, &mut cell.value, replacement),
+                },
+                SearchIn::Annotations => {
+                    match cell.extra.as_mut().and_then(|v| v.annotation.as_mut()) {
+                        Some(annotation) => {
+                            matcher.replace_tags(annotation.text_mut(), replacement)
+                        }
+                        None => false,
+                    }
+                }
+            };
+            if replaced {
+                if query.search_in != SearchIn::Annotations && sheet.dirty_tracking {
+                    sheet.dirty.insert((*row, *col));
+                }
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+// Text as it is, everything else as displayed.
+fn value_text<'a>(
+    book: &WorkBook,
+    sheet: usize,
+    row: u32,
+    col: u32,
+    cell: &'a CellData,
+) -> Option<Cow<'a, str>> {
+    match &cell.value {
+        Value::Empty => None,
+        Value::Text(_) | Value::TextXml(_) => Some(cell.value.as_cow_str_or("")),
+        _ => Some(Cow::from(book.formatted_value(sheet, row, col))),
+    }
+}
+
+// Only text can be replaced.
+fn replace_value(matcher: &Matcher, value: &mut Value, replacement: &str) -> bool {
+    match value {
+        Value::Text(s) => match matcher.replace(s, replacement) {
+            Some(v) => {
+                *s = v;
+                true
+            }
+            None => false,
+        },
+        Value::TextXml(tags) => matcher.replace_tags(tags, replacement),
+        _ => false,
+    }
+}
+
+// Splits off the namespace prefix "of:".
+fn strip_prefix(formula: &str) -> (&str, &str) {
+    match formula.strip_prefix("of:") {
+        Some(v) => ("of:", v),
+        None => ("", formula),
+    }
+}
+
+// Contains at least one text node.
+fn has_text(tag: &TextTag) -> bool {
+    tag.content().iter().any(|c| match c {
+        XmlContent::Text(_) => true,
+        XmlContent::Tag(t) => has_text(t),
+    })
+}
+
+// The first text node gets the text, all others are cleared.
+fn set_text(tag: &mut TextTag, text: &str, first: &mut bool) {
+    for c in tag.content_mut() {
+        match c {
+            XmlContent::Text(t) => {
+                if *first {
+                    *t = text.to_string();
+                    *first = false;
+                } else {
+                    t.clear();
+                }
+            }
+            XmlContent::Tag(t) => set_text(t, text, first),
+        }
+    }
+}
@@ -0,0 +1,799 @@
+//!
+//! Conversion between ValueFormat and spreadsheet format codes like
+//! `#,##0.00;[RED]-#,##0.00` or `DD.MM.YYYY HH:MM`.
+//!
+
+use crate::condition::ValueCondition;
+use crate::format::{FormatPart, FormatPartType, ValueFormat};
+use crate::style::stylemap::StyleMap;
+use crate::{CellRef, OdsError, ValueType};
+use color::Rgb;
+
+const COLORS: [(&str, &str); 8] = [
+    ("BLACK", "#000000"),
+    ("BLUE", "#0000ff"),
+    ("CYAN", "#00ffff"),
+    ("GREEN", "#00ff00"),
+    ("MAGENTA", "#ff00ff"),
+    ("RED", "#ff0000"),
+    ("WHITE", "#ffffff"),
+    ("YELLOW", "#ffff00"),
+];
+
+// Default conditions if a format code has more than one section.
+const DEFAULT_CONDITIONS: [&[&str]; 4] = [&[], &[">=0"], &[">0", "<0"], &[">0", "<0", "=0"]];
+
+/// Splits the format code into sections. Quotes, escapes and brackets
+/// are respected.
+fn split_sections(code: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut start = 0;
+    let mut quote = false;
+    let mut bracket = false;
+    let mut escape = false;
+    for (i, c) in code.char_indices() {
+        if escape {
+            escape = false;
+            continue;
+        }
+        match c {
+            '\\' if !quote => escape = true,
+            '"' => quote = !quote,
+            '[' if !quote => bracket = true,
+            ']' if !quote => bracket = false,
+            ';' if !quote && !bracket => {
+                res.push(&code[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    res.push(&code[start..]);
+    res
+}
+
+/// Parses a format code. Returns one ValueFormat for each section, the
+/// last one is the main format that links to the others via stylemaps.
+/// The other sections are named after the main format with a suffix
+/// P0, P1, ...
+pub(crate) fn parse_format_code(name: &str, code: &str) -> Result<Vec<ValueFormat>, OdsError> {
+    let sections = split_sections(code);
+    if sections.len() > 4 {
+        return Err(OdsError::Parse(format!(
+            "Too many sections in format code {}",
+            code
+        )));
+    }
+
+    let mut parsed = Vec::new();
+    for section in &sections {
+        parsed.push(
+            parse_section(section)
+                .map_err(|e| OdsError::Parse(format!("{} in format code {}", e, code)))?,
+        );
+    }
+
+    let explicit = parsed.iter().any(|(cond, _)| cond.is_some());
+    let defaults = DEFAULT_CONDITIONS[parsed.len() - 1];
+    let last = parsed.len() - 1;
+
+    let mut res = Vec::new();
+    let mut stylemaps = Vec::new();
+    for (idx, (cond, mut format)) in parsed.into_iter().enumerate() {
+        if idx == last {
+            format.set_name(name);
+            for stylemap in stylemaps {
+                format.push_stylemap(stylemap);
+            }
+            res.push(format);
+            break;
+        }
+
+        let cond = if explicit {
+            match cond {
+                Some(cond) => cond,
+                // sections without condition are used as fallback.
+                None => continue,
+            }
+        } else {
+            defaults[idx].to_string()
+        };
+        let sub_name = format!("{}P{}", name, idx);
+        format.set_name(sub_name.as_str());
+        stylemaps.push(StyleMap::new(
+            ValueCondition::new(format!("value(){}", cond)),
+            sub_name,
+            CellRef::new(),
+        ));
+        res.push(format);
+    }
+
+    Ok(res)
+}
+
+// Collects the parts of one section.
+#[derive(Default)]
+struct Section {
+    parts: Vec<FormatPart>,
+    text: String,
+    is_date: bool,
+    is_time: bool,
+    is_bool: bool,
+    is_percent: bool,
+    is_currency: bool,
+    is_text: bool,
+    is_number: bool,
+    elapsed: bool,
+}
+
+impl Section {
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            let mut part = FormatPart::new(FormatPartType::Text);
+            part.set_content(std::mem::take(&mut self.text));
+            self.parts.push(part);
+        }
+    }
+
+    fn push(&mut self, part: FormatPart) {
+        self.flush();
+        match part.part_type() {
+            FormatPartType::Number
+            | FormatPartType::ScientificNumber
+            | FormatPartType::Fraction => self.is_number = true,
+            FormatPartType::Day
+            | FormatPartType::Month
+            | FormatPartType::Year
+            | FormatPartType::Era
+            | FormatPartType::DayOfWeek
+            | FormatPartType::WeekOfYear
+            | FormatPartType::Quarter => self.is_date = true,
+            FormatPartType::Hours
+            | FormatPartType::Minutes
+            | FormatPartType::Seconds
+            | FormatPartType::AmPm => self.is_time = true,
+            FormatPartType::Boolean => self.is_bool = true,
+            FormatPartType::CurrencySymbol => self.is_currency = true,
+            FormatPartType::TextContent => self.is_text = true,
+            FormatPartType::Text | FormatPartType::FillCharacter => {}
+        }
+        self.parts.push(part);
+    }
+
+    fn push_styled(&mut self, part_type: FormatPartType, long: bool) {
+        let mut part = FormatPart::new(part_type);
+        if long {
+            part.set_attr("number:style", "long".to_string());
+        }
+        self.push(part);
+    }
+
+    // Last date or time part.
+    fn last_datetime(&self) -> Option<FormatPartType> {
+        self.parts
+            .iter()
+            .rev()
+            .map(|v| v.part_type())
+            .find(|v| !matches!(v, FormatPartType::Text | FormatPartType::FillCharacter))
+    }
+
+    fn value_type(&self) -> ValueType {
+        if self.is_date {
+            ValueType::DateTime
+        } else if self.is_time {
+            ValueType::TimeDuration
+        } else if self.is_bool {
+            ValueType::Boolean
+        } else if self.is_percent {
+            ValueType::Percentage
+        } else if self.is_currency {
+            ValueType::Currency
+        } else if self.is_text && !self.is_number {
+            ValueType::Text
+        } else {
+            ValueType::Number
+        }
+    }
+}
+
+fn is_placeholder(c: char) -> bool {
+    matches!(c, '0' | '#' | '?')
+}
+
+fn placeholder_at(chars: &[char], idx: usize) -> bool {
+    matches!(chars.get(idx), Some(c) if is_placeholder(*c))
+}
+
+// Parses one section, returns the condition and the format.
+fn parse_section(code: &str) -> Result<(Option<String>, ValueFormat), String> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut section = Section::default();
+    let mut condition = None;
+    let mut color = None;
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let upper: String = chars[i..].iter().collect::<String>().to_uppercase();
+        match c {
+            '"' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|v| *v == '"')
+                    .ok_or_else(|| "Unterminated string".to_string())?;
+                section.text.extend(&chars[i + 1..i + 1 + end]);
+                i += end + 2;
+            }
+            '\\' => {
+                if let Some(c) = chars.get(i + 1) {
+                    section.text.push(*c);
+                }
+                i += 2;
+            }
+            '_' => {
+                section.text.push(' ');
+                i += 2;
+            }
+            '*' => {
+                if let Some(c) = chars.get(i + 1) {
+                    let mut part = FormatPart::new(FormatPartType::FillCharacter);
+                    part.set_content(c.to_string());
+                    section.push(part);
+                }
+                i += 2;
+            }
+            '[' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|v| *v == ']')
+                    .ok_or_else(|| "Missing ]".to_string())?;
+                let content: String = chars[i + 1..i + 1 + end].iter().collect();
+                i += end + 2;
+                parse_bracket(&content, &mut section, &mut condition, &mut color);
+            }
+            '@' => {
+                section.push(FormatPart::new(FormatPartType::TextContent));
+                i += 1;
+            }
+            '%' => {
+                section.is_percent = true;
+                section.text.push('%');
+                i += 1;
+            }
+            '.' if section.last_datetime() == Some(FormatPartType::Seconds)
+                && chars.get(i + 1) == Some(&'0') =>
+            {
+                let n = chars[i + 1..].iter().take_while(|v| **v == '0').count();
+                if let Some(part) = section.parts.last_mut() {
+                    part.set_attr("number:decimal-places", n.to_string());
+                }
+                i += n + 1;
+            }
+            c if is_placeholder(c) || (c == '.' && placeholder_at(&chars, i + 1)) => {
+                let len = number_pattern_len(&chars[i..]);
+                let pattern: String = chars[i..i + len].iter().collect();
+                section.push(parse_number_pattern(&pattern)?);
+                i += len;
+            }
+            _ if upper.starts_with("GENERAL") => {
+                let mut part = FormatPart::new(FormatPartType::Number);
+                part.set_attr("number:min-integer-digits", "1".to_string());
+                section.push(part);
+                i += 7;
+            }
+            _ if upper.starts_with("BOOLEAN") => {
+                section.push(FormatPart::new(FormatPartType::Boolean));
+                i += 7;
+            }
+            _ if upper.starts_with("AM/PM") => {
+                section.push(FormatPart::new(FormatPartType::AmPm));
+                i += 5;
+            }
+            _ if upper.starts_with("A/P") => {
+                section.push(FormatPart::new(FormatPartType::AmPm));
+                i += 3;
+            }
+            c if matches!(
+                c.to_ascii_uppercase(),
+                'Y' | 'M' | 'D' | 'N' | 'H' | 'S' | 'Q' | 'W' | 'G'
+            ) =>
+            {
+                let u = c.to_ascii_uppercase();
+                let n = chars[i..]
+                    .iter()
+                    .take_while(|v| v.to_ascii_uppercase() == u)
+                    .count();
+                i += n;
+                match u {
+                    'Y' => section.push_styled(FormatPartType::Year, n > 2),
+                    'M' => {
+                        let minutes = n <= 2
+                            && (section.last_datetime() == Some(FormatPartType::Hours)
+                                || next_letter(&chars[i..]) == Some('S'));
+                        if minutes {
+                            section.push_styled(FormatPartType::Minutes, n == 2);
+                        } else if n <= 2 {
+                            section.push_styled(FormatPartType::Month, n == 2);
+                        } else {
+                            let mut part = FormatPart::new(FormatPartType::Month);
+                            part.set_attr("number:textual", "true".to_string());
+                            if n == 4 {
+                                part.set_attr("number:style", "long".to_string());
+                            }
+                            section.push(part);
+                        }
+                    }
+                    'D' => match n {
+                        1 | 2 => section.push_styled(FormatPartType::Day, n == 2),
+                        _ => section.push_styled(FormatPartType::DayOfWeek, n > 3),
+                    },
+                    'N' => section.push_styled(FormatPartType::DayOfWeek, n > 2),
+                    'H' => section.push_styled(FormatPartType::Hours, n > 1),
+                    'S' => section.push_styled(FormatPartType::Seconds, n > 1),
+                    'Q' => section.push_styled(FormatPartType::Quarter, n > 1),
+                    'W' => section.push(FormatPart::new(FormatPartType::WeekOfYear)),
+                    'G' => section.push_styled(FormatPartType::Era, n > 2),
+                    _ => unreachable!(),
+                }
+            }
+            c => {
+                section.text.push(c);
+                i += 1;
+            }
+        }
+    }
+    section.flush();
+
+    let mut format = ValueFormat::new_named("", section.value_type());
+    if section.elapsed {
+        format.set_truncate_on_overflow(false);
+    }
+    if let Some(color) = color {
+        format.set_color(color);
+    }
+    for part in section.parts {
+        format.push_part(part);
+    }
+    Ok((condition, format))
+}
+
+// First letter of the remaining code, skipping quoted text.
+fn next_letter(chars: &[char]) -> Option<char> {
+    let mut quote = false;
+    for c in chars {
+        if *c == '"' {
+            quote = !quote;
+        } else if !quote && c.is_alphabetic() {
+            return Some(c.to_ascii_uppercase());
+        }
+    }
+    None
+}
+
+fn parse_bracket(
+    content: &str,
+    section: &mut Section,
+    condition: &mut Option<String>,
+    color: &mut Option<Rgb<u8>>,
+) {
+    let upper = content.to_uppercase();
+    if let Some(currency) = content.strip_prefix('$') {
+        // [$€-407], the part after the - is the locale.
+        let symbol = match currency.rfind('-') {
+            Some(idx) => &currency[..idx],
+            None => currency,
+        };
+        if !symbol.is_empty() {
+            let mut part = FormatPart::new(FormatPartType::CurrencySymbol);
+            part.set_content(symbol);
+            section.push(part);
+        }
+    } else if content.starts_with(['<', '>', '=']) {
+        let cond: String = content.chars().filter(|v| !v.is_whitespace()).collect();
+        let cond = match cond.strip_prefix("<>") {
+            Some(rhs) => format!("!={}", rhs),
+            None => cond,
+        };
+        *condition = Some(cond);
+    } else if let Some((_, rgb)) = COLORS.iter().find(|(name, _)| *name == upper) {
+        *color = Some(Rgb::new(
+            u8::from_str_radix(&rgb[1..3], 16).unwrap_or_default(),
+            u8::from_str_radix(&rgb[3..5], 16).unwrap_or_default(),
+            u8::from_str_radix(&rgb[5..7], 16).unwrap_or_default(),
+        ));
+    } else if !upper.is_empty() && upper.chars().all(|v| matches!(v, 'H' | 'M' | 'S')) {
+        // elapsed time
+        section.elapsed = true;
+        let long = upper.len() > 1;
+        match upper.chars().next() {
+            Some('H') => section.push_styled(FormatPartType::Hours, long),
+            Some('M') => section.push_styled(FormatPartType::Minutes, long),
+            _ => section.push_styled(FormatPartType::Seconds, long),
+        }
+    }
+    // everything else like [NatNum1] or [~buddhist] is ignored.
+}
+
+// Length of a number pattern like #,##0.00 or # ?/? or 0.00E+00.
+fn number_pattern_len(chars: &[char]) -> usize {
+    let mut i = 0;
+    let mut fraction = false;
+    while i < chars.len() {
+        let c = chars[i];
+        if is_placeholder(c) || c == ',' || (c == '.' && placeholder_at(chars, i + 1)) {
+            i += 1;
+        } else if (c == 'E' || c == 'e')
+            && matches!(chars.get(i + 1), Some('+') | Some('-'))
+            && placeholder_at(chars, i + 2)
+        {
+            i += 2;
+        } else if c == '/' && !fraction {
+            fraction = true;
+            i += 1;
+        } else if fraction && c.is_ascii_digit() {
+            i += 1;
+        } else if c == ' ' && !fraction {
+            // the integer part of a fraction.
+            let rest = &chars[i..];
+            let n = rest.iter().take_while(|v| **v == ' ').count();
+            let m = rest[n..].iter().take_while(|v| is_placeholder(**v)).count();
+            if m > 0 && rest.get(n + m) == Some(&'/') {
+                i += n;
+            } else {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn count(s: &str, f: fn(char) -> bool) -> usize {
+    s.chars().filter(|v| f(*v)).count()
+}
+
+fn parse_number_pattern(pattern: &str) -> Result<FormatPart, String> {
+    if let Some(idx) = pattern.find(['E', 'e']) {
+        let (mantissa, exponent) = (&pattern[..idx], &pattern[idx + 1..]);
+        let (int, dec) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let mut part = FormatPart::new(FormatPartType::ScientificNumber);
+        part.set_attr(
+            "number:decimal-places",
+            count(dec, is_placeholder).to_string(),
+        );
+        part.set_attr(
+            "number:min-decimal-places",
+            count(dec, |v| v == '0').to_string(),
+        );
+        part.set_attr(
+            "number:min-integer-digits",
+            count(int, |v| v == '0').max(1).to_string(),
+        );
+        part.set_attr(
+            "number:min-exponent-digits",
+            count(exponent, is_placeholder).to_string(),
+        );
+        part.set_attr(
+            "number:forced-exponent-sign",
+            exponent.starts_with('+').to_string(),
+        );
+        return Ok(part);
+    }
+
+    if let Some((num, den)) = pattern.split_once('/') {
+        let mut part = FormatPart::new(FormatPartType::Fraction);
+        let num = num.trim_end();
+        let (int, num) = match num.rfind(' ') {
+            Some(idx) => (Some(num[..idx].trim_end()), &num[idx + 1..]),
+            None => (None, num),
+        };
+        if let Some(int) = int {
+            part.set_attr(
+                "number:min-integer-digits",
+                count(int, |v| v == '0').to_string(),
+            );
+            if int.contains(',') {
+                part.set_attr("number:grouping", "true".to_string());
+            }
+        }
+        part.set_attr(
+            "number:min-numerator-digits",
+            count(num, is_placeholder).to_string(),
+        );
+        let den = den.trim();
+        if den.chars().all(|v| v.is_ascii_digit()) && den.chars().any(|v| v != '0') {
+            let den: u32 = den
+                .parse()
+                .map_err(|_| format!("Invalid denominator {}", den))?;
+            part.set_attr("number:denominator-value", den.to_string());
+            part.set_attr(
+                "number:min-denominator-digits",
+                den.to_string().len().to_string(),
+            );
+        } else {
+            let n = count(den, is_placeholder);
+            part.set_attr("number:min-denominator-digits", n.to_string());
+            part.set_attr(
+                "number:max-denominator-value",
+                (10u32.pow(n.min(9) as u32) - 1).to_string(),
+            );
+        }
+        return Ok(part);
+    }
+
+    // trailing commas divide by 1000.
+    let trimmed = pattern.trim_end_matches(',');
+    let factor = pattern.len() - trimmed.len();
+    let (int, dec) = trimmed.split_once('.').unwrap_or((trimmed, ""));
+
+    let mut part = FormatPart::new(FormatPartType::Number);
+    part.set_attr(
+        "number:decimal-places",
+        count(dec, is_placeholder).to_string(),
+    );
+    part.set_attr(
+        "number:min-decimal-places",
+        count(dec, |v| v == '0').to_string(),
+    );
+    part.set_attr(
+        "number:min-integer-digits",
+        count(int, |v| v == '0').to_string(),
+    );
+    if int.contains(',') {
+        part.set_attr("number:grouping", "true".to_string());
+    }
+    if factor > 0 {
+        part.set_attr(
+            "number:display-factor",
+            10f64.powi(3 * factor as i32).to_string(),
+        );
+    }
+    Ok(part)
+}
+
+/// Creates the format code for the parts of the format. Stylemaps are not
+/// included.
+pub(crate) fn section_code(format: &ValueFormat) -> String {
+    let mut buf = String::new();
+
+    if let Some(color) = format.textstyle().attr("fo:color") {
+        if let Some((name, _)) = COLORS.iter().find(|(_, rgb)| rgb == color) {
+            buf.push('[');
+            buf.push_str(name);
+            buf.push(']');
+        }
+    }
+
+    let is_numeric = format.parts().iter().any(|v| {
+        matches!(
+            v.part_type(),
+            FormatPartType::Number | FormatPartType::ScientificNumber | FormatPartType::Fraction
+        )
+    });
+    let mut elapsed =
+        format.attrmap().attr("number:truncate-on-overflow") == Some(&"false".to_string());
+
+    for part in format.parts() {
+        let long = part.attr_def("number:style", "") == "long";
+        let attr = |name: &str| -> Option<usize> {
+            part.attrmap().attr(name).and_then(|v| v.parse().ok())
+        };
+        match part.part_type() {
+            FormatPartType::Number => match attr("number:decimal-places") {
+                None => buf.push_str("General"),
+                Some(decimals) => {
+                    let grouping = part.attr_def("number:grouping", "") == "true";
+                    push_integer(
+                        &mut buf,
+                        attr("number:min-integer-digits").unwrap_or(1),
+                        grouping,
+                    );
+                    let min_dec = attr("number:min-decimal-places").unwrap_or(decimals);
+                    push_decimals(&mut buf, decimals, min_dec);
+                    if let Some(factor) = part
+                        .attrmap()
+                        .attr("number:display-factor")
+                        .and_then(|v| v.parse::<f64>().ok())
+                    {
+                        let mut factor = factor;
+                        while factor >= 1000.0 {
+                            buf.push(',');
+                            factor /= 1000.0;
+                        }
+                    }
+                }
+            },
+            FormatPartType::ScientificNumber => {
+                let decimals = attr("number:decimal-places").unwrap_or(0);
+                push_integer(
+                    &mut buf,
+                    attr("number:min-integer-digits").unwrap_or(1).max(1),
+                    false,
+                );
+                push_decimals(
+                    &mut buf,
+                    decimals,
+                    attr("number:min-decimal-places").unwrap_or(decimals),
+                );
+                if part.attr_def("number:forced-exponent-sign", "true") == "true" {
+                    buf.push_str("E+");
+                } else {
+                    buf.push_str("E-");
+                }
+                let n = attr("number:min-exponent-digits").unwrap_or(2).max(1);
+                buf.push_str(&"0".repeat(n));
+            }
+            FormatPartType::Fraction => {
+                if let Some(n) = attr("number:min-integer-digits") {
+                    let grouping = part.attr_def("number:grouping", "") == "true";
+                    push_integer(&mut buf, n, grouping);
+                    buf.push(' ');
+                }
+                let n = attr("number:min-numerator-digits").unwrap_or(1).max(1);
+                buf.push_str(&"?".repeat(n));
+                buf.push('/');
+                match attr("number:denominator-value") {
+                    Some(den) => buf.push_str(&den.to_string()),
+                    None => {
+                        let n = attr("number:min-denominator-digits").unwrap_or(1).max(1);
+                        buf.push_str(&"?".repeat(n));
+                    }
+                }
+            }
+            FormatPartType::CurrencySymbol => {
+                buf.push_str("[$");
+                if let Some(symbol) = part.content() {
+                    buf.push_str(symbol);
+                }
+                buf.push(']');
+            }
+            FormatPartType::FillCharacter => {
+                buf.push('*');
+                if let Some(c) = part.content() {
+                    buf.push_str(c);
+                }
+            }
+            FormatPartType::Day => buf.push_str(if long { "DD" } else { "D" }),
+            FormatPartType::Month => {
+                let textual = part.attr_def("number:textual", "") == "true";
+                buf.push_str(match (textual, long) {
+                    (true, true) => "MMMM",
+                    (true, false) => "MMM",
+                    (false, true) => "MM",
+                    (false, false) => "M",
+                });
+            }
+            FormatPartType::Year => buf.push_str(if long { "YYYY" } else { "YY" }),
+            FormatPartType::Era => buf.push_str(if long { "GGG" } else { "G" }),
+            FormatPartType::DayOfWeek => buf.push_str(if long { "DDDD" } else { "DDD" }),
+            FormatPartType::WeekOfYear => buf.push_str("WW"),
+            FormatPartType::Quarter => buf.push_str(if long { "QQ" } else { "Q" }),
+            FormatPartType::Hours | FormatPartType::Minutes | FormatPartType::Seconds => {
+                let c = match part.part_type() {
+                    FormatPartType::Hours => "H",
+                    FormatPartType::Minutes => "M",
+                    _ => "S",
+                };
+                let code = c.repeat(if long { 2 } else { 1 });
+                if elapsed {
+                    buf.push('[');
+                    buf.push_str(&code);
+                    buf.push(']');
+                    elapsed = false;
+                } else {
+                    buf.push_str(&code);
+                }
+                if part.part_type() == FormatPartType::Seconds {
+                    let decimals = attr("number:decimal-places").unwrap_or(0);
+                    if decimals > 0 {
+                        buf.push('.');
+                        buf.push_str(&"0".repeat(decimals));
+                    }
+                }
+            }
+            FormatPartType::AmPm => buf.push_str("AM/PM"),
+            FormatPartType::Boolean => buf.push_str("BOOLEAN"),
+            FormatPartType::Text => {
+                if let Some(text) = part.content() {
+                    push_text(&mut buf, text, is_numeric, format.value_type());
+                }
+            }
+            FormatPartType::TextContent => buf.push('@'),
+        }
+    }
+
+    buf
+}
+
+fn push_integer(buf: &mut String, min_int: usize, grouping: bool) {
+    if grouping {
+        let len = min_int.max(4);
+        let mut digits = String::new();
+        for i in (0..len).rev() {
+            digits.push(if i < min_int { '0' } else { '#' });
+            if i > 0 && i % 3 == 0 {
+                digits.push(',');
+            }
+        }
+        buf.push_str(&digits);
+    } else if min_int == 0 {
+        buf.push('#');
+    } else {
+        buf.push_str(&"0".repeat(min_int));
+    }
+}
+
+fn push_decimals(buf: &mut String, decimals: usize, min_dec: usize) {
+    if decimals > 0 {
+        buf.push('.');
+        buf.push_str(&"0".repeat(min_dec.min(decimals)));
+        buf.push_str(&"#".repeat(decimals.saturating_sub(min_dec)));
+    }
+}
+
+fn push_text(buf: &mut String, text: &str, is_numeric: bool, value_type: ValueType) {
+    if text == "%" && value_type == ValueType::Percentage {
+        buf.push('%');
+        return;
+    }
+    let plain = |v: char| match v {
+        ' ' | '-' | '/' | ':' | '(' | ')' => true,
+        '.' | ',' => !is_numeric,
+        _ => false,
+    };
+    // only the part between the first and the last special char is quoted.
+    let start = text.find(|v| !plain(v)).unwrap_or(text.len());
+    let end = text.rfind(|v| !plain(v)).map_or(start, |v| {
+        v + text[v..].chars().next().map_or(0, char::len_utf8)
+    });
+    buf.push_str(&text[..start]);
+    for (i, t) in text[start..end].split('"').enumerate() {
+        if i > 0 {
+            buf.push_str("\\\"");
+        }
+        if !t.is_empty() {
+            buf.push('"');
+            buf.push_str(t);
+            buf.push('"');
+        }
+    }
+    buf.push_str(&text[end..]);
+}
+
+/// Creates the full format code for the format including the sections
+/// referenced by the stylemaps.
+pub(crate) fn format_code<'a, F>(format: &ValueFormat, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<&'a ValueFormat>,
+{
+    let mut sections = Vec::new();
+    if let Some(stylemaps) = format.stylemaps() {
+        for stylemap in stylemaps {
+            if let Some(mapped) = lookup(stylemap.applied_style()) {
+                let cond = stylemap.condition();
+                let cond = cond
+                    .strip_prefix("value()")
+                    .unwrap_or(cond)
+                    .replace("!=", "<>");
+                sections.push((cond, section_code(mapped)));
+            }
+        }
+    }
+
+    let conditions: Vec<&str> = sections.iter().map(|(v, _)| v.as_str()).collect();
+    let is_default = sections.len() < 4 && DEFAULT_CONDITIONS[sections.len()] == conditions;
+
+    let mut buf = String::new();
+    for (cond, code) in &sections {
+        if !is_default {
+            buf.push('[');
+            buf.push_str(cond);
+            buf.push(']');
+        }
+        buf.push_str(code);
+        buf.push(';');
+    }
+    buf.push_str(&section_code(format));
+    buf
+}
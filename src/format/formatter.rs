@@ -0,0 +1,638 @@
+//!
+//! Formats values to their display text using a ValueFormat.
+//!
+
+use crate::format::{FormatPart, FormatPartType, ValueFormat};
+use crate::{Value, ValueType};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use icu_locid::Locale;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Day zero for the numeric representation of dates.
+pub(crate) fn base_date() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1899, 12, 30)
+        .and_then(|v| v.and_hms_opt(0, 0, 0))
+        .expect("valid date")
+}
+
+/// Number as shown with the "General" format. Uses up to 15 significant
+/// digits.
+pub(crate) fn format_general(v: f64) -> String {
+    if v == 0.0 {
+        return "0".to_string();
+    }
+    let mag = v.abs().log10().floor() as i32;
+    if !(-5..15).contains(&mag) {
+        return format!("{:E}", v);
+    }
+    let decimals = (14 - mag).max(0) as usize;
+    let s = format!("{:.*}", decimals, v);
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    }
+}
+
+/// Display text for a value without a ValueFormat.
+pub(crate) fn format_default(value: &Value) -> String {
+    match value {
+        Value::Empty => String::new(),
+        Value::Boolean(b) => format_bool(*b),
+        Value::Number(v) => format_general(*v),
+        Value::Percentage(v) => format!("{}%", format_general(*v * 100.0)),
+        Value::Currency(v, c) => {
+            format!("{} {}", String::from_utf8_lossy(c), format_general(*v))
+        }
+        Value::Text(s) => s.clone(),
+        Value::TextXml(t) => {
+            let mut buf = String::new();
+            for (i, tag) in t.iter().enumerate() {
+                if i > 0 {
+                    buf.push('\n');
+                }
+                tag.extract_text(&mut buf);
+            }
+            buf
+        }
+        Value::DateTime(d) => {
+            if d.time().num_seconds_from_midnight() == 0 && d.time().nanosecond() == 0 {
+                d.format("%Y-%m-%d").to_string()
+            } else {
+                d.format("%Y-%m-%d %H:%M:%S").to_string()
+            }
+        }
+        Value::TimeDuration(d) => {
+            let secs = d.num_seconds();
+            format!(
+                "{}{:02}:{:02}:{:02}",
+                if secs < 0 { "-" } else { "" },
+                secs.abs() / 3600,
+                (secs.abs() / 60) % 60,
+                secs.abs() % 60
+            )
+        }
+    }
+}
+
+fn format_bool(b: bool) -> String {
+    if b { "TRUE" } else { "FALSE" }.to_string()
+}
+
+/// Numeric value used for the conditions of a stylemap.
+pub(crate) fn numeric_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(v) | Value::Percentage(v) | Value::Currency(v, _) => Some(*v),
+        Value::DateTime(d) => Some(serial(d)),
+        Value::TimeDuration(d) => Some(duration_days(d)),
+        _ => None,
+    }
+}
+
+fn serial(d: &NaiveDateTime) -> f64 {
+    let ms = d.signed_duration_since(base_date()).num_milliseconds();
+    ms as f64 / 86_400_000.0
+}
+
+fn duration_days(d: &Duration) -> f64 {
+    d.num_milliseconds() as f64 / 86_400_000.0
+}
+
+/// Evaluates the condition of a stylemap as found in a value format,
+/// e.g. "value()>=0".
+pub(crate) fn condition_matches(condition: &str, v: f64) -> bool {
+    let cond = condition.trim();
+    let cond = cond
+        .strip_prefix("value()")
+        .or_else(|| cond.strip_prefix("cell-content()"))
+        .unwrap_or(cond)
+        .trim_start();
+    type Test = fn(f64, f64) -> bool;
+    let ops: [(&str, Test); 7] = [
+        (">=", |a, b| a >= b),
+        ("<=", |a, b| a <= b),
+        ("!=", |a, b| a != b),
+        ("<>", |a, b| a != b),
+        ("=", |a, b| a == b),
+        ("<", |a, b| a < b),
+        (">", |a, b| a > b),
+    ];
+    for (op, test) in ops {
+        if let Some(rhs) = cond.strip_prefix(op) {
+            return match rhs.trim().parse::<f64>() {
+                Ok(rhs) => test(v, rhs),
+                Err(_) => false,
+            };
+        }
+    }
+    false
+}
+
+// Sub-formats for negative values contain their own sign.
+fn is_negative_condition(condition: &str) -> bool {
+    condition.contains('<') && !condition.contains("<>")
+}
+
+/// Formats the value with the format. The stylemaps of the format are
+/// resolved with the lookup function.
+pub(crate) fn format_mapped<'a, F>(format: &ValueFormat, value: &Value, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<&'a ValueFormat>,
+{
+    if let (Some(stylemaps), Some(v)) = (format.stylemaps(), numeric_value(value)) {
+        if !stylemaps.is_empty() {
+            for stylemap in stylemaps {
+                if condition_matches(stylemap.condition(), v) {
+                    if let Some(mapped) = lookup(stylemap.applied_style()) {
+                        return format_parts(
+                            mapped,
+                            value,
+                            !is_negative_condition(stylemap.condition()),
+                        );
+                    }
+                }
+            }
+            // The remaining section of a multi-section format.
+            return format_parts(format, value, false);
+        }
+    }
+    format_parts(format, value, true)
+}
+
+/// Formats the value with the parts of the format.
+pub(crate) fn format_value(format: &ValueFormat, value: &Value) -> String {
+    format_parts(format, value, true)
+}
+
+fn has_part(format: &ValueFormat, part_type: FormatPartType) -> bool {
+    format.parts.iter().any(|v| v.part_type == part_type)
+}
+
+fn attr_u32(part: &FormatPart, name: &str) -> Option<u32> {
+    part.attr.attr(name).and_then(|v| v.parse().ok())
+}
+
+fn attr_bool(part: &FormatPart, name: &str) -> Option<bool> {
+    part.attr.attr(name).and_then(|v| v.parse().ok())
+}
+
+fn is_long(part: &FormatPart) -> bool {
+    part.attr.attr("number:style").map(|v| v.as_str()) == Some("long")
+}
+
+// Decimal and grouping separator.
+fn separators(format: &ValueFormat) -> (char, char) {
+    locale_separators(format.locale().as_ref())
+}
+
+/// Decimal and grouping separator for a locale.
+pub(crate) fn locale_separators(locale: Option<&Locale>) -> (char, char) {
+    let locale = match locale {
+        Some(locale) => locale,
+        None => return ('.', ','),
+    };
+    let language = locale.id.language.to_string();
+    let region = locale.id.region.map(|v| v.to_string());
+    match (language.as_str(), region.as_deref()) {
+        ("de" | "it", Some("CH")) => ('.', '\''),
+        ("de" | "it" | "es" | "nl" | "pt" | "da" | "id" | "tr" | "el", _) => (',', '.'),
+        ("fr" | "ru" | "pl" | "cs" | "sk" | "fi" | "sv" | "nb" | "no" | "uk" | "hu", _) => {
+            (',', '\u{a0}')
+        }
+        _ => ('.', ','),
+    }
+}
+
+// Inserts the grouping separator.
+fn group(int: &str, sep: char) -> String {
+    let digits: Vec<char> = int.chars().rev().collect();
+    let mut groups: Vec<String> = digits.chunks(3).map(|v| v.iter().rev().collect()).collect();
+    groups.reverse();
+    groups.join(&sep.to_string())
+}
+
+// Pads the integer part and trims the decimals.
+fn push_digits(
+    buf: &mut String,
+    s: &str,
+    min_int: usize,
+    min_dec: usize,
+    grouping: bool,
+    seps: (char, char),
+) {
+    let (int, dec) = match s.split_once('.') {
+        Some((int, dec)) => (int, dec),
+        None => (s, ""),
+    };
+    let mut int = int.trim_start_matches('0').to_string();
+    while int.len() < min_int {
+        int.insert(0, '0');
+    }
+    let mut dec = dec.to_string();
+    while dec.len() > min_dec && dec.ends_with('0') {
+        dec.pop();
+    }
+
+    if grouping {
+        buf.push_str(&group(&int, seps.1));
+    } else {
+        buf.push_str(&int);
+    }
+    if !dec.is_empty() {
+        buf.push(seps.0);
+        buf.push_str(&dec);
+    }
+}
+
+fn push_number(buf: &mut String, part: &FormatPart, v: f64, seps: (char, char)) {
+    let v = match part.attr.attr("number:display-factor") {
+        Some(factor) => match factor.parse::<f64>() {
+            Ok(factor) if factor != 0.0 => v / factor,
+            _ => v,
+        },
+        None => v,
+    };
+    let min_int = attr_u32(part, "number:min-integer-digits").unwrap_or(1) as usize;
+    let grouping = attr_bool(part, "number:grouping").unwrap_or(false);
+
+    let decimals = match attr_u32(part, "number:decimal-places") {
+        Some(decimals) => decimals as usize,
+        None => {
+            let s = format_general(v);
+            if s.contains('E') {
+                buf.push_str(&s);
+            } else {
+                push_digits(buf, &s, min_int, 0, grouping, seps);
+            }
+            return;
+        }
+    };
+    let min_dec = attr_u32(part, "number:min-decimal-places")
+        .map(|v| v as usize)
+        .unwrap_or(decimals);
+
+    let s = format!("{:.*}", decimals, v);
+    if let Some(replacement) = part.attr.attr("number:decimal-replacement") {
+        if let Some((int, dec)) = s.split_once('.') {
+            if dec.chars().all(|c| c == '0') {
+                push_digits(buf, int, min_int, 0, grouping, seps);
+                buf.push(seps.0);
+                buf.push_str(replacement);
+                return;
+            }
+        }
+    }
+    push_digits(buf, &s, min_int, min_dec, grouping, seps);
+}
+
+fn push_scientific(buf: &mut String, part: &FormatPart, v: f64, seps: (char, char)) {
+    let decimals = attr_u32(part, "number:decimal-places").unwrap_or(0) as usize;
+    let min_dec = attr_u32(part, "number:min-decimal-places")
+        .map(|v| v as usize)
+        .unwrap_or(decimals);
+    let min_int = attr_u32(part, "number:min-integer-digits")
+        .unwrap_or(1)
+        .max(1) as i32;
+    let interval = attr_u32(part, "number:exponent-interval")
+        .unwrap_or(1)
+        .max(1) as i32;
+    let min_exp = attr_u32(part, "number:min-exponent-digits").unwrap_or(2) as usize;
+    let forced_sign = attr_bool(part, "number:forced-exponent-sign").unwrap_or(true);
+
+    let mut exp = if v == 0.0 {
+        0
+    } else {
+        v.log10().floor() as i32 - (min_int - 1)
+    };
+    if interval > 1 {
+        exp -= exp.rem_euclid(interval);
+    }
+    let mut mantissa = format!("{:.*}", decimals, v / 10f64.powi(exp));
+    // rounding can overflow the integer digits.
+    if v != 0.0 {
+        let limit = 10f64.powi(min_int.max(interval));
+        if mantissa.parse::<f64>().unwrap_or(0.0) >= limit {
+            exp += if interval > 1 { interval } else { 1 };
+            mantissa = format!("{:.*}", decimals, v / 10f64.powi(exp));
+        }
+    }
+
+    push_digits(buf, &mantissa, min_int as usize, min_dec, false, seps);
+    buf.push('E');
+    if exp < 0 {
+        buf.push('-');
+    } else if forced_sign {
+        buf.push('+');
+    }
+    buf.push_str(&format!("{:0w$}", exp.abs(), w = min_exp));
+}
+
+fn push_fraction(buf: &mut String, part: &FormatPart, v: f64, seps: (char, char)) {
+    let mixed = part.attr.attr("number:min-integer-digits").is_some();
+    let min_int = attr_u32(part, "number:min-integer-digits").unwrap_or(0) as usize;
+    let min_num = attr_u32(part, "number:min-numerator-digits").unwrap_or(1) as usize;
+    let min_den = attr_u32(part, "number:min-denominator-digits").unwrap_or(1) as usize;
+    let grouping = attr_bool(part, "number:grouping").unwrap_or(false);
+
+    let (mut int, frac) = if mixed {
+        (v.trunc(), v.fract())
+    } else {
+        (0.0, v)
+    };
+
+    let (mut num, den) = match attr_u32(part, "number:denominator-value") {
+        Some(den) if den > 0 => ((frac * den as f64).round(), den as f64),
+        _ => {
+            let max_den = match attr_u32(part, "number:max-denominator-value") {
+                Some(max_den) if max_den > 0 => max_den,
+                _ => 10u32.saturating_pow(min_den.min(5) as u32) - 1,
+            };
+            let mut best = (frac.round(), 1.0, (frac - frac.round()).abs());
+            for den in 2..=max_den.max(1) {
+                let den = den as f64;
+                let num = (frac * den).round();
+                let err = (frac - num / den).abs();
+                if err < best.2 - 1e-12 {
+                    best = (num, den, err);
+                }
+            }
+            (best.0, best.1)
+        }
+    };
+    if mixed && num >= den {
+        int += 1.0;
+        num -= den;
+    }
+
+    if mixed && (int != 0.0 || num == 0.0 || min_int > 0) {
+        push_digits(
+            buf,
+            &format!("{:.0}", int),
+            min_int.max(1),
+            0,
+            grouping,
+            seps,
+        );
+        if num == 0.0 {
+            return;
+        }
+        buf.push(' ');
+    } else if num == 0.0 {
+        buf.push('0');
+        return;
+    }
+    buf.push_str(&format!("{:>w$}", format!("{:.0}", num), w = min_num));
+    buf.push('/');
+    buf.push_str(&format!("{:<w$}", format!("{:.0}", den), w = min_den));
+}
+
+// Fraction digits of the seconds.
+fn second_decimals(format: &ValueFormat) -> u32 {
+    format
+        .parts
+        .iter()
+        .filter(|v| v.part_type == FormatPartType::Seconds)
+        .filter_map(|v| attr_u32(v, "number:decimal-places"))
+        .max()
+        .unwrap_or(0)
+}
+
+fn ordinal(n: u32) -> &'static str {
+    match n {
+        1 => "1st",
+        2 => "2nd",
+        3 => "3rd",
+        _ => "4th",
+    }
+}
+
+fn format_parts(format: &ValueFormat, value: &Value, sign: bool) -> String {
+    // Text values are only formatted by text formats.
+    let plain = match value {
+        Value::Empty => true,
+        Value::Text(_) | Value::TextXml(_) => !has_part(format, FormatPartType::TextContent),
+        Value::Boolean(_) => !has_part(format, FormatPartType::Boolean),
+        _ => false,
+    };
+    if plain {
+        return format_default(value);
+    }
+
+    let seps = separators(format);
+
+    let mut number = numeric_value(value).unwrap_or(match value {
+        Value::Boolean(true) => 1.0,
+        _ => 0.0,
+    });
+    if format.value_type() == ValueType::Percentage {
+        number *= 100.0;
+    }
+
+    let datetime = match value {
+        Value::DateTime(d) => Some(*d),
+        Value::TimeDuration(d) => base_date().checked_add_signed(*d),
+        _ => {
+            let ms = (number * 86_400_000.0).round();
+            if ms.abs() < 1e15 {
+                base_date().checked_add_signed(Duration::milliseconds(ms as i64))
+            } else {
+                None
+            }
+        }
+    };
+
+    // Elapsed time in seconds, rounded to the displayed precision.
+    let sec_decimals = second_decimals(format);
+    let time = {
+        let secs = match value {
+            Value::DateTime(d) => {
+                d.time().num_seconds_from_midnight() as f64 + d.time().nanosecond() as f64 / 1e9
+            }
+            Value::TimeDuration(d) => d.num_milliseconds() as f64 / 1000.0,
+            _ => number * 86400.0,
+        };
+        let scale = 10f64.powi(sec_decimals as i32);
+        (secs.abs() * scale).round() / scale
+    };
+    let truncate = match format.attr.attr("number:truncate-on-overflow") {
+        Some(v) => v.parse().unwrap_or(true),
+        None => !matches!(value, Value::TimeDuration(_)),
+    };
+    let am_pm = has_part(format, FormatPartType::AmPm);
+
+    let mut buf = String::new();
+    let mut is_zero = true;
+    let mut is_numeric = false;
+    for part in &format.parts {
+        match part.part_type {
+            FormatPartType::Number => {
+                let start = buf.len();
+                push_number(&mut buf, part, number.abs(), seps);
+                is_zero &= !buf[start..].contains(['1', '2', '3', '4', '5', '6', '7', '8', '9']);
+                is_numeric = true;
+            }
+            FormatPartType::ScientificNumber => {
+                push_scientific(&mut buf, part, number.abs(), seps);
+                is_zero &= number == 0.0;
+                is_numeric = true;
+            }
+            FormatPartType::Fraction => {
+                let start = buf.len();
+                push_fraction(&mut buf, part, number.abs(), seps);
+                is_zero &= !buf[start..].contains(['1', '2', '3', '4', '5', '6', '7', '8', '9']);
+                is_numeric = true;
+            }
+            FormatPartType::CurrencySymbol => match (&part.content, value) {
+                (Some(symbol), _) => buf.push_str(symbol),
+                (None, Value::Currency(_, c)) => buf.push_str(&String::from_utf8_lossy(c)),
+                _ => {}
+            },
+            FormatPartType::FillCharacter => {}
+            FormatPartType::Day => {
+                if let Some(d) = datetime {
+                    if is_long(part) {
+                        buf.push_str(&format!("{:02}", d.day()));
+                    } else {
+                        buf.push_str(&d.day().to_string());
+                    }
+                }
+            }
+            FormatPartType::Month => {
+                if let Some(d) = datetime {
+                    let textual = attr_bool(part, "number:textual").unwrap_or(false);
+                    let name = MONTHS[d.month0() as usize];
+                    match (textual, is_long(part)) {
+                        (true, true) => buf.push_str(name),
+                        (true, false) => buf.push_str(&name[..3]),
+                        (false, true) => buf.push_str(&format!("{:02}", d.month())),
+                        (false, false) => buf.push_str(&d.month().to_string()),
+                    }
+                }
+            }
+            FormatPartType::Year => {
+                if let Some(d) = datetime {
+                    if is_long(part) {
+                        buf.push_str(&format!("{:04}", d.year()));
+                    } else {
+                        buf.push_str(&format!("{:02}", d.year().rem_euclid(100)));
+                    }
+                }
+            }
+            FormatPartType::Era => {
+                if let Some(d) = datetime {
+                    buf.push_str(if d.year() > 0 { "AD" } else { "BC" });
+                }
+            }
+            FormatPartType::DayOfWeek => {
+                if let Some(d) = datetime {
+                    let name = WEEKDAYS[d.weekday().num_days_from_monday() as usize];
+                    if is_long(part) {
+                        buf.push_str(name);
+                    } else {
+                        buf.push_str(&name[..3]);
+                    }
+                }
+            }
+            FormatPartType::WeekOfYear => {
+                if let Some(d) = datetime {
+                    buf.push_str(&d.iso_week().week().to_string());
+                }
+            }
+            FormatPartType::Quarter => {
+                if let Some(d) = datetime {
+                    let q = d.month0() / 3 + 1;
+                    if is_long(part) {
+                        buf.push_str(ordinal(q));
+                        buf.push_str(" quarter");
+                    } else {
+                        buf.push_str(&format!("Q{}", q));
+                    }
+                }
+            }
+            FormatPartType::Hours => {
+                let mut h = (time / 3600.0).floor() as u64;
+                if truncate || am_pm {
+                    h %= 24;
+                }
+                if am_pm {
+                    h = match h % 12 {
+                        0 => 12,
+                        h => h,
+                    };
+                }
+                if is_long(part) {
+                    buf.push_str(&format!("{:02}", h));
+                } else {
+                    buf.push_str(&h.to_string());
+                }
+            }
+            FormatPartType::Minutes => {
+                let m = (time / 60.0).floor() as u64 % 60;
+                if is_long(part) {
+                    buf.push_str(&format!("{:02}", m));
+                } else {
+                    buf.push_str(&m.to_string());
+                }
+            }
+            FormatPartType::Seconds => {
+                let decimals = attr_u32(part, "number:decimal-places").unwrap_or(0) as usize;
+                let s = time % 60.0;
+                let width = if is_long(part) { 2 } else { 1 };
+                let width = if decimals > 0 {
+                    width + 1 + decimals
+                } else {
+                    width
+                };
+                let s = format!("{:0w$.d$}", s, w = width, d = decimals);
+                buf.push_str(&s.replace('.', &seps.0.to_string()));
+            }
+            FormatPartType::AmPm => {
+                let h = (time / 3600.0).floor() as u64 % 24;
+                buf.push_str(if h < 12 { "AM" } else { "PM" });
+            }
+            FormatPartType::Boolean => {
+                let b = match value {
+                    Value::Boolean(b) => *b,
+                    _ => number != 0.0,
+                };
+                buf.push_str(&format_bool(b));
+            }
+            FormatPartType::Text => {
+                if let Some(text) = &part.content {
+                    buf.push_str(text);
+                }
+            }
+            FormatPartType::TextContent => buf.push_str(&format_default(value)),
+        }
+    }
+
+    if sign && is_numeric && number < 0.0 && !is_zero {
+        buf.insert(0, '-');
+    }
+
+    buf
+}
@@ -22,18 +22,25 @@
 //! v.part_number().decimal_places(3);
 //! ```
 //!
-//! X!!
-//! The output formatting is a rough approximation with the possibilities
-//! offered by format! and chrono::format. Especially there is no trace of
-//! i18n. But on the other hand the formatting rules are applied by LibreOffice
-//! when opening the spreadsheet so typically nobody notices this.
+//! ValueFormat::format_value() and WorkBook::format_value() create the
+//! display text for a value. This is a rough approximation, the decimal and
+//! grouping separators follow the locale for some languages, but the names
+//! of months and weekdays are english only. On the other hand the formatting
+//! rules are applied by LibreOffice when opening the spreadsheet so typically
+//! nobody notices this.
 //!
 
 mod builder;
+mod code;
 mod create;
+mod formatter;
 
 pub use builder::*;
+pub(crate) use code::{format_code, parse_format_code};
 pub use create::*;
+pub(crate) use formatter::{
+    base_date, format_default, format_general, format_mapped, locale_separators,
+};
 
 use crate::attrmap2::AttrMap2;
 use crate::format::{
@@ -50,7 +57,7 @@ use crate::style::units::{
 use crate::style::{
     color_string, percent_string, shadow_string, StyleOrigin, StyleUse, TextStyleRef,
 };
-use crate::{OdsError, ValueType};
+use crate::{OdsError, Value, ValueType};
 use color::Rgb;
 use icu_locid::subtags::{Language, Region, Script};
 use icu_locid::{LanguageIdentifier, Locale};
@@ -1015,6 +1022,75 @@ impl ValueFormat {
     pub fn stylemaps_mut(&mut self) -> &mut Vec<StyleMap> {
         self.stylemaps.get_or_insert_with(Vec::new)
     }
+
+    /// Formats the value with the parts of this format.
+    ///
+    /// The stylemaps are not applied, they refer to other formats by name.
+    /// Use WorkBook::format_value() for this.
+    ///
+    /// ```
+    /// use spreadsheet_ods::{Value, ValueFormat, ValueType};
+    ///
+    /// let mut v = ValueFormat::new_named("n2", ValueType::Number);
+    /// v.part_number().fixed_decimal_places(2).grouping().push();
+    /// assert_eq!(v.format_value(&Value::Number(-1234.567)), "-1,234.57");
+    /// ```
+    pub fn format_value(&self, value: &Value) -> String {
+        formatter::format_value(self, value)
+    }
+
+    /// Creates value formats from a format code like `#,##0.00;[RED]-#,##0.00`.
+    ///
+    /// A code with multiple sections results in multiple formats. The
+    /// last one is the main format with the given name, it refers to the
+    /// others via stylemaps. The other formats are named name + "P0",
+    /// name + "P1", ... All of them must be added to the workbook.
+    ///
+    /// Sections without explicit condition get the usual defaults:
+    /// positive;negative, positive;negative;zero and
+    /// positive;negative;zero;text.
+    ///
+    /// ```
+    /// use icu_locid::locale;
+    /// use spreadsheet_ods::{ValueFormat, ValueType, WorkBook};
+    ///
+    /// let mut wb = WorkBook::new_empty();
+    /// let formats =
+    ///     ValueFormat::from_format_code("money", "#,##0.00 \"EUR\";[RED]-#,##0.00", locale!("de-AT"))
+    ///         .unwrap();
+    /// assert_eq!(formats.len(), 2);
+    /// assert_eq!(formats[1].name(), "money");
+    /// assert_eq!(formats[1].value_type(), ValueType::Number);
+    /// for f in formats {
+    ///     wb.add_format(f);
+    /// }
+    /// ```
+    pub fn from_format_code<S: AsRef<str>>(
+        name: S,
+        code: &str,
+        locale: Locale,
+    ) -> Result<Vec<ValueFormat>, OdsError> {
+        let mut formats = parse_format_code(name.as_ref(), code)?;
+        for format in formats.iter_mut() {
+            format.set_locale(locale.clone());
+        }
+        Ok(formats)
+    }
+
+    /// Creates the format code for this format. The stylemaps refer to
+    /// other formats by name and are not included, use
+    /// WorkBook::format_code() for this.
+    ///
+    /// ```
+    /// use icu_locid::locale;
+    /// use spreadsheet_ods::ValueFormat;
+    ///
+    /// let formats = ValueFormat::from_format_code("dt", "DD.MM.YYYY HH:MM", locale!("de")).unwrap();
+    /// assert_eq!(formats[0].to_format_code(), "DD.MM.YYYY HH:MM");
+    /// ```
+    pub fn to_format_code(&self) -> String {
+        code::section_code(self)
+    }
 }
 
 /// Identifies the structural parts of a value format.
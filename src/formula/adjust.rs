@@ -0,0 +1,342 @@
+//!
+//! Adjusts references when rows or columns are inserted or deleted and
+//! when cells are copied or moved.
+//!
+
+use crate::formula::{parse, Expr};
+use crate::refs::push_tablename;
+use crate::{CellRange, CellRef, OdsError};
+
+/// Structural change of a sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shift {
+    /// Insert n rows before the given row.
+    InsertRows(u32, u32),
+    /// Delete n rows starting with the given row.
+    DeleteRows(u32, u32),
+    /// Insert n columns before the given column.
+    InsertCols(u32, u32),
+    /// Delete n columns starting with the given column.
+    DeleteCols(u32, u32),
+}
+
+impl Shift {
+    /// New position of a single row or column. None if it was deleted.
+    fn index(at: u32, n: u32, insert: bool, idx: u32) -> Option<u32> {
+        if insert {
+            if idx >= at {
+                Some(idx.saturating_add(n))
+            } else {
+                Some(idx)
+            }
+        } else if idx < at {
+            Some(idx)
+        } else if idx - at < n {
+            None
+        } else {
+            Some(idx - n)
+        }
+    }
+
+    /// New bounds of an interval. None if it was deleted completely.
+    fn interval(at: u32, n: u32, insert: bool, from: u32, to: u32) -> Option<(u32, u32)> {
+        let (from, to) = (from.min(to), from.max(to));
+        if insert {
+            let from = Self::index(at, n, true, from)?;
+            let to = Self::index(at, n, true, to)?;
+            Some((from, to))
+        } else {
+            match (
+                Self::index(at, n, false, from),
+                Self::index(at, n, false, to),
+            ) {
+                (Some(from), Some(to)) => Some((from, to)),
+                // starts in the deleted part.
+                (None, Some(to)) => Some((at, to)),
+                // ends in the deleted part.
+                (Some(from), None) => Some((from, at - 1)),
+                (None, None) => None,
+            }
+        }
+    }
+
+    /// New position of the row interval.
+    pub(crate) fn rows(&self, from: u32, to: u32) -> Option<(u32, u32)> {
+        match *self {
+            Shift::InsertRows(at, n) => Self::interval(at, n, true, from, to),
+            Shift::DeleteRows(at, n) => Self::interval(at, n, false, from, to),
+            _ => Some((from, to)),
+        }
+    }
+
+    /// New position of the column interval.
+    pub(crate) fn cols(&self, from: u32, to: u32) -> Option<(u32, u32)> {
+        match *self {
+            Shift::InsertCols(at, n) => Self::interval(at, n, true, from, to),
+            Shift::DeleteCols(at, n) => Self::interval(at, n, false, from, to),
+            _ => Some((from, to)),
+        }
+    }
+
+    /// New position of a cell. None if it was deleted.
+    pub(crate) fn cell(&self, row: u32, col: u32) -> Option<(u32, u32)> {
+        let (row, _) = self.rows(row, row)?;
+        let (col, _) = self.cols(col, col)?;
+        Some((row, col))
+    }
+
+    /// Adjusts the cell reference. Returns false if the cell was deleted.
+    pub(crate) fn cellref(&self, cellref: &mut CellRef) -> bool {
+        match self.cell(cellref.row(), cellref.col()) {
+            Some((row, col)) => {
+                cellref.set_row(row);
+                cellref.set_col(col);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adjusts the range. A range shrinks if part of it is deleted.
+    /// Returns false if the range was deleted completely.
+    pub(crate) fn cellrange(&self, range: &mut CellRange) -> bool {
+        let rows = self.rows(range.row(), range.to_row());
+        let cols = self.cols(range.col(), range.to_col());
+        match (rows, cols) {
+            (Some((row, to_row)), Some((col, to_col))) => {
+                range.set_row(row);
+                range.set_to_row(to_row);
+                range.set_col(col);
+                range.set_to_col(to_col);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adjusts all references in the expression that point to the
+    /// changed sheet. Deleted references are replaced with [.#REF!].
+    pub(crate) fn expr<F>(&self, expr: &mut Expr, is_target: F)
+    where
+        F: Fn(Option<&String>) -> bool,
+    {
+        expr.visit_mut(&mut |e| {
+            let deleted = match e {
+                Expr::CellRef(c) if is_target(c.table()) => !self.cellref(c),
+                Expr::CellRange(r) if is_target(r.table()) => !self.cellrange(r),
+                _ => false,
+            };
+            if deleted {
+                *e = invalid_ref(e);
+            }
+        });
+    }
+
+    /// Adjusts the formula. Returns the new formula if anything changed.
+    /// Formulas that can't be parsed are left as they are.
+    pub(crate) fn formula<F>(&self, formula: &str, is_target: F) -> Option<String>
+    where
+        F: Fn(Option<&String>) -> bool,
+    {
+        rewrite(formula, |e| self.expr(e, is_target))
+    }
+}
+
+/// Applies the function to the parsed formula. Returns the new formula if
+/// anything changed. Formulas that can't be parsed are left as they are.
+fn rewrite<F>(formula: &str, f: F) -> Option<String>
+where
+    F: FnOnce(&mut Expr),
+{
+    let expr = parse(formula).ok()?;
+    let mut adjusted = expr.clone();
+    f(&mut adjusted);
+    if adjusted != expr {
+        Some(adjusted.to_formula())
+    } else {
+        None
+    }
+}
+
+/// Replacement for a reference that no longer exists. Keeps the table
+/// name and writes #REF! for the cell part, the way LibreOffice does.
+fn invalid_ref(e: &Expr) -> Expr {
+    let mut buf = String::new();
+    match e {
+        Expr::CellRef(c) => push_tablename(&mut buf, c.table(), c.col_abs() || c.row_abs()),
+        Expr::CellRange(r) => push_tablename(
+            &mut buf,
+            r.table(),
+            r.col_abs() || r.row_abs() || r.to_col_abs() || r.to_row_abs(),
+        ),
+        _ => buf.push('.'),
+    }
+    buf.push_str("#REF!");
+    Expr::InvalidRef(buf)
+}
+
+fn offset(idx: u32, delta: i64) -> Option<u32> {
+    u32::try_from(i64::from(idx) + delta).ok()
+}
+
+/// Translates the relative references by the given offset, the way a paste
+/// does. Absolute references are kept. References that end up outside of
+/// the sheet are replaced with [.#REF!].
+pub(crate) fn translate_formula(formula: &str, drow: i64, dcol: i64) -> Option<String> {
+    rewrite(formula, |expr| {
+        expr.visit_mut(&mut |e| {
+            let valid = match e {
+                Expr::CellRef(c) => translate_cellref(c, drow, dcol),
+                Expr::CellRange(r) => translate_cellrange(r, drow, dcol),
+                _ => true,
+            };
+            if !valid {
+                *e = invalid_ref(e);
+            }
+        })
+    })
+}
+
+fn translate_cellref(c: &mut CellRef, drow: i64, dcol: i64) -> bool {
+    let row = if c.row_abs() {
+        Some(c.row())
+    } else {
+        offset(c.row(), drow)
+    };
+    let col = if c.col_abs() {
+        Some(c.col())
+    } else {
+        offset(c.col(), dcol)
+    };
+    match (row, col) {
+        (Some(row), Some(col)) => {
+            c.set_row(row);
+            c.set_col(col);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn translate_cellrange(r: &mut CellRange, drow: i64, dcol: i64) -> bool {
+    let mut from = CellRef::local(r.row(), r.col());
+    from.set_row_abs(r.row_abs());
+    from.set_col_abs(r.col_abs());
+    let mut to = CellRef::local(r.to_row(), r.to_col());
+    to.set_row_abs(r.to_row_abs());
+    to.set_col_abs(r.to_col_abs());
+    if translate_cellref(&mut from, drow, dcol) && translate_cellref(&mut to, drow, dcol) {
+        r.set_row(from.row());
+        r.set_col(from.col());
+        r.set_to_row(to.row());
+        r.set_to_col(to.col());
+        true
+    } else {
+        false
+    }
+}
+
+/// Adds the table-name to all local references.
+pub(crate) fn qualify_formula(formula: &str, table: &str) -> Option<String> {
+    rewrite(formula, |expr| {
+        expr.visit_mut(&mut |e| match e {
+            Expr::CellRef(c) if c.table().is_none() => c.set_table(table),
+            Expr::CellRange(r) if r.table().is_none() => r.set_table(table),
+            _ => {}
+        })
+    })
+}
+
+/// Block of cells that is moved to another position.
+#[derive(Debug)]
+pub(crate) struct Move<'a> {
+    /// Source block, normalized.
+    pub(crate) src: (u32, u32, u32, u32),
+    /// Row offset.
+    pub(crate) drow: i64,
+    /// Column offset.
+    pub(crate) dcol: i64,
+    /// Destination sheet, if it's not the same.
+    pub(crate) table: Option<&'a str>,
+}
+
+impl Move<'_> {
+    fn inside(&self, row: u32, col: u32, to_row: u32, to_col: u32) -> bool {
+        let (r0, c0, r1, c1) = self.src;
+        row.min(to_row) >= r0
+            && row.max(to_row) <= r1
+            && col.min(to_col) >= c0
+            && col.max(to_col) <= c1
+    }
+
+    /// References that lie completely inside the moved block follow it,
+    /// regardless of being absolute or not.
+    pub(crate) fn formula<F>(&self, formula: &str, is_target: F) -> Option<String>
+    where
+        F: Fn(Option<&String>) -> bool,
+    {
+        rewrite(formula, |expr| {
+            expr.visit_mut(&mut |e| match e {
+                Expr::CellRef(c)
+                    if is_target(c.table()) && self.inside(c.row(), c.col(), c.row(), c.col()) =>
+                {
+                    // always inside the sheet.
+                    let (row, col) = (offset(c.row(), self.drow), offset(c.col(), self.dcol));
+                    c.set_row(row.unwrap_or_default());
+                    c.set_col(col.unwrap_or_default());
+                    if let Some(table) = self.table {
+                        c.set_table(table);
+                    }
+                }
+                Expr::CellRange(r)
+                    if is_target(r.table())
+                        && self.inside(r.row(), r.col(), r.to_row(), r.to_col()) =>
+                {
+                    r.set_row(offset(r.row(), self.drow).unwrap_or_default());
+                    r.set_col(offset(r.col(), self.dcol).unwrap_or_default());
+                    r.set_to_row(offset(r.to_row(), self.drow).unwrap_or_default());
+                    r.set_to_col(offset(r.to_col(), self.dcol).unwrap_or_default());
+                    if let Some(table) = self.table {
+                        r.set_table(table);
+                    }
+                }
+                _ => {}
+            })
+        })
+    }
+}
+
+/// Bounds of the range as (row, col, to_row, to_col), with row <= to_row
+/// and col <= to_col.
+pub(crate) fn normalized(range: &CellRange) -> (u32, u32, u32, u32) {
+    (
+        range.row().min(range.to_row()),
+        range.col().min(range.to_col()),
+        range.row().max(range.to_row()),
+        range.col().max(range.to_col()),
+    )
+}
+
+/// Offset from the start of the range to the destination.
+pub(crate) fn range_offset(range: &CellRange, dst: &CellRef) -> (i64, i64) {
+    let (row, col, _, _) = normalized(range);
+    (
+        i64::from(dst.row()) - i64::from(row),
+        i64::from(dst.col()) - i64::from(col),
+    )
+}
+
+/// Fails if the range doesn't fit into the sheet when its top left corner
+/// is placed at dst.
+pub(crate) fn check_destination(range: &CellRange, dst: &CellRef) -> Result<(), OdsError> {
+    let (row, col, to_row, to_col) = normalized(range);
+    if dst.row().checked_add(to_row - row).is_none()
+        || dst.col().checked_add(to_col - col).is_none()
+    {
+        return Err(OdsError::Ods(format!(
+            "Range {} doesn't fit into the sheet at {}",
+            range, dst
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,327 @@
+//!
+//! Syntax tree for formulas.
+//!
+
+use crate::refs::{push_cellrange, push_cellref};
+use crate::{CellRange, CellRef};
+use std::fmt::{Display, Formatter};
+
+/// Prefix operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// +
+    Plus,
+    /// -
+    Neg,
+    /// % as postfix operator.
+    Percent,
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnaryOp::Plus => write!(f, "+"),
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Percent => write!(f, "%"),
+        }
+    }
+}
+
+/// Infix operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// +
+    Add,
+    /// -
+    Sub,
+    /// *
+    Mul,
+    /// /
+    Div,
+    /// ^
+    Pow,
+    /// &
+    Concat,
+    /// =
+    Eq,
+    /// <>
+    Ne,
+    /// <
+    Lt,
+    /// <=
+    Le,
+    /// >
+    Gt,
+    /// >=
+    Ge,
+    /// : Range operator for two references.
+    Range,
+    /// ! Intersection of two references.
+    Intersect,
+    /// ~ Union of two references.
+    Union,
+}
+
+impl BinaryOp {
+    /// Binding power of the operator. Higher binds tighter.
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => 1,
+            BinaryOp::Concat => 2,
+            BinaryOp::Add | BinaryOp::Sub => 3,
+            BinaryOp::Mul | BinaryOp::Div => 4,
+            BinaryOp::Pow => 5,
+            BinaryOp::Union => 6,
+            BinaryOp::Intersect => 7,
+            BinaryOp::Range => 8,
+        }
+    }
+
+    /// Operators that combine references. They bind tighter than the
+    /// prefix operators.
+    pub(crate) fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Range | BinaryOp::Intersect | BinaryOp::Union
+        )
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "^",
+            BinaryOp::Concat => "&",
+            BinaryOp::Eq => "=",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Range => ":",
+            BinaryOp::Intersect => "!",
+            BinaryOp::Union => "~",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Parsed formula.
+///
+/// The Display impl writes the expression without the namespace prefix,
+/// to_formula() returns the complete formula as used in table:formula.
+///
+/// ```
+/// use spreadsheet_ods::formula::{parse, Expr};
+///
+/// let f = parse("of:=SUM([.A1:.B3])*2").unwrap();
+/// assert_eq!(f.to_string(), "SUM([.A1:.B3])*2");
+/// assert_eq!(f.to_formula(), "of:=SUM([.A1:.B3])*2");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Number literal.
+    Number(f64),
+    /// String literal.
+    Text(String),
+    /// Error literal like #REF! or #DIV/0!.
+    Error(String),
+    /// Reference that contains an error, like [.#REF!] or [Sheet1.#REF!].
+    /// Contains the text between the brackets. Evaluates to #REF!.
+    InvalidRef(String),
+    /// Reference to a single cell.
+    CellRef(CellRef),
+    /// Reference to a range of cells.
+    CellRange(CellRange),
+    /// Named expression.
+    Name(String),
+    /// An omitted function parameter like the second parameter in IF(A;;B).
+    Missing,
+    /// Parenthesized expression.
+    Parens(Box<Expr>),
+    /// Prefix or postfix operator.
+    Unary(UnaryOp, Box<Expr>),
+    /// Infix operator.
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Function call with name and parameters.
+    Function(String, Vec<Expr>),
+    /// Inline array. Contains rows of columns.
+    Array(Vec<Vec<Expr>>),
+}
+
+impl Expr {
+    /// Returns the complete formula text including the "of:=" prefix.
+    pub fn to_formula(&self) -> String {
+        format!("of:={}", self)
+    }
+
+    /// Calls the function for this node and all child nodes.
+    /// Children are visited after their parent.
+    pub fn visit<F>(&self, f: &mut F)
+    where
+        F: FnMut(&Expr),
+    {
+        f(self);
+        match self {
+            Expr::Number(_)
+            | Expr::Text(_)
+            | Expr::Error(_)
+            | Expr::InvalidRef(_)
+            | Expr::CellRef(_)
+            | Expr::CellRange(_)
+            | Expr::Name(_)
+            | Expr::Missing => {}
+            Expr::Parens(e) | Expr::Unary(_, e) => e.visit(f),
+            Expr::Binary(_, l, r) => {
+                l.visit(f);
+                r.visit(f);
+            }
+            Expr::Function(_, args) => {
+                for a in args {
+                    a.visit(f);
+                }
+            }
+            Expr::Array(rows) => {
+                for a in rows.iter().flatten() {
+                    a.visit(f);
+                }
+            }
+        }
+    }
+
+    /// Calls the function for this node and all child nodes and allows
+    /// modifications. Children are visited after their parent, so a replaced
+    /// node is visited with its new children.
+    pub fn visit_mut<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(&mut Expr),
+    {
+        f(self);
+        match self {
+            Expr::Number(_)
+            | Expr::Text(_)
+            | Expr::Error(_)
+            | Expr::InvalidRef(_)
+            | Expr::CellRef(_)
+            | Expr::CellRange(_)
+            | Expr::Name(_)
+            | Expr::Missing => {}
+            Expr::Parens(e) | Expr::Unary(_, e) => e.visit_mut(f),
+            Expr::Binary(_, l, r) => {
+                l.visit_mut(f);
+                r.visit_mut(f);
+            }
+            Expr::Function(_, args) => {
+                for a in args {
+                    a.visit_mut(f);
+                }
+            }
+            Expr::Array(rows) => {
+                for a in rows.iter_mut().flatten() {
+                    a.visit_mut(f);
+                }
+            }
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Number(v) => write!(f, "{}", v),
+            Expr::Text(v) => write!(f, "\"{}\"", v.replace('"', "\"\"")),
+            Expr::Error(v) => write!(f, "{}", v),
+            Expr::InvalidRef(v) => write!(f, "[{}]", v),
+            Expr::CellRef(v) => {
+                let mut buf = String::new();
+                push_cellref(&mut buf, v);
+                write!(f, "[{}]", buf)
+            }
+            Expr::CellRange(v) => {
+                let mut buf = String::new();
+                push_cellrange(&mut buf, v);
+                write!(f, "[{}]", buf)
+            }
+            Expr::Name(v) => write!(f, "{}", v),
+            Expr::Missing => Ok(()),
+            Expr::Parens(e) => write!(f, "({})", e),
+            Expr::Unary(UnaryOp::Percent, e) => {
+                fmt_operand(
+                    f,
+                    e,
+                    matches!(&**e, Expr::Binary(op, _, _) if !op.is_reference())
+                        || matches!(**e, Expr::Unary(UnaryOp::Plus | UnaryOp::Neg, _)),
+                )?;
+                write!(f, "%")
+            }
+            Expr::Unary(op, e) => {
+                write!(f, "{}", op)?;
+                fmt_operand(
+                    f,
+                    e,
+                    matches!(&**e, Expr::Binary(op, _, _) if !op.is_reference()),
+                )
+            }
+            Expr::Binary(op, l, r) => {
+                let prec = op.precedence();
+                let refop = op.is_reference();
+                fmt_operand(
+                    f,
+                    l,
+                    matches!(&**l, Expr::Binary(lop, _, _) if lop.precedence() < prec)
+                        || refop && matches!(**l, Expr::Unary(..)),
+                )?;
+                write!(f, "{}", op)?;
+                fmt_operand(
+                    f,
+                    r,
+                    matches!(&**r, Expr::Binary(rop, _, _) if rop.precedence() <= prec)
+                        || refop && matches!(**r, Expr::Unary(..)),
+                )
+            }
+            Expr::Function(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, a) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, "{}", a)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Array(rows) => {
+                write!(f, "{{")?;
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    for (j, a) in row.iter().enumerate() {
+                        if j > 0 {
+                            write!(f, ";")?;
+                        }
+                        write!(f, "{}", a)?;
+                    }
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+// Writes an operand and adds parentheses if the tree structure requires them.
+fn fmt_operand(f: &mut Formatter<'_>, e: &Expr, parens: bool) -> std::fmt::Result {
+    if parens {
+        write!(f, "({})", e)
+    } else {
+        write!(f, "{}", e)
+    }
+}
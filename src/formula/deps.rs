@@ -0,0 +1,322 @@
+//!
+//! Dependencies between formulas and cells.
+//!
+
+use crate::formula::{parse, BinaryOp, Expr};
+use crate::{CellRange, CellRef, OdsError, WorkBook};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Sheet index, row, column.
+pub(crate) type CellKey = (usize, u32, u32);
+
+/// Sheet index, row, column, to_row, to_col. Normalized.
+pub(crate) type RangeKey = (usize, u32, u32, u32, u32);
+
+/// Index of the sheet for a table-name.
+pub(crate) fn resolve_sheet(
+    book: &WorkBook,
+    sheet: usize,
+    table: Option<&String>,
+) -> Option<usize> {
+    match table {
+        Some(table) => book.sheet_idx(table),
+        None => Some(sheet),
+    }
+}
+
+/// The range for a reference expression. The range operator : gives the
+/// enclosing range of both sides, if they are on the same sheet.
+pub(crate) fn ref_range(book: &WorkBook, sheet: usize, expr: &Expr) -> Option<RangeKey> {
+    match expr {
+        Expr::CellRef(c) => {
+            let s = resolve_sheet(book, sheet, c.table())?;
+            Some((s, c.row(), c.col(), c.row(), c.col()))
+        }
+        Expr::CellRange(r) => {
+            let s = resolve_sheet(book, sheet, r.table())?;
+            Some((
+                s,
+                r.row().min(r.to_row()),
+                r.col().min(r.to_col()),
+                r.row().max(r.to_row()),
+                r.col().max(r.to_col()),
+            ))
+        }
+        Expr::Parens(e) => ref_range(book, sheet, e),
+        Expr::Binary(BinaryOp::Range, l, r) => {
+            let l = ref_range(book, sheet, l)?;
+            let r = ref_range(book, sheet, r)?;
+            if l.0 != r.0 {
+                return None;
+            }
+            Some((l.0, l.1.min(r.1), l.2.min(r.2), l.3.max(r.3), l.4.max(r.4)))
+        }
+        _ => None,
+    }
+}
+
+/// All references of the expression. References to unknown sheets are
+/// dropped.
+pub(crate) fn expr_ranges(book: &WorkBook, sheet: usize, expr: &Expr) -> Vec<RangeKey> {
+    let mut res = Vec::new();
+    expr.visit(&mut |e| match e {
+        Expr::Binary(BinaryOp::Range, _, _) => {
+            if let Some(r) = ref_range(book, sheet, e) {
+                res.push(r);
+            }
+        }
+        Expr::CellRef(c) => {
+            if let Some(s) = resolve_sheet(book, sheet, c.table()) {
+                res.push((s, c.row(), c.col(), c.row(), c.col()));
+            }
+        }
+        Expr::CellRange(r) => {
+            if let Some(s) = resolve_sheet(book, sheet, r.table()) {
+                res.push((
+                    s,
+                    r.row().min(r.to_row()),
+                    r.col().min(r.to_col()),
+                    r.row().max(r.to_row()),
+                    r.col().max(r.to_col()),
+                ));
+            }
+        }
+        _ => {}
+    });
+    res
+}
+
+fn contains(r: &RangeKey, c: &CellKey) -> bool {
+    r.0 == c.0 && c.1 >= r.1 && c.1 <= r.3 && c.2 >= r.2 && c.2 <= r.4
+}
+
+/// Range references are indexed by blocks of this many columns.
+const COL_BLOCK: u32 = 16;
+
+/// Graph of the references between the formulas of a workbook.
+///
+/// The graph is a snapshot, it must be rebuilt after the workbook
+/// has been modified.
+///
+/// ```
+/// use spreadsheet_ods::formula::DependencyGraph;
+/// use spreadsheet_ods::{CellRef, Sheet, WorkBook};
+///
+/// let mut wb = WorkBook::new_empty();
+/// let mut sh = Sheet::new("one");
+/// sh.set_value(0, 0, 1);
+/// sh.set_formula(0, 1, "of:=[.A1]+1");
+/// sh.set_formula(0, 2, "of:=[.B1]*2");
+/// wb.push_sheet(sh);
+///
+/// let graph = DependencyGraph::new(&wb).unwrap();
+/// let dep = graph.dependents(&CellRef::remote("one", 0, 0));
+/// assert_eq!(dep, vec![CellRef::remote("one", 0, 1), CellRef::remote("one", 0, 2)]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    sheets: Vec<String>,
+    // Direct references for each formula cell.
+    precedents: HashMap<CellKey, Vec<RangeKey>>,
+    // Index: single cell references.
+    cell_refs: HashMap<CellKey, Vec<CellKey>>,
+    // Index: range references for each sheet and block of columns.
+    range_refs: HashMap<(usize, u32), Vec<(RangeKey, CellKey)>>,
+}
+
+impl DependencyGraph {
+    /// Parses all formulas of the workbook and builds the graph.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn new(book: &WorkBook) -> Result<Self, OdsError> {
+        let mut graph = DependencyGraph {
+            sheets: book.sheets.iter().map(|v| v.name.clone()).collect(),
+            ..Default::default()
+        };
+
+        for (s, sheet) in book.sheets.iter().enumerate() {
+            for ((row, col), cell) in sheet.data.iter() {
+                if let Some(formula) = &cell.formula {
+                    let key = (s, *row, *col);
+                    let refs = expr_ranges(book, s, &parse(formula)?);
+                    for r in &refs {
+                        if r.1 == r.3 && r.2 == r.4 {
+                            graph
+                                .cell_refs
+                                .entry((r.0, r.1, r.2))
+                                .or_default()
+                                .push(key);
+                        } else {
+                            for block in r.2 / COL_BLOCK..=r.4 / COL_BLOCK {
+                                graph
+                                    .range_refs
+                                    .entry((r.0, block))
+                                    .or_default()
+                                    .push((*r, key));
+                            }
+                        }
+                    }
+                    graph.precedents.insert(key, refs);
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    fn key(&self, cell: &CellRef) -> Option<CellKey> {
+        let table = cell.table()?;
+        let s = self.sheets.iter().position(|v| v == table)?;
+        Some((s, cell.row(), cell.col()))
+    }
+
+    fn cell_ref(&self, key: &CellKey) -> CellRef {
+        CellRef::remote(&self.sheets[key.0], key.1, key.2)
+    }
+
+    /// Returns the references used in the formula of the given cell.
+    /// The cell must contain a table-name.
+    pub fn precedents(&self, cell: &CellRef) -> Vec<CellRange> {
+        let key = match self.key(cell) {
+            Some(key) => key,
+            None => return Vec::new(),
+        };
+        match self.precedents.get(&key) {
+            Some(refs) => refs
+                .iter()
+                .map(|r| CellRange::remote(&self.sheets[r.0], r.1, r.2, r.3, r.4))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Formula cells that reference the cell directly.
+    fn direct_dependents(&self, key: &CellKey) -> Vec<CellKey> {
+        let mut res = Vec::new();
+        if let Some(v) = self.cell_refs.get(key) {
+            res.extend(v.iter().copied());
+        }
+        if let Some(v) = self.range_refs.get(&(key.0, key.2 / COL_BLOCK)) {
+            for (r, f) in v {
+                if contains(r, key) {
+                    res.push(*f);
+                }
+            }
+        }
+        res
+    }
+
+    fn dependents_keys(&self, start: &[CellKey]) -> BTreeSet<CellKey> {
+        let mut found = BTreeSet::new();
+        let mut queue: VecDeque<CellKey> = start.iter().copied().collect();
+        while let Some(key) = queue.pop_front() {
+            for dep in self.direct_dependents(&key) {
+                if found.insert(dep) {
+                    queue.push_back(dep);
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns all formula cells that depend directly or indirectly on
+    /// the given cell. The cell must contain a table-name.
+    pub fn dependents(&self, cell: &CellRef) -> Vec<CellRef> {
+        match self.key(cell) {
+            Some(key) => self
+                .dependents_keys(&[key])
+                .iter()
+                .map(|v| self.cell_ref(v))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns all formula cells that depend on any of the given cells.
+    pub(crate) fn dependents_of_keys(&self, start: &[CellKey]) -> Vec<CellKey> {
+        self.dependents_keys(start).into_iter().collect()
+    }
+
+    /// Finds all circular references. Each cycle contains the formula
+    /// cells that are involved.
+    pub fn cycles(&self) -> Vec<Vec<CellRef>> {
+        // formula -> formula edges
+        let mut nodes: Vec<CellKey> = self.precedents.keys().copied().collect();
+        nodes.sort_unstable();
+        let mut edges: HashMap<CellKey, Vec<CellKey>> = HashMap::new();
+        for n in &nodes {
+            for d in self.direct_dependents(n) {
+                edges.entry(d).or_default().push(*n);
+            }
+        }
+
+        // Tarjan, iterative.
+        let mut index: HashMap<CellKey, (usize, usize)> = HashMap::new();
+        let mut on_stack: HashSet<CellKey> = HashSet::new();
+        let mut scc_stack: Vec<CellKey> = Vec::new();
+        let mut next_index = 0;
+        let mut cycles = Vec::new();
+        let empty = Vec::new();
+
+        for root in &nodes {
+            if index.contains_key(root) {
+                continue;
+            }
+            let mut call_stack: Vec<(CellKey, usize)> = vec![(*root, 0)];
+            index.insert(*root, (next_index, next_index));
+            next_index += 1;
+            scc_stack.push(*root);
+            on_stack.insert(*root);
+
+            while let Some((node, edge_idx)) = call_stack.last_mut() {
+                let node = *node;
+                let succ = edges.get(&node).unwrap_or(&empty);
+                if let Some(next) = succ.get(*edge_idx).copied() {
+                    *edge_idx += 1;
+                    if let Some((next_idx, _)) = index.get(&next).copied() {
+                        if on_stack.contains(&next) {
+                            let e = index.get_mut(&node).expect("node");
+                            e.1 = e.1.min(next_idx);
+                        }
+                    } else {
+                        index.insert(next, (next_index, next_index));
+                        next_index += 1;
+                        scc_stack.push(next);
+                        on_stack.insert(next);
+                        call_stack.push((next, 0));
+                    }
+                } else {
+                    call_stack.pop();
+                    let (node_idx, node_low) = index[&node];
+                    if let Some((parent, _)) = call_stack.last() {
+                        let e = index.get_mut(parent).expect("parent");
+                        e.1 = e.1.min(node_low);
+                    }
+                    if node_idx == node_low {
+                        let mut scc = Vec::new();
+                        while let Some(v) = scc_stack.pop() {
+                            on_stack.remove(&v);
+                            scc.push(v);
+                            if v == node {
+                                break;
+                            }
+                        }
+                        let self_loop = succ.contains(&node);
+                        if scc.len() > 1 || self_loop {
+                            scc.sort_unstable();
+                            cycles.push(scc);
+                        }
+                    }
+                }
+            }
+        }
+
+        cycles.sort();
+        cycles
+            .iter()
+            .map(|v| v.iter().map(|v| self.cell_ref(v)).collect())
+            .collect()
+    }
+}
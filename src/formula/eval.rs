@@ -0,0 +1,926 @@
+//!
+//! Evaluates formulas.
+//!
+//! Only a core set of functions is available. Dynamic references like
+//! INDIRECT or OFFSET are not supported.
+//!
+
+use crate::format::{base_date, format_general, format_mapped, parse_format_code};
+use crate::formula::deps::{expr_ranges, ref_range, resolve_sheet, CellKey};
+use crate::formula::{parse, BinaryOp, Expr, UnaryOp};
+use crate::{CellRef, OdsError, Value, WorkBook};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Returns early with the spreadsheet error contained in the Err.
+macro_rules! try_val {
+    ($e:expr) => {
+        match $e {
+            Ok(v) => v,
+            Err(e) => return Ok(Val::Error(e)),
+        }
+    };
+}
+
+/// Recalculates all formulas of the workbook and stores the results as
+/// cell values.
+///
+/// Spreadsheet errors like #DIV/0! are stored as text, the same way
+/// LibreOffice does. Unsupported functions give #NAME? and a wrong number
+/// of parameters gives #N/A, check_formula() finds them beforehand.
+/// The reference operators ! and ~ give #VALUE!.
+/// Circular references and formulas that cannot be parsed are returned
+/// as OdsError, no cell value is changed in that case.
+///
+/// ```
+/// use spreadsheet_ods::{Sheet, WorkBook};
+///
+/// let mut wb = WorkBook::new_empty();
+/// let mut sh = Sheet::new("one");
+/// sh.set_value(0, 0, 21);
+/// sh.set_formula(0, 1, "of:=[.A1]*2");
+/// wb.push_sheet(sh);
+///
+/// wb.recalc().unwrap();
+/// assert_eq!(wb.sheet(0).value(0, 1).as_f64_or(0.0), 42.0);
+/// ```
+pub fn recalc(book: &mut WorkBook) -> Result<(), OdsError> {
+    let mut roots = Vec::new();
+    for (s, sheet) in book.sheets.iter().enumerate() {
+        for ((row, col), cell) in sheet.data.iter() {
+            if cell.formula.is_some() {
+                roots.push((s, *row, *col));
+            }
+        }
+    }
+    recalc_cells(book, &roots)?;
+
+    for sheet in book.sheets.iter_mut() {
+        sheet.dirty.clear();
+    }
+
+    Ok(())
+}
+
+/// Recalculates only the formulas that depend on cells that have been
+/// changed since the last recalculation. See Sheet::dirty_cells().
+///
+/// If any sheet doesn't track its changes everything is recalculated.
+pub fn recalc_dirty(book: &mut WorkBook) -> Result<(), OdsError> {
+    if book.sheets.iter().any(|v| !v.dirty_tracking) {
+        return recalc(book);
+    }
+
+    let mut dirty = Vec::new();
+    for (s, sheet) in book.sheets.iter().enumerate() {
+        for (row, col) in sheet.dirty.iter() {
+            dirty.push((s, *row, *col));
+        }
+    }
+
+    let mut roots = book.dependency_graph()?.dependents_of_keys(&dirty);
+    for (s, row, col) in dirty {
+        if book.sheet(s).formula(row, col).is_some() {
+            roots.push((s, row, col));
+        }
+    }
+    recalc_cells(book, &roots)?;
+
+    for sheet in book.sheets.iter_mut() {
+        sheet.dirty.clear();
+    }
+
+    Ok(())
+}
+
+// Evaluates the formula cells and stores the results.
+fn recalc_cells(book: &mut WorkBook, roots: &[CellKey]) -> Result<(), OdsError> {
+    let results = {
+        let mut ev = Evaluator::new(book);
+        ev.compute(roots)?;
+        ev.results
+    };
+
+    // Only values change, the dependency graph stays valid.
+    for ((s, row, col), v) in results {
+        if let Some(cell) = book.sheets[s].data.get_mut(&(row, col)) {
+            cell.value = to_value(v, &cell.value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Evaluates a formula in the context of the given sheet.
+/// Formulas that are referenced are evaluated too, but the results
+/// are not stored.
+pub fn eval(book: &WorkBook, sheet: usize, formula: &str) -> Result<Value, OdsError> {
+    let expr = parse(formula)?;
+
+    let mut ev = Evaluator::new(book);
+    let deps = formula_cells(book, sheet, &expr);
+    ev.compute(&deps)?;
+
+    let v = ev.eval_expr(sheet, &expr)?;
+    let v = ev.scalar(v);
+    Ok(to_value(v, &Value::Empty))
+}
+
+/// Intermediate values.
+#[derive(Debug, Clone, PartialEq)]
+enum Val {
+    Empty,
+    Bool(bool),
+    Number(f64),
+    /// Date as serial number.
+    Date(f64),
+    Text(String),
+    Error(String),
+    /// Range reference. Normalized.
+    Range(usize, u32, u32, u32, u32),
+    Array(Vec<Vec<Val>>),
+}
+
+const ERR_DIV0: &str = "#DIV/0!";
+const ERR_VALUE: &str = "#VALUE!";
+const ERR_REF: &str = "#REF!";
+const ERR_NAME: &str = "#NAME?";
+const ERR_NUM: &str = "#NUM!";
+const ERR_NA: &str = "#N/A";
+
+fn err<T>(e: &str) -> Result<T, String> {
+    Err(e.to_string())
+}
+
+struct Evaluator<'a> {
+    book: &'a WorkBook,
+    formulas: HashMap<CellKey, Expr>,
+    results: HashMap<CellKey, Val>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(book: &'a WorkBook) -> Self {
+        Self {
+            book,
+            formulas: Default::default(),
+            results: Default::default(),
+        }
+    }
+
+    // Parses the formula of the cell if necessary and returns the
+    // formula cells it depends on.
+    fn deps(&mut self, key: CellKey) -> Result<Vec<CellKey>, OdsError> {
+        if !self.formulas.contains_key(&key) {
+            let (s, row, col) = key;
+            let formula = match self.book.sheet(s).formula(row, col) {
+                Some(f) => f,
+                None => return Ok(Vec::new()),
+            };
+            self.formulas.insert(key, parse(formula)?);
+        }
+        Ok(formula_cells(self.book, key.0, &self.formulas[&key]))
+    }
+
+    // Evaluates all the given formula cells and everything they depend on.
+    fn compute(&mut self, roots: &[CellKey]) -> Result<(), OdsError> {
+        // 1 = in progress, 2 = done
+        let mut state: HashMap<CellKey, u8> = HashMap::new();
+        let mut order = Vec::new();
+
+        for root in roots {
+            if state.contains_key(root) || self.results.contains_key(root) {
+                continue;
+            }
+            state.insert(*root, 1);
+            let mut stack = vec![(*root, self.deps(*root)?, 0usize)];
+
+            while let Some((key, deps, idx)) = stack.last_mut() {
+                if let Some(dep) = deps.get(*idx).copied() {
+                    *idx += 1;
+                    match state.get(&dep) {
+                        Some(1) => {
+                            let start = stack.iter().position(|v| v.0 == dep).unwrap_or(0);
+                            let mut cycle: Vec<String> =
+                                stack[start..].iter().map(|v| self.cell_name(v.0)).collect();
+                            cycle.push(self.cell_name(dep));
+                            return Err(OdsError::Ods(format!(
+                                "Circular reference {}",
+                                cycle.join(" -> ")
+                            )));
+                        }
+                        Some(_) => {}
+                        None => {
+                            if !self.results.contains_key(&dep) {
+                                state.insert(dep, 1);
+                                let dep_deps = self.deps(dep)?;
+                                stack.push((dep, dep_deps, 0));
+                            }
+                        }
+                    }
+                } else {
+                    state.insert(*key, 2);
+                    order.push(*key);
+                    stack.pop();
+                }
+            }
+        }
+
+        for key in order {
+            let v = self.eval_expr(key.0, &self.formulas[&key])?;
+            let v = self.scalar(v);
+            self.results.insert(key, v);
+        }
+
+        Ok(())
+    }
+
+    fn cell_name(&self, (s, row, col): CellKey) -> String {
+        CellRef::remote(self.book.sheet(s).name(), row, col).to_string()
+    }
+
+    // Current value of a cell.
+    fn cell_val(&self, s: usize, row: u32, col: u32) -> Val {
+        if let Some(v) = self.results.get(&(s, row, col)) {
+            v.clone()
+        } else {
+            value_to_val(self.book.sheet(s).value(row, col))
+        }
+    }
+
+    // Resolves ranges to a single value.
+    fn scalar(&self, v: Val) -> Val {
+        match v {
+            Val::Range(s, row, col, to_row, to_col) => {
+                if row == to_row && col == to_col {
+                    self.cell_val(s, row, col)
+                } else {
+                    Val::Error(ERR_VALUE.to_string())
+                }
+            }
+            Val::Array(mut rows) => {
+                if rows.len() == 1 && rows[0].len() == 1 {
+                    rows[0].remove(0)
+                } else {
+                    Val::Error(ERR_VALUE.to_string())
+                }
+            }
+            v => v,
+        }
+    }
+
+    // All non-empty values of a range or array.
+    fn values(&self, v: &Val) -> Vec<Val> {
+        match v {
+            Val::Range(s, row, col, to_row, to_col) => self
+                .book
+                .sheet(*s)
+                .data
+                .range((*row, *col)..=(*to_row, *to_col))
+                .filter(|((_, c), _)| *c >= *col && *c <= *to_col)
+                .map(|((r, c), _)| self.cell_val(*s, *r, *c))
+                .filter(|v| *v != Val::Empty)
+                .collect(),
+            Val::Array(rows) => rows
+                .iter()
+                .flatten()
+                .filter(|v| **v != Val::Empty)
+                .cloned()
+                .collect(),
+            Val::Empty => Vec::new(),
+            v => vec![v.clone()],
+        }
+    }
+
+    // Non-empty values of the first column of a range or array with the row offset.
+    fn first_column(&self, v: &Val) -> Vec<(u32, Val)> {
+        match v {
+            Val::Range(s, row, col, to_row, _) => self
+                .book
+                .sheet(*s)
+                .data
+                .range((*row, *col)..=(*to_row, *col))
+                .filter(|((_, c), _)| *c == *col)
+                .map(|((r, c), _)| (*r - *row, self.cell_val(*s, *r, *c)))
+                .filter(|(_, v)| *v != Val::Empty)
+                .collect(),
+            Val::Array(rows) => rows
+                .iter()
+                .enumerate()
+                .filter_map(|(r, v)| v.first().map(|v| (r as u32, v.clone())))
+                .collect(),
+            v => vec![(0, v.clone())],
+        }
+    }
+
+    // Number of columns of a range or array.
+    fn width(&self, v: &Val) -> u32 {
+        match v {
+            Val::Range(_, _, col, _, to_col) => to_col - col + 1,
+            Val::Array(rows) => rows.iter().map(|v| v.len()).max().unwrap_or(0) as u32,
+            _ => 1,
+        }
+    }
+
+    // Value at the offset of a range or array.
+    fn offset(&self, v: &Val, row: u32, col: u32) -> Val {
+        match v {
+            Val::Range(s, r, c, _, _) => self.cell_val(*s, r + row, c + col),
+            Val::Array(rows) => rows
+                .get(row as usize)
+                .and_then(|v| v.get(col as usize))
+                .cloned()
+                .unwrap_or(Val::Empty),
+            v => v.clone(),
+        }
+    }
+
+    fn eval_scalar(&self, sheet: usize, expr: &Expr) -> Result<Val, OdsError> {
+        let v = self.eval_expr(sheet, expr)?;
+        Ok(self.scalar(v))
+    }
+
+    fn eval_expr(&self, sheet: usize, expr: &Expr) -> Result<Val, OdsError> {
+        let v = match expr {
+            Expr::Number(v) => Val::Number(*v),
+            Expr::Text(v) => Val::Text(v.clone()),
+            Expr::Error(v) => Val::Error(v.clone()),
+            Expr::InvalidRef(_) => Val::Error(ERR_REF.to_string()),
+            Expr::Missing => Val::Empty,
+            Expr::CellRef(c) => match resolve_sheet(self.book, sheet, c.table()) {
+                Some(s) => self.cell_val(s, c.row(), c.col()),
+                None => Val::Error(ERR_REF.to_string()),
+            },
+            Expr::CellRange(r) => match resolve_sheet(self.book, sheet, r.table()) {
+                Some(s) => Val::Range(
+                    s,
+                    r.row().min(r.to_row()),
+                    r.col().min(r.to_col()),
+                    r.row().max(r.to_row()),
+                    r.col().max(r.to_col()),
+                ),
+                None => Val::Error(ERR_REF.to_string()),
+            },
+            Expr::Name(n) => {
+                if n.eq_ignore_ascii_case("TRUE") {
+                    Val::Bool(true)
+                } else if n.eq_ignore_ascii_case("FALSE") {
+                    Val::Bool(false)
+                } else {
+                    Val::Error(ERR_NAME.to_string())
+                }
+            }
+            Expr::Parens(e) => self.eval_expr(sheet, e)?,
+            Expr::Unary(op, e) => {
+                let v = self.eval_scalar(sheet, e)?;
+                match op {
+                    UnaryOp::Plus => v,
+                    UnaryOp::Neg => Val::Number(-try_val!(to_number(&v))),
+                    UnaryOp::Percent => Val::Number(try_val!(to_number(&v)) / 100.0),
+                }
+            }
+            Expr::Binary(BinaryOp::Range, ..) => match ref_range(self.book, sheet, expr) {
+                Some((s, row, col, to_row, to_col)) => Val::Range(s, row, col, to_row, to_col),
+                None => Val::Error(ERR_REF.to_string()),
+            },
+            Expr::Binary(op, l, r) => {
+                let l = self.eval_scalar(sheet, l)?;
+                let r = self.eval_scalar(sheet, r)?;
+                binary(*op, l, r)?
+            }
+            Expr::Function(name, args) => self.call(sheet, &name.to_uppercase(), args)?,
+            Expr::Array(rows) => {
+                let mut vrows = Vec::new();
+                for row in rows {
+                    let mut vrow = Vec::new();
+                    for e in row {
+                        vrow.push(self.eval_scalar(sheet, e)?);
+                    }
+                    vrows.push(vrow);
+                }
+                Val::Array(vrows)
+            }
+        };
+        Ok(v)
+    }
+
+    // Collects the numbers of all parameters. Text and empty cells in
+    // references are ignored, other values must be numbers.
+    fn numbers(&self, sheet: usize, args: &[Expr]) -> Result<Result<Vec<f64>, String>, OdsError> {
+        let mut res = Vec::new();
+        for a in args {
+            if let Expr::Missing = a {
+                continue;
+            }
+            let v = self.eval_expr(sheet, a)?;
+            let cells = match v {
+                Val::Range(..) | Val::Array(..) => self.values(&v),
+                v if matches!(a, Expr::CellRef(_)) => self.values(&v),
+                v => match to_number(&v) {
+                    Ok(n) => {
+                        res.push(n);
+                        continue;
+                    }
+                    Err(e) => return Ok(Err(e)),
+                },
+            };
+            for v in cells {
+                match v {
+                    Val::Number(n) | Val::Date(n) => res.push(n),
+                    Val::Bool(b) => res.push(if b { 1.0 } else { 0.0 }),
+                    Val::Error(e) => return Ok(Err(e)),
+                    _ => {}
+                }
+            }
+        }
+        Ok(Ok(res))
+    }
+
+    fn arg(&self, sheet: usize, args: &[Expr], idx: usize) -> Result<Val, OdsError> {
+        match args.get(idx) {
+            Some(e) => self.eval_scalar(sheet, e),
+            None => Ok(Val::Empty),
+        }
+    }
+
+    fn call(&self, sheet: usize, name: &str, args: &[Expr]) -> Result<Val, OdsError> {
+        match arity(name) {
+            None => return Ok(Val::Error(ERR_NAME.to_string())),
+            Some((min, max)) if args.len() < min || args.len() > max => {
+                return Ok(Val::Error(ERR_NA.to_string()))
+            }
+            Some(_) => {}
+        }
+
+        let v = match name {
+            "SUM" => {
+                let v = try_val!(self.numbers(sheet, args)?);
+                Val::Number(v.iter().sum())
+            }
+            "PRODUCT" => {
+                let v = try_val!(self.numbers(sheet, args)?);
+                Val::Number(v.iter().product())
+            }
+            "AVERAGE" => {
+                let v = try_val!(self.numbers(sheet, args)?);
+                if v.is_empty() {
+                    Val::Error(ERR_DIV0.to_string())
+                } else {
+                    Val::Number(v.iter().sum::<f64>() / v.len() as f64)
+                }
+            }
+            "MIN" => {
+                let v = try_val!(self.numbers(sheet, args)?);
+                Val::Number(v.into_iter().reduce(f64::min).unwrap_or(0.0))
+            }
+            "MAX" => {
+                let v = try_val!(self.numbers(sheet, args)?);
+                Val::Number(v.into_iter().reduce(f64::max).unwrap_or(0.0))
+            }
+            "COUNT" => {
+                let mut count = 0;
+                for a in args {
+                    let v = self.eval_expr(sheet, a)?;
+                    count += self
+                        .values(&v)
+                        .iter()
+                        .filter(|v| matches!(v, Val::Number(_) | Val::Date(_)))
+                        .count();
+                }
+                Val::Number(count as f64)
+            }
+            "COUNTA" => {
+                let mut count = 0;
+                for a in args {
+                    let v = self.eval_expr(sheet, a)?;
+                    count += self.values(&v).len();
+                }
+                Val::Number(count as f64)
+            }
+            "IF" => {
+                let cond = self.arg(sheet, args, 0)?;
+                if try_val!(to_bool(&cond)) {
+                    match args.get(1) {
+                        Some(Expr::Missing) => Val::Number(0.0),
+                        Some(e) => self.eval_expr(sheet, e)?,
+                        None => Val::Bool(true),
+                    }
+                } else {
+                    match args.get(2) {
+                        Some(Expr::Missing) => Val::Number(0.0),
+                        Some(e) => self.eval_expr(sheet, e)?,
+                        None => Val::Bool(false),
+                    }
+                }
+            }
+            "IFERROR" => match self.arg(sheet, args, 0)? {
+                Val::Error(_) => self.eval_expr(sheet, &args[1])?,
+                v => v,
+            },
+            "ISERROR" => Val::Bool(matches!(self.arg(sheet, args, 0)?, Val::Error(_))),
+            "ISBLANK" => Val::Bool(matches!(self.arg(sheet, args, 0)?, Val::Empty)),
+            "TRUE" => Val::Bool(true),
+            "FALSE" => Val::Bool(false),
+            "NOT" => Val::Bool(!try_val!(to_bool(&self.arg(sheet, args, 0)?))),
+            "AND" | "OR" => {
+                let mut res = name == "AND";
+                for a in args {
+                    let v = self.eval_expr(sheet, a)?;
+                    for v in self.values(&v) {
+                        let b = match v {
+                            Val::Text(_) => continue,
+                            v => try_val!(to_bool(&v)),
+                        };
+                        if name == "AND" {
+                            res &= b;
+                        } else {
+                            res |= b;
+                        }
+                    }
+                }
+                Val::Bool(res)
+            }
+            "ABS" => Val::Number(try_val!(to_number(&self.arg(sheet, args, 0)?)).abs()),
+            "INT" => Val::Number(try_val!(to_number(&self.arg(sheet, args, 0)?)).floor()),
+            "MOD" => {
+                let a = try_val!(to_number(&self.arg(sheet, args, 0)?));
+                let b = try_val!(to_number(&self.arg(sheet, args, 1)?));
+                if b == 0.0 {
+                    Val::Error(ERR_DIV0.to_string())
+                } else {
+                    Val::Number(a - b * (a / b).floor())
+                }
+            }
+            "ROUND" | "ROUNDUP" | "ROUNDDOWN" => {
+                let v = try_val!(to_number(&self.arg(sheet, args, 0)?));
+                let digits = try_val!(to_number(&self.arg(sheet, args, 1)?)).trunc();
+                let f = 10f64.powf(digits);
+                let r = match name {
+                    "ROUND" => (v * f).round(),
+                    "ROUNDUP" => (v * f).abs().ceil().copysign(v),
+                    _ => (v * f).trunc(),
+                };
+                Val::Number(r / f)
+            }
+            "DATE" => {
+                let y = try_val!(to_number(&self.arg(sheet, args, 0)?)).trunc() as i32;
+                let m = try_val!(to_number(&self.arg(sheet, args, 1)?)).trunc() as i32;
+                let d = try_val!(to_number(&self.arg(sheet, args, 2)?)).trunc() as i64;
+                // two digit years as LibreOffice does by default.
+                let y = if (0..30).contains(&y) {
+                    y + 2000
+                } else if (30..100).contains(&y) {
+                    y + 1900
+                } else {
+                    y
+                };
+                let y = y + (m - 1).div_euclid(12);
+                let m = (m - 1).rem_euclid(12) + 1;
+                match NaiveDate::from_ymd_opt(y, m as u32, 1)
+                    .and_then(|v| v.and_hms_opt(0, 0, 0))
+                    .and_then(|v| v.checked_add_signed(Duration::days(d - 1)))
+                {
+                    Some(v) => Val::Date(to_serial(v)),
+                    None => Val::Error(ERR_NUM.to_string()),
+                }
+            }
+            "YEAR" | "MONTH" | "DAY" => {
+                let v = try_val!(to_number(&self.arg(sheet, args, 0)?));
+                match from_serial(v) {
+                    Some(d) => Val::Number(match name {
+                        "YEAR" => d.year() as f64,
+                        "MONTH" => d.month() as f64,
+                        _ => d.day() as f64,
+                    }),
+                    None => Val::Error(ERR_NUM.to_string()),
+                }
+            }
+            "TEXT" => {
+                let v = self.arg(sheet, args, 0)?;
+                let fmt = try_val!(to_text(&self.arg(sheet, args, 1)?));
+                match v {
+                    Val::Text(s) => Val::Text(s),
+                    v => Val::Text(format_text(try_val!(to_number(&v)), &fmt)),
+                }
+            }
+            "CONCATENATE" => {
+                let mut buf = String::new();
+                for i in 0..args.len() {
+                    buf.push_str(&try_val!(to_text(&self.arg(sheet, args, i)?)));
+                }
+                Val::Text(buf)
+            }
+            "LEN" => Val::Number(
+                try_val!(to_text(&self.arg(sheet, args, 0)?))
+                    .chars()
+                    .count() as f64,
+            ),
+            "UPPER" => Val::Text(try_val!(to_text(&self.arg(sheet, args, 0)?)).to_uppercase()),
+            "LOWER" => Val::Text(try_val!(to_text(&self.arg(sheet, args, 0)?)).to_lowercase()),
+            "VLOOKUP" => {
+                let needle = self.arg(sheet, args, 0)?;
+                if let Val::Error(e) = needle {
+                    return Ok(Val::Error(e));
+                }
+                let table = self.eval_expr(sheet, &args[1])?;
+                if let Val::Error(e) = table {
+                    return Ok(Val::Error(e));
+                }
+                let col = try_val!(to_number(&self.arg(sheet, args, 2)?)).trunc();
+                let sorted = match args.get(3) {
+                    None | Some(Expr::Missing) => true,
+                    Some(_) => try_val!(to_bool(&self.arg(sheet, args, 3)?)),
+                };
+                if col < 1.0 {
+                    return Ok(Val::Error(ERR_VALUE.to_string()));
+                }
+                if col as u32 > self.width(&table) {
+                    return Ok(Val::Error(ERR_REF.to_string()));
+                }
+
+                let mut found = None;
+                for (row, v) in self.first_column(&table) {
+                    if sorted {
+                        if type_rank(&v) != type_rank(&needle) {
+                            continue;
+                        }
+                        match compare(&v, &needle) {
+                            Ordering::Greater => break,
+                            _ => found = Some(row),
+                        }
+                    } else if compare(&v, &needle) == Ordering::Equal {
+                        found = Some(row);
+                        break;
+                    }
+                }
+                match found {
+                    Some(row) => self.offset(&table, row, col as u32 - 1),
+                    None => Val::Error(ERR_NA.to_string()),
+                }
+            }
+            _ => Val::Error(ERR_NAME.to_string()),
+        };
+        Ok(v)
+    }
+}
+
+// Minimum and maximum number of parameters of the supported functions.
+fn arity(name: &str) -> Option<(usize, usize)> {
+    let v = match name {
+        "SUM" | "PRODUCT" | "AVERAGE" | "MIN" | "MAX" | "COUNT" | "COUNTA" | "AND" | "OR"
+        | "CONCATENATE" => (1, usize::MAX),
+        "TRUE" | "FALSE" => (0, 0),
+        "ISERROR" | "ISBLANK" | "NOT" | "ABS" | "INT" | "YEAR" | "MONTH" | "DAY" | "LEN"
+        | "UPPER" | "LOWER" => (1, 1),
+        "IF" => (1, 3),
+        "ROUND" | "ROUNDUP" | "ROUNDDOWN" => (1, 2),
+        "IFERROR" | "MOD" | "TEXT" => (2, 2),
+        "DATE" => (3, 3),
+        "VLOOKUP" => (3, 4),
+        _ => return None,
+    };
+    Some(v)
+}
+
+/// Checks that the formula only uses supported functions with the right
+/// number of parameters.
+///
+/// recalc() doesn't fail for these, it stores #NAME? or #N/A in the
+/// cell as LibreOffice does.
+///
+/// ```
+/// use spreadsheet_ods::formula::check_formula;
+///
+/// assert!(check_formula("of:=SUM([.A1:.A3])").is_ok());
+/// assert!(check_formula("of:=MOD([.A1])").is_err());
+/// assert!(check_formula("of:=NOSUCHFUNCTION(1)").is_err());
+/// ```
+pub fn check_formula(formula: &str) -> Result<(), OdsError> {
+    let expr = parse(formula)?;
+    let mut res = Ok(());
+    expr.visit(&mut |e| {
+        if res.is_err() {
+            return;
+        }
+        if let Expr::Function(name, args) = e {
+            let name = name.to_uppercase();
+            match arity(&name) {
+                None => res = Err(OdsError::Ods(format!("Unsupported function {}", name))),
+                Some((min, max)) if args.len() < min || args.len() > max => {
+                    res = Err(OdsError::Ods(format!(
+                        "Wrong number of parameters for {}",
+                        name
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+    });
+    res
+}
+
+// All cells with a formula that are referenced by the expression.
+fn formula_cells(book: &WorkBook, sheet: usize, expr: &Expr) -> Vec<CellKey> {
+    let mut res = Vec::new();
+    for (s, row, col, to_row, to_col) in expr_ranges(book, sheet, expr) {
+        for ((r, c), cell) in book.sheet(s).data.range((row, col)..=(to_row, to_col)) {
+            if *c >= col && *c <= to_col && cell.formula.is_some() {
+                res.push((s, *r, *c));
+            }
+        }
+    }
+    res
+}
+
+// Days since 1899-12-30.
+fn to_serial(dt: NaiveDateTime) -> f64 {
+    (dt - base_date()).num_milliseconds() as f64 / 86_400_000.0
+}
+
+fn from_serial(v: f64) -> Option<NaiveDateTime> {
+    if !v.is_finite() || v.abs() > 3_000_000.0 {
+        return None;
+    }
+    base_date().checked_add_signed(Duration::milliseconds((v * 86_400_000.0).round() as i64))
+}
+
+fn value_to_val(v: &Value) -> Val {
+    match v {
+        Value::Empty => Val::Empty,
+        Value::Boolean(b) => Val::Bool(*b),
+        Value::Number(n) | Value::Percentage(n) | Value::Currency(n, _) => Val::Number(*n),
+        Value::Text(_) | Value::TextXml(_) => Val::Text(v.as_cow_str_or("").to_string()),
+        Value::DateTime(d) => Val::Date(to_serial(*d)),
+        Value::TimeDuration(d) => Val::Number(d.num_milliseconds() as f64 / 86_400_000.0),
+    }
+}
+
+// Converts the result. Keeps the value-type of the old value for numbers.
+fn to_value(v: Val, old: &Value) -> Value {
+    match v {
+        Val::Empty => Value::Number(0.0),
+        Val::Bool(b) => Value::Boolean(b),
+        Val::Number(n) => match old {
+            Value::Percentage(_) => Value::Percentage(n),
+            Value::Currency(_, c) => Value::Currency(n, *c),
+            Value::TimeDuration(_) => {
+                Value::TimeDuration(Duration::milliseconds((n * 86_400_000.0).round() as i64))
+            }
+            _ => Value::Number(n),
+        },
+        Val::Date(n) => match from_serial(n) {
+            Some(d) => Value::DateTime(d),
+            None => Value::Number(n),
+        },
+        Val::Text(s) => Value::Text(s),
+        Val::Error(e) => Value::Text(e),
+        Val::Range(..) | Val::Array(..) => Value::Text(ERR_VALUE.to_string()),
+    }
+}
+
+fn to_number(v: &Val) -> Result<f64, String> {
+    match v {
+        Val::Empty => Ok(0.0),
+        Val::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+        Val::Number(n) | Val::Date(n) => Ok(*n),
+        Val::Text(s) => s.trim().parse::<f64>().or_else(|_| err(ERR_VALUE)),
+        Val::Error(e) => Err(e.clone()),
+        Val::Range(..) | Val::Array(..) => err(ERR_VALUE),
+    }
+}
+
+fn to_bool(v: &Val) -> Result<bool, String> {
+    match v {
+        Val::Empty => Ok(false),
+        Val::Bool(b) => Ok(*b),
+        Val::Number(n) | Val::Date(n) => Ok(*n != 0.0),
+        Val::Text(s) => {
+            if s.eq_ignore_ascii_case("TRUE") {
+                Ok(true)
+            } else if s.eq_ignore_ascii_case("FALSE") {
+                Ok(false)
+            } else {
+                err(ERR_VALUE)
+            }
+        }
+        Val::Error(e) => Err(e.clone()),
+        Val::Range(..) | Val::Array(..) => err(ERR_VALUE),
+    }
+}
+
+fn to_text(v: &Val) -> Result<String, String> {
+    match v {
+        Val::Empty => Ok(String::new()),
+        Val::Bool(b) => Ok(if *b { "TRUE" } else { "FALSE" }.to_string()),
+        Val::Number(n) | Val::Date(n) => Ok(format_general(*n)),
+        Val::Text(s) => Ok(s.clone()),
+        Val::Error(e) => Err(e.clone()),
+        Val::Range(..) | Val::Array(..) => err(ERR_VALUE),
+    }
+}
+
+// Number as text with up to 15 significant digits.
+// Number, Text, Boolean
+fn type_rank(v: &Val) -> u8 {
+    match v {
+        Val::Empty | Val::Number(_) | Val::Date(_) => 0,
+        Val::Text(_) => 1,
+        Val::Bool(_) => 2,
+        Val::Error(_) | Val::Range(..) | Val::Array(..) => 3,
+    }
+}
+
+// Comparison as done for the comparison operators.
+// Numbers are sorted before text which comes before booleans.
+// Text comparison is case-insensitive.
+fn compare(l: &Val, r: &Val) -> Ordering {
+    match (l, r) {
+        (Val::Empty, Val::Text(s)) => "".cmp(&s.to_lowercase()),
+        (Val::Text(s), Val::Empty) => s.to_lowercase().as_str().cmp(""),
+        (Val::Empty, Val::Bool(b)) => false.cmp(b),
+        (Val::Bool(b), Val::Empty) => b.cmp(&false),
+        (Val::Text(a), Val::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (Val::Bool(a), Val::Bool(b)) => a.cmp(b),
+        (l, r) => {
+            let rank = type_rank(l).cmp(&type_rank(r));
+            if rank != Ordering::Equal {
+                rank
+            } else {
+                let a = to_number(l).unwrap_or(0.0);
+                let b = to_number(r).unwrap_or(0.0);
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            }
+        }
+    }
+}
+
+// The range operator is handled with the references, intersection and
+// union are not supported.
+fn binary(op: BinaryOp, l: Val, r: Val) -> Result<Val, OdsError> {
+    if let Val::Error(e) = l {
+        return Ok(Val::Error(e));
+    }
+    if let Val::Error(e) = r {
+        return Ok(Val::Error(e));
+    }
+
+    let v = match op {
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Pow => {
+            let a = try_val!(to_number(&l));
+            let b = try_val!(to_number(&r));
+            let res = match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                BinaryOp::Mul => a * b,
+                BinaryOp::Div => {
+                    if b == 0.0 {
+                        return Ok(Val::Error(ERR_DIV0.to_string()));
+                    }
+                    a / b
+                }
+                _ => a.powf(b),
+            };
+            if !res.is_finite() {
+                return Ok(Val::Error(ERR_NUM.to_string()));
+            }
+            // date arithmetic
+            match (op, &l, &r) {
+                (BinaryOp::Add, Val::Date(_), Val::Date(_)) => Val::Number(res),
+                (BinaryOp::Add, Val::Date(_), _) | (BinaryOp::Add, _, Val::Date(_)) => {
+                    Val::Date(res)
+                }
+                (BinaryOp::Sub, Val::Date(_), Val::Date(_)) => Val::Number(res),
+                (BinaryOp::Sub, Val::Date(_), _) => Val::Date(res),
+                _ => Val::Number(res),
+            }
+        }
+        BinaryOp::Concat => {
+            let mut a = try_val!(to_text(&l));
+            a.push_str(&try_val!(to_text(&r)));
+            Val::Text(a)
+        }
+        BinaryOp::Eq => Val::Bool(compare(&l, &r) == Ordering::Equal),
+        BinaryOp::Ne => Val::Bool(compare(&l, &r) != Ordering::Equal),
+        BinaryOp::Lt => Val::Bool(compare(&l, &r) == Ordering::Less),
+        BinaryOp::Le => Val::Bool(compare(&l, &r) != Ordering::Greater),
+        BinaryOp::Gt => Val::Bool(compare(&l, &r) == Ordering::Greater),
+        BinaryOp::Ge => Val::Bool(compare(&l, &r) != Ordering::Less),
+        BinaryOp::Range | BinaryOp::Intersect | BinaryOp::Union => {
+            Val::Error(ERR_VALUE.to_string())
+        }
+    };
+    Ok(v)
+}
+
+// Applies a format code as used by the TEXT function.
+fn format_text(v: f64, code: &str) -> String {
+    match parse_format_code("", code) {
+        Ok(formats) => match formats.last() {
+            Some(main) => format_mapped(main, &Value::Number(v), |name| {
+                formats.iter().find(|v| v.name() == name)
+            }),
+            None => format_general(v),
+        },
+        Err(_) => format_general(v),
+    }
+}
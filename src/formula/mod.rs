@@ -1,10 +1,34 @@
 //!
-//! For now defines functions to create cell references for formulas.
+//! Defines functions to create cell references for formulas,
+//! a parser that turns a formula into a syntax tree and an
+//! evaluator for a core set of functions.
+//!
+//! ```
+//! use spreadsheet_ods::formula::{parse, Expr};
+//! use spreadsheet_ods::CellRef;
+//!
+//! let mut f = parse("of:=[.A1]+[.B1]").unwrap();
+//! f.visit_mut(&mut |e| {
+//!     if let Expr::CellRef(c) = e {
+//!         c.set_row(c.row() + 1);
+//!     }
+//! });
+//! assert_eq!(f.to_formula(), "of:=[.A2]+[.B2]");
+//! ```
 //!
 
-use crate::refs::{CellRange, CellRef};
+pub(crate) mod adjust;
+mod ast;
+mod deps;
+mod eval;
+mod parser;
 
-// TODO: more formula stuff. parsing?
+pub use ast::*;
+pub use deps::DependencyGraph;
+pub use eval::*;
+pub use parser::*;
+
+use crate::refs::{CellRange, CellRef};
 
 /// Creates a cell-reference for use in formulas.
 pub fn fcellref(row: u32, col: u32) -> String {
@@ -0,0 +1,382 @@
+//!
+//! Parser for OpenFormula expressions.
+//!
+
+use crate::formula::{BinaryOp, Expr, UnaryOp};
+use crate::refs::{parse_cellrange, parse_cellref};
+use crate::OdsError;
+
+/// Parses a formula as found in table:formula.
+///
+/// The namespace prefix "of:" and the leading "=" are optional.
+///
+/// ```
+/// use spreadsheet_ods::formula::{parse, BinaryOp, Expr};
+///
+/// let f = parse("of:=SUM([.A1:.B3])*2").unwrap();
+/// if let Expr::Binary(op, _, _) = f {
+///     assert_eq!(op, BinaryOp::Mul);
+/// }
+/// ```
+pub fn parse(formula: &str) -> Result<Expr, OdsError> {
+    let mut buf = formula;
+    if let Some(idx) = buf.find(":=") {
+        // namespace prefix.
+        if buf[..idx].chars().all(|c| c.is_ascii_alphanumeric()) {
+            buf = &buf[idx + 1..];
+        }
+    }
+    let buf = buf.strip_prefix('=').unwrap_or(buf);
+
+    let mut pos = 0usize;
+    let expr = parse_expr(buf, &mut pos, 0)?;
+    skip_ws(buf, &mut pos);
+    if pos < buf.len() {
+        return Err(OdsError::Parse(format!(
+            "Unexpected input at {} in formula {}",
+            pos, formula
+        )));
+    }
+    Ok(expr)
+}
+
+fn skip_ws(buf: &str, pos: &mut usize) {
+    for (p, c) in buf[*pos..].char_indices() {
+        if !c.is_whitespace() {
+            *pos += p;
+            return;
+        }
+    }
+    *pos = buf.len();
+}
+
+fn peek(buf: &str, pos: &mut usize) -> Option<char> {
+    skip_ws(buf, pos);
+    buf[*pos..].chars().next()
+}
+
+// Reads the next infix operator without consuming it.
+fn peek_binary(buf: &str, pos: &mut usize) -> Option<(BinaryOp, usize)> {
+    skip_ws(buf, pos);
+    let s = &buf[*pos..];
+    let op = if s.starts_with("<>") {
+        (BinaryOp::Ne, 2)
+    } else if s.starts_with("<=") {
+        (BinaryOp::Le, 2)
+    } else if s.starts_with(">=") {
+        (BinaryOp::Ge, 2)
+    } else {
+        match s.chars().next()? {
+            '+' => (BinaryOp::Add, 1),
+            '-' => (BinaryOp::Sub, 1),
+            '*' => (BinaryOp::Mul, 1),
+            '/' => (BinaryOp::Div, 1),
+            '^' => (BinaryOp::Pow, 1),
+            '&' => (BinaryOp::Concat, 1),
+            '=' => (BinaryOp::Eq, 1),
+            '<' => (BinaryOp::Lt, 1),
+            '>' => (BinaryOp::Gt, 1),
+            ':' => (BinaryOp::Range, 1),
+            '!' => (BinaryOp::Intersect, 1),
+            '~' => (BinaryOp::Union, 1),
+            _ => return None,
+        }
+    };
+    Some(op)
+}
+
+// Precedence climbing. All infix operators are left associative.
+fn parse_expr(buf: &str, pos: &mut usize, min_prec: u8) -> Result<Expr, OdsError> {
+    let mut lhs = parse_unary(buf, pos)?;
+
+    while let Some((op, len)) = peek_binary(buf, pos) {
+        let prec = op.precedence();
+        if prec <= min_prec {
+            break;
+        }
+        *pos += len;
+        let rhs = parse_expr(buf, pos, prec)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+// Prefix operators bind tighter than the arithmetic operators, but the
+// reference operators : ! ~ bind tighter still. Postfix % comes between
+// the two.
+fn parse_unary(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    match peek(buf, pos) {
+        Some('-') => {
+            *pos += 1;
+            Ok(Expr::Unary(UnaryOp::Neg, Box::new(parse_unary(buf, pos)?)))
+        }
+        Some('+') => {
+            *pos += 1;
+            Ok(Expr::Unary(UnaryOp::Plus, Box::new(parse_unary(buf, pos)?)))
+        }
+        _ => {
+            let mut expr = parse_refexpr(buf, pos, 0)?;
+            while peek(buf, pos) == Some('%') {
+                *pos += 1;
+                expr = Expr::Unary(UnaryOp::Percent, Box::new(expr));
+            }
+            Ok(expr)
+        }
+    }
+}
+
+// Precedence climbing for the reference operators only.
+fn parse_refexpr(buf: &str, pos: &mut usize, min_prec: u8) -> Result<Expr, OdsError> {
+    let mut lhs = parse_primary(buf, pos)?;
+
+    while let Some((op, len)) = peek_binary(buf, pos) {
+        if !op.is_reference() {
+            break;
+        }
+        let prec = op.precedence();
+        if prec <= min_prec {
+            break;
+        }
+        *pos += len;
+        let rhs = parse_refexpr(buf, pos, prec)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+fn parse_primary(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    let c = match peek(buf, pos) {
+        None => {
+            return Err(OdsError::Parse(format!(
+                "Unexpected end of formula {}",
+                buf
+            )))
+        }
+        Some(c) => c,
+    };
+
+    match c {
+        '(' => {
+            *pos += 1;
+            let e = parse_expr(buf, pos, 0)?;
+            expect(buf, pos, ')')?;
+            Ok(Expr::Parens(Box::new(e)))
+        }
+        '[' => parse_reference(buf, pos),
+        '"' => parse_string(buf, pos),
+        '{' => parse_array(buf, pos),
+        '#' => parse_error(buf, pos),
+        '0'..='9' | '.' => parse_number(buf, pos),
+        c if c.is_alphabetic() || c == '_' => parse_identifier(buf, pos),
+        c => Err(OdsError::Parse(format!(
+            "Unexpected character {} at {} in formula {}",
+            c, pos, buf
+        ))),
+    }
+}
+
+fn expect(buf: &str, pos: &mut usize, c: char) -> Result<(), OdsError> {
+    if peek(buf, pos) == Some(c) {
+        *pos += c.len_utf8();
+        Ok(())
+    } else {
+        Err(OdsError::Parse(format!(
+            "Expected {} at {} in formula {}",
+            c, pos, buf
+        )))
+    }
+}
+
+fn parse_reference(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    // find the closing bracket, table names can be quoted.
+    let start = *pos + 1;
+    let mut end = None;
+    let mut quote = false;
+    for (p, c) in buf[start..].char_indices() {
+        if c == '\'' {
+            quote = !quote;
+        } else if c == ']' && !quote {
+            end = Some(start + p);
+            break;
+        }
+    }
+    let end = match end {
+        None => {
+            return Err(OdsError::Parse(format!(
+                "Missing ] for the reference at {} in formula {}",
+                pos, buf
+            )))
+        }
+        Some(end) => end,
+    };
+    *pos = end + 1;
+
+    let reference = &buf[start..end];
+    if reference.contains("#REF!") {
+        return Ok(Expr::InvalidRef(reference.to_string()));
+    }
+
+    let mut rpos = 0usize;
+    if let Ok(cellref) = parse_cellref(reference, &mut rpos) {
+        if rpos == reference.len() {
+            return Ok(Expr::CellRef(cellref));
+        }
+    }
+
+    let mut rpos = 0usize;
+    let range = parse_cellrange(reference, &mut rpos)?;
+    if rpos != reference.len() {
+        return Err(OdsError::Parse(format!(
+            "Invalid reference [{}] in formula {}",
+            reference, buf
+        )));
+    }
+    Ok(Expr::CellRange(range))
+}
+
+fn parse_string(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    let mut text = String::new();
+    let mut it = buf[*pos + 1..].char_indices().peekable();
+    while let Some((p, c)) = it.next() {
+        if c == '"' {
+            if let Some((_, '"')) = it.peek() {
+                it.next();
+                text.push('"');
+            } else {
+                *pos += 1 + p + 1;
+                return Ok(Expr::Text(text));
+            }
+        } else {
+            text.push(c);
+        }
+    }
+    Err(OdsError::Parse(format!(
+        "Unterminated string at {} in formula {}",
+        pos, buf
+    )))
+}
+
+fn parse_error(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    const ERRORS: [&str; 7] = [
+        "#NULL!", "#DIV/0!", "#VALUE!", "#REF!", "#NAME?", "#NUM!", "#N/A",
+    ];
+    let s = &buf[*pos..];
+    match ERRORS.iter().find(|e| s.starts_with(*e)) {
+        Some(e) => {
+            *pos += e.len();
+            Ok(Expr::Error(e.to_string()))
+        }
+        None => Err(OdsError::Parse(format!(
+            "Unknown error literal at {} in formula {}",
+            pos, buf
+        ))),
+    }
+}
+
+fn parse_number(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    let s = &buf[*pos..];
+    let b = s.as_bytes();
+    let mut i = 0;
+    while i < b.len() && b[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < b.len() && b[i] == b'.' {
+        i += 1;
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i < b.len() && (b[i] == b'e' || b[i] == b'E') {
+        let mut j = i + 1;
+        if j < b.len() && (b[j] == b'+' || b[j] == b'-') {
+            j += 1;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+    let v = s[..i]
+        .parse::<f64>()
+        .map_err(|_| OdsError::Parse(format!("Invalid number at {} in formula {}", pos, buf)))?;
+    *pos += i;
+    Ok(Expr::Number(v))
+}
+
+fn parse_identifier(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    let s = &buf[*pos..];
+    let len = s
+        .char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == '.'))
+        .map(|(p, _)| p)
+        .unwrap_or(s.len());
+    let name = &s[..len];
+    *pos += len;
+
+    if peek(buf, pos) == Some('(') {
+        *pos += 1;
+        let mut args = Vec::new();
+        if peek(buf, pos) == Some(')') {
+            *pos += 1;
+            return Ok(Expr::Function(name.to_string(), args));
+        }
+        loop {
+            match peek(buf, pos) {
+                Some(';') | Some(')') => args.push(Expr::Missing),
+                _ => args.push(parse_expr(buf, pos, 0)?),
+            }
+            match peek(buf, pos) {
+                Some(';') => *pos += 1,
+                Some(')') => {
+                    *pos += 1;
+                    break;
+                }
+                _ => {
+                    return Err(OdsError::Parse(format!(
+                        "Expected ; or ) at {} in formula {}",
+                        pos, buf
+                    )))
+                }
+            }
+        }
+        Ok(Expr::Function(name.to_string(), args))
+    } else {
+        Ok(Expr::Name(name.to_string()))
+    }
+}
+
+fn parse_array(buf: &str, pos: &mut usize) -> Result<Expr, OdsError> {
+    *pos += 1;
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    if peek(buf, pos) == Some('}') {
+        *pos += 1;
+        return Ok(Expr::Array(rows));
+    }
+    loop {
+        row.push(parse_expr(buf, pos, 0)?);
+        match peek(buf, pos) {
+            Some(';') => *pos += 1,
+            Some('|') => {
+                *pos += 1;
+                rows.push(std::mem::take(&mut row));
+            }
+            Some('}') => {
+                *pos += 1;
+                rows.push(row);
+                break;
+            }
+            _ => {
+                return Err(OdsError::Parse(format!(
+                    "Expected ;, | or }} at {} in formula {}",
+                    pos, buf
+                )))
+            }
+        }
+    }
+    Ok(Expr::Array(rows))
+}
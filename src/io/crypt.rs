@@ -0,0 +1,233 @@
+//!
+//! Encryption of the files in the package.
+//!
+//! Each file is deflated and then encrypted. The key is derived from
+//! the password with PBKDF2, the parameters are stored in the
+//! manifest:encryption-data of the manifest.
+//!
+//! Writes AES-256-CBC with a SHA-256 start key like LibreOffice.
+//! Reads that and the legacy Blowfish-CFB with a SHA-1 start key.
+//!
+
+use crate::OdsError;
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{AsyncStreamCipher, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::Aes256;
+use blowfish::Blowfish;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+pub(crate) const AES256_CBC: &str = "http://www.w3.org/2001/04/xmlenc#aes256-cbc";
+pub(crate) const BLOWFISH_CFB: &str = "Blowfish CFB";
+pub(crate) const SHA256: &str = "http://www.w3.org/2000/09/xmldsig#sha256";
+pub(crate) const SHA1: &str = "SHA1";
+pub(crate) const SHA256_1K: &str = "urn:oasis:names:tc:opendocument:xmlns:manifest:1.0#sha256-1k";
+pub(crate) const SHA1_1K: &str = "SHA1/1K";
+pub(crate) const SHA1_1K_URN: &str = "urn:oasis:names:tc:opendocument:xmlns:manifest:1.0#sha1-1k";
+pub(crate) const PBKDF2: &str = "PBKDF2";
+
+// Same as LibreOffice.
+const ITERATION_COUNT: u32 = 100000;
+// Upper limit for reading. A huge count from a crafted manifest would
+// keep PBKDF2 busy for hours.
+const MAX_ITERATION_COUNT: u32 = 10_000_000;
+
+/// Content of manifest:encryption-data. The binary values are already
+/// base64 decoded.
+#[derive(Clone, Debug)]
+pub(crate) struct EncryptionData {
+    pub(crate) checksum_type: String,
+    pub(crate) checksum: Vec<u8>,
+    pub(crate) algorithm_name: String,
+    pub(crate) initialisation_vector: Vec<u8>,
+    pub(crate) start_key_generation_name: String,
+    pub(crate) start_key_size: u32,
+    pub(crate) key_derivation_name: String,
+    pub(crate) key_size: u32,
+    pub(crate) iteration_count: u32,
+    pub(crate) salt: Vec<u8>,
+}
+
+impl Default for EncryptionData {
+    // Defaults as defined for the manifest attributes.
+    fn default() -> Self {
+        Self {
+            checksum_type: SHA1_1K.to_string(),
+            checksum: Vec::new(),
+            algorithm_name: BLOWFISH_CFB.to_string(),
+            initialisation_vector: Vec::new(),
+            start_key_generation_name: SHA1.to_string(),
+            start_key_size: 20,
+            key_derivation_name: PBKDF2.to_string(),
+            key_size: 16,
+            iteration_count: 0,
+            salt: Vec::new(),
+        }
+    }
+}
+
+/// Decrypts and inflates one file.
+pub(crate) fn decrypt(
+    data: &EncryptionData,
+    password: &str,
+    buf: Vec<u8>,
+) -> Result<Vec<u8>, OdsError> {
+    // The manifest is not trusted, the key size must fit the algorithm.
+    let key_size = match data.algorithm_name.as_str() {
+        AES256_CBC => 32,
+        BLOWFISH_CFB => 16,
+        v => {
+            return Err(OdsError::Ods(format!(
+                "Unsupported encryption algorithm {}",
+                v
+            )))
+        }
+    };
+    if data.key_size != key_size {
+        return Err(OdsError::Ods(format!(
+            "Invalid key size {} for {}",
+            data.key_size, data.algorithm_name
+        )));
+    }
+    if data.iteration_count == 0 || data.iteration_count > MAX_ITERATION_COUNT {
+        return Err(OdsError::Ods(format!(
+            "Invalid iteration count {}",
+            data.iteration_count
+        )));
+    }
+
+    let key = derive_key(data, password)?;
+
+    let compressed = match data.algorithm_name.as_str() {
+        AES256_CBC => {
+            let mut buf =
+                cbc::Decryptor::<Aes256>::new_from_slices(&key, &data.initialisation_vector)
+                    .map_err(|_| OdsError::Ods("Invalid key or initialisation vector".to_string()))?
+                    .decrypt_padded_vec_mut::<NoPadding>(&buf)
+                    .map_err(|_| OdsError::Ods("Invalid length of encrypted data".to_string()))?;
+            // W3C padding, only the last byte counts.
+            let pad = buf.last().copied().unwrap_or(0) as usize;
+            if pad == 0 || pad > 16 || pad > buf.len() {
+                return Err(OdsError::Ods("Invalid password".to_string()));
+            }
+            buf.truncate(buf.len() - pad);
+            buf
+        }
+        BLOWFISH_CFB => {
+            let mut buf = buf;
+            cfb_mode::Decryptor::<Blowfish>::new_from_slices(&key, &data.initialisation_vector)
+                .map_err(|_| OdsError::Ods("Invalid key or initialisation vector".to_string()))?
+                .decrypt(&mut buf);
+            buf
+        }
+        v => {
+            return Err(OdsError::Ods(format!(
+                "Unsupported encryption algorithm {}",
+                v
+            )))
+        }
+    };
+
+    if checksum(&data.checksum_type, &compressed)? != data.checksum {
+        return Err(OdsError::Ods("Invalid password".to_string()));
+    }
+
+    let mut buf = Vec::new();
+    DeflateDecoder::new(compressed.as_slice()).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Encrypts the files of one package with AES-256-CBC. The key is
+/// derived only once, all files share the salt but each one gets
+/// its own initialisation vector.
+pub(crate) struct Encryptor {
+    key: Vec<u8>,
+    salt: Vec<u8>,
+}
+
+impl Encryptor {
+    pub(crate) fn new(password: &str) -> Result<Self, OdsError> {
+        let salt = random_bytes(16)?;
+        let key = derive_key(&Self::encryption_data(salt.clone()), password)?;
+        Ok(Self { key, salt })
+    }
+
+    fn encryption_data(salt: Vec<u8>) -> EncryptionData {
+        EncryptionData {
+            checksum_type: SHA256_1K.to_string(),
+            checksum: Vec::new(),
+            algorithm_name: AES256_CBC.to_string(),
+            initialisation_vector: Vec::new(),
+            start_key_generation_name: SHA256.to_string(),
+            start_key_size: 32,
+            key_derivation_name: PBKDF2.to_string(),
+            key_size: 32,
+            iteration_count: ITERATION_COUNT,
+            salt,
+        }
+    }
+
+    /// Deflates and encrypts one file.
+    pub(crate) fn encrypt(&self, buf: &[u8]) -> Result<(Vec<u8>, EncryptionData), OdsError> {
+        let mut enc = DeflateEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(buf)?;
+        let compressed = enc.finish()?;
+
+        let mut data = Self::encryption_data(self.salt.clone());
+        data.initialisation_vector = random_bytes(16)?;
+        data.checksum = checksum(&data.checksum_type, &compressed)?;
+
+        let buf = cbc::Encryptor::<Aes256>::new_from_slices(&self.key, &data.initialisation_vector)
+            .map_err(|_| OdsError::Ods("Invalid key or initialisation vector".to_string()))?
+            .encrypt_padded_vec_mut::<Pkcs7>(&compressed);
+
+        Ok((buf, data))
+    }
+}
+
+// Start key and PBKDF2.
+fn derive_key(data: &EncryptionData, password: &str) -> Result<Vec<u8>, OdsError> {
+    let start_key = match data.start_key_generation_name.as_str() {
+        SHA256 => Sha256::digest(password.as_bytes()).to_vec(),
+        SHA1 | "http://www.w3.org/2000/09/xmldsig#sha1" => {
+            Sha1::digest(password.as_bytes()).to_vec()
+        }
+        v => {
+            return Err(OdsError::Ods(format!(
+                "Unsupported start key generation {}",
+                v
+            )))
+        }
+    };
+    if data.key_derivation_name != PBKDF2 {
+        return Err(OdsError::Ods(format!(
+            "Unsupported key derivation {}",
+            data.key_derivation_name
+        )));
+    }
+
+    let mut key = vec![0u8; data.key_size as usize];
+    pbkdf2::pbkdf2_hmac::<Sha1>(&start_key, &data.salt, data.iteration_count, &mut key);
+    Ok(key)
+}
+
+// Digest of the first 1024 bytes of the compressed data.
+fn checksum(checksum_type: &str, buf: &[u8]) -> Result<Vec<u8>, OdsError> {
+    let buf = &buf[..buf.len().min(1024)];
+    match checksum_type {
+        SHA256_1K => Ok(Sha256::digest(buf).to_vec()),
+        SHA1_1K | SHA1_1K_URN => Ok(Sha1::digest(buf).to_vec()),
+        v => Err(OdsError::Ods(format!("Unsupported checksum type {}", v))),
+    }
+}
+
+fn random_bytes(n: usize) -> Result<Vec<u8>, OdsError> {
+    let mut buf = vec![0u8; n];
+    getrandom::getrandom(&mut buf)
+        .map_err(|e| OdsError::Ods(format!("No random numbers available: {}", e)))?;
+    Ok(buf)
+}
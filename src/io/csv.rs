@@ -0,0 +1,513 @@
+//!
+//! Reads and writes CSV files.
+//!
+
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use encoding_rs::Encoding;
+use icu_locid::Locale;
+
+use crate::format::{format_default, locale_separators};
+use crate::{OdsError, Sheet, Value};
+
+/// Quoting of the fields when writing a CSV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvQuote {
+    /// Only fields containing the delimiter, quotes or line breaks.
+    Necessary,
+    /// All fields.
+    Always,
+    /// All fields that are not numbers.
+    NonNumeric,
+    /// Never quote. When reading, quotes are not special.
+    Never,
+}
+
+/// Options for reading and writing CSV and TSV files.
+///
+/// ```
+/// use icu_locid::locale;
+/// use spreadsheet_ods::{CsvOptions, Sheet};
+///
+/// let csv = "Name;Price;Date\nApple;1,50;24.12.2021\nPear;12%;";
+/// let options = CsvOptions::new()
+///     .delimiter(b';')
+///     .header(true)
+///     .locale(locale!("de-AT"));
+/// let sheet = Sheet::from_csv("fruit", csv.as_bytes(), &options).unwrap();
+/// assert_eq!(sheet.value(1, 1).as_f64_or(0.0), 1.5);
+///
+/// let mut buf = Vec::new();
+/// sheet.to_csv(&mut buf, &CsvOptions::new()).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    delimiter: u8,
+    quote: u8,
+    quote_style: CsvQuote,
+    encoding: &'static Encoding,
+    header: bool,
+    detect: bool,
+    locale: Option<Locale>,
+    formatted: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsvOptions {
+    /// Comma separated, UTF-8, detects the value types with the
+    /// number format of en-US and ISO dates.
+    pub fn new() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            quote_style: CsvQuote::Necessary,
+            encoding: encoding_rs::UTF_8,
+            header: false,
+            detect: true,
+            locale: None,
+            formatted: false,
+        }
+    }
+
+    /// Field delimiter. Use b'\t' for TSV.
+    #[must_use]
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Quote character.
+    #[must_use]
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// When to quote fields.
+    #[must_use]
+    pub fn quote_style(mut self, quote_style: CsvQuote) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Character encoding. When reading a byte order mark overrides this.
+    /// UTF-16 can only be read, writing uses UTF-8 instead.
+    #[must_use]
+    pub fn encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Reading: The first row contains the column names. They are read
+    /// as text and the row is marked as header row of the sheet.
+    #[must_use]
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Reading: Detect numbers, percentages, currencies, booleans and
+    /// dates. Otherwise everything is read as text.
+    #[must_use]
+    pub fn detect(mut self, detect: bool) -> Self {
+        self.detect = detect;
+        self
+    }
+
+    /// Reading: Locale for the decimal and grouping separator and the
+    /// order of day, month and year.
+    #[must_use]
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Writing: Use the display text of the cells as given by their
+    /// ValueFormat. Otherwise the raw values are written, numbers with
+    /// a decimal point and dates in ISO format.
+    #[must_use]
+    pub fn formatted(mut self, formatted: bool) -> Self {
+        self.formatted = formatted;
+        self
+    }
+}
+
+/// Reads a CSV file into a new sheet.
+pub(crate) fn read_csv<R: Read>(
+    sheet: &mut Sheet,
+    mut read: R,
+    options: &CsvOptions,
+) -> Result<(), OdsError> {
+    let mut buf = Vec::new();
+    read.read_to_end(&mut buf)?;
+    let (text, _, _) = options.encoding.decode(&buf);
+
+    let mut csv = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .quoting(options.quote_style != CsvQuote::Never)
+        .from_reader(text.as_bytes());
+
+    let detect = Detect::new(options.locale.as_ref());
+
+    // empty lines are skipped by the reader, but they are empty rows. The
+    // position of a record is where the reader started, before the empty
+    // lines.
+    let mut skip = 0u32;
+    for (row, record) in csv.records().enumerate() {
+        let record = record?;
+        if let Some(pos) = record.position() {
+            let empty = text[pos.byte() as usize..]
+                .bytes()
+                .take_while(|c| *c == b'\n' || *c == b'\r')
+                .filter(|c| *c == b'\n')
+                .count();
+            skip += empty as u32;
+        }
+        let row = row as u32 + skip;
+
+        for (col, field) in record.iter().enumerate() {
+            if field.is_empty() {
+                continue;
+            }
+            let value = if !options.detect || (options.header && row == 0) {
+                Value::Text(field.to_string())
+            } else {
+                detect.value(field)
+            };
+            sheet.set_value(row, col as u32, value);
+        }
+    }
+
+    if options.header {
+        sheet.set_header_rows(0, 0);
+    }
+
+    Ok(())
+}
+
+/// Writes the sheet as CSV. The function gives the display text for a
+/// cell, if the options ask for formatted values.
+pub(crate) fn write_csv<W, F>(
+    sheet: &Sheet,
+    mut write: W,
+    options: &CsvOptions,
+    format: F,
+) -> Result<(), OdsError>
+where
+    W: Write,
+    F: Fn(u32, u32, &Value) -> String,
+{
+    let mut builder = csv::WriterBuilder::new();
+    builder
+        .flexible(true)
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .quote_style(match options.quote_style {
+            CsvQuote::Necessary => csv::QuoteStyle::Necessary,
+            CsvQuote::Always => csv::QuoteStyle::Always,
+            CsvQuote::NonNumeric => csv::QuoteStyle::NonNumeric,
+            CsvQuote::Never => csv::QuoteStyle::Never,
+        });
+
+    let mut buf = Vec::new();
+    let (rows, cols) = sheet.used_grid_size();
+    let mut record = Vec::new();
+    let mut next_row = 0;
+    for row in 0..rows {
+        // repeated rows exist only once.
+        if row < next_row {
+            continue;
+        }
+
+        record.clear();
+        for col in 0..cols {
+            let value = sheet.value(row, col);
+            let text = if options.formatted {
+                Cow::from(format(row, col, value))
+            } else {
+                raw_text(value)
+            };
+            record.push(text);
+        }
+        // no trailing empty fields.
+        while matches!(record.last(), Some(v) if v.is_empty()) {
+            record.pop();
+        }
+
+        let repeat = sheet.row_repeat(row).max(1);
+        if record.is_empty() {
+            // the csv writer would write "" for an empty record. and don't
+            // repeat rows that only contain formatting.
+            buf.push(b'\n');
+        } else {
+            let mut csv = builder.from_writer(&mut buf);
+            for _ in 0..repeat {
+                csv.write_record(record.iter().map(|v| v.as_bytes()))?;
+            }
+            csv.flush()?;
+        }
+        next_row = row + repeat;
+    }
+    if options.encoding == encoding_rs::UTF_8 {
+        write.write_all(&buf)?;
+    } else {
+        let text = String::from_utf8_lossy(&buf);
+        let (buf, _, _) = options.encoding.encode(&text);
+        write.write_all(&buf)?;
+    }
+
+    Ok(())
+}
+
+// Value as plain text.
+fn raw_text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Number(v) | Value::Percentage(v) | Value::Currency(v, _) => Cow::from(v.to_string()),
+        Value::Text(s) => Cow::from(s),
+        Value::DateTime(d) => {
+            if d.time() == NaiveTime::from_hms_opt(0, 0, 0).expect("time") {
+                Cow::from(d.format("%Y-%m-%d").to_string())
+            } else {
+                Cow::from(d.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+            }
+        }
+        _ => Cow::from(format_default(value)),
+    }
+}
+
+// Order of the date parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    Dmy,
+    Mdy,
+    Ymd,
+}
+
+// Type detection for the fields.
+#[derive(Debug)]
+struct Detect {
+    decimal: char,
+    grouping: char,
+    date_order: DateOrder,
+    date_sep: char,
+}
+
+impl Detect {
+    fn new(locale: Option<&Locale>) -> Self {
+        let (decimal, grouping) = locale_separators(locale);
+        let (date_order, date_sep) = match locale {
+            Some(locale) => {
+                let language = locale.id.language.to_string();
+                let region = locale.id.region.map(|v| v.to_string());
+                match (language.as_str(), region.as_deref()) {
+                    ("en", None | Some("US")) => (DateOrder::Mdy, '/'),
+                    ("en" | "fr" | "es" | "it" | "pt" | "el", _) => (DateOrder::Dmy, '/'),
+                    ("nl", _) => (DateOrder::Dmy, '-'),
+                    ("ja" | "zh" | "ko" | "hu", _) => (DateOrder::Ymd, '.'),
+                    ("sv" | "lt", _) => (DateOrder::Ymd, '-'),
+                    _ => (DateOrder::Dmy, '.'),
+                }
+            }
+            None => (DateOrder::Ymd, '-'),
+        };
+
+        Self {
+            decimal,
+            grouping,
+            date_order,
+            date_sep,
+        }
+    }
+
+    fn value(&self, field: &str) -> Value {
+        let s = field.trim();
+        if s.eq_ignore_ascii_case("true") {
+            Value::Boolean(true)
+        } else if s.eq_ignore_ascii_case("false") {
+            Value::Boolean(false)
+        } else if let Some(v) = self.number(s) {
+            Value::Number(v)
+        } else if let Some(v) = self.percentage(s) {
+            Value::Percentage(v)
+        } else if let Some((v, cur)) = self.currency(s) {
+            Value::new_currency(cur, v)
+        } else if let Some(v) = self.datetime(s) {
+            Value::DateTime(v)
+        } else {
+            Value::Text(field.to_string())
+        }
+    }
+
+    fn percentage(&self, s: &str) -> Option<f64> {
+        let s = s.strip_suffix('%')?.trim_end();
+        self.number(s).map(|v| v / 100.0)
+    }
+
+    fn currency(&self, s: &str) -> Option<(f64, &'static str)> {
+        let (neg, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s.trim_start()),
+            None => (false, s),
+        };
+
+        for (sym, iso) in CURRENCIES {
+            let number = if let Some(v) = s.strip_prefix(sym) {
+                v.trim_start()
+            } else if let Some(v) = s.strip_suffix(sym) {
+                v.trim_end()
+            } else {
+                continue;
+            };
+            // -€5 and €-5 are both fine, but not both signs.
+            let v = match self.number(number) {
+                Some(v) if neg && v < 0.0 => return None,
+                Some(v) if neg => -v,
+                Some(v) => v,
+                None => continue,
+            };
+            return Some((v, iso));
+        }
+
+        None
+    }
+
+    fn number(&self, s: &str) -> Option<f64> {
+        let (mantissa, exp) = match s.find(['e', 'E']) {
+            Some(idx) => (&s[..idx], Some(s[idx + 1..].parse::<i32>().ok()?)),
+            None => (s, None),
+        };
+
+        let mut buf = String::new();
+        let mut chars = mantissa.chars().peekable();
+        if let Some(c) = chars.next_if(|c| *c == '-' || *c == '+') {
+            buf.push(c);
+        }
+
+        // integer part with optional grouping.
+        let int_start = buf.len();
+        let mut digits = 0;
+        let mut group = None;
+        while let Some(c) = chars.next_if(|c| *c != self.decimal) {
+            if c.is_ascii_digit() {
+                buf.push(c);
+                digits += 1;
+                group = group.map(|v| v + 1);
+            } else if c == self.grouping || (self.grouping == '\u{a0}' && c == ' ') {
+                // the first group has 1-3 digits, the others exactly 3.
+                match group {
+                    None if (1..=3).contains(&digits) => {}
+                    Some(3) => {}
+                    _ => return None,
+                }
+                group = Some(0);
+            } else {
+                return None;
+            }
+        }
+        if !matches!(group, None | Some(3)) {
+            return None;
+        }
+        // leading zeros are kept as text, like zip codes or article numbers.
+        if buf.len() - int_start > 1 && buf[int_start..].starts_with('0') {
+            return None;
+        }
+
+        // decimals.
+        if chars.next().is_some() {
+            buf.push('.');
+            for c in chars {
+                if c.is_ascii_digit() {
+                    buf.push(c);
+                    digits += 1;
+                } else {
+                    return None;
+                }
+            }
+        }
+
+        if digits == 0 {
+            return None;
+        }
+
+        let v = buf.parse::<f64>().ok()?;
+        match exp {
+            Some(exp) => Some(v * 10f64.powi(exp)),
+            None => Some(v),
+        }
+    }
+
+    fn datetime(&self, s: &str) -> Option<NaiveDateTime> {
+        let (date, time) = match s.split_once(['T', ' ']) {
+            Some((date, time)) => (date, Some(time.trim())),
+            None => (s, None),
+        };
+
+        let date = self
+            .date(date, DateOrder::Ymd, '-')
+            .or_else(|| self.date(date, self.date_order, self.date_sep))?;
+        let time = match time {
+            Some(time) => Self::time(time)?,
+            None => NaiveTime::from_hms_opt(0, 0, 0)?,
+        };
+
+        Some(date.and_time(time))
+    }
+
+    fn date(&self, s: &str, order: DateOrder, sep: char) -> Option<NaiveDate> {
+        let mut parts = s.split(sep);
+        let p0 = parts.next()?;
+        let p1 = parts.next()?;
+        let p2 = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let (y, m, d) = match order {
+            DateOrder::Dmy => (p2, p1, p0),
+            DateOrder::Mdy => (p2, p0, p1),
+            DateOrder::Ymd => (p0, p1, p2),
+        };
+        if !(1..=2).contains(&m.len()) || !(1..=2).contains(&d.len()) {
+            return None;
+        }
+        let year = match y.len() {
+            4 => y.parse::<i32>().ok()?,
+            2 => match y.parse::<i32>().ok()? {
+                y if y < 30 => 2000 + y,
+                y => 1900 + y,
+            },
+            _ => return None,
+        };
+
+        NaiveDate::from_ymd_opt(year, m.parse().ok()?, d.parse().ok()?)
+    }
+
+    fn time(s: &str) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(s, "%H:%M:%S%.f")
+            .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+            .ok()
+    }
+}
+
+// Recognized currency symbols.
+const CURRENCIES: [(&str, &str); 10] = [
+    ("€", "EUR"),
+    ("$", "USD"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("EUR", "EUR"),
+    ("USD", "USD"),
+    ("GBP", "GBP"),
+    ("JPY", "JPY"),
+    ("CHF", "CHF"),
+    ("Fr.", "CHF"),
+];
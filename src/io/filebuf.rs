@@ -57,6 +57,13 @@ impl FileBuf {
         false
     }
 
+    pub(crate) fn get<S: AsRef<str>>(&self, name: S) -> Option<&[u8]> {
+        self.buf.iter().find_map(|it| match it {
+            FileBufEntry::File(n, buf) if n == name.as_ref() => Some(buf.as_slice()),
+            _ => None,
+        })
+    }
+
     pub(crate) fn push_dir<S: Into<String>>(&mut self, dir: S) {
         self.buf.push(FileBufEntry::Dir(dir.into()));
     }
@@ -0,0 +1,65 @@
+//!
+//! Entries of META-INF/manifest.xml.
+//!
+
+use crate::io::crypt::EncryptionData;
+
+/// One manifest:file-entry.
+#[derive(Clone, Debug)]
+pub(crate) struct ManifestEntry {
+    pub(crate) full_path: String,
+    pub(crate) version: Option<String>,
+    pub(crate) media_type: String,
+    /// Uncompressed size. Only used for encrypted files.
+    pub(crate) size: Option<u64>,
+    pub(crate) encryption: Option<EncryptionData>,
+}
+
+impl ManifestEntry {
+    pub(crate) fn new<S: Into<String>, T: Into<String>>(full_path: S, media_type: T) -> Self {
+        Self {
+            full_path: full_path.into(),
+            version: None,
+            media_type: media_type.into(),
+            size: None,
+            encryption: None,
+        }
+    }
+}
+
+/// Holds the media-types of all files in the package.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Manifest {
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub(crate) fn iter(&self) -> core::slice::Iter<'_, ManifestEntry> {
+        self.entries.iter()
+    }
+
+    pub(crate) fn get<S: AsRef<str>>(&self, full_path: S) -> Option<&ManifestEntry> {
+        self.entries
+            .iter()
+            .find(|v| v.full_path == full_path.as_ref())
+    }
+
+    pub(crate) fn contains<S: AsRef<str>>(&self, full_path: S) -> bool {
+        self.entries
+            .iter()
+            .any(|v| v.full_path == full_path.as_ref())
+    }
+
+    /// Adds an entry. Replaces an existing entry with the same path.
+    pub(crate) fn push(&mut self, entry: ManifestEntry) {
+        if let Some(e) = self
+            .entries
+            .iter_mut()
+            .find(|v| v.full_path == entry.full_path)
+        {
+            *e = entry;
+        } else {
+            self.entries.push(entry);
+        }
+    }
+}
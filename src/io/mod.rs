@@ -1,8 +1,13 @@
+mod crypt;
+pub(crate) mod csv;
 pub(crate) mod filebuf;
+pub(crate) mod manifest;
 pub(crate) mod read;
+pub(crate) mod read_stream;
 pub(crate) mod write;
+pub(crate) mod write_stream;
 
-mod parse;
+pub(crate) mod parse;
 mod tmp2zip;
 mod xmlwriter;
 mod zip_out;
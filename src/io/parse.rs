@@ -4,8 +4,11 @@
 //! For many cases this omits the transformation to a &str
 
 use crate::{OdsError, Visibility};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use chrono::Duration;
 use chrono::NaiveDateTime;
+use color::Rgb;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::digit1;
@@ -65,6 +68,11 @@ pub(crate) fn parse_u32(input: &[u8]) -> Result<u32, OdsError> {
     Ok(token_u32(input)?.1)
 }
 
+/// Parse a u64.
+pub(crate) fn parse_u64(input: &[u8]) -> Result<u64, OdsError> {
+    Ok(from_utf8(input)?.parse::<u64>()?)
+}
+
 /// Parse a i64.
 pub(crate) fn parse_i64(input: &[u8]) -> Result<i64, OdsError> {
     Ok(token_i64(input)?.1)
@@ -85,11 +93,34 @@ pub(crate) fn parse_f64(input: &[u8]) -> Result<f64, OdsError> {
     Ok(token_float(input)?.1)
 }
 
+/// Parse base64 binary data.
+pub(crate) fn parse_base64(input: &[u8]) -> Result<Vec<u8>, OdsError> {
+    STANDARD
+        .decode(input)
+        .map_err(|e| OdsError::Parse(format!("Invalid base64 {}", e)))
+}
+
 /// Parse a XML Schema datetime.
 pub(crate) fn parse_datetime(input: &[u8]) -> Result<NaiveDateTime, OdsError> {
     Ok(token_datetime(input)?.1)
 }
 
+/// Parse a color #rrggbb.
+pub(crate) fn parse_color(input: &[u8]) -> Result<Rgb<u8>, OdsError> {
+    let hex = |v: &[u8]| u8::from_str_radix(from_utf8(v).ok()?, 16).ok();
+    if input.len() == 7 && input[0] == b'#' {
+        if let (Some(r), Some(g), Some(b)) =
+            (hex(&input[1..3]), hex(&input[3..5]), hex(&input[5..7]))
+        {
+            return Ok(Rgb::new(r, g, b));
+        }
+    }
+    Err(OdsError::Parse(format!(
+        "Invalid color {}",
+        from_utf8(input)?
+    )))
+}
+
 /// Parse a XML Schema time duration.
 pub(crate) fn parse_duration(input: &[u8]) -> Result<Duration, OdsError> {
     Ok(token_duration(input)?.1)
@@ -277,23 +308,29 @@ fn token_duration(input: &[u8]) -> IResult<&[u8], Duration> {
             opt(terminated(token_datepart, byte(b'Y'))),
             opt(terminated(token_datepart, byte(b'M'))),
             opt(terminated(token_datepart, byte(b'D'))),
-            byte(b'T'),
-            terminated(token_datepart, byte(b'H')),
-            terminated(token_datepart, byte(b'M')),
-            terminated(
-                pair(token_datepart, opt(preceded(byte(b'.'), token_nano))),
-                byte(b'S'),
-            ),
+            opt(preceded(
+                byte(b'T'),
+                tuple((
+                    opt(terminated(token_datepart, byte(b'H'))),
+                    opt(terminated(token_datepart, byte(b'M'))),
+                    opt(terminated(
+                        pair(token_datepart, opt(preceded(byte(b'.'), token_nano))),
+                        byte(b'S'),
+                    )),
+                )),
+            )),
         )),
         eof,
     )(input)?;
 
-    let result = if let Some(nanos) = result.7 .1 {
-        Duration::seconds(result.5 * 3600 + result.6 * 60 + result.7 .0)
-            + Duration::nanoseconds(nanos)
-    } else {
-        Duration::seconds(result.5 * 3600 + result.6 * 60 + result.7 .0)
-    };
+    // Years and months have no fixed length and are ignored.
+    let days = result.3.unwrap_or(0);
+    let (hours, minutes, seconds) = result.4.unwrap_or((None, None, None));
+    let (seconds, nanos) = seconds.unwrap_or((0, None));
+
+    let result = Duration::seconds(
+        days * 86400 + hours.unwrap_or(0) * 3600 + minutes.unwrap_or(0) * 60 + seconds,
+    ) + Duration::nanoseconds(nanos.unwrap_or(0));
 
     Ok((input, result))
 }
@@ -386,6 +423,10 @@ mod tests {
             parse_duration(b"PT12H12M12.223S")?.num_milliseconds(),
             43932223
         );
+        assert_eq!(parse_duration(b"PT2M10S")?.num_seconds(), 130);
+        assert_eq!(parse_duration(b"P1DT1H")?.num_seconds(), 90000);
+        assert_eq!(parse_duration(b"P0D")?.num_seconds(), 0);
+        parse_duration(b"PT1X").unwrap_err();
         Ok(())
     }
 
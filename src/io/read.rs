@@ -1,38 +1,54 @@
 use std::convert::{TryFrom, TryInto};
 use std::fs::File;
-use std::io::{BufReader, Cursor, Read, Seek};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
 use std::path::Path;
 
 use chrono::{Duration, NaiveDateTime};
+use color::Rgb;
 use quick_xml::events::{BytesStart, Event};
-use zip::read::ZipFile;
 use zip::ZipArchive;
 
+use crate::annotation::Annotation;
 use crate::attrmap2::AttrMap2;
 use crate::condition::{Condition, ValueCondition};
+use crate::conditional::{
+    CfCondition, CfRule, CfTest, CfValue, CfValueType, ColorScale, ConditionalFormat, DataBar,
+    IconSet, IconSetType,
+};
 use crate::config::{Config, ConfigItem, ConfigItemType, ConfigValue};
+use crate::database::{
+    DatabaseRange, Filter, FilterCondition, FilterDataType, FilterOperator, SortBy, SortDataType,
+    SortOrder,
+};
+use crate::draw::Image;
 use crate::ds::bufstack::BufStack;
 use crate::ds::detach::Detach;
 use crate::error::OdsError;
 use crate::format::{FormatPart, FormatPartType};
+use crate::io::crypt::{decrypt, EncryptionData};
+use crate::io::manifest::ManifestEntry;
 use crate::io::parse::{
-    parse_bool, parse_currency, parse_datetime, parse_duration, parse_f64, parse_i16, parse_i32,
-    parse_i64, parse_string, parse_u32, parse_visibility,
+    parse_base64, parse_bool, parse_color, parse_currency, parse_datetime, parse_duration,
+    parse_f64, parse_i16, parse_i32, parse_i64, parse_string, parse_u32, parse_u64,
+    parse_visibility,
 };
 use crate::io::{DUMP_UNUSED, DUMP_XML};
-use crate::refs::{parse_cellranges, parse_cellref};
+use crate::metadata::{MetaUserDefined, MetaValue, Metadata};
+use crate::named::{NamedExpression, NamedExpressions, NamedRange};
+use crate::protection::{ProtectionKey, SheetProtection};
+use crate::refs::{parse_cellrange, parse_cellranges, parse_cellref};
 use crate::style::stylemap::StyleMap;
 use crate::style::tabstop::TabStop;
 use crate::style::{
-    ColStyle, FontFaceDecl, GraphicStyle, HeaderFooter, MasterPage, PageStyle, ParagraphStyle,
-    RowStyle, StyleOrigin, StyleUse, TableStyle, TextStyle,
+    CellStyleRef, ColStyle, FontFaceDecl, GraphicStyle, GraphicStyleRef, HeaderFooter, MasterPage,
+    PageStyle, ParagraphStyle, RowStyle, StyleOrigin, StyleUse, TableStyle, TextStyle,
 };
 use crate::text::{TextP, TextTag};
 use crate::validation::{MessageType, Validation, ValidationError, ValidationHelp};
 use crate::xmltree::{XmlContent, XmlTag};
 use crate::{
-    CellData, CellStyle, ColRange, Length, RowRange, Sheet, SplitMode, Value, ValueFormat,
-    ValueType, Visibility, WorkBook,
+    add_group, CellData, CellStyle, ColRange, Grouped, RowRange, Sheet, SplitMode, Value,
+    ValueFormat, ValueType, Visibility, WorkBook,
 };
 use quick_xml::events::attributes::Attribute;
 use std::borrow::Cow;
@@ -41,32 +57,68 @@ use std::str::from_utf8;
 /// Reads an ODS-file from a buffer
 pub fn read_ods_buf(buf: &[u8]) -> Result<WorkBook, OdsError> {
     let zip = ZipArchive::new(Cursor::new(buf))?;
-    read_ods_impl(zip)
+    read_ods_impl(zip, None)
 }
 
 /// Reads an ODS-file.
 pub fn read_ods<P: AsRef<Path>>(path: P) -> Result<WorkBook, OdsError> {
     let file = File::open(path.as_ref())?;
     let zip = ZipArchive::new(file)?;
-    read_ods_impl(zip)
+    read_ods_impl(zip, None)
+}
+
+/// Reads a password protected ODS-file from a buffer.
+pub fn read_ods_buf_with_password(buf: &[u8], password: &str) -> Result<WorkBook, OdsError> {
+    let zip = ZipArchive::new(Cursor::new(buf))?;
+    read_ods_impl(zip, Some(password))
+}
+
+/// Reads a password protected ODS-file. Supports AES-256 as written by
+/// current LibreOffice versions and the older Blowfish encryption.
+/// A wrong password results in an error.
+///
+/// Files that are not encrypted are read as usual.
+pub fn read_ods_with_password<P: AsRef<Path>>(
+    path: P,
+    password: &str,
+) -> Result<WorkBook, OdsError> {
+    let file = File::open(path.as_ref())?;
+    let zip = ZipArchive::new(file)?;
+    read_ods_impl(zip, Some(password))
 }
 
 /// Reads an ODS-file.
-fn read_ods_impl<R: Read + Seek>(mut zip: ZipArchive<R>) -> Result<WorkBook, OdsError> {
+fn read_ods_impl<R: Read + Seek>(
+    mut zip: ZipArchive<R>,
+    password: Option<&str>,
+) -> Result<WorkBook, OdsError> {
     let mut book = WorkBook::new_empty();
     let mut bufstack = BufStack::new();
 
-    read_content(&mut bufstack, &mut book, &mut zip.by_name("content.xml")?)?;
-    read_styles(&mut bufstack, &mut book, &mut zip.by_name("styles.xml")?)?;
+    // may not exist. needed first, it knows about encrypted files.
+    if let Ok(z) = zip.by_name("META-INF/manifest.xml") {
+        read_manifest(&mut bufstack, &mut book, z)?;
+    }
+
+    let z = open_file(&mut zip, &book, "content.xml", password)?;
+    read_content(&mut bufstack, &mut book, z, true)?;
+    let z = open_file(&mut zip, &book, "styles.xml", password)?;
+    read_styles(&mut bufstack, &mut book, z)?;
     // may not exist.
-    if let Ok(mut z) = zip.by_name("settings.xml") {
-        read_settings(&mut bufstack, &mut book, &mut z)?;
+    if zip.by_name("settings.xml").is_ok() {
+        let z = open_file(&mut zip, &book, "settings.xml", password)?;
+        read_settings(&mut bufstack, &mut book, z)?;
     } else {
         book.config = default_settings();
     }
+    // may not exist.
+    if zip.by_name("meta.xml").is_ok() {
+        let z = open_file(&mut zip, &book, "meta.xml", password)?;
+        read_meta(&mut bufstack, &mut book, z)?;
+    }
 
     // read all extras.
-    read_filebuf(&mut book, &mut zip)?;
+    read_filebuf(&mut book, &mut zip, password)?;
 
     // We do some data duplication here, to make everything easier to use.
     calc_derived(&mut book)?;
@@ -74,22 +126,117 @@ fn read_ods_impl<R: Read + Seek>(mut zip: ZipArchive<R>) -> Result<WorkBook, Ods
     Ok(book)
 }
 
+/// Reads everything but the sheets. Used by the streaming reader.
+pub(crate) fn read_ods_head<R: Read + Seek>(
+    bs: &mut BufStack,
+    zip: &mut ZipArchive<R>,
+    password: Option<&str>,
+) -> Result<WorkBook, OdsError> {
+    let mut book = WorkBook::new_empty();
+
+    // may not exist.
+    if let Ok(z) = zip.by_name("META-INF/manifest.xml") {
+        read_manifest(bs, &mut book, z)?;
+    }
+
+    let z = open_file(zip, &book, "content.xml", password)?;
+    read_content(bs, &mut book, z, false)?;
+    let z = open_file(zip, &book, "styles.xml", password)?;
+    read_styles(bs, &mut book, z)?;
+    // may not exist.
+    if zip.by_name("settings.xml").is_ok() {
+        let z = open_file(zip, &book, "settings.xml", password)?;
+        read_settings(bs, &mut book, z)?;
+    } else {
+        book.config = default_settings();
+    }
+    // may not exist.
+    if zip.by_name("meta.xml").is_ok() {
+        let z = open_file(zip, &book, "meta.xml", password)?;
+        read_meta(bs, &mut book, z)?;
+    }
+
+    calc_derived(&mut book)?;
+
+    Ok(book)
+}
+
+/// Reads a flat ODS-file from a buffer.
+pub fn read_fods_buf(buf: &[u8]) -> Result<WorkBook, OdsError> {
+    read_fods_impl(buf)
+}
+
+/// Reads a flat ODS-file. This is a single XML document that contains
+/// the content, styles, settings and meta data.
+pub fn read_fods<P: AsRef<Path>>(path: P) -> Result<WorkBook, OdsError> {
+    let file = File::open(path.as_ref())?;
+    read_fods_impl(file)
+}
+
+/// Reads a flat ODS-file.
+fn read_fods_impl<R: Read>(read: R) -> Result<WorkBook, OdsError> {
+    let mut book = WorkBook::new_empty();
+    let mut bufstack = BufStack::new();
+
+    // may not exist.
+    book.config = default_settings();
+
+    read_content(&mut bufstack, &mut book, read, true)?;
+
+    // We do some data duplication here, to make everything easier to use.
+    calc_derived(&mut book)?;
+
+    Ok(book)
+}
+
+// Opens a file of the package. Encrypted files are decrypted.
+pub(crate) fn open_file<'a, R: Read + Seek>(
+    zip: &'a mut ZipArchive<R>,
+    book: &WorkBook,
+    name: &str,
+    password: Option<&str>,
+) -> Result<Box<dyn Read + 'a>, OdsError> {
+    let mut ze = zip.by_name(name)?;
+    match book.manifest.get(name).and_then(|v| v.encryption.as_ref()) {
+        Some(encryption) => {
+            let password = password.ok_or_else(|| {
+                OdsError::Ods("The file is encrypted, a password is needed".to_string())
+            })?;
+            let mut buf = Vec::new();
+            ze.read_to_end(&mut buf)?;
+            Ok(Box::new(Cursor::new(decrypt(encryption, password, buf)?)))
+        }
+        None => Ok(Box::new(ze)),
+    }
+}
+
 // Loads all unprocessed files as byte blobs into a buffer.
 fn read_filebuf<R: Read + Seek>(
     book: &mut WorkBook,
     zip: &mut ZipArchive<R>,
+    password: Option<&str>,
 ) -> Result<(), OdsError> {
     for idx in 0..zip.len() {
         let mut ze = zip.by_index(idx)?;
 
-        // These three are always interpreted and rewritten from scratch.
+        // These are always interpreted and rewritten from scratch.
         // They have their own mechanism to cope with unknown data.
-        if !matches!(ze.name(), "settings.xml" | "styles.xml" | "content.xml") {
+        if !matches!(
+            ze.name(),
+            "settings.xml" | "styles.xml" | "content.xml" | "meta.xml" | "META-INF/manifest.xml"
+        ) {
             if ze.is_dir() {
                 book.filebuf.push_dir(ze.name());
             } else if ze.is_file() {
                 let mut buf = Vec::new();
                 ze.read_to_end(&mut buf)?;
+                let encryption = book
+                    .manifest
+                    .get(ze.name())
+                    .and_then(|v| v.encryption.as_ref());
+                if let (Some(encryption), Some(password)) = (encryption, password) {
+                    buf = decrypt(encryption, password, buf)?;
+                }
                 book.filebuf.push_file(ze.name(), buf);
             }
         }
@@ -133,7 +280,9 @@ fn calc_derived(book: &mut WorkBook) -> Result<(), OdsError> {
             if let Some(style_name) = &ch.style {
                 if let Some(style) = book.colstyle(style_name) {
                     if style.use_optimal_col_width()? {
-                        ch.set_width(Length::Default);
+                        // LibreOffice doesn't recalculate the width on load,
+                        // keep the last one.
+                        ch.set_optimal_width(style.col_width()?);
                     } else {
                         ch.set_width(style.col_width()?);
                     }
@@ -146,7 +295,7 @@ fn calc_derived(book: &mut WorkBook) -> Result<(), OdsError> {
             if let Some(style_name) = &rh.style {
                 if let Some(style) = book.rowstyle(style_name) {
                     if style.use_optimal_row_height()? {
-                        rh.set_height(Length::Default);
+                        rh.set_optimal_height(style.row_height()?);
                     } else {
                         rh.set_height(style.row_height()?);
                     }
@@ -184,12 +333,27 @@ fn calc_derived(book: &mut WorkBook) -> Result<(), OdsError> {
             if let Some(ConfigValue::Short(n)) = cc.get_value_rec(&["ActiveSplitRange"]) {
                 sheet.config_mut().active_split_range = *n;
             }
+            if let Some(ConfigValue::Int(n)) = cc.get_value_rec(&["PositionLeft"]) {
+                sheet.config_mut().position_left = *n as u32;
+            }
+            if let Some(ConfigValue::Int(n)) = cc.get_value_rec(&["PositionRight"]) {
+                sheet.config_mut().position_right = *n as u32;
+            }
+            if let Some(ConfigValue::Int(n)) = cc.get_value_rec(&["PositionTop"]) {
+                sheet.config_mut().position_top = *n as u32;
+            }
+            if let Some(ConfigValue::Int(n)) = cc.get_value_rec(&["PositionBottom"]) {
+                sheet.config_mut().position_bottom = *n as u32;
+            }
             if let Some(ConfigValue::Short(n)) = cc.get_value_rec(&["ZoomType"]) {
                 sheet.config_mut().zoom_type = *n;
             }
             if let Some(ConfigValue::Int(n)) = cc.get_value_rec(&["ZoomValue"]) {
                 sheet.config_mut().zoom_value = *n;
             }
+            if let Some(ConfigValue::Int(n)) = cc.get_value_rec(&["PageViewZoomValue"]) {
+                sheet.config_mut().page_view_zoom_value = *n;
+            }
             if let Some(ConfigValue::Boolean(n)) = cc.get_value_rec(&["ShowGrid"]) {
                 sheet.config_mut().show_grid = *n;
             }
@@ -201,14 +365,18 @@ fn calc_derived(book: &mut WorkBook) -> Result<(), OdsError> {
     Ok(())
 }
 
-// Reads the content.xml
-fn read_content(
+// Reads the content.xml or a flat ODS-file. Stops at the first table
+// if tables is false.
+fn read_content<R: Read>(
     bs: &mut BufStack,
     book: &mut WorkBook,
-    zip_file: &mut ZipFile<'_>,
+    read: R,
+    tables: bool,
 ) -> Result<(), OdsError> {
     // xml parser
-    let mut xml = quick_xml::Reader::from_reader(BufReader::new(zip_file));
+    let mut xml = quick_xml::Reader::from_reader(BufReader::new(read));
+    // The flat format contains the styles.xml parts too.
+    let mut origin = StyleOrigin::Content;
     // Do not trim text data. All text read contains significant whitespace.
     // The rest is ignored anyway.
     //
@@ -225,17 +393,24 @@ fn read_content(
             Event::Decl(_) => {}
 
             Event::Start(xml_tag)
-            if xml_tag.name() == b"office:body"
-                || xml_tag.name() == b"office:spreadsheet" => {
+            if xml_tag.name() == b"office:body" => {
                 // noop
             }
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"office:spreadsheet" => {
+                read_spreadsheet_attr(book, xml_tag)?;
+            }
             Event::End(xml_tag)
             if xml_tag.name() == b"office:body"
                 || xml_tag.name() == b"office:spreadsheet" => {
                 // noop
             }
             Event::Start(xml_tag)
-            if xml_tag.name() == b"office:document-content" => {
+            if xml_tag.name() == b"office:document-content"
+                || xml_tag.name() == b"office:document" => {
+                if xml_tag.name() == b"office:document" {
+                    origin = StyleOrigin::Styles;
+                }
                 for attr in xml_tag.attributes().with_checks(false) {
                     match attr? {
                         attr if attr.key == b"office:version" => {
@@ -248,17 +423,28 @@ fn read_content(
                 }
             }
             Event::End(xml_tag)
-            if xml_tag.name() == b"office:document-content" => {
+            if xml_tag.name() == b"office:document-content"
+                || xml_tag.name() == b"office:document" => {
                 // noop
             }
 
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"office:meta" => {
+                let meta = read_xml(bs, b"office:meta", &mut xml, &xml_tag, empty_tag)?;
+                read_metadata(book, meta)?;
+            }
+
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"office:settings" =>
+                book.config = Detach::new(read_office_settings(bs, &mut xml)?),
+
             Event::Start(xml_tag)
             if xml_tag.name() == b"office:font-face-decls" =>
-                read_fonts(bs, book, StyleOrigin::Content, &mut xml)?,
+                read_fonts(bs, book, origin, &mut xml)?,
 
             Event::Start(xml_tag)
             if xml_tag.name() == b"office:styles" =>
-                read_styles_tag(bs, book, StyleOrigin::Content, &mut xml)?,
+                read_styles_tag(bs, book, origin, &mut xml)?,
 
             Event::Start(xml_tag)
             if xml_tag.name() == b"office:automatic-styles" =>
@@ -266,15 +452,23 @@ fn read_content(
 
             Event::Start(xml_tag)
             if xml_tag.name() == b"office:master-styles" =>
-                read_master_styles(bs, book, StyleOrigin::Content, &mut xml)?,
+                read_master_styles(bs, book, origin, &mut xml)?,
 
             Event::Start(xml_tag)
             if xml_tag.name() == b"table:content-validations" =>
                 read_validations(bs, book, &mut xml)?,
 
             Event::Start(xml_tag)
-            if xml_tag.name() == b"table:table" =>
-                book.push_sheet(read_table(bs, &mut xml, xml_tag)?),
+            if xml_tag.name() == b"table:table" => {
+                if tables {
+                    book.push_sheet(read_table(bs, &mut xml, xml_tag)?);
+                } else {
+                    // skip, but read the epilogue after the tables.
+                    let mut skip = bs.get_buf();
+                    xml.read_to_end(xml_tag.name(), &mut skip)?;
+                    bs.push(skip);
+                }
+            }
 
             Event::Empty(xml_tag) |
             Event::Start(xml_tag)
@@ -288,8 +482,6 @@ fn read_content(
                 xml_tag.name() == b"table:calculation-settings" ||
                 xml_tag.name() == b"table:label-ranges" ||
                 /* epilogue */
-                xml_tag.name() == b"table:named-expressions" ||
-                xml_tag.name() == b"table:database-ranges" ||
                 xml_tag.name() == b"table:data-pilot-tables" ||
                 xml_tag.name() == b"table:consolidation" ||
                 xml_tag.name() == b"table:dde-links" => {
@@ -297,6 +489,20 @@ fn read_content(
                 book.extra.push(v);
             }
 
+            Event::Empty(xml_tag) |
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"table:named-expressions" => {
+                let v = read_xml(bs, xml_tag.name(), &mut xml, &xml_tag, empty_tag)?;
+                read_named_expressions(&mut book.named_expressions, v)?;
+            }
+
+            Event::Empty(xml_tag) |
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"table:database-ranges" => {
+                let v = read_xml(bs, xml_tag.name(), &mut xml, &xml_tag, empty_tag)?;
+                read_database_ranges(book, v)?;
+            }
+
             Event::End(xml_tag)
             if /* prelude */ xml_tag.name() == b"office:scripts" ||
                 xml_tag.name() == b"table:tracked-changes" ||
@@ -332,9 +538,9 @@ fn read_content(
 }
 
 // Reads the table.
-fn read_table(
+fn read_table<B: BufRead>(
     bs: &mut BufStack,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: BytesStart<'_>,
 ) -> Result<Sheet, OdsError> {
     let mut sheet = Sheet::new("");
@@ -356,6 +562,9 @@ fn read_table(
 
     let mut col_range_from = 0;
     let mut row_range_from = 0;
+    // Open groups with start and display.
+    let mut col_groups = Vec::new();
+    let mut row_groups = Vec::new();
 
     let mut buf = bs.get_buf();
     loop {
@@ -370,6 +579,32 @@ fn read_table(
                 break;
             }
 
+            Event::Start(xml_tag) |
+            Event::Empty(xml_tag)
+            if xml_tag.name() == b"table:shapes" => {
+                let shapes = read_xml(bs, xml_tag.name(), xml, &xml_tag, empty_tag)?;
+                read_shapes(&mut sheet, shapes);
+            }
+
+            Event::Start(xml_tag) |
+            Event::Empty(xml_tag)
+            if xml_tag.name() == b"calcext:conditional-formats" => {
+                let cf = read_xml(bs, xml_tag.name(), xml, &xml_tag, empty_tag)?;
+                read_conditional_formats(&mut sheet, cf)?;
+            }
+
+            Event::Empty(xml_tag)
+            if xml_tag.name() == b"loext:table-protection" => {
+                read_table_protection(&mut sheet, xml_tag)?;
+            }
+
+            Event::Start(xml_tag) |
+            Event::Empty(xml_tag)
+            if xml_tag.name() == b"table:named-expressions" => {
+                let v = read_xml(bs, xml_tag.name(), xml, &xml_tag, empty_tag)?;
+                read_named_expressions(&mut sheet.named_expressions, v)?;
+            }
+
             Event::Start(xml_tag) |
             Event::Empty(xml_tag)
             if /* prelude */ xml_tag.name() == b"table:title" ||
@@ -377,11 +612,7 @@ fn read_table(
                 xml_tag.name() == b"table:table-source" ||
                 xml_tag.name() == b"office:dde-source" ||
                 xml_tag.name() == b"table:scenario" ||
-                xml_tag.name() == b"office:forms" ||
-                xml_tag.name() == b"table:shapes" ||
-                /* epilogue */
-                xml_tag.name() == b"table:named-expressions" ||
-                xml_tag.name() == b"calcext:conditional-formats" => {
+                xml_tag.name() == b"office:forms" => {
                 sheet.extra.push(read_xml(bs, xml_tag.name(), xml, &xml_tag, empty_tag)?);
             }
 
@@ -412,6 +643,30 @@ fn read_table(
                 table_col = read_table_col_attr(&mut sheet, table_col,  &xml_tag)?;
             }
 
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"table:table-column-group" => {
+                col_groups.push((table_col, read_group_attr(&xml_tag)?));
+            }
+
+            Event::End(xml_tag)
+            if xml_tag.name() == b"table:table-column-group" => {
+                if let Some((from, display)) = col_groups.pop() {
+                    read_group(&mut sheet.col_groups, from, table_col, display)?;
+                }
+            }
+
+            Event::Start(xml_tag)
+            if xml_tag.name() == b"table:table-row-group" => {
+                row_groups.push((row, read_group_attr(&xml_tag)?));
+            }
+
+            Event::End(xml_tag)
+            if xml_tag.name() == b"table:table-row-group" => {
+                if let Some((from, display)) = row_groups.pop() {
+                    read_group(&mut sheet.row_groups, from, row, display)?;
+                }
+            }
+
             Event::Start(xml_tag)
             if xml_tag.name() == b"table:table-header-rows" => {
                 row_range_from = row;
@@ -456,12 +711,14 @@ fn read_table(
 
             Event::Empty(xml_tag)
             if xml_tag.name() == b"table:table-cell" || xml_tag.name() == b"table:covered-table-cell" => {
-                col = read_empty_table_cell(&mut sheet, row, col, xml_tag)?;
+                let (cell, repeat) = read_empty_table_cell(xml_tag)?;
+                col = add_table_cell(&mut sheet, row, col, cell, repeat);
             }
 
             Event::Start(xml_tag)
             if xml_tag.name() == b"table:table-cell" || xml_tag.name() == b"table:covered-table-cell" => {
-                col = read_table_cell2(bs, &mut sheet, row, col, xml, xml_tag)?;
+                let (cell, repeat) = read_table_cell2(bs, xml, xml_tag)?;
+                col = add_table_cell(&mut sheet, row, col, Some(cell), repeat);
             }
 
             _ => {
@@ -475,8 +732,41 @@ fn read_table(
     Ok(sheet)
 }
 
+// Reads the display attribute of a row/column group.
+pub(crate) fn read_group_attr(xml_tag: &BytesStart<'_>) -> Result<bool, OdsError> {
+    let mut display = true;
+    for attr in xml_tag.attributes().with_checks(false) {
+        match attr? {
+            attr if attr.key == b"table:display" => {
+                display = parse_bool(&attr.value)?;
+            }
+            attr => {
+                dump_unused("read_group_attr", xml_tag.name(), &attr)?;
+            }
+        }
+    }
+    Ok(display)
+}
+
+// Adds a group that ended before next. Empty groups are dropped.
+pub(crate) fn read_group(
+    groups: &mut Vec<Grouped>,
+    from: u32,
+    next: u32,
+    display: bool,
+) -> Result<(), OdsError> {
+    if next > from {
+        add_group(groups, Grouped::new(from, next - 1, display))?;
+    }
+    Ok(())
+}
+
 // Reads the table attributes.
-fn read_table_attr(sheet: &mut Sheet, xml_tag: BytesStart<'_>) -> Result<(), OdsError> {
+pub(crate) fn read_table_attr(sheet: &mut Sheet, xml_tag: BytesStart<'_>) -> Result<(), OdsError> {
+    let mut protected = false;
+    let mut key = None;
+    let mut digest_algorithm = None;
+
     for attr in xml_tag.attributes().with_checks(false) {
         match attr? {
             attr if attr.key == b"table:name" => {
@@ -496,17 +786,98 @@ fn read_table_attr(sheet: &mut Sheet, xml_tag: BytesStart<'_>) -> Result<(), Ods
                 let mut pos = 0usize;
                 sheet.print_ranges = parse_cellranges(from_utf8(v.as_ref())?, &mut pos)?;
             }
+            attr if attr.key == b"table:protected" => {
+                protected = parse_bool(&attr.value)?;
+            }
+            attr if attr.key == b"table:protection-key" => {
+                key = Some(parse_string(&attr.value)?);
+            }
+            attr if attr.key == b"table:protection-key-digest-algorithm" => {
+                digest_algorithm = Some(parse_string(&attr.value)?);
+            }
             attr => {
                 dump_unused("read_table_attr", xml_tag.name(), &attr)?;
             }
         }
     }
 
+    if protected {
+        let mut protection = SheetProtection::new();
+        protection.set_key(key.map(|v| ProtectionKey::new(v, digest_algorithm)));
+        sheet.protection = Some(protection);
+    }
+
+    Ok(())
+}
+
+// Reads loext:table-protection. Only relevant for a protected sheet.
+pub(crate) fn read_table_protection(
+    sheet: &mut Sheet,
+    xml_tag: BytesStart<'_>,
+) -> Result<(), OdsError> {
+    let protection = match &mut sheet.protection {
+        Some(protection) => protection,
+        None => return Ok(()),
+    };
+
+    for attr in xml_tag.attributes().with_checks(false) {
+        match attr? {
+            attr if attr.key == b"loext:select-protected-cells" => {
+                protection.set_select_protected_cells(parse_bool(&attr.value)?);
+            }
+            attr if attr.key == b"loext:select-unprotected-cells" => {
+                protection.set_select_unprotected_cells(parse_bool(&attr.value)?);
+            }
+            attr if attr.key == b"loext:insert-columns" => {
+                protection.set_insert_columns(parse_bool(&attr.value)?);
+            }
+            attr if attr.key == b"loext:insert-rows" => {
+                protection.set_insert_rows(parse_bool(&attr.value)?);
+            }
+            attr if attr.key == b"loext:delete-columns" => {
+                protection.set_delete_columns(parse_bool(&attr.value)?);
+            }
+            attr if attr.key == b"loext:delete-rows" => {
+                protection.set_delete_rows(parse_bool(&attr.value)?);
+            }
+            attr => {
+                dump_unused("read_table_protection", xml_tag.name(), &attr)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Reads the attributes of office:spreadsheet.
+fn read_spreadsheet_attr(book: &mut WorkBook, xml_tag: BytesStart<'_>) -> Result<(), OdsError> {
+    let mut key = None;
+    let mut digest_algorithm = None;
+
+    for attr in xml_tag.attributes().with_checks(false) {
+        match attr? {
+            attr if attr.key == b"table:structure-protected" => {
+                book.structure_protected = parse_bool(&attr.value)?;
+            }
+            attr if attr.key == b"table:protection-key" => {
+                key = Some(parse_string(&attr.value)?);
+            }
+            attr if attr.key == b"table:protection-key-digest-algorithm" => {
+                digest_algorithm = Some(parse_string(&attr.value)?);
+            }
+            attr => {
+                dump_unused("read_spreadsheet_attr", xml_tag.name(), &attr)?;
+            }
+        }
+    }
+
+    book.structure_key = key.map(|v| ProtectionKey::new(v, digest_algorithm));
+
     Ok(())
 }
 
 // Reads table-row attributes. Returns the repeat-count.
-fn read_table_row_attr(
+pub(crate) fn read_table_row_attr(
     xml_tag: BytesStart<'_>,
 ) -> Result<(u32, Option<String>, Option<String>, Visibility), OdsError> {
     let mut row_repeat: u32 = 1;
@@ -539,7 +910,7 @@ fn read_table_row_attr(
 }
 
 // Reads the table-column attributes. Creates as many copies as indicated.
-fn read_table_col_attr(
+pub(crate) fn read_table_col_attr(
     sheet: &mut Sheet,
     mut table_col: u32,
     xml_tag: &BytesStart<'_>,
@@ -606,14 +977,33 @@ struct ReadTableCell2 {
     content: TextContent,
 }
 
-fn read_table_cell2(
-    bs: &mut BufStack,
+// Adds the cell with its repeat-count. Returns the next column.
+fn add_table_cell(
     sheet: &mut Sheet,
     row: u32,
     mut col: u32,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    cell: Option<CellData>,
+    mut cell_repeat: u32,
+) -> u32 {
+    if let Some(cell) = cell {
+        while cell_repeat > 1 {
+            sheet.add_cell_data(row, col, cell.clone());
+            col += 1;
+            cell_repeat -= 1;
+        }
+        sheet.add_cell_data(row, col, cell);
+        col + 1
+    } else {
+        col + cell_repeat
+    }
+}
+
+// Reads a table-cell. Returns the cell and the repeat-count.
+pub(crate) fn read_table_cell2<B: BufRead>(
+    bs: &mut BufStack,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: BytesStart<'_>,
-) -> Result<u32, OdsError> {
+) -> Result<(CellData, u32), OdsError> {
     // Current cell tag
     let tag_name = xml_tag.name();
 
@@ -625,6 +1015,7 @@ fn read_table_cell2(
         style: None,
         validation_name: None,
         span: Default::default(),
+        extra: None,
     };
 
     let mut tc = ReadTableCell2 {
@@ -711,18 +1102,24 @@ fn read_table_cell2(
             Event::Empty(xml_tag) if xml_tag.name() == b"text:p" => {
                 // noop
             }
+            Event::Start(xml_tag) if xml_tag.name() == b"office:annotation" => {
+                let annotation = read_annotation(bs, xml, &xml_tag, false)?;
+                cell.extra_mut().annotation = Some(annotation);
+            }
+            Event::Empty(xml_tag) if xml_tag.name() == b"office:annotation" => {
+                let annotation = read_annotation(bs, xml, &xml_tag, true)?;
+                cell.extra_mut().annotation = Some(annotation);
+            }
+            Event::Start(xml_tag) if xml_tag.name() == b"draw:frame" => {
+                let frame = read_xml(bs, b"draw:frame", xml, &xml_tag, false)?;
+                // other shapes are not supported.
+                if is_image_frame(&frame) {
+                    cell.extra_mut().images.push(frame_to_image(frame));
+                }
+            }
 
             Event::End(xml_tag) if xml_tag.name() == tag_name => {
                 parse_value2(tc, &mut cell)?;
-
-                while cell_repeat > 1 {
-                    sheet.add_cell_data(row, col, cell.clone());
-                    col += 1;
-                    cell_repeat -= 1;
-                }
-                sheet.add_cell_data(row, col, cell);
-                col += 1;
-
                 break;
             }
 
@@ -739,7 +1136,488 @@ fn read_table_cell2(
     }
     bs.push(buf);
 
-    Ok(col)
+    Ok((cell, cell_repeat))
+}
+
+// A draw:frame that contains a draw:image.
+fn is_image_frame(frame: &XmlTag) -> bool {
+    frame.name() == "draw:frame"
+        && frame.content().iter().any(|v| match v {
+            XmlContent::Tag(t) => {
+                t.name() == "draw:image" && t.attrmap().attr("xlink:href").is_some()
+            }
+            XmlContent::Text(_) => false,
+        })
+}
+
+// Converts a draw:frame with a draw:image.
+fn frame_to_image(frame: XmlTag) -> Image {
+    let mut image = Image::new("");
+    for (k, v) in frame.attrmap().iter() {
+        match k.as_ref() {
+            "draw:name" => image.set_name(v.as_str()),
+            "draw:style-name" => image.set_style(&GraphicStyleRef::from(v)),
+            _ => image.attrmap_mut().set_attr(k.as_ref(), v.clone()),
+        }
+    }
+    for c in frame.content() {
+        if let XmlContent::Tag(t) = c {
+            if t.name() == "draw:image" {
+                if let Some(href) = t.attrmap().attr("xlink:href") {
+                    image.set_href(href.as_str());
+                    break;
+                }
+            }
+        }
+    }
+    image
+}
+
+// Splits table:shapes into images and the rest.
+pub(crate) fn read_shapes(sheet: &mut Sheet, mut shapes: XmlTag) {
+    let content = std::mem::take(shapes.content_mut());
+    for c in content {
+        match c {
+            XmlContent::Tag(t) if is_image_frame(&t) => {
+                sheet.images.push(frame_to_image(t));
+            }
+            c => shapes.content_mut().push(c),
+        }
+    }
+    if !shapes.is_empty() {
+        sheet.extra.push(shapes);
+    }
+}
+
+// Converts table:named-expressions.
+pub(crate) fn read_named_expressions(
+    named: &mut NamedExpressions,
+    tag: XmlTag,
+) -> Result<(), OdsError> {
+    for c in tag.content() {
+        let t = match c {
+            XmlContent::Tag(t) => t,
+            XmlContent::Text(_) => continue,
+        };
+        let attr = t.attrmap();
+        let name = attr.attr_def("table:name", "");
+        match t.name() {
+            "table:named-range" => {
+                let range = attr.attr_def("table:cell-range-address", "");
+                let mut range = NamedRange::new(name, parse_cellrange(range, &mut 0)?);
+                if let Some(v) = attr.attr("table:base-cell-address") {
+                    range.set_base_cell(parse_cellref(v, &mut 0)?);
+                }
+                if let Some(v) = attr.attr("table:range-usable-as") {
+                    range.set_usable_as(v);
+                }
+                named.add_range(range);
+            }
+            "table:named-expression" => {
+                let expression = attr.attr_def("table:expression", "");
+                let mut expression = NamedExpression::new(name, expression);
+                if let Some(v) = attr.attr("table:base-cell-address") {
+                    expression.set_base_cell(parse_cellref(v, &mut 0)?);
+                }
+                named.add_expression(expression);
+            }
+            _ => {
+                if DUMP_UNUSED {
+                    println!(" read_named_expressions unused {:?}", t);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Converts table:database-ranges. The anonymous ranges are the
+// autofilters of the sheets.
+fn read_database_ranges(book: &mut WorkBook, mut tag: XmlTag) -> Result<(), OdsError> {
+    for c in std::mem::take(tag.content_mut()) {
+        let mut t = match c {
+            XmlContent::Tag(t) if t.name() == "table:database-range" => t,
+            XmlContent::Tag(t) => {
+                if DUMP_UNUSED {
+                    println!(" read_database_ranges unused {:?}", t);
+                }
+                continue;
+            }
+            XmlContent::Text(_) => continue,
+        };
+
+        let attr = t.attrmap();
+        let name = attr.attr_def("table:name", "").to_string();
+        let range = parse_cellrange(attr.attr_def("table:target-range-address", ""), &mut 0)?;
+        let mut dr = DatabaseRange::new(name, range);
+        for (k, v) in t.attrmap().iter() {
+            match k.as_ref() {
+                "table:name" | "table:target-range-address" => {}
+                "table:contains-header" => dr.set_contains_header(v == "true"),
+                "table:display-filter-buttons" => dr.set_display_filter_buttons(v == "true"),
+                _ => dr.attrmap_mut().set_attr(k.as_ref(), v.clone()),
+            }
+        }
+
+        // Anything that can't be read is kept as it is.
+        for c in std::mem::take(t.content_mut()) {
+            match c {
+                XmlContent::Tag(t) if t.name() == "table:filter" => match read_filter_tag(&t) {
+                    Ok(filter) => {
+                        for (k, v) in t.attrmap().iter() {
+                            dr.filter_attrmap_mut().set_attr(k.as_ref(), v.clone());
+                        }
+                        dr.set_filter(Some(filter));
+                    }
+                    Err(_) => dr.extra.push(t),
+                },
+                XmlContent::Tag(t) if t.name() == "table:sort" => match read_sort(&t) {
+                    Ok(sort) => {
+                        for (k, v) in t.attrmap().iter() {
+                            dr.sort_attrmap_mut().set_attr(k.as_ref(), v.clone());
+                        }
+                        for sort_by in sort {
+                            dr.push_sort(sort_by);
+                        }
+                    }
+                    Err(_) => dr.extra.push(t),
+                },
+                XmlContent::Tag(t) => dr.extra.push(t),
+                XmlContent::Text(_) => {}
+            }
+        }
+
+        if let Some(idx) = dr.name().strip_prefix("__Anonymous_Sheet_DB__") {
+            let idx = match dr.range().table() {
+                Some(table) => book.sheet_idx(table),
+                None => None,
+            }
+            .or_else(|| idx.parse::<usize>().ok());
+            if let Some(sheet) = idx.and_then(|idx| book.sheets.get_mut(idx)) {
+                sheet.database_range = Some(dr);
+            } else {
+                // no sheets when streaming.
+                book.database_ranges.push(dr);
+            }
+        } else {
+            book.database_ranges.push(dr);
+        }
+    }
+    Ok(())
+}
+
+// Converts the content of table:filter. Fails for anything unexpected.
+fn read_filter_tag(tag: &XmlTag) -> Result<Filter, OdsError> {
+    let mut filter = None;
+    for c in tag.content() {
+        match c {
+            XmlContent::Tag(t) if filter.is_none() => filter = Some(read_filter(t)?),
+            XmlContent::Tag(t) => {
+                return Err(OdsError::Parse(format!(
+                    "Unexpected {} in table:filter",
+                    t.name()
+                )))
+            }
+            XmlContent::Text(_) => {}
+        }
+    }
+    filter.ok_or_else(|| OdsError::Parse("Empty table:filter".to_string()))
+}
+
+// Converts table:filter-and, table:filter-or and table:filter-condition.
+fn read_filter(tag: &XmlTag) -> Result<Filter, OdsError> {
+    match tag.name() {
+        "table:filter-condition" => {
+            let attr = tag.attrmap();
+            for (k, _) in attr.iter() {
+                match k.as_ref() {
+                    "table:field-number"
+                    | "table:operator"
+                    | "table:value"
+                    | "table:data-type"
+                    | "table:case-sensitive" => {}
+                    _ => return Err(OdsError::Parse(format!("Unexpected attribute {}", k))),
+                }
+            }
+            let mut cond = FilterCondition::new(
+                attr.attr_def("table:field-number", "0").parse::<u32>()?,
+                FilterOperator::try_from(attr.attr_def("table:operator", ""))?,
+                attr.attr_def("table:value", ""),
+            );
+            cond.set_data_type(FilterDataType::try_from(
+                attr.attr_def("table:data-type", "text"),
+            )?);
+            cond.set_case_sensitive(attr.attr_def("table:case-sensitive", "false") == "true");
+            let mut set_items = Vec::new();
+            for c in tag.content() {
+                match c {
+                    XmlContent::Tag(t) if t.name() == "table:filter-set-item" => {
+                        set_items.push(t.attrmap().attr_def("table:value", "").to_string());
+                    }
+                    XmlContent::Tag(t) => {
+                        return Err(OdsError::Parse(format!("Unexpected {}", t.name())))
+                    }
+                    XmlContent::Text(_) => {}
+                }
+            }
+            cond.set_set_items(set_items);
+            Ok(Filter::Condition(cond))
+        }
+        "table:filter-and" | "table:filter-or" => {
+            let mut filters = Vec::new();
+            for c in tag.content() {
+                if let XmlContent::Tag(t) = c {
+                    filters.push(read_filter(t)?);
+                }
+            }
+            if tag.name() == "table:filter-and" {
+                Ok(Filter::And(filters))
+            } else {
+                Ok(Filter::Or(filters))
+            }
+        }
+        _ => Err(OdsError::Parse(format!("Unexpected {}", tag.name()))),
+    }
+}
+
+// Converts the content of table:sort. Fails for anything unexpected.
+fn read_sort(tag: &XmlTag) -> Result<Vec<SortBy>, OdsError> {
+    let mut sort = Vec::new();
+    for c in tag.content() {
+        match c {
+            XmlContent::Tag(t) if t.name() == "table:sort-by" => sort.push(read_sort_by(t)?),
+            XmlContent::Tag(t) => {
+                return Err(OdsError::Parse(format!(
+                    "Unexpected {} in table:sort",
+                    t.name()
+                )))
+            }
+            XmlContent::Text(_) => {}
+        }
+    }
+    Ok(sort)
+}
+
+// Converts table:sort-by.
+fn read_sort_by(tag: &XmlTag) -> Result<SortBy, OdsError> {
+    let attr = tag.attrmap();
+    for (k, _) in attr.iter() {
+        match k.as_ref() {
+            "table:field-number" | "table:order" | "table:data-type" => {}
+            _ => return Err(OdsError::Parse(format!("Unexpected attribute {}", k))),
+        }
+    }
+    let mut sort_by = SortBy::new(
+        attr.attr_def("table:field-number", "0").parse::<u32>()?,
+        SortOrder::try_from(attr.attr_def("table:order", "ascending"))?,
+    );
+    // Other values are the names of user defined sort lists.
+    sort_by.set_data_type(SortDataType::from(
+        attr.attr_def("table:data-type", "automatic"),
+    ));
+    Ok(sort_by)
+}
+
+// Converts calcext:conditional-formats.
+pub(crate) fn read_conditional_formats(sheet: &mut Sheet, mut tag: XmlTag) -> Result<(), OdsError> {
+    for c in std::mem::take(tag.content_mut()) {
+        let mut cf_tag = match c {
+            XmlContent::Tag(t) if t.name() == "calcext:conditional-format" => t,
+            _ => continue,
+        };
+
+        let range = match cf_tag.attrmap().attr("calcext:target-range-address") {
+            Some(v) => parse_cellranges(v, &mut 0)?.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let mut cf = ConditionalFormat::new_ranges(range);
+        for c in std::mem::take(cf_tag.content_mut()) {
+            if let XmlContent::Tag(t) = c {
+                cf.push_rule(read_cf_rule(t));
+            }
+        }
+        sheet.conditional_formats.push(cf);
+    }
+    Ok(())
+}
+
+// Converts one rule. Anything unknown is kept as xml.
+fn read_cf_rule(tag: XmlTag) -> CfRule {
+    let rule = match tag.name() {
+        "calcext:condition" => read_cf_condition(&tag),
+        "calcext:color-scale" => read_cf_color_scale(&tag),
+        "calcext:data-bar" => read_cf_data_bar(&tag),
+        "calcext:icon-set" => read_cf_icon_set(&tag),
+        _ => Ok(None),
+    };
+    match rule {
+        Ok(Some(rule)) => rule,
+        _ => CfRule::Other(tag),
+    }
+}
+
+fn read_cf_condition(tag: &XmlTag) -> Result<Option<CfRule>, OdsError> {
+    let attr = tag.attrmap();
+    let (test, style) = match (
+        attr.attr("calcext:value"),
+        attr.attr("calcext:apply-style-name"),
+    ) {
+        (Some(test), Some(style)) => (CfTest::parse(test), CellStyleRef::from(style)),
+        _ => return Ok(None),
+    };
+    let mut cond = CfCondition::new(test, &style);
+    if let Some(v) = attr.attr("calcext:base-cell-address") {
+        cond.set_base_cell(parse_cellref(v, &mut 0)?);
+    }
+    Ok(Some(CfRule::Condition(cond)))
+}
+
+fn read_cf_value(tag: &XmlTag) -> Result<CfValue, OdsError> {
+    let attr = tag.attrmap();
+    let value_type = CfValueType::try_from(attr.attr_def("calcext:type", ""))?;
+    let value = attr.attr_def("calcext:value", "0");
+    Ok(CfValue::new(value_type, value))
+}
+
+fn read_cf_color_scale(tag: &XmlTag) -> Result<Option<CfRule>, OdsError> {
+    let mut scale = ColorScale::new();
+    for c in tag.content() {
+        match c {
+            XmlContent::Tag(t) if t.name() == "calcext:color-scale-entry" => {
+                let color = parse_color(t.attrmap().attr_def("calcext:color", "").as_bytes())?;
+                scale.push(read_cf_value(t)?, color);
+            }
+            XmlContent::Text(_) => {}
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(CfRule::ColorScale(scale)))
+}
+
+fn read_cf_data_bar(tag: &XmlTag) -> Result<Option<CfRule>, OdsError> {
+    let mut bar = DataBar::new(Rgb::new(0, 0, 0));
+    for (k, v) in tag.attrmap().iter() {
+        match k.as_ref() {
+            "calcext:positive-color" => bar.set_positive_color(parse_color(v.as_bytes())?),
+            "calcext:negative-color" => bar.set_negative_color(parse_color(v.as_bytes())?),
+            "calcext:gradient" => bar.set_gradient(parse_bool(v.as_bytes())?),
+            _ => bar.attrmap_mut().set_attr(k.as_ref(), v.to_string()),
+        }
+    }
+    let mut entries = Vec::new();
+    for c in tag.content() {
+        match c {
+            XmlContent::Tag(t) if t.name() == "calcext:formatting-entry" => {
+                entries.push(read_cf_value(t)?);
+            }
+            XmlContent::Text(_) => {}
+            _ => return Ok(None),
+        }
+    }
+    if let [min, max] = entries.as_slice() {
+        bar.set_min(min.clone());
+        bar.set_max(max.clone());
+        Ok(Some(CfRule::DataBar(bar)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_cf_icon_set(tag: &XmlTag) -> Result<Option<CfRule>, OdsError> {
+    let attr = tag.attrmap();
+    let mut icons = IconSet::new(IconSetType::try_from(
+        attr.attr_def("calcext:icon-set-type", ""),
+    )?);
+    if let Some(v) = attr.attr("calcext:show-value") {
+        icons.set_show_value(parse_bool(v.as_bytes())?);
+    }
+    let mut entries = Vec::new();
+    for c in tag.content() {
+        match c {
+            XmlContent::Tag(t) if t.name() == "calcext:formatting-entry" => {
+                entries.push(read_cf_value(t)?);
+            }
+            XmlContent::Text(_) => {}
+            _ => return Ok(None),
+        }
+    }
+    icons.set_entries(entries);
+    Ok(Some(CfRule::IconSet(icons)))
+}
+
+// Reads an office:annotation.
+fn read_annotation<B: BufRead>(
+    bs: &mut BufStack,
+    xml: &mut quick_xml::Reader<B>,
+    xml_tag: &BytesStart<'_>,
+    empty_tag: bool,
+) -> Result<Annotation, OdsError> {
+    let mut annotation = Annotation::new_empty();
+
+    for attr in xml_tag.attributes().with_checks(false) {
+        match attr? {
+            attr if attr.key == b"office:name" => {
+                annotation.set_name(parse_string(&attr.value)?);
+            }
+            attr if attr.key == b"office:display" => {
+                annotation.set_display(parse_bool(&attr.value)?);
+            }
+            attr => {
+                let k = xml.decode(attr.key)?;
+                let v = attr.unescape_and_decode_value(xml)?;
+                annotation.attrmap_mut().set_attr(k, v);
+            }
+        }
+    }
+
+    if empty_tag {
+        return Ok(annotation);
+    }
+
+    let mut buf = bs.get_buf();
+    loop {
+        let evt = xml.read_event(&mut buf)?;
+        let empty_tag = matches!(evt, Event::Empty(_));
+        if DUMP_XML {
+            println!(" read_annotation {:?}", evt);
+        }
+        match evt {
+            Event::Start(ref xml_tag) if xml_tag.name() == b"dc:creator" => {
+                let mut author = String::new();
+                read_xml(bs, b"dc:creator", xml, xml_tag, false)?.extract_text(&mut author);
+                annotation.set_author(author);
+            }
+            Event::Start(ref xml_tag) if xml_tag.name() == b"dc:date" => {
+                let mut date = String::new();
+                read_xml(bs, b"dc:date", xml, xml_tag, false)?.extract_text(&mut date);
+                annotation.set_date(parse_datetime(date.as_bytes())?);
+            }
+            Event::Start(ref xml_tag) | Event::Empty(ref xml_tag)
+                if xml_tag.name() == b"text:p" || xml_tag.name() == b"text:list" =>
+            {
+                let text = read_xml(bs, xml_tag.name(), xml, xml_tag, empty_tag)?;
+                annotation.push_text(text);
+            }
+            Event::Start(ref xml_tag) if xml_tag.name() == b"meta:date-string" => {
+                // the date as displayed. not used.
+                read_xml(bs, b"meta:date-string", xml, xml_tag, false)?;
+            }
+            Event::End(ref e) if e.name() == b"office:annotation" => {
+                break;
+            }
+            Event::Eof => {
+                break;
+            }
+            _ => {
+                dump_unused2("read_annotation", &evt)?;
+            }
+        }
+
+        buf.clear();
+    }
+    bs.push(buf);
+
+    Ok(annotation)
 }
 
 fn append_text(new_txt: TextContent, mut tc: ReadTableCell2) -> ReadTableCell2 {
@@ -887,12 +1765,9 @@ fn parse_value2(tc: ReadTableCell2, cell: &mut CellData) -> Result<(), OdsError>
 /// Reads a table-cell from an empty XML tag.
 /// There seems to be no data associated, but it can have a style and a formula.
 /// And first of all we need the repeat count for the correct placement.
-fn read_empty_table_cell(
-    sheet: &mut Sheet,
-    row: u32,
-    mut col: u32,
+pub(crate) fn read_empty_table_cell(
     xml_tag: BytesStart<'_>,
-) -> Result<u32, OdsError> {
+) -> Result<(Option<CellData>, u32), OdsError> {
     let mut cell = None;
     // Default advance is one column.
     let mut cell_repeat = 1;
@@ -924,27 +1799,15 @@ fn read_empty_table_cell(
         }
     }
 
-    if let Some(cell) = cell {
-        while cell_repeat > 1 {
-            sheet.add_cell_data(row, col, cell.clone());
-            col += 1;
-            cell_repeat -= 1;
-        }
-        sheet.add_cell_data(row, col, cell);
-        col += 1;
-    } else {
-        col += cell_repeat;
-    }
-
-    Ok(col)
+    Ok((cell, cell_repeat))
 }
 
 // reads a font-face
-fn read_fonts(
+fn read_fonts<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(), OdsError> {
     let mut font: FontFaceDecl = FontFaceDecl::new();
@@ -992,10 +1855,10 @@ fn read_fonts(
 }
 
 // reads the page-layout tag
-fn read_page_style(
+fn read_page_style<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
 ) -> Result<(), OdsError> {
     let mut pl = PageStyle::new("");
@@ -1067,10 +1930,10 @@ fn read_page_style(
     Ok(())
 }
 
-fn read_validations(
+fn read_validations<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
 ) -> Result<(), OdsError> {
     let mut valid = Validation::new();
 
@@ -1232,11 +2095,11 @@ fn read_validations(
 }
 
 // read the master-styles tag
-fn read_master_styles(
+fn read_master_styles<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(), OdsError> {
     let mut buf = bs.get_buf();
@@ -1274,11 +2137,11 @@ fn read_master_styles(
 }
 
 // read the master-page tag
-fn read_master_page(
+fn read_master_page<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     _origin: StyleOrigin,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
 ) -> Result<(), OdsError> {
     let mut masterpage = MasterPage::empty();
@@ -1374,10 +2237,10 @@ fn read_master_page(
 }
 
 // reads any header or footer tags
-fn read_headerfooter(
+fn read_headerfooter<B: BufRead>(
     bs: &mut BufStack,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
 ) -> Result<HeaderFooter, OdsError> {
     let mut hf = HeaderFooter::new();
@@ -1455,11 +2318,11 @@ fn read_headerfooter(
 }
 
 // reads the office-styles tag
-fn read_styles_tag(
+fn read_styles_tag<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // not attributes
 ) -> Result<(), OdsError> {
     let mut buf = bs.get_buf();
@@ -1531,11 +2394,11 @@ fn read_styles_tag(
 }
 
 // read the automatic-styles tag
-fn read_auto_styles(
+fn read_auto_styles<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(), OdsError> {
     let mut buf = bs.get_buf();
@@ -1598,12 +2461,12 @@ fn read_auto_styles(
 }
 
 // Reads any of the number:xxx tags
-fn read_value_format(
+fn read_value_format<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
 ) -> Result<(), OdsError> {
     let mut valuestyle = ValueFormat::new();
@@ -1797,13 +2660,13 @@ fn read_part(xml_tag: &BytesStart<'_>, part_type: FormatPartType) -> Result<Form
 
 #[allow(clippy::too_many_arguments)]
 // style:style tag
-fn read_style_style(
+fn read_style_style<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -1852,13 +2715,13 @@ fn read_style_style(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_tablestyle(
+fn read_tablestyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -1910,13 +2773,13 @@ fn read_tablestyle(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_rowstyle(
+fn read_rowstyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -1967,13 +2830,13 @@ fn read_rowstyle(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_colstyle(
+fn read_colstyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -2024,13 +2887,13 @@ fn read_colstyle(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_cellstyle(
+fn read_cellstyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -2096,13 +2959,13 @@ fn read_cellstyle(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_paragraphstyle(
+fn read_paragraphstyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -2168,13 +3031,13 @@ fn read_paragraphstyle(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_textstyle(
+fn read_textstyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -2225,13 +3088,13 @@ fn read_textstyle(
 // style:style tag
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::too_many_arguments)]
-fn read_graphicstyle(
+fn read_graphicstyle<B: BufRead>(
     bs: &mut BufStack,
     book: &mut WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<(), OdsError> {
@@ -2340,12 +3203,8 @@ fn copy_attr2(attrmap: &mut AttrMap2, xml_tag: &BytesStart<'_>) -> Result<(), Od
     Ok(())
 }
 
-fn read_styles(
-    bs: &mut BufStack,
-    book: &mut WorkBook,
-    zip_file: &mut ZipFile<'_>,
-) -> Result<(), OdsError> {
-    let mut xml = quick_xml::Reader::from_reader(BufReader::new(zip_file));
+fn read_styles<R: Read>(bs: &mut BufStack, book: &mut WorkBook, read: R) -> Result<(), OdsError> {
+    let mut xml = quick_xml::Reader::from_reader(BufReader::new(read));
     // Do not trim text data. All text read contains significant whitespace.
     // The rest is ignored anyway.
     //
@@ -2398,6 +3257,121 @@ fn read_styles(
     Ok(())
 }
 
+// Reads the meta.xml.
+fn read_meta<R: Read>(bs: &mut BufStack, book: &mut WorkBook, read: R) -> Result<(), OdsError> {
+    let mut xml = quick_xml::Reader::from_reader(BufReader::new(read));
+
+    let mut buf = bs.get_buf();
+    loop {
+        let evt = xml.read_event(&mut buf)?;
+        if DUMP_XML {
+            println!(" read_meta {:?}", evt);
+        }
+        match evt {
+            Event::Start(xml_tag) if xml_tag.name() == b"office:meta" => {
+                let meta = read_xml(bs, b"office:meta", &mut xml, &xml_tag, false)?;
+                read_metadata(book, meta)?;
+            }
+            Event::Eof => {
+                break;
+            }
+            _ => {}
+        }
+
+        buf.clear();
+    }
+    bs.push(buf);
+
+    Ok(())
+}
+
+// Converts office:meta.
+fn read_metadata(book: &mut WorkBook, meta: XmlTag) -> Result<(), OdsError> {
+    let mut metadata = Metadata::default();
+
+    for c in meta.content() {
+        let t = match c {
+            XmlContent::Tag(t) => t,
+            XmlContent::Text(_) => continue,
+        };
+        let mut text = String::new();
+        t.extract_text(&mut text);
+
+        match t.name() {
+            "meta:generator" => metadata.generator = text,
+            "dc:title" => metadata.title = text,
+            "dc:description" => metadata.description = text,
+            "dc:subject" => metadata.subject = text,
+            "meta:keyword" => metadata.keywords.push(text),
+            "meta:initial-creator" => metadata.initial_creator = text,
+            "dc:creator" => metadata.creator = text,
+            "meta:printed-by" => metadata.printed_by = text,
+            "meta:creation-date" => metadata.creation_date = Some(parse_meta_datetime(&text)?),
+            "dc:date" => metadata.date = Some(parse_meta_datetime(&text)?),
+            "meta:print-date" => metadata.print_date = Some(parse_meta_datetime(&text)?),
+            "dc:language" => metadata.language = text,
+            "meta:editing-cycles" => metadata.editing_cycles = parse_u32(text.as_bytes())?,
+            "meta:editing-duration" => metadata.editing_duration = parse_duration(text.as_bytes())?,
+            "meta:document-statistic" => {
+                let stat = &mut metadata.document_statistics;
+                for (k, v) in t.attrmap().iter() {
+                    let v = parse_u32(v.as_bytes())?;
+                    match k.as_ref() {
+                        "meta:cell-count" => stat.cell_count = v,
+                        "meta:character-count" => stat.character_count = v,
+                        "meta:draw-count" => stat.draw_count = v,
+                        "meta:frame-count" => stat.frame_count = v,
+                        "meta:image-count" => stat.image_count = v,
+                        "meta:non-whitespace-character-count" => {
+                            stat.non_whitespace_character_count = v
+                        }
+                        "meta:object-count" => stat.object_count = v,
+                        "meta:ole-object-count" => stat.ole_object_count = v,
+                        "meta:page-count" => stat.page_count = v,
+                        "meta:paragraph-count" => stat.paragraph_count = v,
+                        "meta:row-count" => stat.row_count = v,
+                        "meta:sentence-count" => stat.sentence_count = v,
+                        "meta:syllable-count" => stat.syllable_count = v,
+                        "meta:table-count" => stat.table_count = v,
+                        "meta:word-count" => stat.word_count = v,
+                        _ => {
+                            if DUMP_UNUSED {
+                                println!(" read_metadata unused {} {}", k.as_ref(), v);
+                            }
+                        }
+                    }
+                }
+            }
+            "meta:user-defined" => {
+                let attr = t.attrmap();
+                let name = attr.attr_def("meta:name", "");
+                let value = match attr.attr_def("meta:value-type", "string") {
+                    "boolean" => MetaValue::Boolean(parse_bool(text.as_bytes())?),
+                    "date" => MetaValue::Datetime(parse_meta_datetime(&text)?),
+                    "float" => MetaValue::Float(parse_f64(text.as_bytes())?),
+                    "time" => MetaValue::TimeDuration(parse_duration(text.as_bytes())?),
+                    _ => MetaValue::String(text),
+                };
+                metadata
+                    .user_defined
+                    .push(MetaUserDefined::new(name, value));
+            }
+            _ => {
+                metadata.extra.push(t.clone());
+            }
+        }
+    }
+
+    book.metadata = metadata;
+
+    Ok(())
+}
+
+// Dates in the metadata may have a timezone.
+fn parse_meta_datetime(text: &str) -> Result<NaiveDateTime, OdsError> {
+    parse_datetime(text.trim_end_matches('Z').as_bytes())
+}
+
 #[allow(unused_variables)]
 pub(crate) fn default_settings() -> Detach<Config> {
     let mut dc = Detach::new(Config::new());
@@ -2489,12 +3463,153 @@ pub(crate) fn default_settings() -> Detach<Config> {
     dc
 }
 
-fn read_settings(
-    bs: &mut BufStack,
-    book: &mut WorkBook,
-    zip_file: &mut ZipFile<'_>,
+fn read_manifest<R: Read>(bs: &mut BufStack, book: &mut WorkBook, read: R) -> Result<(), OdsError> {
+    let mut xml = quick_xml::Reader::from_reader(BufReader::new(read));
+
+    // file-entry with children.
+    let mut current = None;
+
+    let mut buf = bs.get_buf();
+    loop {
+        let evt = xml.read_event(&mut buf)?;
+        let empty_tag = matches!(evt, Event::Empty(_));
+        if DUMP_XML {
+            println!(" read_manifest {:?}", evt);
+        }
+
+        match evt {
+            Event::Decl(_) => {}
+
+            Event::Start(xml_tag) | Event::Empty(xml_tag)
+                if xml_tag.name() == b"manifest:manifest" =>
+            {
+                // noop
+            }
+            Event::End(xml_tag) if xml_tag.name() == b"manifest:manifest" => {
+                // noop
+            }
+
+            Event::Start(xml_tag) | Event::Empty(xml_tag)
+                if xml_tag.name() == b"manifest:file-entry" =>
+            {
+                let mut entry = ManifestEntry::new("", "");
+
+                for attr in xml_tag.attributes().with_checks(false) {
+                    let attr = attr?;
+                    match attr.key {
+                        b"manifest:full-path" => {
+                            entry.full_path = attr.unescape_and_decode_value(&xml)?;
+                        }
+                        b"manifest:version" => {
+                            entry.version = Some(attr.unescape_and_decode_value(&xml)?);
+                        }
+                        b"manifest:media-type" => {
+                            entry.media_type = attr.unescape_and_decode_value(&xml)?;
+                        }
+                        b"manifest:size" => {
+                            entry.size = Some(parse_u64(&attr.value)?);
+                        }
+                        _ => {
+                            dump_unused("read_manifest", xml_tag.name(), &attr)?;
+                        }
+                    }
+                }
+
+                if empty_tag {
+                    book.manifest.push(entry);
+                } else {
+                    current = Some(entry);
+                }
+            }
+            Event::End(xml_tag) if xml_tag.name() == b"manifest:file-entry" => {
+                if let Some(entry) = current.take() {
+                    book.manifest.push(entry);
+                }
+            }
+
+            Event::Start(xml_tag) | Event::Empty(xml_tag)
+                if xml_tag.name() == b"manifest:encryption-data"
+                    || xml_tag.name() == b"manifest:algorithm"
+                    || xml_tag.name() == b"manifest:start-key-generation"
+                    || xml_tag.name() == b"manifest:key-derivation" =>
+            {
+                if let Some(entry) = &mut current {
+                    let data = entry.encryption.get_or_insert_with(Default::default);
+                    read_encryption_data_attr(data, &xml_tag)?;
+                }
+            }
+            Event::End(xml_tag)
+                if xml_tag.name() == b"manifest:encryption-data"
+                    || xml_tag.name() == b"manifest:algorithm"
+                    || xml_tag.name() == b"manifest:start-key-generation"
+                    || xml_tag.name() == b"manifest:key-derivation" =>
+            {
+                // noop
+            }
+
+            Event::Eof => {
+                break;
+            }
+            _ => {
+                dump_unused2("read_manifest", &evt)?;
+            }
+        }
+
+        buf.clear();
+    }
+    bs.push(buf);
+
+    Ok(())
+}
+
+// Attributes of manifest:encryption-data and its children.
+fn read_encryption_data_attr(
+    data: &mut EncryptionData,
+    xml_tag: &BytesStart<'_>,
 ) -> Result<(), OdsError> {
-    let mut xml = quick_xml::Reader::from_reader(BufReader::new(zip_file));
+    for attr in xml_tag.attributes().with_checks(false) {
+        match attr? {
+            attr if attr.key == b"manifest:checksum-type" => {
+                data.checksum_type = parse_string(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:checksum" => {
+                data.checksum = parse_base64(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:algorithm-name" => {
+                data.algorithm_name = parse_string(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:initialisation-vector" => {
+                data.initialisation_vector = parse_base64(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:start-key-generation-name" => {
+                data.start_key_generation_name = parse_string(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:key-derivation-name" => {
+                data.key_derivation_name = parse_string(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:key-size" => {
+                if xml_tag.name() == b"manifest:start-key-generation" {
+                    data.start_key_size = parse_u32(&attr.value)?;
+                } else {
+                    data.key_size = parse_u32(&attr.value)?;
+                }
+            }
+            attr if attr.key == b"manifest:iteration-count" => {
+                data.iteration_count = parse_u32(&attr.value)?;
+            }
+            attr if attr.key == b"manifest:salt" => {
+                data.salt = parse_base64(&attr.value)?;
+            }
+            attr => {
+                dump_unused("read_encryption_data_attr", xml_tag.name(), &attr)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_settings<R: Read>(bs: &mut BufStack, book: &mut WorkBook, read: R) -> Result<(), OdsError> {
+    let mut xml = quick_xml::Reader::from_reader(BufReader::new(read));
     // Do not trim text data. All text read contains significant whitespace.
     // The rest is ignored anyway.
     //
@@ -2537,9 +3652,9 @@ fn read_settings(
 }
 
 // read the automatic-styles tag
-fn read_office_settings(
+fn read_office_settings<B: BufRead>(
     bs: &mut BufStack,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<Config, OdsError> {
     let mut config = Config::new();
@@ -2572,10 +3687,10 @@ fn read_office_settings(
 }
 
 // read the automatic-styles tag
-fn read_config_item_set(
+fn read_config_item_set<B: BufRead>(
     bs: &mut BufStack,
     xml_tag: &BytesStart<'_>,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(String, ConfigItem), OdsError> {
     let mut name = None;
@@ -2638,10 +3753,10 @@ fn read_config_item_set(
 }
 
 // read the automatic-styles tag
-fn read_config_item_map_indexed(
+fn read_config_item_map_indexed<B: BufRead>(
     bs: &mut BufStack,
     xml_tag: &BytesStart<'_>,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(String, ConfigItem), OdsError> {
     let mut name = None;
@@ -2697,10 +3812,10 @@ fn read_config_item_map_indexed(
 }
 
 // read the automatic-styles tag
-fn read_config_item_map_named(
+fn read_config_item_map_named<B: BufRead>(
     bs: &mut BufStack,
     xml_tag: &BytesStart<'_>,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(String, ConfigItem), OdsError> {
     let mut name = None;
@@ -2762,10 +3877,10 @@ fn read_config_item_map_named(
 }
 
 // read the automatic-styles tag
-fn read_config_item_map_entry(
+fn read_config_item_map_entry<B: BufRead>(
     bs: &mut BufStack,
     xml_tag: &BytesStart<'_>,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(Option<String>, ConfigItem), OdsError> {
     let mut name = None;
@@ -2822,10 +3937,10 @@ fn read_config_item_map_entry(
 }
 
 // read the automatic-styles tag
-fn read_config_item(
+fn read_config_item<B: BufRead>(
     bs: &mut BufStack,
     xml_tag: &BytesStart<'_>,
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     // no attributes
 ) -> Result<(String, ConfigValue), OdsError> {
     #[derive(PartialEq)]
@@ -2955,10 +4070,10 @@ fn read_config_item(
 }
 
 // Reads a part of the XML as XmlTag's, and returns the first content XmlTag.
-fn read_xml_content(
+fn read_xml_content<B: BufRead>(
     bs: &mut BufStack,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<Option<XmlTag>, OdsError> {
@@ -2977,10 +4092,10 @@ fn read_xml_content(
 }
 
 // Reads a part of the XML as XmlTag's.
-fn read_xml(
+pub(crate) fn read_xml<B: BufRead>(
     bs: &mut BufStack,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<XmlTag, OdsError> {
@@ -3054,10 +4169,10 @@ fn read_xml(
     Ok(stack.pop().unwrap())
 }
 
-fn read_text_or_tag(
+fn read_text_or_tag<B: BufRead>(
     bs: &mut BufStack,
     end_tag: &[u8],
-    xml: &mut quick_xml::Reader<BufReader<&mut ZipFile<'_>>>,
+    xml: &mut quick_xml::Reader<B>,
     xml_tag: &BytesStart<'_>,
     empty_tag: bool,
 ) -> Result<TextContent, OdsError> {
@@ -3215,7 +4330,7 @@ fn dump_unused(func: &str, tag: &[u8], attr: &Attribute<'_>) -> Result<(), OdsEr
     Ok(())
 }
 
-fn dump_unused2(func: &str, evt: &Event<'_>) -> Result<(), OdsError> {
+pub(crate) fn dump_unused2(func: &str, evt: &Event<'_>) -> Result<(), OdsError> {
     if DUMP_UNUSED {
         println!("unused attr: {} ({:?})", func, evt);
     }
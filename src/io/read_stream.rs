@@ -0,0 +1,561 @@
+//!
+//! Reads an ODS-file sheet by sheet and row by row.
+//!
+
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::Path;
+
+use quick_xml::events::Event;
+use zip::ZipArchive;
+
+use crate::ds::bufstack::BufStack;
+use crate::io::read::{
+    dump_unused2, open_file, read_conditional_formats, read_empty_table_cell, read_group,
+    read_group_attr, read_named_expressions, read_ods_head, read_shapes, read_table_attr,
+    read_table_cell2, read_table_col_attr, read_table_protection, read_table_row_attr, read_xml,
+};
+use crate::io::DUMP_XML;
+use crate::{CellContent, CellData, ColRange, OdsError, RowRange, Sheet, Visibility, WorkBook};
+
+/// Reads an ODS-file without loading all the cell data into memory.
+///
+/// Opening the file reads the styles, value formats, validations and the
+/// settings. They are available via book(), but the workbook contains no
+/// sheets. The cell data is read with the RowReader returned by rows().
+///
+/// The named expressions and database ranges come after the sheets in the
+/// file. To find them the content is parsed once when opening, without
+/// keeping any cell data. The database ranges of the sheets are found in
+/// book().database_ranges() with their internal names
+/// `__Anonymous_Sheet_DB__n`.
+///
+/// Encrypted files can be read with open_with_password(). The content is
+/// decrypted as a whole, so it is kept in memory.
+///
+/// ```no_run
+/// use spreadsheet_ods::{OdsError, OdsStreamReader};
+///
+/// fn sum() -> Result<f64, OdsError> {
+///     let mut ods = OdsStreamReader::open("test_out/big.ods")?;
+///     let mut rows = ods.rows()?;
+///
+///     let mut sum = 0.0;
+///     while let Some(sheet) = rows.next_sheet()? {
+///         println!("{}", sheet.name());
+///         while let Some(row) = rows.next_row()? {
+///             for (_col, cell) in row {
+///                 sum += cell.value.as_f64_or(0.0);
+///             }
+///         }
+///     }
+///     Ok(sum)
+/// }
+/// ```
+pub struct OdsStreamReader<R: Read + Seek> {
+    zip: ZipArchive<R>,
+    book: WorkBook,
+    bs: BufStack,
+    password: Option<String>,
+}
+
+impl<R: Read + Seek> Debug for OdsStreamReader<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OdsStreamReader")
+            .field("book", &self.book)
+            .finish()
+    }
+}
+
+impl OdsStreamReader<File> {
+    /// Opens an ODS-file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, OdsError> {
+        Self::new(File::open(path.as_ref())?)
+    }
+
+    /// Opens a password protected ODS-file.
+    pub fn open_with_password<P: AsRef<Path>>(path: P, password: &str) -> Result<Self, OdsError> {
+        Self::new_with_password(File::open(path.as_ref())?, password)
+    }
+}
+
+impl<'a> OdsStreamReader<Cursor<&'a [u8]>> {
+    /// Reads an ODS-file from a buffer.
+    pub fn from_buf(buf: &'a [u8]) -> Result<Self, OdsError> {
+        Self::new(Cursor::new(buf))
+    }
+}
+
+impl<R: Read + Seek> OdsStreamReader<R> {
+    /// Reads the ODS-file from any source.
+    pub fn new(read: R) -> Result<Self, OdsError> {
+        Self::new_impl(read, None)
+    }
+
+    /// Reads a password protected ODS-file from any source.
+    pub fn new_with_password(read: R, password: &str) -> Result<Self, OdsError> {
+        Self::new_impl(read, Some(password))
+    }
+
+    fn new_impl(read: R, password: Option<&str>) -> Result<Self, OdsError> {
+        let mut zip = ZipArchive::new(read)?;
+        let mut bs = BufStack::new();
+        let book = read_ods_head(&mut bs, &mut zip, password)?;
+        Ok(Self {
+            zip,
+            book,
+            bs,
+            password: password.map(|v| v.to_string()),
+        })
+    }
+
+    /// Workbook with all styles, formats and validations, but without any
+    /// sheets.
+    pub fn book(&self) -> &WorkBook {
+        &self.book
+    }
+
+    /// Starts reading the cell data. Each call starts at the first sheet
+    /// again.
+    pub fn rows(&mut self) -> Result<RowReader<'_>, OdsError> {
+        let content = open_file(
+            &mut self.zip,
+            &self.book,
+            "content.xml",
+            self.password.as_deref(),
+        )?;
+        Ok(RowReader {
+            book: &self.book,
+            bs: &mut self.bs,
+            xml: quick_xml::Reader::from_reader(BufReader::new(content)),
+            buf: Vec::new(),
+            in_table: false,
+            sheet: None,
+            pending_row: None,
+            repeat: None,
+            row: 0,
+            table_col: 0,
+            col_range_from: 0,
+            row_range_from: 0,
+            col_groups: Vec::new(),
+            row_groups: Vec::new(),
+        })
+    }
+}
+
+// Attributes of a table-row.
+type RowAttr = (u32, Option<String>, Option<String>, Visibility);
+
+/// Reads the sheets and rows of the ODS-file one after the other.
+///
+/// Only the current row is kept in memory. The repeat counts for rows and
+/// cells are resolved when iterating, rows without any cell data are
+/// skipped.
+pub struct RowReader<'a> {
+    book: &'a WorkBook,
+    bs: &'a mut BufStack,
+    xml: quick_xml::Reader<BufReader<Box<dyn Read + 'a>>>,
+    buf: Vec<u8>,
+
+    // Inside a table:table.
+    in_table: bool,
+    // Current sheet without cell data.
+    sheet: Option<Sheet>,
+    // Attributes of a table-row whose cells are not read yet.
+    pending_row: Option<RowAttr>,
+    // Row that still has to be repeated n times.
+    repeat: Option<(StreamRow, u32)>,
+
+    // Next row.
+    row: u32,
+    // Next column for table-column.
+    table_col: u32,
+    col_range_from: u32,
+    row_range_from: u32,
+    // Open groups with start and display.
+    col_groups: Vec<(u32, bool)>,
+    row_groups: Vec<(u32, bool)>,
+}
+
+impl Debug for RowReader<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RowReader")
+            .field("sheet", &self.sheet)
+            .field("row", &self.row)
+            .finish()
+    }
+}
+
+impl<'a> RowReader<'a> {
+    /// Workbook with all styles, formats and validations.
+    pub fn book(&self) -> &'a WorkBook {
+        self.book
+    }
+
+    /// The current sheet. It contains the sheet attributes and the column
+    /// headers, but no cell data.
+    pub fn sheet(&self) -> Option<&Sheet> {
+        self.sheet.as_ref()
+    }
+
+    /// Skips the rest of the current sheet and starts the next one.
+    /// Returns None after the last sheet.
+    pub fn next_sheet(&mut self) -> Result<Option<&Sheet>, OdsError> {
+        if self.in_table {
+            let mut buf = self.bs.get_buf();
+            self.xml.read_to_end(b"table:table", &mut buf)?;
+            self.bs.push(buf);
+            self.in_table = false;
+        }
+        self.sheet = None;
+        self.pending_row = None;
+        self.repeat = None;
+
+        loop {
+            self.buf.clear();
+            let evt = self.xml.read_event(&mut self.buf)?;
+            if DUMP_XML {
+                println!(" next_sheet {:?}", evt);
+            }
+            match evt {
+                Event::Start(xml_tag) if xml_tag.name() == b"table:table" => {
+                    let mut sheet = Sheet::new("");
+                    read_table_attr(&mut sheet, xml_tag)?;
+                    self.sheet = Some(sheet);
+                    self.in_table = true;
+                    self.row = 0;
+                    self.table_col = 0;
+                    self.col_range_from = 0;
+                    self.row_range_from = 0;
+                    self.col_groups.clear();
+                    self.row_groups.clear();
+                    break;
+                }
+                Event::Eof => {
+                    return Ok(None);
+                }
+                _ => {
+                    // everything else is either read by OdsStreamReader
+                    // or not of interest.
+                }
+            }
+        }
+
+        // Read up to the first row to get the column headers.
+        self.read_table()?;
+
+        Ok(self.sheet.as_ref())
+    }
+
+    /// Returns the next row of the current sheet. Returns None at the
+    /// end of the sheet.
+    pub fn next_row(&mut self) -> Result<Option<StreamRow>, OdsError> {
+        if let Some((row, n)) = &mut self.repeat {
+            let mut next = row.clone();
+            next.row += 1;
+            *n -= 1;
+            if *n == 0 {
+                self.repeat = None;
+            } else {
+                *row = next.clone();
+            }
+            return Ok(Some(next));
+        }
+
+        loop {
+            if self.pending_row.is_none() {
+                self.read_table()?;
+            }
+            let (repeat, style, cellstyle, visible) = match self.pending_row.take() {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+
+            let cells = self.read_row_cells()?;
+            let row = self.row;
+            self.row += repeat;
+
+            if !cells.is_empty() {
+                let stream_row = StreamRow {
+                    row,
+                    style,
+                    cellstyle,
+                    visible,
+                    cells,
+                };
+                if repeat > 1 {
+                    self.repeat = Some((stream_row.clone(), repeat - 1));
+                }
+                return Ok(Some(stream_row));
+            }
+        }
+    }
+
+    // Reads the table structure until the next table-row starts or the
+    // table ends.
+    fn read_table(&mut self) -> Result<(), OdsError> {
+        if !self.in_table {
+            return Ok(());
+        }
+        let sheet = match &mut self.sheet {
+            Some(sheet) => sheet,
+            None => return Ok(()),
+        };
+
+        loop {
+            self.buf.clear();
+            let evt = self.xml.read_event(&mut self.buf)?;
+            let empty_tag = matches!(evt, Event::Empty(_));
+            if DUMP_XML {
+                println!(" read_table {:?}", evt);
+            }
+            match evt {
+                Event::End(xml_tag) if xml_tag.name() == b"table:table" => {
+                    self.in_table = false;
+                    break;
+                }
+
+                Event::Start(xml_tag) | Event::Empty(xml_tag)
+                    if xml_tag.name() == b"table:shapes" =>
+                {
+                    let shapes =
+                        read_xml(self.bs, xml_tag.name(), &mut self.xml, &xml_tag, empty_tag)?;
+                    read_shapes(sheet, shapes);
+                }
+
+                Event::Start(xml_tag) | Event::Empty(xml_tag)
+                    if xml_tag.name() == b"calcext:conditional-formats" =>
+                {
+                    let cf = read_xml(self.bs, xml_tag.name(), &mut self.xml, &xml_tag, empty_tag)?;
+                    read_conditional_formats(sheet, cf)?;
+                }
+
+                Event::Empty(xml_tag) if xml_tag.name() == b"loext:table-protection" => {
+                    read_table_protection(sheet, xml_tag)?;
+                }
+
+                Event::Start(xml_tag) | Event::Empty(xml_tag)
+                    if xml_tag.name() == b"table:named-expressions" =>
+                {
+                    let v = read_xml(self.bs, xml_tag.name(), &mut self.xml, &xml_tag, empty_tag)?;
+                    read_named_expressions(&mut sheet.named_expressions, v)?;
+                }
+
+                Event::Start(xml_tag) | Event::Empty(xml_tag)
+                    if xml_tag.name() == b"table:title"
+                        || xml_tag.name() == b"table:desc"
+                        || xml_tag.name() == b"table:table-source"
+                        || xml_tag.name() == b"office:dde-source"
+                        || xml_tag.name() == b"table:scenario"
+                        || xml_tag.name() == b"office:forms" =>
+                {
+                    sheet.extra.push(read_xml(
+                        self.bs,
+                        xml_tag.name(),
+                        &mut self.xml,
+                        &xml_tag,
+                        empty_tag,
+                    )?);
+                }
+
+                Event::Start(xml_tag) if xml_tag.name() == b"table:table-header-columns" => {
+                    self.col_range_from = self.table_col;
+                }
+                Event::End(xml_tag) if xml_tag.name() == b"table:table-header-columns" => {
+                    sheet.header_cols = Some(ColRange::new(
+                        self.col_range_from,
+                        self.table_col.saturating_sub(1),
+                    ));
+                }
+                Event::Empty(xml_tag) if xml_tag.name() == b"table:table-column" => {
+                    self.table_col = read_table_col_attr(sheet, self.table_col, &xml_tag)?;
+                }
+
+                Event::Start(xml_tag) if xml_tag.name() == b"table:table-column-group" => {
+                    self.col_groups
+                        .push((self.table_col, read_group_attr(&xml_tag)?));
+                }
+                Event::End(xml_tag) if xml_tag.name() == b"table:table-column-group" => {
+                    if let Some((from, display)) = self.col_groups.pop() {
+                        read_group(&mut sheet.col_groups, from, self.table_col, display)?;
+                    }
+                }
+
+                Event::Start(xml_tag) if xml_tag.name() == b"table:table-row-group" => {
+                    self.row_groups.push((self.row, read_group_attr(&xml_tag)?));
+                }
+                Event::End(xml_tag) if xml_tag.name() == b"table:table-row-group" => {
+                    if let Some((from, display)) = self.row_groups.pop() {
+                        read_group(&mut sheet.row_groups, from, self.row, display)?;
+                    }
+                }
+
+                Event::Start(xml_tag) if xml_tag.name() == b"table:table-header-rows" => {
+                    self.row_range_from = self.row;
+                }
+                Event::End(xml_tag) if xml_tag.name() == b"table:table-header-rows" => {
+                    sheet.header_rows = Some(RowRange::new(
+                        self.row_range_from,
+                        self.row.saturating_sub(1),
+                    ));
+                }
+
+                Event::Start(xml_tag) if xml_tag.name() == b"table:table-row" => {
+                    self.pending_row = Some(read_table_row_attr(xml_tag)?);
+                    break;
+                }
+                Event::Empty(xml_tag) if xml_tag.name() == b"table:table-row" => {
+                    let (repeat, _, _, _) = read_table_row_attr(xml_tag)?;
+                    self.row += repeat;
+                }
+
+                Event::Eof => {
+                    return Err(OdsError::Xml(quick_xml::Error::UnexpectedEof(
+                        "table:table".to_string(),
+                    )));
+                }
+
+                _ => {
+                    dump_unused2("read_table", &evt)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Reads the cells of the current table-row.
+    fn read_row_cells(&mut self) -> Result<Vec<(u32, CellData, u32)>, OdsError> {
+        let mut cells = Vec::new();
+        let mut col = 0;
+        loop {
+            self.buf.clear();
+            let evt = self.xml.read_event(&mut self.buf)?;
+            if DUMP_XML {
+                println!(" read_row_cells {:?}", evt);
+            }
+            match evt {
+                Event::End(xml_tag) if xml_tag.name() == b"table:table-row" => {
+                    break;
+                }
+
+                Event::Empty(xml_tag)
+                    if xml_tag.name() == b"table:table-cell"
+                        || xml_tag.name() == b"table:covered-table-cell" =>
+                {
+                    let (cell, repeat) = read_empty_table_cell(xml_tag)?;
+                    if let Some(cell) = cell {
+                        cells.push((col, cell, repeat));
+                    }
+                    col += repeat;
+                }
+
+                Event::Start(xml_tag)
+                    if xml_tag.name() == b"table:table-cell"
+                        || xml_tag.name() == b"table:covered-table-cell" =>
+                {
+                    let (cell, repeat) = read_table_cell2(self.bs, &mut self.xml, xml_tag)?;
+                    cells.push((col, cell, repeat));
+                    col += repeat;
+                }
+
+                Event::Eof => {
+                    return Err(OdsError::Xml(quick_xml::Error::UnexpectedEof(
+                        "table:table-row".to_string(),
+                    )));
+                }
+
+                _ => {
+                    dump_unused2("read_row_cells", &evt)?;
+                }
+            }
+        }
+        Ok(cells)
+    }
+}
+
+/// One row read by the RowReader.
+///
+/// Iterating over the row returns the column index and the cell content.
+/// Repeated cells are returned once for each column.
+#[derive(Debug, Clone)]
+pub struct StreamRow {
+    row: u32,
+    style: Option<String>,
+    cellstyle: Option<String>,
+    visible: Visibility,
+    // Column, cell, repeat-count.
+    cells: Vec<(u32, CellData, u32)>,
+}
+
+impl StreamRow {
+    /// Row index.
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+
+    /// Row style.
+    pub fn rowstyle(&self) -> Option<&String> {
+        self.style.as_ref()
+    }
+
+    /// Default cell style for the row.
+    pub fn row_cellstyle(&self) -> Option<&String> {
+        self.cellstyle.as_ref()
+    }
+
+    /// Visibility of the row.
+    pub fn visible(&self) -> Visibility {
+        self.visible
+    }
+
+    /// Number of cells, repeated cells are counted once for each column.
+    pub fn len(&self) -> usize {
+        self.cells.iter().map(|(_, _, n)| *n as usize).sum()
+    }
+
+    /// No cells.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
+
+impl IntoIterator for StreamRow {
+    type Item = (u32, CellContent);
+    type IntoIter = StreamRowIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        StreamRowIter {
+            cells: self.cells.into_iter(),
+            current: None,
+        }
+    }
+}
+
+/// Iterator over the cells of a StreamRow.
+#[derive(Debug)]
+pub struct StreamRowIter {
+    cells: std::vec::IntoIter<(u32, CellData, u32)>,
+    current: Option<(u32, CellData, u32)>,
+}
+
+impl Iterator for StreamRowIter {
+    type Item = (u32, CellContent);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.is_none() {
+            self.current = self.cells.next();
+        }
+        let (col, cell, repeat) = self.current.as_mut()?;
+        let col_idx = *col;
+        if *repeat > 1 {
+            *col += 1;
+            *repeat -= 1;
+            Some((col_idx, cell.clone().into_content()))
+        } else {
+            let (_, cell, _) = self.current.take()?;
+            Some((col_idx, cell.into_content()))
+        }
+    }
+}
@@ -1,29 +1,43 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io;
-use std::io::{Cursor, Seek, Write};
+use std::io::{BufWriter, Cursor, Read, Seek, Write};
 use std::path::Path;
 
-use chrono::NaiveDateTime;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Duration;
 use zip::write::FileOptions;
+use zip::ZipArchive;
 
-use crate::config::{ConfigItem, ConfigItemType, ConfigValue};
+use crate::annotation::Annotation;
+use crate::conditional::{CfRule, CfValue, ConditionalFormat};
+use crate::config::{Config, ConfigItem, ConfigItemType, ConfigValue};
+use crate::database::{DatabaseRange, Filter, FilterDataType};
+use crate::draw::Image;
 use crate::error::OdsError;
-use crate::format::FormatPartType;
+use crate::format::{format_default, FormatPartType};
+use crate::io::crypt::{EncryptionData, Encryptor};
 use crate::io::filebuf::FileBufEntry;
+use crate::io::manifest::ManifestEntry;
 use crate::io::xmlwriter::XmlWriter;
-use crate::io::zip_out::{ZipOut, ZipWrite};
-use crate::refs::{cellranges_string, CellRange};
+use crate::io::zip_out::ZipOut;
+use crate::metadata::MetaValue;
+use crate::named::NamedExpressions;
+use crate::protection::ProtectionKey;
+use crate::refs::{cellranges_string, CellRange, CellRef};
 use crate::style::{
-    CellStyle, ColStyle, FontFaceDecl, GraphicStyle, HeaderFooter, MasterPage, PageStyle,
-    ParagraphStyle, RowStyle, StyleOrigin, StyleUse, TableStyle, TextStyle,
+    color_string, CellStyle, ColStyle, FontFaceDecl, GraphicStyle, HeaderFooter, MasterPage,
+    PageStyle, ParagraphStyle, RowStyle, StyleOrigin, StyleUse, TableStyle, TextStyle,
 };
 use crate::validation::ValidationDisplay;
 use crate::xmltree::{XmlContent, XmlTag};
-use crate::{CellContentRef, Length, Sheet, Value, ValueFormat, ValueType, Visibility, WorkBook};
+use crate::{
+    check_headers, CellContentRef, Length, Sheet, Value, ValueFormat, ValueType, Visibility,
+    WorkBook,
+};
 
-type OdsWriter<W> = ZipOut<W>;
-type XmlOdsWriter<'a, W> = XmlWriter<ZipWrite<'a, W>>;
+pub(crate) type OdsWriter<W> = ZipOut<W>;
 
 /// Writes the ODS file into a supplied buffer.
 pub fn write_ods_buf_uncompressed(book: &mut WorkBook, buf: Vec<u8>) -> Result<Vec<u8>, OdsError> {
@@ -75,16 +89,229 @@ fn write_ods_impl<W: Write + Seek>(
     Ok(zip_writer.zip()?)
 }
 
-fn sanity_checks(book: &mut WorkBook) -> Result<(), OdsError> {
+/// Writes the ODS file encrypted with a password into a supplied buffer.
+pub fn write_ods_buf_with_password(
+    book: &mut WorkBook,
+    buf: Vec<u8>,
+    password: &str,
+) -> Result<Vec<u8>, OdsError> {
+    let zip_writer = ZipOut::<Cursor<Vec<u8>>>::new_buf(buf)?;
+    Ok(write_ods_encrypted(book, zip_writer, password)?.into_inner())
+}
+
+/// Writes the ODS file encrypted with a password.
+///
+/// Uses AES-256-CBC with a PBKDF2 derived key the same way as
+/// LibreOffice. The mimetype and the manifest are not encrypted,
+/// everything else is.
+pub fn write_ods_with_password<P: AsRef<Path>>(
+    book: &mut WorkBook,
+    ods_path: P,
+    password: &str,
+) -> Result<(), OdsError> {
+    let zip_writer = ZipOut::<File>::new_file(ods_path.as_ref())?;
+    write_ods_encrypted(book, zip_writer, password)?;
+    Ok(())
+}
+
+/// Writes the plain ODS to a buffer first and encrypts each file of
+/// it afterwards.
+fn write_ods_encrypted<W: Write + Seek>(
+    book: &mut WorkBook,
+    mut zip_writer: OdsWriter<W>,
+    password: &str,
+) -> Result<W, OdsError> {
+    let plain = write_ods_impl(book, ZipOut::<Cursor<Vec<u8>>>::new_buf(Vec::new())?)?;
+    let mut plain = ZipArchive::new(plain)?;
+
+    let mut entries = manifest_entries(book);
+    let encryptor = Encryptor::new(password)?;
+
+    for idx in 0..plain.len() {
+        let mut ze = plain.by_index(idx)?;
+        let name = ze.name().to_string();
+
+        if ze.is_dir() {
+            zip_writer.add_directory(name, FileOptions::default())?;
+        } else if name == "META-INF/manifest.xml" {
+            // written last
+        } else {
+            let mut buf = Vec::new();
+            ze.read_to_end(&mut buf)?;
+
+            let entry = entries.iter_mut().find(|v| v.full_path == name);
+            if let Some(entry) = entry {
+                let (encrypted, encryption) = encryptor.encrypt(&buf)?;
+                entry.size = Some(buf.len() as u64);
+                entry.encryption = Some(encryption);
+                buf = encrypted;
+            }
+
+            let mut w = zip_writer.start_file_stored(name, FileOptions::default())?;
+            w.write_all(&buf)?;
+        }
+    }
+
+    write_manifest_entries(book, &entries, &mut zip_writer)?;
+
+    Ok(zip_writer.zip()?)
+}
+
+/// Writes the workbook as flat ODS-file into a supplied buffer.
+pub fn write_fods_buf(book: &mut WorkBook, buf: Vec<u8>) -> Result<Vec<u8>, OdsError> {
+    write_fods_impl(book, buf)
+}
+
+/// Writes the workbook as flat ODS-file. This is a single XML document
+/// that contains the content, styles, settings and meta data.
+///
+/// Any other files of the package, e.g. images or the thumbnail,
+/// are not written.
+pub fn write_fods<P: AsRef<Path>>(book: &mut WorkBook, fods_path: P) -> Result<(), OdsError> {
+    let file = BufWriter::new(File::create(fods_path.as_ref())?);
+    write_fods_impl(book, file)?.flush()?;
+    Ok(())
+}
+
+/// Writes the flat ODS file.
+fn write_fods_impl<W: Write>(book: &mut WorkBook, write: W) -> Result<W, OdsError> {
+    sanity_checks(book)?;
+
+    store_derived(book)?;
+
+    let mut xml_out = XmlWriter::new(write);
+
+    xml_out.dtd("UTF-8")?;
+
+    xml_out.elem("office:document")?;
+    write_namespaces(&mut xml_out)?;
+    xml_out.attr(
+        "xmlns:config",
+        "urn:oasis:names:tc:opendocument:xmlns:config:1.0",
+    )?;
+    xml_out.attr("office:version", book.version())?;
+    xml_out.attr(
+        "office:mimetype",
+        "application/vnd.oasis.opendocument.spreadsheet",
+    )?;
+
+    write_office_meta(book, &mut xml_out)?;
+
+    write_office_settings(book, &mut xml_out)?;
+
+    xml_out.empty("office:scripts")?;
+
+    xml_out.elem("office:font-face-decls")?;
+    write_font_decl(&book.fonts, StyleOrigin::Styles, &mut xml_out)?;
+    write_font_decl(&book.fonts, StyleOrigin::Content, &mut xml_out)?;
+    xml_out.end_elem("office:font-face-decls")?;
+
+    xml_out.elem("office:styles")?;
+    write_styles(book, StyleOrigin::Styles, StyleUse::Default, &mut xml_out)?;
+    write_styles(book, StyleOrigin::Styles, StyleUse::Named, &mut xml_out)?;
+    write_valuestyles(
+        &book.formats,
+        StyleOrigin::Styles,
+        StyleUse::Named,
+        &mut xml_out,
+    )?;
+    write_valuestyles(
+        &book.formats,
+        StyleOrigin::Styles,
+        StyleUse::Default,
+        &mut xml_out,
+    )?;
+    xml_out.end_elem("office:styles")?;
+
+    // the automatic styles of styles.xml and content.xml end up
+    // in the same place.
+    xml_out.elem("office:automatic-styles")?;
+    write_pagestyles(&book.pagestyles, &mut xml_out)?;
+    write_styles(book, StyleOrigin::Styles, StyleUse::Automatic, &mut xml_out)?;
+    write_valuestyles(
+        &book.formats,
+        StyleOrigin::Styles,
+        StyleUse::Automatic,
+        &mut xml_out,
+    )?;
+    write_styles(
+        book,
+        StyleOrigin::Content,
+        StyleUse::Automatic,
+        &mut xml_out,
+    )?;
+    write_valuestyles(
+        &book.formats,
+        StyleOrigin::Content,
+        StyleUse::Automatic,
+        &mut xml_out,
+    )?;
+    xml_out.end_elem("office:automatic-styles")?;
+
+    xml_out.elem("office:master-styles")?;
+    write_masterpage(&book.masterpages, &mut xml_out)?;
+    xml_out.end_elem("office:master-styles")?;
+
+    write_body_head(book, &mut xml_out)?;
+    for sheet in &book.sheets {
+        write_sheet(book, sheet, &mut xml_out)?;
+    }
+    write_body_tail(book, &mut xml_out)?;
+
+    xml_out.end_elem("office:document")?;
+
+    xml_out.close()?;
+
+    Ok(xml_out.into_inner())
+}
+
+/// Stores the view settings of the sheet in Views/0/Tables.
+pub(crate) fn store_view_settings(config: &mut Config, sheet: &Sheet) {
+    let bc = config.create_path(&[
+        ("ooo:view-settings", ConfigItemType::Set),
+        ("Views", ConfigItemType::Vec),
+        ("0", ConfigItemType::Entry),
+        ("Tables", ConfigItemType::Map),
+        (sheet.name().as_str(), ConfigItemType::Entry),
+    ]);
+
+    bc.insert("CursorPositionX", sheet.config().cursor_x);
+    bc.insert("CursorPositionY", sheet.config().cursor_y);
+    bc.insert("HorizontalSplitMode", sheet.config().hor_split_mode as i16);
+    bc.insert("VerticalSplitMode", sheet.config().vert_split_mode as i16);
+    bc.insert("HorizontalSplitPosition", sheet.config().hor_split_pos);
+    bc.insert("VerticalSplitPosition", sheet.config().vert_split_pos);
+    bc.insert("ActiveSplitRange", sheet.config().active_split_range);
+    bc.insert("PositionLeft", sheet.config().position_left);
+    bc.insert("PositionRight", sheet.config().position_right);
+    bc.insert("PositionTop", sheet.config().position_top);
+    bc.insert("PositionBottom", sheet.config().position_bottom);
+    bc.insert("ZoomType", sheet.config().zoom_type);
+    bc.insert("ZoomValue", sheet.config().zoom_value);
+    bc.insert("PageViewZoomValue", sheet.config().page_view_zoom_value);
+    bc.insert("ShowGrid", sheet.config().show_grid);
+}
+
+// The values ordered by name, so the output is the same every time.
+fn sorted_values<T>(map: &HashMap<String, T>) -> Vec<&T> {
+    let mut v: Vec<_> = map.iter().collect();
+    v.sort_by(|a, b| a.0.cmp(b.0));
+    v.into_iter().map(|(_, v)| v).collect()
+}
+
+pub(crate) fn sanity_checks(book: &mut WorkBook) -> Result<(), OdsError> {
     if book.sheets.is_empty() {
         return Err(OdsError::Ods("Workbook contains no sheets.".to_string()));
     }
+    for sheet in book.sheets.iter() {
+        check_headers(sheet)?;
+    }
     Ok(())
 }
 
 #[allow(clippy::collapsible_else_if)]
 #[allow(clippy::collapsible_if)]
-fn store_derived(book: &mut WorkBook) -> Result<(), OdsError> {
+pub(crate) fn store_derived(book: &mut WorkBook) -> Result<(), OdsError> {
     let mut config = book.config.detach(0);
 
     let bc = config.create_path(&[
@@ -100,9 +327,21 @@ fn store_derived(book: &mut WorkBook) -> Result<(), OdsError> {
     bc.insert("ShowGrid", book.config().show_grid);
     bc.insert("ShowPageBreaks", book.config().show_page_breaks);
 
+    let mut cell_count = 0;
+    let mut object_count = 0;
+
     for i in 0..book.num_sheets() {
         let mut sheet = book.detach_sheet(i);
 
+        cell_count += sheet.data.len();
+        object_count += sheet.images.len();
+        object_count += sheet
+            .data
+            .values()
+            .filter_map(|v| v.images())
+            .map(|v| v.len())
+            .sum::<usize>();
+
         // Set the column widths.
         for ch in sheet.col_header.values_mut() {
             // Any non default values?
@@ -121,6 +360,9 @@ fn store_derived(book: &mut WorkBook) -> Result<(), OdsError> {
                         style.set_col_width(Length::Default);
                     } else {
                         style.set_col_width(ch.width());
+                        if ch.optimal() || style.use_optimal_col_width().unwrap_or(false) {
+                            style.set_use_optimal_col_width(ch.optimal());
+                        }
                     }
                 }
             }
@@ -141,34 +383,15 @@ fn store_derived(book: &mut WorkBook) -> Result<(), OdsError> {
                         style.set_row_height(Length::Default);
                     } else {
                         style.set_row_height(rh.height());
+                        if rh.optimal() || style.use_optimal_row_height().unwrap_or(false) {
+                            style.set_use_optimal_row_height(rh.optimal());
+                        }
                     }
                 }
             }
         }
 
-        let bc = config.create_path(&[
-            ("ooo:view-settings", ConfigItemType::Set),
-            ("Views", ConfigItemType::Vec),
-            ("0", ConfigItemType::Entry),
-            ("Tables", ConfigItemType::Map),
-            (sheet.name().as_str(), ConfigItemType::Entry),
-        ]);
-
-        bc.insert("CursorPositionX", sheet.config().cursor_x);
-        bc.insert("CursorPositionY", sheet.config().cursor_y);
-        bc.insert("HorizontalSplitMode", sheet.config().hor_split_mode as i16);
-        bc.insert("VerticalSplitMode", sheet.config().vert_split_mode as i16);
-        bc.insert("HorizontalSplitPosition", sheet.config().hor_split_pos);
-        bc.insert("VerticalSplitPosition", sheet.config().vert_split_pos);
-        bc.insert("ActiveSplitRange", sheet.config().active_split_range);
-        bc.insert("PositionLeft", sheet.config().position_left);
-        bc.insert("PositionRight", sheet.config().position_right);
-        bc.insert("PositionTop", sheet.config().position_top);
-        bc.insert("PositionBottom", sheet.config().position_bottom);
-        bc.insert("ZoomType", sheet.config().zoom_type);
-        bc.insert("ZoomValue", sheet.config().zoom_value);
-        bc.insert("PageViewZoomValue", sheet.config().page_view_zoom_value);
-        bc.insert("ShowGrid", sheet.config().show_grid);
+        store_view_settings(&mut config, &sheet);
 
         let bc = config.create_path(&[
             ("ooo:configuration-settings", ConfigItemType::Set),
@@ -182,10 +405,15 @@ fn store_derived(book: &mut WorkBook) -> Result<(), OdsError> {
 
     book.config.attach(config);
 
+    let stat = &mut book.metadata.document_statistics;
+    stat.table_count = book.sheets.len() as u32;
+    stat.cell_count = cell_count as u32;
+    stat.object_count = object_count as u32;
+
     Ok(())
 }
 
-fn copy_workbook<W: Write + Seek>(
+pub(crate) fn copy_workbook<W: Write + Seek>(
     book: &WorkBook,
     zip_writer: &mut OdsWriter<W>,
 ) -> Result<(), OdsError> {
@@ -204,15 +432,12 @@ fn copy_workbook<W: Write + Seek>(
     Ok(())
 }
 
-fn write_mimetype<W: Write + Seek>(
+pub(crate) fn write_mimetype<W: Write + Seek>(
     book: &WorkBook,
     zip_out: &mut OdsWriter<W>,
 ) -> Result<(), io::Error> {
     if !book.filebuf.contains("mimetype") {
-        let mut w = zip_out.start_file(
-            "mimetype",
-            FileOptions::default().compression_method(zip::CompressionMethod::Stored),
-        )?;
+        let mut w = zip_out.start_file_stored("mimetype", FileOptions::default())?;
 
         let mime = "application/vnd.oasis.opendocument.spreadsheet";
         w.write_all(mime.as_bytes())?;
@@ -221,67 +446,140 @@ fn write_mimetype<W: Write + Seek>(
     Ok(())
 }
 
-fn write_manifest<W: Write + Seek>(
+pub(crate) fn write_manifest<W: Write + Seek>(
     book: &WorkBook,
     zip_out: &mut OdsWriter<W>,
 ) -> Result<(), OdsError> {
-    if !book.filebuf.contains("META-INF/manifest.xml") {
+    if !book.filebuf.contains("META-INF/") {
         zip_out.add_directory("META-INF", FileOptions::default())?;
-        let w = zip_out.start_file("META-INF/manifest.xml", FileOptions::default())?;
-
-        let mut xml_out = XmlWriter::new(w);
+    }
+    write_manifest_entries(book, &manifest_entries(book), zip_out)
+}
 
-        xml_out.dtd("UTF-8")?;
+// All file-entries except the root. Any encryption data from reading
+// the file is dropped.
+fn manifest_entries(book: &WorkBook) -> Vec<ManifestEntry> {
+    let mut entries = Vec::new();
 
-        xml_out.elem("manifest:manifest")?;
-        xml_out.attr(
-            "xmlns:manifest",
-            "urn:oasis:names:tc:opendocument:xmlns:manifest:1.0",
-        )?;
-        xml_out.attr("manifest:version", book.version())?;
+    // the standard files.
+    for (path, media_type) in [
+        ("manifest.rdf", "application/rdf+xml"),
+        ("styles.xml", "text/xml"),
+        ("meta.xml", "text/xml"),
+        ("content.xml", "text/xml"),
+        ("settings.xml", "text/xml"),
+    ] {
+        if !book.manifest.contains(path) {
+            entries.push(ManifestEntry::new(path, media_type));
+        }
+    }
 
-        xml_out.empty("manifest:file-entry")?;
-        xml_out.attr("manifest:full-path", "/")?;
-        xml_out.attr("manifest:version", book.version())?;
-        xml_out.attr(
-            "manifest:media-type",
-            "application/vnd.oasis.opendocument.spreadsheet",
-        )?;
+    // everything from the original file and any added images.
+    for entry in book.manifest.iter().filter(|v| v.full_path != "/") {
+        let mut entry = entry.clone();
+        entry.size = None;
+        entry.encryption = None;
+        entries.push(entry);
+    }
 
-        //        xml_out.write_event(xml_empty_a("manifest:file-entry", vec![
-        //            ("manifest:full-path", String::from("Configurations2/")),
-        //            ("manifest:media-type", String::from("application/vnd.sun.xml.ui.configuration")),
-        //        ]))?;
+    entries
+}
 
-        xml_out.empty("manifest:file-entry")?;
-        xml_out.attr("manifest:full-path", "manifest.rdf")?;
-        xml_out.attr("manifest:media-type", "application/rdf+xml")?;
+fn write_manifest_entries<W: Write + Seek>(
+    book: &WorkBook,
+    entries: &[ManifestEntry],
+    zip_out: &mut OdsWriter<W>,
+) -> Result<(), OdsError> {
+    let w = zip_out.start_file("META-INF/manifest.xml", FileOptions::default())?;
 
-        xml_out.empty("manifest:file-entry")?;
-        xml_out.attr("manifest:full-path", "styles.xml")?;
-        xml_out.attr("manifest:media-type", "text/xml")?;
+    let mut xml_out = XmlWriter::new(w);
 
-        xml_out.empty("manifest:file-entry")?;
-        xml_out.attr("manifest:full-path", "meta.xml")?;
-        xml_out.attr("manifest:media-type", "text/xml")?;
+    xml_out.dtd("UTF-8")?;
 
-        xml_out.empty("manifest:file-entry")?;
-        xml_out.attr("manifest:full-path", "content.xml")?;
-        xml_out.attr("manifest:media-type", "text/xml")?;
+    xml_out.elem("manifest:manifest")?;
+    xml_out.attr(
+        "xmlns:manifest",
+        "urn:oasis:names:tc:opendocument:xmlns:manifest:1.0",
+    )?;
+    xml_out.attr("manifest:version", book.version())?;
 
-        xml_out.empty("manifest:file-entry")?;
-        xml_out.attr("manifest:full-path", "settings.xml")?;
-        xml_out.attr("manifest:media-type", "text/xml")?;
+    xml_out.empty("manifest:file-entry")?;
+    xml_out.attr("manifest:full-path", "/")?;
+    xml_out.attr("manifest:version", book.version())?;
+    xml_out.attr(
+        "manifest:media-type",
+        "application/vnd.oasis.opendocument.spreadsheet",
+    )?;
 
-        xml_out.end_elem("manifest:manifest")?;
+    //        xml_out.write_event(xml_empty_a("manifest:file-entry", vec![
+    //            ("manifest:full-path", String::from("Configurations2/")),
+    //            ("manifest:media-type", String::from("application/vnd.sun.xml.ui.configuration")),
+    //        ]))?;
 
-        xml_out.close()?;
+    for entry in entries {
+        if entry.encryption.is_some() {
+            xml_out.elem("manifest:file-entry")?;
+        } else {
+            xml_out.empty("manifest:file-entry")?;
+        }
+        xml_out.attr_esc("manifest:full-path", entry.full_path.as_str())?;
+        if let Some(version) = &entry.version {
+            xml_out.attr_esc("manifest:version", version.as_str())?;
+        }
+        xml_out.attr_esc("manifest:media-type", entry.media_type.as_str())?;
+        if let Some(size) = entry.size {
+            xml_out.attr("manifest:size", size.to_string())?;
+        }
+        if let Some(encryption) = &entry.encryption {
+            write_encryption_data(encryption, &mut xml_out)?;
+            xml_out.end_elem("manifest:file-entry")?;
+        }
     }
 
+    xml_out.end_elem("manifest:manifest")?;
+
+    xml_out.close()?;
+
     Ok(())
 }
 
-fn write_manifest_rdf<W: Write + Seek>(
+fn write_encryption_data<W: Write>(
+    data: &EncryptionData,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    xml_out.elem("manifest:encryption-data")?;
+    xml_out.attr_esc("manifest:checksum-type", data.checksum_type.as_str())?;
+    xml_out.attr("manifest:checksum", STANDARD.encode(&data.checksum))?;
+
+    xml_out.empty("manifest:algorithm")?;
+    xml_out.attr_esc("manifest:algorithm-name", data.algorithm_name.as_str())?;
+    xml_out.attr(
+        "manifest:initialisation-vector",
+        STANDARD.encode(&data.initialisation_vector),
+    )?;
+
+    xml_out.empty("manifest:start-key-generation")?;
+    xml_out.attr_esc(
+        "manifest:start-key-generation-name",
+        data.start_key_generation_name.as_str(),
+    )?;
+    xml_out.attr("manifest:key-size", data.start_key_size.to_string())?;
+
+    xml_out.empty("manifest:key-derivation")?;
+    xml_out.attr_esc(
+        "manifest:key-derivation-name",
+        data.key_derivation_name.as_str(),
+    )?;
+    xml_out.attr("manifest:key-size", data.key_size.to_string())?;
+    xml_out.attr("manifest:iteration-count", data.iteration_count.to_string())?;
+    xml_out.attr("manifest:salt", STANDARD.encode(&data.salt))?;
+
+    xml_out.end_elem("manifest:encryption-data")?;
+
+    Ok(())
+}
+
+pub(crate) fn write_manifest_rdf<W: Write + Seek>(
     book: &WorkBook,
     zip_out: &mut OdsWriter<W>,
 ) -> Result<(), OdsError> {
@@ -337,54 +635,163 @@ fn write_manifest_rdf<W: Write + Seek>(
     Ok(())
 }
 
-fn write_meta<W: Write + Seek>(
+pub(crate) fn write_meta<W: Write + Seek>(
     book: &WorkBook,
     zip_out: &mut OdsWriter<W>,
 ) -> Result<(), OdsError> {
-    if !book.filebuf.contains("meta.xml") {
-        let w = zip_out.start_file("meta.xml", FileOptions::default())?;
+    let w = zip_out.start_file("meta.xml", FileOptions::default())?;
 
-        let mut xml_out = XmlWriter::new(w);
+    let mut xml_out = XmlWriter::new(w);
 
-        xml_out.dtd("UTF-8")?;
+    xml_out.dtd("UTF-8")?;
 
-        xml_out.elem("office:document-meta")?;
-        xml_out.attr(
-            "xmlns:meta",
-            "urn:oasis:names:tc:opendocument:xmlns:meta:1.0",
-        )?;
-        xml_out.attr(
-            "xmlns:office",
-            "urn:oasis:names:tc:opendocument:xmlns:office:1.0",
-        )?;
-        xml_out.attr("office:version", book.version())?;
+    xml_out.elem("office:document-meta")?;
+    xml_out.attr(
+        "xmlns:meta",
+        "urn:oasis:names:tc:opendocument:xmlns:meta:1.0",
+    )?;
+    xml_out.attr(
+        "xmlns:office",
+        "urn:oasis:names:tc:opendocument:xmlns:office:1.0",
+    )?;
+    xml_out.attr("xmlns:dc", "http://purl.org/dc/elements/1.1/")?;
+    xml_out.attr("xmlns:xlink", "http://www.w3.org/1999/xlink")?;
+    xml_out.attr("office:version", book.version())?;
 
-        xml_out.elem("office:meta")?;
+    write_office_meta(book, &mut xml_out)?;
 
-        xml_out.elem_text("meta:generator", "spreadsheet-ods 0.11.0")?;
-        let s = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
-        let d = NaiveDateTime::from_timestamp(s.as_secs() as i64, 0);
-        xml_out.elem_text(
-            "meta:creation-date",
-            &d.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
-        )?;
-        xml_out.elem_text("meta:editing-duration", "P0D")?;
-        xml_out.elem_text("meta:editing-cycles", "1")?;
-        // xml_out.elem_text_esc("meta:initial-creator", &username::get_user_name().unwrap())?;
+    xml_out.end_elem("office:document-meta")?;
+
+    xml_out.close()?;
+
+    Ok(())
+}
 
-        // TODO: allow to set this data.
+/// Writes the office:meta.
+fn write_office_meta<W: Write>(
+    book: &WorkBook,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    let meta = &book.metadata;
 
-        xml_out.end_elem("office:meta")?;
+    xml_out.elem("office:meta")?;
 
-        xml_out.end_elem("office:document-meta")?;
+    for (name, text) in [
+        ("meta:generator", &meta.generator),
+        ("dc:title", &meta.title),
+        ("dc:description", &meta.description),
+        ("dc:subject", &meta.subject),
+    ] {
+        if !text.is_empty() {
+            xml_out.elem_text_esc(name, text)?;
+        }
+    }
+    for keyword in &meta.keywords {
+        xml_out.elem_text_esc("meta:keyword", keyword)?;
+    }
+    for (name, text) in [
+        ("meta:initial-creator", &meta.initial_creator),
+        ("dc:creator", &meta.creator),
+        ("meta:printed-by", &meta.printed_by),
+    ] {
+        if !text.is_empty() {
+            xml_out.elem_text_esc(name, text)?;
+        }
+    }
+    for (name, date) in [
+        ("meta:creation-date", &meta.creation_date),
+        ("dc:date", &meta.date),
+        ("meta:print-date", &meta.print_date),
+    ] {
+        if let Some(date) = date {
+            xml_out.elem_text(name, date.format("%Y-%m-%dT%H:%M:%S%.f").to_string())?;
+        }
+    }
+    if !meta.language.is_empty() {
+        xml_out.elem_text_esc("dc:language", &meta.language)?;
+    }
+    xml_out.elem_text("meta:editing-cycles", meta.editing_cycles.to_string())?;
+    xml_out.elem_text(
+        "meta:editing-duration",
+        duration_string(&meta.editing_duration),
+    )?;
 
-        xml_out.close()?;
+    for tag in &meta.extra {
+        write_xmltag(tag, xml_out)?;
+    }
+
+    let stat = &meta.document_statistics;
+    xml_out.empty("meta:document-statistic")?;
+    xml_out.attr("meta:table-count", stat.table_count.to_string())?;
+    xml_out.attr("meta:cell-count", stat.cell_count.to_string())?;
+    xml_out.attr("meta:object-count", stat.object_count.to_string())?;
+    for (name, count) in [
+        ("meta:character-count", stat.character_count),
+        ("meta:draw-count", stat.draw_count),
+        ("meta:frame-count", stat.frame_count),
+        ("meta:image-count", stat.image_count),
+        (
+            "meta:non-whitespace-character-count",
+            stat.non_whitespace_character_count,
+        ),
+        ("meta:ole-object-count", stat.ole_object_count),
+        ("meta:page-count", stat.page_count),
+        ("meta:paragraph-count", stat.paragraph_count),
+        ("meta:row-count", stat.row_count),
+        ("meta:sentence-count", stat.sentence_count),
+        ("meta:syllable-count", stat.syllable_count),
+        ("meta:word-count", stat.word_count),
+    ] {
+        if count != 0 {
+            xml_out.attr(name, count.to_string())?;
+        }
+    }
+
+    for user in &meta.user_defined {
+        xml_out.elem("meta:user-defined")?;
+        xml_out.attr_esc("meta:name", user.name.as_str())?;
+        match &user.value {
+            MetaValue::Boolean(v) => {
+                xml_out.attr("meta:value-type", "boolean")?;
+                xml_out.text(v.to_string())?;
+            }
+            MetaValue::Datetime(v) => {
+                xml_out.attr("meta:value-type", "date")?;
+                xml_out.text(v.format("%Y-%m-%dT%H:%M:%S%.f").to_string())?;
+            }
+            MetaValue::Float(v) => {
+                xml_out.attr("meta:value-type", "float")?;
+                xml_out.text(v.to_string())?;
+            }
+            MetaValue::TimeDuration(v) => {
+                xml_out.attr("meta:value-type", "time")?;
+                xml_out.text(duration_string(v))?;
+            }
+            MetaValue::String(v) => {
+                xml_out.attr("meta:value-type", "string")?;
+                xml_out.text_esc(v)?;
+            }
+        }
+        xml_out.end_elem("meta:user-defined")?;
     }
 
+    xml_out.end_elem("office:meta")?;
+
     Ok(())
 }
 
-fn write_settings<W: Write + Seek>(
+/// Duration as PThhHmmMss.fffS.
+pub(crate) fn duration_string(d: &Duration) -> String {
+    format!(
+        "PT{}H{}M{}.{:03}S",
+        d.num_hours(),
+        d.num_minutes() % 60,
+        d.num_seconds() % 60,
+        d.num_milliseconds() % 1000
+    )
+}
+
+pub(crate) fn write_settings<W: Write + Seek>(
     book: &WorkBook,
     zip_out: &mut OdsWriter<W>,
 ) -> Result<(), OdsError> {
@@ -405,6 +812,19 @@ fn write_settings<W: Write + Seek>(
         "urn:oasis:names:tc:opendocument:xmlns:config:1.0",
     )?;
     xml_out.attr("office:version", book.version())?;
+    write_office_settings(book, &mut xml_out)?;
+    xml_out.end_elem("office:document-settings")?;
+
+    xml_out.close()?;
+
+    Ok(())
+}
+
+/// Writes office:settings.
+fn write_office_settings<W: Write>(
+    book: &WorkBook,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
     xml_out.elem("office:settings")?;
 
     for (name, item) in book.config.iter() {
@@ -412,7 +832,7 @@ fn write_settings<W: Write + Seek>(
             ConfigItem::Value(_) => {
                 panic!("office-settings must not contain config-item");
             }
-            ConfigItem::Set(_) => write_config_item_set(name, item, &mut xml_out)?,
+            ConfigItem::Set(_) => write_config_item_set(name, item, xml_out)?,
             ConfigItem::Vec(_) => {
                 panic!("office-settings must not contain config-item-map-index")
             }
@@ -426,17 +846,14 @@ fn write_settings<W: Write + Seek>(
     }
 
     xml_out.end_elem("office:settings")?;
-    xml_out.end_elem("office:document-settings")?;
-
-    xml_out.close()?;
 
     Ok(())
 }
 
-fn write_config_item_set<W: Write + Seek>(
+fn write_config_item_set<W: Write>(
     name: &str,
     set: &ConfigItem,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     xml_out.elem("config:config-item-set")?;
     xml_out.attr("config:name", name)?;
@@ -458,10 +875,10 @@ fn write_config_item_set<W: Write + Seek>(
     Ok(())
 }
 
-fn write_config_item_map_indexed<W: Write + Seek>(
+fn write_config_item_map_indexed<W: Write>(
     name: &str,
     vec: &ConfigItem,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     xml_out.elem("config:config-item-map-indexed")?;
     xml_out.attr("config:name", name)?;
@@ -495,10 +912,10 @@ fn write_config_item_map_indexed<W: Write + Seek>(
     Ok(())
 }
 
-fn write_config_item_map_named<W: Write + Seek>(
+fn write_config_item_map_named<W: Write>(
     name: &str,
     map: &ConfigItem,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     xml_out.elem("config:config-item-map-named")?;
     xml_out.attr("config:name", name)?;
@@ -524,10 +941,10 @@ fn write_config_item_map_named<W: Write + Seek>(
     Ok(())
 }
 
-fn write_config_item_map_entry<W: Write + Seek>(
+fn write_config_item_map_entry<W: Write>(
     name: Option<&String>,
     map_entry: &ConfigItem,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     xml_out.elem("config:config-item-map-entry")?;
     if let Some(name) = name {
@@ -551,10 +968,10 @@ fn write_config_item_map_entry<W: Write + Seek>(
     Ok(())
 }
 
-fn write_config_item<W: Write + Seek>(
+fn write_config_item<W: Write>(
     name: &str,
     value: &ConfigValue,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     let is_empty = match value {
         ConfigValue::Base64Binary(t) => t.is_empty(),
@@ -642,7 +1059,7 @@ fn write_config_item<W: Write + Seek>(
 //    Ok(())
 //}
 
-fn write_ods_styles<W: Write + Seek>(
+pub(crate) fn write_ods_styles<W: Write + Seek>(
     book: &WorkBook,
     zip_out: &mut OdsWriter<W>,
 ) -> Result<(), OdsError> {
@@ -787,9 +1204,54 @@ fn write_ods_content<W: Write + Seek>(
     let w = zip_out.start_file("content.xml", FileOptions::default())?;
     let mut xml_out = XmlWriter::new(w);
 
+    write_content_head(book, &mut xml_out)?;
+
+    for sheet in &book.sheets {
+        write_sheet(book, sheet, &mut xml_out)?;
+    }
+
+    write_content_tail(book, &mut xml_out)?;
+
+    xml_out.close()?;
+
+    Ok(())
+}
+
+/// Writes everything in content.xml up to the first table.
+pub(crate) fn write_content_head<W: Write>(
+    book: &WorkBook,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
     xml_out.dtd("UTF-8")?;
 
     xml_out.elem("office:document-content")?;
+    write_namespaces(xml_out)?;
+
+    xml_out.attr("office:version", book.version())?;
+
+    xml_out.empty("office:scripts")?;
+
+    xml_out.elem("office:font-face-decls")?;
+    write_font_decl(&book.fonts, StyleOrigin::Content, xml_out)?;
+    xml_out.end_elem("office:font-face-decls")?;
+
+    xml_out.elem("office:automatic-styles")?;
+    write_styles(book, StyleOrigin::Content, StyleUse::Automatic, xml_out)?;
+    write_valuestyles(
+        &book.formats,
+        StyleOrigin::Content,
+        StyleUse::Automatic,
+        xml_out,
+    )?;
+    xml_out.end_elem("office:automatic-styles")?;
+
+    write_body_head(book, xml_out)?;
+
+    Ok(())
+}
+
+/// Writes the namespaces for the content.xml and the flat ODS-file.
+fn write_namespaces<W: Write>(xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
     xml_out.attr(
         "xmlns:meta",
         "urn:oasis:names:tc:opendocument:xmlns:meta:1.0",
@@ -880,31 +1342,19 @@ fn write_ods_content<W: Write + Seek>(
         "urn:oasis:names:tc:opendocument:xmlns:presentation:1.0",
     )?;
 
-    xml_out.attr("office:version", book.version())?;
-
-    xml_out.empty("office:scripts")?;
-
-    xml_out.elem("office:font-face-decls")?;
-    write_font_decl(&book.fonts, StyleOrigin::Content, &mut xml_out)?;
-    xml_out.end_elem("office:font-face-decls")?;
-
-    xml_out.elem("office:automatic-styles")?;
-    write_styles(
-        book,
-        StyleOrigin::Content,
-        StyleUse::Automatic,
-        &mut xml_out,
-    )?;
-    write_valuestyles(
-        &book.formats,
-        StyleOrigin::Content,
-        StyleUse::Automatic,
-        &mut xml_out,
-    )?;
-    xml_out.end_elem("office:automatic-styles")?;
+    Ok(())
+}
 
+/// Writes office:body up to the first table.
+fn write_body_head<W: Write>(book: &WorkBook, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
     xml_out.elem("office:body")?;
     xml_out.elem("office:spreadsheet")?;
+    if book.structure_protected {
+        xml_out.attr("table:structure-protected", "true")?;
+        if let Some(key) = &book.structure_key {
+            write_protection_key(key, xml_out)?;
+        }
+    }
 
     // extra tags. pass through only
     for tag in &book.extra {
@@ -918,45 +1368,55 @@ fn write_ods_content<W: Write + Seek>(
             tag.name() == "table:calculation-settings" ||
             tag.name() == "table:label-ranges"
         {
-            write_xmltag(tag, &mut xml_out)?;
+            write_xmltag(tag, xml_out)?;
         }
     }
 
-    write_content_validations(book, &mut xml_out)?;
+    write_content_validations(book, xml_out)?;
 
-    for sheet in &book.sheets {
-        write_sheet(book, sheet, &mut xml_out)?;
-    }
+    Ok(())
+}
+
+/// Writes everything in content.xml after the last table.
+pub(crate) fn write_content_tail<W: Write>(
+    book: &WorkBook,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    write_body_tail(book, xml_out)?;
+    xml_out.end_elem("office:document-content")?;
+
+    Ok(())
+}
+
+/// Writes the rest of office:body after the last table.
+fn write_body_tail<W: Write>(book: &WorkBook, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
+    write_named_expressions(&book.named_expressions, xml_out)?;
+    write_database_ranges(book, xml_out)?;
 
     // extra tags. pass through only
     for tag in &book.extra {
-        if tag.name() == "table:named-expressions"
-            || tag.name() == "table:database-ranges"
-            || tag.name() == "table:data-pilot-tables"
+        if tag.name() == "table:data-pilot-tables"
             || tag.name() == "table:consolidation"
             || tag.name() == "table:dde-links"
         {
-            write_xmltag(tag, &mut xml_out)?;
+            write_xmltag(tag, xml_out)?;
         }
     }
 
     xml_out.end_elem("office:spreadsheet")?;
     xml_out.end_elem("office:body")?;
-    xml_out.end_elem("office:document-content")?;
-
-    xml_out.close()?;
 
     Ok(())
 }
 
-fn write_content_validations<W: Write + Seek>(
+fn write_content_validations<W: Write>(
     book: &WorkBook,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     if !book.validations.is_empty() {
         xml_out.elem("table:content-validations")?;
 
-        for valid in book.validations.values() {
+        for valid in sorted_values(&book.validations) {
             xml_out.elem("table:content-validation")?;
             xml_out.attr_esc("table:name", valid.name())?;
             let mut cond = "of:".to_string();
@@ -1021,7 +1481,7 @@ fn write_content_validations<W: Write + Seek>(
 }
 
 /// Is the cell hidden, and if yes how many more columns are hit.
-fn check_hidden(ranges: &[CellRange], row: u32, col: u32) -> (bool, u32) {
+pub(crate) fn check_hidden(ranges: &[CellRange], row: u32, col: u32) -> (bool, u32) {
     if let Some(found) = ranges.iter().find(|s| s.contains(row, col)) {
         (true, found.to_col() - col)
     } else {
@@ -1037,42 +1497,14 @@ pub(crate) fn remove_outlooped(ranges: &mut Vec<CellRange>, row: u32, col: u32)
         .collect();
 }
 
-fn write_sheet<W: Write + Seek>(
+fn write_sheet<W: Write>(
     book: &WorkBook,
     sheet: &Sheet,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    xml_out.elem("table:table")?;
-    xml_out.attr_esc("table:name", &*sheet.name)?;
-    if let Some(style) = &sheet.style {
-        xml_out.attr_esc("table:style-name", style.as_str())?;
-    }
-    if let Some(print_ranges) = &sheet.print_ranges {
-        xml_out.attr_esc("table:print-ranges", &cellranges_string(print_ranges))?;
-    }
-    if !sheet.print() {
-        xml_out.attr("table:print", "false")?;
-    }
-    if !sheet.display() {
-        xml_out.attr("table:display", "false")?;
-    }
-
     let max_cell = sheet.used_grid_size();
 
-    for tag in &sheet.extra {
-        if tag.name() == "table:title"
-            || tag.name() == "table:desc"
-            || tag.name() == "table:table-source"
-            || tag.name() == "office:dde-source"
-            || tag.name() == "table:scenario"
-            || tag.name() == "office:forms"
-            || tag.name() == "table:shapes"
-        {
-            write_xmltag(tag, xml_out)?;
-        }
-    }
-
-    write_table_columns(sheet, max_cell, xml_out)?;
+    write_sheet_head(sheet, max_cell, xml_out)?;
 
     // list of current spans
     let mut spans = Vec::<CellRange>::new();
@@ -1120,7 +1552,7 @@ fn write_sheet<W: Write + Seek>(
         // After the first cell there is always an open row tag that
         // needs to be closed.
         if backward_dr > 0 && !first_cell {
-            write_end_last_row(sheet, cur_row, backward_dr, xml_out)?;
+            write_end_last_row(sheet, last_r, last_r_repeat, xml_out)?;
         }
 
         // Any empty rows before this one?
@@ -1139,65 +1571,446 @@ fn write_sheet<W: Write + Seek>(
             }
         }
 
-        // Start a new row if there is a delta or we are at the start.
-        // Fills in any blank cells before the current cell.
-        if backward_dr > 0 || first_cell {
-            write_start_current_row(sheet, cur_row, backward_dc, xml_out)?;
-        }
+        // Start a new row if there is a delta or we are at the start.
+        // Fills in any blank cells before the current cell.
+        if backward_dr > 0 || first_cell {
+            write_start_current_row(sheet, cur_row, backward_dc, xml_out)?;
+        }
+
+        // Remove no longer usefull cell-spans.
+        remove_outlooped(&mut spans, cur_row, cur_col);
+
+        // Current cell is hidden?
+        let (is_hidden, hidden_cols) = check_hidden(&spans, cur_row, cur_col);
+
+        // And now to something completely different ...
+        write_cell(book, &cell, is_hidden, xml_out)?;
+
+        // There may be some blank cells until the next one, but only one less the forward.
+        if forward_dc > 1 {
+            write_empty_cells(forward_dc, hidden_cols, xml_out)?;
+        }
+
+        // The last cell we will write? We can close the last row here,
+        // where we have all the data.
+        if is_last_cell {
+            let repeat = sheet.row_header.get(&cur_row).map_or(1, |v| v.repeat);
+            write_end_last_row(sheet, cur_row, repeat, xml_out)?;
+        }
+
+        // maybe span. only if visible, that nicely eliminates all
+        // double hides.
+        if let Some(span) = cell.span {
+            if !is_hidden && (span.row_span > 1 || span.col_span > 1) {
+                spans.push(CellRange::origin_span(cur_row, cur_col, span.into()));
+            }
+        }
+
+        first_cell = false;
+        last_r = cur_row;
+        last_r_repeat = if let Some(row_header) = sheet.row_header.get(&cur_row) {
+            row_header.repeat
+        } else {
+            1
+        };
+        last_c = cur_col;
+    }
+
+    // Groups or headers that reach beyond the data.
+    let next_row = if first_cell {
+        0
+    } else {
+        last_r + last_r_repeat.max(1)
+    };
+    write_empty_rows(
+        sheet,
+        next_row,
+        rows_enclosing_end(sheet),
+        max_cell,
+        xml_out,
+    )?;
+
+    write_sheet_tail(sheet, xml_out)?;
+
+    Ok(())
+}
+
+fn write_protection_key<W: Write>(
+    key: &ProtectionKey,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    xml_out.attr_esc("table:protection-key", key.key())?;
+    if let Some(digest_algorithm) = key.digest_algorithm() {
+        xml_out.attr_esc(
+            "table:protection-key-digest-algorithm",
+            digest_algorithm.as_str(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Starts the table and writes everything up to the first row.
+pub(crate) fn write_sheet_head<W: Write>(
+    sheet: &Sheet,
+    max_cell: (u32, u32),
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    xml_out.elem("table:table")?;
+    xml_out.attr_esc("table:name", &*sheet.name)?;
+    if let Some(style) = &sheet.style {
+        xml_out.attr_esc("table:style-name", style.as_str())?;
+    }
+    if let Some(print_ranges) = &sheet.print_ranges {
+        xml_out.attr_esc("table:print-ranges", &cellranges_string(print_ranges))?;
+    }
+    if !sheet.print() {
+        xml_out.attr("table:print", "false")?;
+    }
+    if !sheet.display() {
+        xml_out.attr("table:display", "false")?;
+    }
+    if let Some(protection) = &sheet.protection {
+        xml_out.attr("table:protected", "true")?;
+        if let Some(key) = protection.key() {
+            write_protection_key(key, xml_out)?;
+        }
+
+        xml_out.empty("loext:table-protection")?;
+        if !protection.select_protected_cells() {
+            xml_out.attr("loext:select-protected-cells", "false")?;
+        }
+        if !protection.select_unprotected_cells() {
+            xml_out.attr("loext:select-unprotected-cells", "false")?;
+        }
+        if protection.insert_columns() {
+            xml_out.attr("loext:insert-columns", "true")?;
+        }
+        if protection.insert_rows() {
+            xml_out.attr("loext:insert-rows", "true")?;
+        }
+        if protection.delete_columns() {
+            xml_out.attr("loext:delete-columns", "true")?;
+        }
+        if protection.delete_rows() {
+            xml_out.attr("loext:delete-rows", "true")?;
+        }
+    }
+
+    for tag in &sheet.extra {
+        if tag.name() == "table:title"
+            || tag.name() == "table:desc"
+            || tag.name() == "table:table-source"
+            || tag.name() == "office:dde-source"
+            || tag.name() == "table:scenario"
+            || tag.name() == "office:forms"
+        {
+            write_xmltag(tag, xml_out)?;
+        }
+    }
+
+    // other shapes and the images anchored to the page.
+    let shapes = sheet.extra.iter().find(|v| v.name() == "table:shapes");
+    if shapes.is_some() || !sheet.images.is_empty() {
+        xml_out.elem("table:shapes")?;
+        if let Some(shapes) = shapes {
+            for (k, v) in shapes.attrmap().iter() {
+                xml_out.attr_esc(k.as_ref(), v.as_str())?;
+            }
+            for c in shapes.content() {
+                match c {
+                    XmlContent::Text(t) => {
+                        xml_out.text_esc(t)?;
+                    }
+                    XmlContent::Tag(t) => {
+                        write_xmltag(t, xml_out)?;
+                    }
+                }
+            }
+        }
+        for image in &sheet.images {
+            write_image(image, xml_out)?;
+        }
+        xml_out.end_elem("table:shapes")?;
+    }
+
+    write_table_columns(sheet, max_cell, xml_out)?;
+
+    Ok(())
+}
+
+/// Ends the table.
+pub(crate) fn write_sheet_tail<W: Write>(
+    sheet: &Sheet,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    write_named_expressions(&sheet.named_expressions, xml_out)?;
+    write_conditional_formats(&sheet.conditional_formats, xml_out)?;
+
+    xml_out.end_elem("table:table")?;
 
-        // Remove no longer usefull cell-spans.
-        remove_outlooped(&mut spans, cur_row, cur_col);
+    Ok(())
+}
 
-        // Current cell is hidden?
-        let (is_hidden, hidden_cols) = check_hidden(&spans, cur_row, cur_col);
+fn write_named_expressions<W: Write>(
+    named: &NamedExpressions,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    if named.is_empty() {
+        return Ok(());
+    }
 
-        // And now to something completely different ...
-        write_cell(book, &cell, is_hidden, xml_out)?;
+    xml_out.elem("table:named-expressions")?;
+    for range in named.ranges() {
+        xml_out.empty("table:named-range")?;
+        xml_out.attr_esc("table:name", range.name())?;
+        xml_out.attr_esc("table:cell-range-address", range.range().to_string())?;
+        if let Some(base_cell) = range.base_cell() {
+            xml_out.attr_esc("table:base-cell-address", base_cell.to_string())?;
+        }
+        if let Some(usable_as) = range.usable_as() {
+            xml_out.attr_esc("table:range-usable-as", usable_as)?;
+        }
+    }
+    for expression in named.expressions() {
+        xml_out.empty("table:named-expression")?;
+        xml_out.attr_esc("table:name", expression.name())?;
+        xml_out.attr_esc("table:expression", expression.expression())?;
+        if let Some(base_cell) = expression.base_cell() {
+            xml_out.attr_esc("table:base-cell-address", base_cell.to_string())?;
+        }
+    }
+    xml_out.end_elem("table:named-expressions")?;
 
-        // There may be some blank cells until the next one, but only one less the forward.
-        if forward_dc > 1 {
-            write_empty_cells(forward_dc, hidden_cols, xml_out)?;
+    Ok(())
+}
+
+fn write_database_ranges<W: Write>(
+    book: &WorkBook,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    if book.database_ranges.is_empty()
+        && book
+            .sheets
+            .iter()
+            .all(|sheet| sheet.database_range.is_none())
+    {
+        return Ok(());
+    }
+
+    xml_out.elem("table:database-ranges")?;
+    for dr in &book.database_ranges {
+        write_database_range(dr, xml_out)?;
+    }
+    // The autofilter of a sheet is written as anonymous database range.
+    for (idx, sheet) in book.sheets.iter().enumerate() {
+        if let Some(dr) = &sheet.database_range {
+            let mut dr = dr.clone();
+            dr.set_name(format!("__Anonymous_Sheet_DB__{}", idx));
+            dr.range_mut().set_table(sheet.name());
+            write_database_range(&dr, xml_out)?;
         }
+    }
+    xml_out.end_elem("table:database-ranges")?;
 
-        // The last cell we will write? We can close the last row here,
-        // where we have all the data.
-        if is_last_cell {
-            write_end_current_row(sheet, cur_row, xml_out)?;
+    Ok(())
+}
+
+fn write_database_range<W: Write>(
+    dr: &DatabaseRange,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    xml_out.elem("table:database-range")?;
+    xml_out.attr_esc("table:name", dr.name())?;
+    xml_out.attr_esc("table:target-range-address", dr.range().to_string())?;
+    if !dr.contains_header() {
+        xml_out.attr("table:contains-header", "false")?;
+    }
+    if dr.display_filter_buttons() {
+        xml_out.attr("table:display-filter-buttons", "true")?;
+    }
+    for (k, v) in dr.attrmap().iter() {
+        xml_out.attr_esc(k.as_ref(), v.as_str())?;
+    }
+
+    for tag in &dr.extra {
+        if tag.name().starts_with("table:database-source") {
+            write_xmltag(tag, xml_out)?;
+        }
+    }
+    // A filter or sort that couldn't be read is kept in extra.
+    if let Some(filter) = dr.filter() {
+        xml_out.elem("table:filter")?;
+        for (k, v) in dr.filter_attrmap().iter() {
+            xml_out.attr_esc(k.as_ref(), v.as_str())?;
+        }
+        write_filter(filter, xml_out)?;
+        xml_out.end_elem("table:filter")?;
+    } else if let Some(tag) = dr.extra.iter().find(|v| v.name() == "table:filter") {
+        write_xmltag(tag, xml_out)?;
+    }
+    if !dr.sort().is_empty() {
+        xml_out.elem("table:sort")?;
+        for (k, v) in dr.sort_attrmap().iter() {
+            xml_out.attr_esc(k.as_ref(), v.as_str())?;
+        }
+        for sort_by in dr.sort() {
+            xml_out.empty("table:sort-by")?;
+            xml_out.attr("table:field-number", sort_by.field().to_string())?;
+            xml_out.attr_esc("table:data-type", sort_by.data_type().to_string())?;
+            xml_out.attr("table:order", sort_by.order().to_string())?;
+        }
+        xml_out.end_elem("table:sort")?;
+    } else if let Some(tag) = dr.extra.iter().find(|v| v.name() == "table:sort") {
+        write_xmltag(tag, xml_out)?;
+    }
+    for tag in &dr.extra {
+        if !matches!(tag.name(), "table:filter" | "table:sort")
+            && !tag.name().starts_with("table:database-source")
+        {
+            write_xmltag(tag, xml_out)?;
         }
+    }
+    xml_out.end_elem("table:database-range")?;
 
-        // maybe span. only if visible, that nicely eliminates all
-        // double hides.
-        if let Some(span) = cell.span {
-            if !is_hidden && (span.row_span > 1 || span.col_span > 1) {
-                spans.push(CellRange::origin_span(cur_row, cur_col, span.into()));
+    Ok(())
+}
+
+fn write_filter<W: Write>(filter: &Filter, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
+    match filter {
+        Filter::Condition(cond) => {
+            if cond.set_items().is_empty() {
+                xml_out.empty("table:filter-condition")?;
+            } else {
+                xml_out.elem("table:filter-condition")?;
+            }
+            xml_out.attr("table:field-number", cond.field().to_string())?;
+            xml_out.attr_esc("table:value", cond.value())?;
+            xml_out.attr_esc("table:operator", cond.operator().to_string())?;
+            if cond.data_type() != FilterDataType::Text {
+                xml_out.attr("table:data-type", cond.data_type().to_string())?;
+            }
+            if cond.case_sensitive() {
+                xml_out.attr("table:case-sensitive", "true")?;
+            }
+            if !cond.set_items().is_empty() {
+                for item in cond.set_items() {
+                    xml_out.empty("table:filter-set-item")?;
+                    xml_out.attr_esc("table:value", item)?;
+                }
+                xml_out.end_elem("table:filter-condition")?;
             }
         }
-
-        first_cell = false;
-        last_r = cur_row;
-        last_r_repeat = if let Some(row_header) = sheet.row_header.get(&cur_row) {
-            row_header.repeat
-        } else {
-            1
-        };
-        last_c = cur_col;
+        Filter::And(filters) => {
+            xml_out.elem("table:filter-and")?;
+            for filter in filters {
+                write_filter(filter, xml_out)?;
+            }
+            xml_out.end_elem("table:filter-and")?;
+        }
+        Filter::Or(filters) => {
+            xml_out.elem("table:filter-or")?;
+            for filter in filters {
+                write_filter(filter, xml_out)?;
+            }
+            xml_out.end_elem("table:filter-or")?;
+        }
     }
+    Ok(())
+}
 
-    xml_out.end_elem("table:table")?;
+fn write_conditional_formats<W: Write>(
+    conditional_formats: &[ConditionalFormat],
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    if conditional_formats.is_empty() {
+        return Ok(());
+    }
 
-    for tag in &sheet.extra {
-        if tag.name() == "table:named-expressions" || tag.name() == "calcext:conditional-formats" {
-            write_xmltag(tag, xml_out)?;
+    xml_out.elem("calcext:conditional-formats")?;
+    for cf in conditional_formats {
+        xml_out.elem("calcext:conditional-format")?;
+        xml_out.attr_esc(
+            "calcext:target-range-address",
+            cellranges_string(cf.range()),
+        )?;
+        for rule in cf.rules() {
+            match rule {
+                CfRule::Condition(cond) => {
+                    xml_out.empty("calcext:condition")?;
+                    xml_out.attr_esc("calcext:apply-style-name", cond.style())?;
+                    xml_out.attr_esc("calcext:value", cond.test().to_string())?;
+                    let base_cell = match cond.base_cell() {
+                        Some(v) => Some(v.clone()),
+                        None => cf.range().first().map(|r| match r.table() {
+                            Some(table) => CellRef::remote(table, r.row(), r.col()),
+                            None => CellRef::local(r.row(), r.col()),
+                        }),
+                    };
+                    if let Some(base_cell) = base_cell {
+                        xml_out.attr_esc("calcext:base-cell-address", base_cell.to_string())?;
+                    }
+                }
+                CfRule::ColorScale(scale) => {
+                    xml_out.elem("calcext:color-scale")?;
+                    for (value, color) in scale.entries() {
+                        xml_out.empty("calcext:color-scale-entry")?;
+                        xml_out.attr_esc("calcext:value", value.value())?;
+                        xml_out.attr("calcext:type", value.value_type().to_string())?;
+                        xml_out.attr("calcext:color", color_string(*color))?;
+                    }
+                    xml_out.end_elem("calcext:color-scale")?;
+                }
+                CfRule::DataBar(bar) => {
+                    xml_out.elem("calcext:data-bar")?;
+                    xml_out.attr("calcext:positive-color", color_string(bar.positive_color()))?;
+                    if let Some(color) = bar.negative_color() {
+                        xml_out.attr("calcext:negative-color", color_string(color))?;
+                    }
+                    if !bar.gradient() {
+                        xml_out.attr("calcext:gradient", "false")?;
+                    }
+                    for (k, v) in bar.attrmap().iter() {
+                        xml_out.attr_esc(k.as_ref(), v.as_str())?;
+                    }
+                    for value in [bar.min(), bar.max()] {
+                        write_cf_value(value, xml_out)?;
+                    }
+                    xml_out.end_elem("calcext:data-bar")?;
+                }
+                CfRule::IconSet(icons) => {
+                    xml_out.elem("calcext:icon-set")?;
+                    xml_out.attr("calcext:icon-set-type", icons.icon_set_type().to_string())?;
+                    if !icons.show_value() {
+                        xml_out.attr("calcext:show-value", "false")?;
+                    }
+                    for value in icons.entries() {
+                        write_cf_value(value, xml_out)?;
+                    }
+                    xml_out.end_elem("calcext:icon-set")?;
+                }
+                CfRule::Other(tag) => {
+                    write_xmltag(tag, xml_out)?;
+                }
+            }
         }
+        xml_out.end_elem("calcext:conditional-format")?;
     }
+    xml_out.end_elem("calcext:conditional-formats")?;
+
+    Ok(())
+}
 
+fn write_cf_value<W: Write>(value: &CfValue, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
+    xml_out.empty("calcext:formatting-entry")?;
+    xml_out.attr_esc("calcext:value", value.value())?;
+    xml_out.attr("calcext:type", value.value_type().to_string())?;
     Ok(())
 }
 
-fn write_empty_cells<W: Write + Seek>(
+pub(crate) fn write_empty_cells<W: Write>(
     mut forward_dc: u32,
     hidden_cols: u32,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     // split between hidden and regular cells.
     if hidden_cols >= forward_dc {
@@ -1223,18 +2036,14 @@ fn write_empty_cells<W: Write + Seek>(
     Ok(())
 }
 
-fn write_start_current_row<W: Write + Seek>(
+pub(crate) fn write_start_current_row<W: Write>(
     sheet: &Sheet,
     cur_row: u32,
     backward_dc: u32,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    // Start of headers
-    if let Some(header_rows) = &sheet.header_rows {
-        if header_rows.row() == cur_row {
-            xml_out.elem("table:table-header-rows")?;
-        }
-    }
+    // Start of groups and headers
+    write_rows_open(sheet, |from, _| from == cur_row, xml_out)?;
 
     xml_out.elem("table:table-row")?;
     if let Some(row_header) = sheet.row_header.get(&cur_row) {
@@ -1265,110 +2074,218 @@ fn write_start_current_row<W: Write + Seek>(
     Ok(())
 }
 
-fn write_end_last_row<W: Write + Seek>(
+pub(crate) fn write_end_last_row<W: Write>(
     sheet: &Sheet,
-    cur_row: u32,
-    backward_dr: u32,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    last_row: u32,
+    last_row_repeat: u32,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     xml_out.end_elem("table:table-row")?;
 
-    // This row was the end of the header.
-    if let Some(header_rows) = &sheet.header_rows {
-        let last_row = cur_row - backward_dr;
-        if header_rows.to_row() == last_row {
-            xml_out.end_elem("table:table-header-rows")?;
-        }
-    }
+    // This row was the end of groups/headers. A repeated row can't be
+    // split up, so everything that starts or ends within the repeat
+    // is moved to its boundaries.
+    let to_row = last_row + last_row_repeat.max(1) - 1;
+    write_rows_close(
+        sheet,
+        |from, to| from <= last_row && to >= last_row && to <= to_row,
+        xml_out,
+    )?;
+    write_rows_open(
+        sheet,
+        |from, to| from > last_row && from <= to_row && to > to_row,
+        xml_out,
+    )?;
 
     Ok(())
 }
 
-fn write_end_current_row<W: Write + Seek>(
+fn write_empty_rows_before<W: Write>(
     sheet: &Sheet,
     cur_row: u32,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    first_cell: bool,
+    backward_dr: u32,
+    max_cell: (u32, u32),
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    xml_out.end_elem("table:table-row")?;
+    // Empty rows in between are 1 less than the delta, except at the very start.
+    let corr = if first_cell { 0u32 } else { 1u32 };
 
-    // This row was the end of the header.
-    if let Some(header_rows) = &sheet.header_rows {
-        if header_rows.to_row() == cur_row {
-            xml_out.end_elem("table:table-header-rows")?;
-        }
+    // Only deltas greater 1 are relevant.
+    // Or if this is the very start.
+    if backward_dr > 1 || first_cell && backward_dr > 0 {
+        write_empty_rows(
+            sheet,
+            cur_row - backward_dr + corr,
+            cur_row,
+            max_cell,
+            xml_out,
+        )?;
     }
 
     Ok(())
 }
 
-fn write_empty_rows_before<W: Write + Seek>(
+/// Writes the empty rows from row up to but excluding to_row. They are
+/// split up where groups or headers start or end.
+pub(crate) fn write_empty_rows<W: Write>(
     sheet: &Sheet,
-    cur_row: u32,
-    first_cell: bool,
-    mut backward_dr: u32,
+    mut row: u32,
+    to_row: u32,
     max_cell: (u32, u32),
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    // Empty rows in between are 1 less than the delta, except at the very start.
-    let mut corr = if first_cell { 0u32 } else { 1u32 };
+    while row < to_row {
+        let next_row = rows_enclosing(sheet)
+            .flat_map(|(from, to, _)| [from, to.saturating_add(1)])
+            .filter(|v| *v > row && *v < to_row)
+            .min()
+            .unwrap_or(to_row);
 
-    // Only deltas greater 1 are relevant.
-    // Or if this is the very start.
-    if backward_dr > 1 || first_cell && backward_dr > 0 {
-        // split up the empty rows, if there is some header stuff.
-        if let Some(header_rows) = &sheet.header_rows {
-            // What was the last_row? Was there a header start since?
-            let last_row = cur_row - backward_dr;
-            if header_rows.row() < cur_row && header_rows.row() > last_row {
-                write_empty_row(
-                    sheet,
-                    last_row,
-                    header_rows.row() - last_row - corr,
-                    max_cell,
-                    xml_out,
-                )?;
-                xml_out.elem("table:table-header-rows")?;
-                // Don't write the empty line for the first header-row, we can
-                // collapse it with the rest. corr suits fine for this.
-                corr = 0;
-                // We correct the empty line count.
-                backward_dr = cur_row - header_rows.row();
-            }
+        write_rows_open(sheet, |from, _| from == row, xml_out)?;
+        write_empty_row(sheet, row, next_row - row, max_cell, xml_out)?;
+        write_rows_close(sheet, |_, to| to == next_row - 1, xml_out)?;
 
-            // What was the last row here? Was there a header end since?
-            let last_row = cur_row - backward_dr;
-            if header_rows.to_row() < cur_row && header_rows.to_row() > cur_row - backward_dr {
-                // Empty lines, including the current line that marks
-                // the end of the header.
-                write_empty_row(
-                    sheet,
-                    last_row,
-                    header_rows.to_row() - last_row - corr + 1,
-                    max_cell,
-                    xml_out,
-                )?;
-                xml_out.end_elem("table:table-header-rows")?;
-                // Correction for table start is no longer needed.
-                corr = 1;
-                // We correct the empty line count.
-                backward_dr = cur_row - header_rows.to_row();
+        row = next_row;
+    }
+
+    Ok(())
+}
+
+/// First row after all groups and headers.
+pub(crate) fn rows_enclosing_end(sheet: &Sheet) -> u32 {
+    rows_enclosing(sheet)
+        .map(|(_, to, _)| to.saturating_add(1))
+        .max()
+        .unwrap_or_default()
+}
+
+// Row groups and header rows. A display flag of None is the header.
+fn rows_enclosing(sheet: &Sheet) -> impl Iterator<Item = (u32, u32, Option<bool>)> + '_ {
+    sheet
+        .row_groups
+        .iter()
+        .map(|v| (v.from(), v.to(), Some(v.display())))
+        .chain(
+            sheet
+                .header_rows
+                .as_ref()
+                .map(|v| (v.row(), v.to_row(), None)),
+        )
+}
+
+// Column groups and header columns. A display flag of None is the header.
+fn cols_enclosing(sheet: &Sheet) -> impl Iterator<Item = (u32, u32, Option<bool>)> + '_ {
+    sheet
+        .col_groups
+        .iter()
+        .map(|v| (v.from(), v.to(), Some(v.display())))
+        .chain(
+            sheet
+                .header_cols
+                .as_ref()
+                .map(|v| (v.col(), v.to_col(), None)),
+        )
+}
+
+fn write_rows_open<W: Write, F: Fn(u32, u32) -> bool>(
+    sheet: &Sheet,
+    filter: F,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    write_enclosing_open(
+        rows_enclosing(sheet),
+        filter,
+        "table:table-row-group",
+        "table:table-header-rows",
+        xml_out,
+    )
+}
+
+fn write_rows_close<W: Write, F: Fn(u32, u32) -> bool>(
+    sheet: &Sheet,
+    filter: F,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    write_enclosing_close(
+        rows_enclosing(sheet),
+        filter,
+        "table:table-row-group",
+        "table:table-header-rows",
+        xml_out,
+    )
+}
+
+// Opens the groups/headers. The outer ones first, a group encloses
+// a header with the same range.
+fn write_enclosing_open<W: Write, I, F>(
+    enclosing: I,
+    filter: F,
+    group_tag: &str,
+    header_tag: &str,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError>
+where
+    I: Iterator<Item = (u32, u32, Option<bool>)>,
+    F: Fn(u32, u32) -> bool,
+{
+    let mut open: Vec<_> = enclosing
+        .filter(|(from, to, _)| filter(*from, *to))
+        .collect();
+    open.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.cmp(&a.1))
+            .then(b.2.is_some().cmp(&a.2.is_some()))
+    });
+    for (_, _, display) in open {
+        if let Some(display) = display {
+            xml_out.elem(group_tag)?;
+            if !display {
+                xml_out.attr("table:display", "false")?;
             }
+        } else {
+            xml_out.elem(header_tag)?;
         }
-
-        // Write out the empty lines.
-        let last_row = cur_row - backward_dr;
-        write_empty_row(sheet, last_row, backward_dr - corr, max_cell, xml_out)?;
     }
+    Ok(())
+}
 
+// Closes the groups/headers in the reverse order.
+fn write_enclosing_close<W: Write, I, F>(
+    enclosing: I,
+    filter: F,
+    group_tag: &str,
+    header_tag: &str,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError>
+where
+    I: Iterator<Item = (u32, u32, Option<bool>)>,
+    F: Fn(u32, u32) -> bool,
+{
+    let mut close: Vec<_> = enclosing
+        .filter(|(from, to, _)| filter(*from, *to))
+        .collect();
+    close.sort_by(|a, b| {
+        a.1.cmp(&b.1)
+            .then(b.0.cmp(&a.0))
+            .then(a.2.is_some().cmp(&b.2.is_some()))
+    });
+    for (_, _, display) in close {
+        if display.is_some() {
+            xml_out.end_elem(group_tag)?;
+        } else {
+            xml_out.end_elem(header_tag)?;
+        }
+    }
     Ok(())
 }
 
-fn write_empty_row<W: Write + Seek>(
+fn write_empty_row<W: Write>(
     sheet: &Sheet,
     cur_row: u32,
     empty_count: u32,
     max_cell: (u32, u32),
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     xml_out.elem("table:table-row")?;
     xml_out.attr("table:number-rows-repeated", &empty_count.to_string())?;
@@ -1397,10 +2314,7 @@ fn write_empty_row<W: Write + Seek>(
     Ok(())
 }
 
-fn write_xmltag<W: Write + Seek>(
-    x: &XmlTag,
-    xml_out: &mut XmlOdsWriter<'_, W>,
-) -> Result<(), OdsError> {
+fn write_xmltag<W: Write>(x: &XmlTag, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
     if x.is_empty() {
         xml_out.empty(x.name())?;
     } else {
@@ -1428,19 +2342,26 @@ fn write_xmltag<W: Write + Seek>(
     Ok(())
 }
 
-fn write_table_columns<W: Write + Seek>(
+fn write_table_columns<W: Write>(
     sheet: &Sheet,
     max_cell: (u32, u32),
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
+    // Groups may reach beyond the data.
+    let max_col = cols_enclosing(sheet)
+        .map(|(_, to, _)| to.saturating_add(1))
+        .fold(max_cell.1, u32::max);
+
     // table:table-column
-    for c in 0..max_cell.1 {
-        // markup header columns
-        if let Some(header_cols) = &sheet.header_cols {
-            if header_cols.col() == c {
-                xml_out.elem("table:table-header-columns")?;
-            }
-        }
+    for c in 0..max_col {
+        // markup groups and header columns
+        write_enclosing_open(
+            cols_enclosing(sheet),
+            |from, _| from == c,
+            "table:table-column-group",
+            "table:table-header-columns",
+            xml_out,
+        )?;
 
         xml_out.empty("table:table-column")?;
         if let Some(col_header) = sheet.col_header.get(&c) {
@@ -1458,23 +2379,25 @@ fn write_table_columns<W: Write + Seek>(
             }
         }
 
-        // markup header columns
-        if let Some(header_cols) = &sheet.header_cols {
-            if header_cols.to_col() == c {
-                xml_out.end_elem("table:table-header-columns")?;
-            }
-        }
+        // markup groups and header columns
+        write_enclosing_close(
+            cols_enclosing(sheet),
+            |_, to| to == c,
+            "table:table-column-group",
+            "table:table-header-columns",
+            xml_out,
+        )?;
     }
 
     Ok(())
 }
 
 #[allow(clippy::single_char_add_str)]
-fn write_cell<W: Write + Seek>(
+pub(crate) fn write_cell<W: Write>(
     book: &WorkBook,
     cell: &CellContentRef<'_>,
     is_hidden: bool,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     let tag = if is_hidden {
         "table:covered-table-cell"
@@ -1482,9 +2405,13 @@ fn write_cell<W: Write + Seek>(
         "table:table-cell"
     };
 
-    match cell.value {
-        None | Some(Value::Empty) => xml_out.empty(tag)?,
-        _ => xml_out.elem(tag)?,
+    let has_content = !matches!(cell.value, None | Some(Value::Empty))
+        || cell.annotation.is_some()
+        || cell.images.is_some();
+    if has_content {
+        xml_out.elem(tag)?;
+    } else {
+        xml_out.empty(tag)?;
     }
 
     if let Some(formula) = cell.formula {
@@ -1521,109 +2448,154 @@ fn write_cell<W: Write + Seek>(
         }
     }
 
-    // This finds the correct ValueFormat, but there is no way to use it.
-    // Falls back to: Output the same string as needed for the value-attribute
-    // and hope for the best. Seems to work well enough.
-    //
-    // let valuestyle = if let Some(style_name) = cell.style {
-    //     book.find_value_format(style_name)
-    // } else {
-    //     None
-    // };
+    // The displayed text uses the ValueFormat of the cell-style.
+    // Text is written as it is, there is no other place for the value.
+    let text = match cell.value {
+        None | Some(Value::Empty) | Some(Value::Text(_)) | Some(Value::TextXml(_)) => String::new(),
+        Some(value) => {
+            let style = cell.style.or_else(|| book.def_style(value.value_type()));
+            match style.and_then(|v| book.find_value_format(v)) {
+                Some(format) => book.format_value(value, format),
+                None => format_default(value),
+            }
+        }
+    };
 
     match cell.value {
         None | Some(Value::Empty) => {}
-        Some(Value::Text(s)) => {
-            xml_out.attr("office:value-type", "string")?;
-            for l in s.split('\n') {
-                xml_out.elem("text:p")?;
-                xml_out.text_esc(l)?;
-                xml_out.end_elem("text:p")?;
-            }
-        }
-        Some(Value::TextXml(t)) => {
+        Some(Value::Text(_)) | Some(Value::TextXml(_)) => {
             xml_out.attr("office:value-type", "string")?;
-            for tt in t.iter() {
-                write_xmltag(tt, xml_out)?;
-            }
         }
         Some(Value::DateTime(d)) => {
             xml_out.attr("office:value-type", "date")?;
             let value = d.format("%Y-%m-%dT%H:%M:%S%.f").to_string();
             xml_out.attr("office:date-value", value.as_str())?;
-            xml_out.elem("text:p")?;
-            xml_out.text_esc(value)?;
-            xml_out.end_elem("text:p")?;
         }
         Some(Value::TimeDuration(d)) => {
             xml_out.attr("office:value-type", "time")?;
-
-            let mut value = String::from("PT");
-            value.push_str(&d.num_hours().to_string());
-            value.push_str("H");
-            value.push_str(&(d.num_minutes() % 60).to_string());
-            value.push_str("M");
-            value.push_str(&(d.num_seconds() % 60).to_string());
-            value.push_str(".");
-            value.push_str(&(d.num_milliseconds() % 1000).to_string());
-            value.push_str("S");
-
-            xml_out.attr("office:time-value", value.as_str())?;
-
-            xml_out.elem("text:p")?;
-            xml_out.text(value)?;
-            xml_out.end_elem("text:p")?;
+            xml_out.attr("office:time-value", duration_string(d).as_str())?;
         }
         Some(Value::Boolean(b)) => {
             xml_out.attr("office:value-type", "boolean")?;
             xml_out.attr("office:boolean-value", if *b { "true" } else { "false" })?;
-            xml_out.elem("text:p")?;
-            xml_out.text(if *b { "true" } else { "false" })?;
-            xml_out.end_elem("text:p")?;
         }
         Some(Value::Currency(v, c)) => {
             xml_out.attr("office:value-type", "currency")?;
             xml_out.attr_esc("office:currency", String::from_utf8_lossy(c))?;
             let value = v.to_string();
             xml_out.attr("office:value", value.as_str())?;
-            xml_out.elem("text:p")?;
-            xml_out.text(String::from_utf8_lossy(c))?;
-            xml_out.text(" ")?;
-            xml_out.text(value)?;
-            xml_out.end_elem("text:p")?;
         }
         Some(Value::Number(v)) => {
             xml_out.attr("office:value-type", "float")?;
             let value = v.to_string();
             xml_out.attr("office:value", value.as_str())?;
-            xml_out.elem("text:p")?;
-            xml_out.text(value)?;
-            xml_out.end_elem("text:p")?;
         }
         Some(Value::Percentage(v)) => {
             xml_out.attr("office:value-type", "percentage")?;
             let value = v.to_string();
             xml_out.attr("office:value", value.as_str())?;
-            xml_out.elem("text:p")?;
-            xml_out.text(value)?;
-            xml_out.end_elem("text:p")?;
+        }
+    }
+
+    // The annotation must be the first child element.
+    if let Some(annotation) = cell.annotation {
+        write_annotation(annotation, xml_out)?;
+    }
+
+    if let Some(images) = cell.images {
+        for image in images {
+            write_image(image, xml_out)?;
         }
     }
 
     match cell.value {
         None | Some(Value::Empty) => {}
-        _ => xml_out.end_elem(tag)?,
+        Some(Value::Text(s)) => {
+            for l in s.split('\n') {
+                xml_out.elem("text:p")?;
+                xml_out.text_esc(l)?;
+                xml_out.end_elem("text:p")?;
+            }
+        }
+        Some(Value::TextXml(t)) => {
+            for tt in t.iter() {
+                write_xmltag(tt, xml_out)?;
+            }
+        }
+        Some(_) => {
+            xml_out.elem("text:p")?;
+            xml_out.text_esc(text)?;
+            xml_out.end_elem("text:p")?;
+        }
+    }
+
+    if has_content {
+        xml_out.end_elem(tag)?;
+    }
+
+    Ok(())
+}
+
+fn write_image<W: Write>(image: &Image, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
+    xml_out.elem("draw:frame")?;
+    if !image.name().is_empty() {
+        xml_out.attr_esc("draw:name", image.name())?;
+    }
+    if let Some(style) = image.style() {
+        xml_out.attr_esc("draw:style-name", style.as_str())?;
+    }
+    for (k, v) in image.attrmap().iter() {
+        xml_out.attr_esc(k.as_ref(), v.as_str())?;
     }
 
+    xml_out.empty("draw:image")?;
+    xml_out.attr_esc("xlink:href", image.href())?;
+    xml_out.attr("xlink:type", "simple")?;
+    xml_out.attr("xlink:show", "embed")?;
+    xml_out.attr("xlink:actuate", "onLoad")?;
+
+    xml_out.end_elem("draw:frame")?;
+    Ok(())
+}
+
+fn write_annotation<W: Write>(
+    annotation: &Annotation,
+    xml_out: &mut XmlWriter<W>,
+) -> Result<(), OdsError> {
+    xml_out.elem("office:annotation")?;
+    xml_out.attr("office:display", annotation.display().to_string())?;
+    if !annotation.name().is_empty() {
+        xml_out.attr_esc("office:name", annotation.name())?;
+    }
+    for (k, v) in annotation.attrmap().iter() {
+        xml_out.attr_esc(k.as_ref(), v.as_str())?;
+    }
+    if let Some(author) = annotation.author() {
+        xml_out.elem("dc:creator")?;
+        xml_out.text_esc(author.as_str())?;
+        xml_out.end_elem("dc:creator")?;
+    }
+    if let Some(date) = annotation.date() {
+        xml_out.elem("dc:date")?;
+        xml_out.text_esc(date.format("%Y-%m-%dT%H:%M:%S%.f").to_string())?;
+        xml_out.end_elem("dc:date")?;
+    }
+    for v in annotation.text() {
+        write_xmltag(v, xml_out)?;
+    }
+    xml_out.end_elem("office:annotation")?;
     Ok(())
 }
 
-fn write_font_decl<W: Write + Seek>(
+fn write_font_decl<W: Write>(
     fonts: &HashMap<String, FontFaceDecl>,
     origin: StyleOrigin,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    for font in fonts.values().filter(|s| s.origin() == origin) {
+    for font in sorted_values(fonts)
+        .into_iter()
+        .filter(|s| s.origin() == origin)
+    {
         xml_out.empty("style:font-face")?;
         xml_out.attr_esc("style:name", font.name().as_str())?;
         for (a, v) in font.attrmap().iter() {
@@ -1633,43 +2605,43 @@ fn write_font_decl<W: Write + Seek>(
     Ok(())
 }
 
-fn write_styles<W: Write + Seek>(
+fn write_styles<W: Write>(
     book: &WorkBook,
     origin: StyleOrigin,
     styleuse: StyleUse,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    for style in book.tablestyles.values() {
+    for style in sorted_values(&book.tablestyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_tablestyle(style, xml_out)?;
         }
     }
-    for style in book.rowstyles.values() {
+    for style in sorted_values(&book.rowstyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_rowstyle(style, xml_out)?;
         }
     }
-    for style in book.colstyles.values() {
+    for style in sorted_values(&book.colstyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_colstyle(style, xml_out)?;
         }
     }
-    for style in book.cellstyles.values() {
+    for style in sorted_values(&book.cellstyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_cellstyle(style, xml_out)?;
         }
     }
-    for style in book.paragraphstyles.values() {
+    for style in sorted_values(&book.paragraphstyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_paragraphstyle(style, xml_out)?;
         }
     }
-    for style in book.textstyles.values() {
+    for style in sorted_values(&book.textstyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_textstyle(style, xml_out)?;
         }
     }
-    for style in book.graphicstyles.values() {
+    for style in sorted_values(&book.graphicstyles) {
         if style.origin() == origin && style.styleuse() == styleuse {
             write_graphicstyle(style, xml_out)?;
         }
@@ -1687,9 +2659,9 @@ fn write_styles<W: Write + Seek>(
     Ok(())
 }
 
-fn write_tablestyle<W: Write + Seek>(
+fn write_tablestyle<W: Write>(
     style: &TableStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
@@ -1723,10 +2695,7 @@ fn write_tablestyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_rowstyle<W: Write + Seek>(
-    style: &RowStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
-) -> Result<(), OdsError> {
+fn write_rowstyle<W: Write>(style: &RowStyle, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
     } else {
@@ -1759,10 +2728,7 @@ fn write_rowstyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_colstyle<W: Write + Seek>(
-    style: &ColStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
-) -> Result<(), OdsError> {
+fn write_colstyle<W: Write>(style: &ColStyle, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
     } else {
@@ -1795,9 +2761,9 @@ fn write_colstyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_cellstyle<W: Write + Seek>(
+fn write_cellstyle<W: Write>(
     style: &CellStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
@@ -1851,9 +2817,9 @@ fn write_cellstyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_paragraphstyle<W: Write + Seek>(
+fn write_paragraphstyle<W: Write>(
     style: &ParagraphStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
@@ -1911,9 +2877,9 @@ fn write_paragraphstyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_textstyle<W: Write + Seek>(
+fn write_textstyle<W: Write>(
     style: &TextStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
@@ -1947,9 +2913,9 @@ fn write_textstyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_graphicstyle<W: Write + Seek>(
+fn write_graphicstyle<W: Write>(
     style: &GraphicStyle,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
     if style.styleuse() == StyleUse::Default {
         xml_out.elem("style:default-style")?;
@@ -1984,14 +2950,14 @@ fn write_graphicstyle<W: Write + Seek>(
     Ok(())
 }
 
-fn write_valuestyles<W: Write + Seek>(
+fn write_valuestyles<W: Write>(
     value_formats: &HashMap<String, ValueFormat>,
     origin: StyleOrigin,
     styleuse: StyleUse,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    for value_format in value_formats
-        .values()
+    for value_format in sorted_values(value_formats)
+        .into_iter()
         .filter(|s| s.origin() == origin && s.styleuse() == styleuse)
     {
         let tag = match value_format.value_type() {
@@ -2078,11 +3044,11 @@ fn write_valuestyles<W: Write + Seek>(
     Ok(())
 }
 
-fn write_pagestyles<W: Write + Seek>(
+fn write_pagestyles<W: Write>(
     styles: &HashMap<String, PageStyle>,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    for style in styles.values() {
+    for style in sorted_values(styles) {
         xml_out.elem("style:page-layout")?;
         xml_out.attr_esc("style:name", &style.name())?;
 
@@ -2117,11 +3083,11 @@ fn write_pagestyles<W: Write + Seek>(
     Ok(())
 }
 
-fn write_masterpage<W: Write + Seek>(
+fn write_masterpage<W: Write>(
     styles: &HashMap<String, MasterPage>,
-    xml_out: &mut XmlOdsWriter<'_, W>,
+    xml_out: &mut XmlWriter<W>,
 ) -> Result<(), OdsError> {
-    for style in styles.values() {
+    for style in sorted_values(styles) {
         xml_out.elem("style:master-page")?;
         xml_out.attr("style:name", &style.name())?;
         xml_out.attr("style:page-layout-name", &style.pagestyle())?;
@@ -2178,10 +3144,7 @@ fn write_masterpage<W: Write + Seek>(
     Ok(())
 }
 
-fn write_regions<W: Write + Seek>(
-    hf: &HeaderFooter,
-    xml_out: &mut XmlOdsWriter<'_, W>,
-) -> Result<(), OdsError> {
+fn write_regions<W: Write>(hf: &HeaderFooter, xml_out: &mut XmlWriter<W>) -> Result<(), OdsError> {
     if let Some(left) = hf.left() {
         xml_out.elem("style:region-left")?;
         write_xmltag(left, xml_out)?;
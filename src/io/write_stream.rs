@@ -0,0 +1,270 @@
+//!
+//! Writes an ODS-file row by row.
+//!
+
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{Cursor, Seek, Write};
+use std::path::Path;
+
+use zip::write::FileOptions;
+
+use crate::io::write::{
+    check_hidden, copy_workbook, remove_outlooped, rows_enclosing_end, sanity_checks,
+    store_derived, write_cell, write_content_head, write_content_tail, write_empty_cells,
+    write_empty_rows, write_end_last_row, write_manifest, write_manifest_rdf, write_meta,
+    write_mimetype, write_ods_styles, write_settings, write_sheet_head, write_sheet_tail,
+    write_start_current_row, OdsWriter,
+};
+use crate::io::xmlwriter::XmlWriter;
+use crate::io::zip_out::ZipOut;
+use crate::{
+    check_headers, CellContent, CellContentRef, CellRange, OdsError, Sheet, Value, WorkBook,
+};
+
+/// Writes an ODS-file without holding all the data in memory.
+///
+/// The workbook given to new() provides the styles, value formats,
+/// validations and settings. They are written immediately, so everything
+/// that is used later must be there from the start. Any sheets in this
+/// workbook are ignored.
+///
+/// Then the sheets are written one after the other, each row with one call
+/// to write_row(). Only the current row is kept in memory.
+///
+/// ```
+/// use spreadsheet_ods::{CellContent, OdsError, OdsStreamWriter, WorkBook};
+///
+/// fn report() -> Result<(), OdsError> {
+///     let mut ods = OdsStreamWriter::new("test_out/report.ods", WorkBook::new_empty())?;
+///     ods.start_sheet("report")?;
+///     ods.write_values(["id", "value"])?;
+///     for i in 0..100 {
+///         let mut cell = CellContent::new();
+///         cell.set_value(i * 2);
+///         ods.write_row([(1, cell)])?;
+///     }
+///     ods.finish()?;
+///     Ok(())
+/// }
+/// # std::fs::create_dir_all("test_out").unwrap();
+/// # report().unwrap();
+/// ```
+pub struct OdsStreamWriter<W: Write + Seek> {
+    book: WorkBook,
+    xml_out: XmlWriter<OdsWriter<W>>,
+    // Inside a table:table.
+    in_sheet: bool,
+    // Next row.
+    row: u32,
+    // Current cell spans.
+    spans: Vec<CellRange>,
+}
+
+impl<W: Write + Seek> Debug for OdsStreamWriter<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OdsStreamWriter")
+            .field("book", &self.book)
+            .field("in_sheet", &self.in_sheet)
+            .field("row", &self.row)
+            .finish()
+    }
+}
+
+impl OdsStreamWriter<File> {
+    /// Creates the ODS-file and writes all styles.
+    pub fn new<P: AsRef<Path>>(path: P, styles: WorkBook) -> Result<Self, OdsError> {
+        Self::start(ZipOut::<File>::new_file(path.as_ref())?, styles)
+    }
+}
+
+impl OdsStreamWriter<Cursor<Vec<u8>>> {
+    /// Writes the ODS-file into the supplied buffer.
+    pub fn new_buf(buf: Vec<u8>, styles: WorkBook) -> Result<Self, OdsError> {
+        Self::start(ZipOut::<Cursor<Vec<u8>>>::new_buf(buf)?, styles)
+    }
+}
+
+impl<W: Write + Seek> OdsStreamWriter<W> {
+    fn start(mut zip_out: OdsWriter<W>, mut book: WorkBook) -> Result<Self, OdsError> {
+        book.sheets.clear();
+
+        // copy all buffered data from the original.
+        copy_workbook(&book, &mut zip_out)?;
+        // write the rest, if necessary.
+        write_mimetype(&book, &mut zip_out)?;
+        write_manifest(&book, &mut zip_out)?;
+        write_manifest_rdf(&book, &mut zip_out)?;
+        write_meta(&book, &mut zip_out)?;
+        write_ods_styles(&book, &mut zip_out)?;
+
+        // settings.xml is written after the content, when all the sheets
+        // are known.
+        zip_out.start_file("content.xml", FileOptions::default())?;
+        let mut xml_out = XmlWriter::new(zip_out);
+        write_content_head(&book, &mut xml_out)?;
+
+        Ok(Self {
+            book,
+            xml_out,
+            in_sheet: false,
+            row: 0,
+            spans: Vec::new(),
+        })
+    }
+
+    /// Workbook with the styles.
+    pub fn book(&self) -> &WorkBook {
+        &self.book
+    }
+
+    /// Ends the current sheet and starts a new one.
+    pub fn start_sheet<S: Into<String>>(&mut self, name: S) -> Result<(), OdsError> {
+        self.start_sheet_from(&Sheet::new(name))
+    }
+
+    /// Ends the current sheet and starts a new one. Uses the attributes,
+    /// the column headers, the row headers and the header rows/columns of
+    /// the given sheet. The cell data of the sheet is not written.
+    ///
+    /// Column widths and row heights are only used if they are set in the
+    /// column/row styles.
+    pub fn start_sheet_from(&mut self, sheet: &Sheet) -> Result<(), OdsError> {
+        self.end_sheet()?;
+
+        let sheet = sheet.clone_no_data();
+        check_headers(&sheet)?;
+
+        let max_col = sheet
+            .col_header
+            .keys()
+            .next_back()
+            .map(|v| v + 1)
+            .unwrap_or(1)
+            .max(sheet.header_cols.as_ref().map_or(0, |v| v.to_col() + 1));
+        write_sheet_head(&sheet, (0, max_col), &mut self.xml_out)?;
+
+        self.book.sheets.push(sheet.into());
+        self.in_sheet = true;
+        self.row = 0;
+        self.spans.clear();
+
+        Ok(())
+    }
+
+    /// Writes the next row. The cells are given with their column index.
+    /// Any gaps are filled with empty cells.
+    ///
+    /// If the row header of the sheet repeats this row, the next call
+    /// writes the row after the repeat.
+    pub fn write_row<I>(&mut self, cells: I) -> Result<(), OdsError>
+    where
+        I: IntoIterator<Item = (u32, CellContent)>,
+    {
+        if !self.in_sheet {
+            return Err(OdsError::Ods("No sheet started.".to_string()));
+        }
+
+        let mut cells: Vec<(u32, CellContent)> = cells.into_iter().collect();
+        cells.sort_by_key(|(col, _)| *col);
+        if let Some(w) = cells.windows(2).find(|w| w[0].0 == w[1].0) {
+            return Err(OdsError::Ods(format!(
+                "Duplicate cell {} in row {}.",
+                w[0].0, self.row
+            )));
+        }
+
+        let sheet = self.book.sheets.last().expect("sheet");
+        let cur_row = self.row;
+        let first_col = cells.first().map_or(0, |(col, _)| *col);
+        write_start_current_row(sheet, cur_row, first_col, &mut self.xml_out)?;
+
+        if cells.is_empty() {
+            self.xml_out.empty("table:table-cell")?;
+        }
+
+        let mut last_col = None;
+        let mut last_hidden_cols = 0;
+        for (cur_col, cell) in &cells {
+            let cur_col = *cur_col;
+            if let Some(last_col) = last_col {
+                if cur_col > last_col + 1 {
+                    write_empty_cells(cur_col - last_col, last_hidden_cols, &mut self.xml_out)?;
+                }
+            }
+
+            remove_outlooped(&mut self.spans, cur_row, cur_col);
+            let (is_hidden, hidden_cols) = check_hidden(&self.spans, cur_row, cur_col);
+
+            let cell_ref = CellContentRef {
+                value: Some(&cell.value),
+                style: cell.style.as_ref(),
+                formula: cell.formula.as_ref(),
+                validation_name: cell.validation_name.as_ref(),
+                span: Some(&cell.span),
+                annotation: cell.annotation.as_deref(),
+                images: Some(&cell.images).filter(|v| !v.is_empty()),
+            };
+            write_cell(&self.book, &cell_ref, is_hidden, &mut self.xml_out)?;
+
+            if !is_hidden && (cell.span.row_span > 1 || cell.span.col_span > 1) {
+                self.spans
+                    .push(CellRange::origin_span(cur_row, cur_col, cell.span.into()));
+            }
+
+            last_col = Some(cur_col);
+            last_hidden_cols = hidden_cols;
+        }
+
+        // A repeated row covers the following rows too.
+        let repeat = sheet
+            .row_header
+            .get(&cur_row)
+            .map_or(1, |v| v.repeat.max(1));
+        write_end_last_row(sheet, cur_row, repeat, &mut self.xml_out)?;
+        self.row += repeat;
+
+        Ok(())
+    }
+
+    /// Writes the next row. The values are written to consecutive columns.
+    pub fn write_values<I, V>(&mut self, values: I) -> Result<(), OdsError>
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        self.write_row(values.into_iter().enumerate().map(|(col, v)| {
+            let mut cell = CellContent::new();
+            cell.set_value(v);
+            (col as u32, cell)
+        }))
+    }
+
+    fn end_sheet(&mut self) -> Result<(), OdsError> {
+        if self.in_sheet {
+            let sheet = self.book.sheets.last().expect("sheet");
+            // Groups or headers that reach beyond the last row.
+            let to_row = rows_enclosing_end(sheet);
+            write_empty_rows(sheet, self.row, to_row, (0, 1), &mut self.xml_out)?;
+            write_sheet_tail(sheet, &mut self.xml_out)?;
+            self.in_sheet = false;
+        }
+        Ok(())
+    }
+
+    /// Ends the last sheet and writes the settings. Returns the
+    /// underlying writer.
+    pub fn finish(mut self) -> Result<W, OdsError> {
+        self.end_sheet()?;
+        sanity_checks(&mut self.book)?;
+
+        write_content_tail(&self.book, &mut self.xml_out)?;
+        self.xml_out.close()?;
+        let mut zip_out = self.xml_out.into_inner();
+
+        store_derived(&mut self.book)?;
+        write_settings(&self.book, &mut zip_out)?;
+
+        Ok(zip_out.zip()?)
+    }
+}
@@ -287,6 +287,11 @@ impl<W: Write> XmlWriter<W> {
         Ok(())
     }
 
+    /// Returns the underlying writer. Call close() first.
+    pub(crate) fn into_inner(self) -> W {
+        *self.writer
+    }
+
     /// Fails if there are any open elements.
     pub(crate) fn close(&mut self) -> io::Result<()> {
         self.write_buf()?;
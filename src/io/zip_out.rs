@@ -63,6 +63,20 @@ impl<W: Write + Seek> ZipOut<W> {
         })
     }
 
+    /// Starts a file without compression, regardless of the compression
+    /// used for the rest.
+    pub(crate) fn start_file_stored<S: Into<String>>(
+        &mut self,
+        name: S,
+        options: FileOptions,
+    ) -> Result<ZipWrite<'_, W>, ZipError> {
+        let options = options.compression_method(CompressionMethod::Stored);
+        self.zip.start_file(name, options)?;
+        Ok(ZipWrite {
+            write: &mut self.zip,
+        })
+    }
+
     pub(crate) fn zip(mut self) -> Result<W, ZipError> {
         self.zip.finish()
     }
@@ -77,3 +91,14 @@ impl<W: Write + Seek> Write for ZipWrite<'_, W> {
         self.write.flush()
     }
 }
+
+/// Writes to the last started file.
+impl<W: Write + Seek> Write for ZipOut<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IOError> {
+        self.zip.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), IOError> {
+        self.zip.flush()
+    }
+}
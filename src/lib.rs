@@ -70,13 +70,36 @@
 //!     * Uses chrono::NaiveDate and NaiveDateTime
 //!   * Column/Row/Cell styles
 //!   * Formulas
-//!     * Only as strings, but support functions for cell/range references.
+//!     * Stored as strings, but can be parsed to a syntax tree.
 //!   * Row/Column spans
 //!   * Header rows/columns, print ranges
+//!   * Row and column groups
 //!   * Formatted text as xml text.
+//!   * Streaming reader for huge files, see OdsStreamReader.
+//!   * Streaming writer, see OdsStreamWriter.
+//!   * Reads and writes flat ODS files (.fods), see read_fods and write_fods.
+//!   * CSV import and export, see CsvOptions.
+//!   * Cell annotations.
+//!   * Images anchored to cells or the page, see WorkBook::add_image.
+//!   * Conditional formats, see the conditional module.
+//!   * Named ranges and named expressions, see the named module.
+//!   * Autofilter and database ranges, see the database module.
+//!   * Sorting of cell ranges, see Sheet::sort_range.
+//!   * Find and replace, see WorkBook::find and WorkBook::replace_all.
+//!   * Estimated optimal column widths and row heights, see
+//!     WorkBook::autofit_cols and WorkBook::autofit_rows.
+//!   * Frozen rows and columns, see Sheet::freeze_panes.
+//!   * Sheet and workbook protection, see the protection module.
+//!   * Document metadata, see WorkBook::metadata.
+//!   * Password encrypted files, see read_ods_with_password and
+//!     write_ods_with_password.
+//!   * Typed row mapping with serde, see the serde module. Needs the
+//!     feature `serde`.
 //!
 //! * Formulas
-//!   * Only as strings.
+//!   * Stored as strings.
+//!   * Parser and printer for the OpenFormula syntax.
+//!   * Recalculation with a core set of functions.
 //!   * Utilities for cell/range references.
 //!
 //! * Styles
@@ -90,6 +113,8 @@
 //!   * The whole set is available.
 //!   * Utility functions for common formats.
 //!   * Basic localization support.
+//!   * Formats values to their display text.
+//!   * Parses and writes format codes like `#,##0.00;[RED]-#,##0.00`.
 //!
 //! * Content validation
 //!
@@ -105,15 +130,11 @@
 //!   * Parsing and formatting
 //!
 //! What might be problematic:
-//! * The text content of each cell is not formatted according to the given ValueFormat,
-//!   but instead is a simple to_string() of the data type. This data is not necessary
+//! * The text content of each cell is formatted according to the given ValueFormat,
+//!   but this is only an approximation. This data is not necessary
 //!   to read the contents correctly. LibreOffice seems to ignore this completely
 //!   and display everything correctly.
 //!
-//! What is not supported:
-//! * Spreadsheets
-//!   * Row and column grouping
-//!
 //! There are a number of features that are not parsed to a structure,
 //! but which are stored as a XML. This might work as long as
 //! these features don't refer to data that is no longer valid after
@@ -127,7 +148,6 @@
 //! * dde-connection-decls
 //! * calculation-settings
 //! * label-ranges
-//! * named-expressions
 //! * database-ranges
 //! * data-pilot-tables
 //! * consolidation
@@ -138,13 +158,13 @@
 //! * scenario
 //! * forms
 //! * shapes
-//! * calcext:conditional-formats
 //!
 //! When storing a previously read ODS file, all the contained files
-//! are copied to the new file, except settings.xml, styles.xml and content.xml.
-//! For a new ODS file mimetype, manifest, manifest.rdf, meta.xml
-//! are filled with minimal defaults. There is no way to set these
-//! for now.
+//! are copied to the new file, except settings.xml, styles.xml, content.xml,
+//! meta.xml and the manifest. The manifest is rewritten with all the original
+//! entries and any added images. meta.xml is written from WorkBook::metadata.
+//! For a new ODS file mimetype and manifest.rdf are filled with minimal
+//! defaults.
 //!
 
 #![doc(html_root_url = "https://docs.rs/spreadsheet-ods/0.4.0")]
@@ -179,21 +199,45 @@
 // NO #![warn(unused_results)]
 #![warn(variant_size_differences)]
 
+use crate::annotation::Annotation;
+use crate::autofit::{pt_length, CellFont};
+use crate::conditional::{CfRule, ConditionalFormat};
+use crate::draw::Image;
 pub use crate::error::OdsError;
 pub use crate::format::{ValueFormat, ValueFormatRef};
-pub use crate::io::read::{read_ods, read_ods_buf};
-pub use crate::io::write::{write_ods, write_ods_buf, write_ods_buf_uncompressed};
+pub use crate::io::csv::{CsvOptions, CsvQuote};
+pub use crate::io::read::{
+    read_fods, read_fods_buf, read_ods, read_ods_buf, read_ods_buf_with_password,
+    read_ods_with_password,
+};
+pub use crate::io::read_stream::{OdsStreamReader, RowReader, StreamRow, StreamRowIter};
+pub use crate::io::write::{
+    write_fods, write_fods_buf, write_ods, write_ods_buf, write_ods_buf_uncompressed,
+    write_ods_buf_with_password, write_ods_with_password,
+};
+pub use crate::io::write_stream::OdsStreamWriter;
 pub use crate::refs::{CellRange, CellRef, ColRange, RowRange};
 pub use crate::style::units::{Angle, Length};
 pub use crate::style::{CellStyle, CellStyleRef};
 use std::borrow::Cow;
 
 use crate::config::Config;
+use crate::database::DatabaseRange;
 use crate::defaultstyles::{DefaultFormat, DefaultStyle};
 use crate::ds::detach::Detach;
 use crate::ds::detach::Detached;
+use crate::find::FindQuery;
+use crate::formula::adjust::{
+    check_destination, normalized, qualify_formula, range_offset, translate_formula, Move, Shift,
+};
 use crate::io::filebuf::FileBuf;
+use crate::io::manifest::{Manifest, ManifestEntry};
 use crate::io::read::default_settings;
+use crate::io::write::store_view_settings;
+use crate::metadata::Metadata;
+use crate::named::NamedExpressions;
+use crate::protection::{ProtectionKey, SheetProtection};
+use crate::sort::{compare_values, SortKey};
 use crate::style::{
     ColStyle, ColStyleRef, FontFaceDecl, GraphicStyle, GraphicStyleRef, MasterPage, MasterPageRef,
     PageStyle, PageStyleRef, ParagraphStyle, ParagraphStyleRef, RowStyle, RowStyleRef, TableStyle,
@@ -209,13 +253,15 @@ use icu_locid::Locale;
 use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 #[cfg(feature = "use_decimal")]
 use rust_decimal::Decimal;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
 use std::iter::FusedIterator;
 use std::ops::RangeBounds;
 use std::str::from_utf8;
+use std::sync::OnceLock;
 
 #[macro_use]
 mod attr_macro;
@@ -223,17 +269,29 @@ mod attr_macro;
 mod unit_macro;
 #[macro_use]
 mod ref_macro;
+pub mod annotation;
 mod attrmap2;
+mod autofit;
 pub mod condition;
+pub mod conditional;
 mod config;
+pub mod database;
 pub mod defaultstyles;
+pub mod draw;
 mod ds;
 pub mod error;
+pub mod find;
 pub mod format;
 pub mod formula;
 mod io;
 mod locale;
+pub mod metadata;
+pub mod named;
+pub mod protection;
 pub mod refs;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod sort;
 pub mod style;
 pub mod text;
 pub mod validation;
@@ -279,6 +337,19 @@ pub struct WorkBook {
     /// Validations.
     validations: HashMap<String, Validation>,
 
+    /// Global named ranges and expressions.
+    named_expressions: NamedExpressions,
+
+    /// Named database ranges.
+    database_ranges: Vec<DatabaseRange>,
+
+    /// Structure of the workbook is protected.
+    structure_protected: bool,
+    structure_key: Option<ProtectionKey>,
+
+    /// Document metadata.
+    metadata: Metadata,
+
     /// Configuration data. Internal cache for all values.
     /// Mapped into WorkBookConfig, SheetConfig.
     config: Detach<Config>,
@@ -287,9 +358,15 @@ pub struct WorkBook {
 
     /// All extra files from the original ODS are copied here.
     filebuf: FileBuf,
+    /// Media-types of the files in the package.
+    manifest: Manifest,
 
     /// other stuff ...
     extra: Vec<XmlTag>,
+
+    /// Dependencies between the formulas. Built on demand and dropped
+    /// whenever a sheet may have changed.
+    dep_graph: OnceLock<formula::DependencyGraph>,
 }
 
 impl fmt::Debug for WorkBook {
@@ -334,10 +411,16 @@ impl fmt::Debug for WorkBook {
         for s in self.validations.values() {
             writeln!(f, "{:?}", s)?;
         }
+        writeln!(f, "{:?}", self.named_expressions)?;
+        for s in &self.database_ranges {
+            writeln!(f, "{:?}", s)?;
+        }
+        writeln!(f, "{:?}", self.metadata)?;
         for xtr in &self.extra {
             writeln!(f, "extras {:?}", xtr)?;
         }
         writeln!(f, "{:#?}", self.filebuf)?;
+        writeln!(f, "{:#?}", self.manifest)?;
         Ok(())
     }
 }
@@ -390,10 +473,17 @@ impl WorkBook {
             pagestyles: Default::default(),
             masterpages: Default::default(),
             validations: Default::default(),
+            named_expressions: Default::default(),
+            database_ranges: Default::default(),
+            structure_protected: false,
+            structure_key: None,
+            metadata: Metadata::new(),
             config: default_settings(),
             workbook_config: Default::default(),
             extra: vec![],
             filebuf: Default::default(),
+            manifest: Default::default(),
+            dep_graph: Default::default(),
         }
     }
 
@@ -518,6 +608,7 @@ impl WorkBook {
     /// Panics if the sheet has already been detached.
     /// Panics if n is out of bounds.
     pub fn detach_sheet(&mut self, n: usize) -> Detached<usize, Sheet> {
+        self.dep_graph.take();
         self.sheets[n].detach(n)
     }
 
@@ -527,6 +618,7 @@ impl WorkBook {
     ///
     /// Panics if n is out of bounds.
     pub fn attach_sheet(&mut self, sheet: Detached<usize, Sheet>) {
+        self.dep_graph.take();
         self.sheets[Detached::key(&sheet)].attach(sheet)
     }
 
@@ -545,16 +637,19 @@ impl WorkBook {
     ///
     /// Panics if n does not exist.
     pub fn sheet_mut(&mut self, n: usize) -> &mut Sheet {
+        self.dep_graph.take();
         self.sheets[n].as_mut()
     }
 
     /// Inserts the sheet at the given position.
     pub fn insert_sheet(&mut self, i: usize, sheet: Sheet) {
+        self.dep_graph.take();
         self.sheets.insert(i, sheet.into());
     }
 
     /// Appends a sheet.
     pub fn push_sheet(&mut self, sheet: Sheet) {
+        self.dep_graph.take();
         self.sheets.push(sheet.into());
     }
 
@@ -564,9 +659,299 @@ impl WorkBook {
     ///
     /// Panics if the sheet was detached.
     pub fn remove_sheet(&mut self, n: usize) -> Sheet {
+        self.dep_graph.take();
         self.sheets.remove(n).take()
     }
 
+    /// Freezes the first rows and columns of a sheet, see
+    /// Sheet::freeze_panes(). The view settings of the sheet in the
+    /// workbook configuration are updated too.
+    ///
+    /// Panics
+    ///
+    /// Panics if n is out of bounds.
+    pub fn freeze_panes(&mut self, n: usize, rows: u32, cols: u32) {
+        self.sheets[n].as_mut().freeze_panes(rows, cols);
+        store_view_settings(&mut self.config, self.sheets[n].as_ref());
+    }
+
+    /// Removes frozen panes and any other split of a sheet, see
+    /// Sheet::unfreeze(). The view settings of the sheet in the workbook
+    /// configuration are updated too.
+    ///
+    /// Panics
+    ///
+    /// Panics if n is out of bounds.
+    pub fn unfreeze(&mut self, n: usize) {
+        self.freeze_panes(n, 0, 0);
+    }
+
+    /// Recalculates all formulas and stores the results as cell values.
+    ///
+    /// See formula::recalc() for the details.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn recalc(&mut self) -> Result<(), OdsError> {
+        formula::recalc(self)
+    }
+
+    /// Recalculates only the formulas that depend on changed cells.
+    ///
+    /// Only sheets with Sheet::set_dirty_tracking() know their changed
+    /// cells. If any sheet doesn't track its changes everything is
+    /// recalculated.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn recalc_dirty(&mut self) -> Result<(), OdsError> {
+        formula::recalc_dirty(self)
+    }
+
+    /// Returns the graph of the references between the formulas.
+    ///
+    /// The graph is built on the first call and kept until a sheet is
+    /// modified.
+    pub fn dependency_graph(&self) -> Result<&formula::DependencyGraph, OdsError> {
+        if let Some(graph) = self.dep_graph.get() {
+            return Ok(graph);
+        }
+        let graph = formula::DependencyGraph::new(self)?;
+        Ok(self.dep_graph.get_or_init(|| graph))
+    }
+
+    /// Returns the references used by the formula in the cell.
+    /// The CellRef must contain the table-name.
+    pub fn precedents_of(&self, cell: &CellRef) -> Result<Vec<CellRange>, OdsError> {
+        Ok(self.dependency_graph()?.precedents(cell))
+    }
+
+    /// Returns all formula cells that depend directly or indirectly on
+    /// the given cell. The CellRef must contain the table-name.
+    pub fn dependents_of(&self, cell: &CellRef) -> Result<Vec<CellRef>, OdsError> {
+        Ok(self.dependency_graph()?.dependents(cell))
+    }
+
+    /// Returns all formula cells that are affected by the cells changed
+    /// since the last recalculation. Only sheets with
+    /// Sheet::set_dirty_tracking() record their changes.
+    pub fn dirty_dependents(&self) -> Result<Vec<CellRef>, OdsError> {
+        let graph = self.dependency_graph()?;
+        let mut dirty = Vec::new();
+        for (s, sheet) in self.sheets.iter().enumerate() {
+            for (row, col) in sheet.dirty_cells() {
+                dirty.push((s, row, col));
+            }
+        }
+        Ok(graph
+            .dependents_of_keys(&dirty)
+            .into_iter()
+            .map(|(s, row, col)| CellRef::remote(self.sheets[s].name(), row, col))
+            .collect())
+    }
+
+    /// Finds all circular references between formulas.
+    /// Each entry lists the cells of one cycle.
+    pub fn circular_references(&self) -> Result<Vec<Vec<CellRef>>, OdsError> {
+        Ok(self.dependency_graph()?.cycles())
+    }
+
+    /// Inserts n empty rows before the given row of the sheet.
+    ///
+    /// Works like Sheet::insert_rows() but adjusts the references in the
+    /// formulas of all sheets and the base-cells of validations and
+    /// stylemaps too.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn insert_rows(&mut self, sheet: usize, row: u32, n: u32) {
+        self.shift(sheet, Shift::InsertRows(row, n));
+    }
+
+    /// Deletes n rows of the sheet starting with the given row.
+    ///
+    /// References to the deleted cells are replaced with [.#REF!].
+    /// See insert_rows() for the details.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn delete_rows(&mut self, sheet: usize, row: u32, n: u32) {
+        self.shift(sheet, Shift::DeleteRows(row, n));
+    }
+
+    /// Inserts n empty columns before the given column of the sheet.
+    ///
+    /// See insert_rows() for the details.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn insert_cols(&mut self, sheet: usize, col: u32, n: u32) {
+        self.shift(sheet, Shift::InsertCols(col, n));
+    }
+
+    /// Deletes n columns of the sheet starting with the given column.
+    ///
+    /// References to the deleted cells are replaced with [.#REF!].
+    /// See insert_rows() for the details.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn delete_cols(&mut self, sheet: usize, col: u32, n: u32) {
+        self.shift(sheet, Shift::DeleteCols(col, n));
+    }
+
+    /// Copies a block of cells, possibly to another sheet. Both src and dst
+    /// must contain a table-name.
+    ///
+    /// See Sheet::copy_range() for the details.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn copy_range(&mut self, src: CellRange, dst: CellRef) -> Result<(), OdsError> {
+        self.dep_graph.take();
+        let src_idx = self.table_idx(src.table())?;
+        let dst_idx = self.table_idx(dst.table())?;
+        check_destination(&src, &dst)?;
+
+        let (cells, last_row, last_col) = self.sheets[src_idx].copy_cells(&src);
+        self.sheets[dst_idx].paste_cells(
+            cells,
+            dst.row(),
+            dst.col(),
+            last_row,
+            last_col,
+            Some(range_offset(&src, &dst)),
+        );
+        Ok(())
+    }
+
+    /// Moves a block of cells, possibly to another sheet. Both src and dst
+    /// must contain a table-name.
+    ///
+    /// The references of all sheets that point into the moved block are
+    /// moved too. When moving to another sheet the references of the moved
+    /// formulas still point to the original sheet.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn move_range(&mut self, src: CellRange, dst: CellRef) -> Result<(), OdsError> {
+        self.dep_graph.take();
+        let src_idx = self.table_idx(src.table())?;
+        let dst_idx = self.table_idx(dst.table())?;
+        check_destination(&src, &dst)?;
+        let src_name = self.sheets[src_idx].name.clone();
+        let dst_name = self.sheets[dst_idx].name.clone();
+
+        let (row, col, to_row, to_col) = normalized(&src);
+        if src_idx != dst_idx {
+            let sheet: &mut Sheet = &mut self.sheets[src_idx];
+            for ((_, c), cell) in sheet.data.range_mut((row, col)..=(to_row, to_col)) {
+                if *c < col || *c > to_col {
+                    continue;
+                }
+                if let Some(formula) = &cell.formula {
+                    if let Some(formula) = qualify_formula(formula, &src_name) {
+                        cell.formula = Some(formula);
+                    }
+                }
+            }
+        }
+
+        let (drow, dcol) = range_offset(&src, &dst);
+        let mv = Move {
+            src: (row, col, to_row, to_col),
+            drow,
+            dcol,
+            table: if src_idx != dst_idx {
+                Some(dst_name.as_str())
+            } else {
+                None
+            },
+        };
+        for (idx, sheet) in self.sheets.iter_mut().enumerate() {
+            sheet.move_refs(&mv, |table| match table {
+                None => idx == src_idx,
+                Some(table) => *table == src_name,
+            });
+        }
+
+        let (cells, last_row, last_col) = self.sheets[src_idx].take_cells(&src);
+        self.sheets[dst_idx].paste_cells(cells, dst.row(), dst.col(), last_row, last_col, None);
+        Ok(())
+    }
+
+    // Index of the sheet for the table-name of a reference.
+    fn table_idx(&self, table: Option<&String>) -> Result<usize, OdsError> {
+        match table {
+            Some(table) => self
+                .sheet_idx(table)
+                .ok_or_else(|| OdsError::Ods(format!("Unknown sheet {}", table))),
+            None => Err(OdsError::Ods("Reference without table-name".to_string())),
+        }
+    }
+
+    // Shifts the sheet and adjusts all references to it.
+    fn shift(&mut self, sheet: usize, shift: Shift) {
+        self.dep_graph.take();
+        self.sheets[sheet].shift(shift);
+
+        let name = self.sheets[sheet].name.clone();
+        let is_target = |table: Option<&String>| table == Some(&name);
+
+        for (idx, other) in self.sheets.iter_mut().enumerate() {
+            if idx == sheet {
+                continue;
+            }
+            let other: &mut Sheet = other;
+            for ((row, col), cell) in other.data.iter_mut() {
+                if let Some(formula) = &cell.formula {
+                    if let Some(formula) = shift.formula(formula, is_target) {
+                        cell.formula = Some(formula);
+                        if other.dirty_tracking {
+                            other.dirty.insert((*row, *col));
+                        }
+                    }
+                }
+            }
+            other.named_expressions.shift(&shift, is_target);
+        }
+        self.named_expressions.shift(&shift, is_target);
+        self.database_ranges
+            .retain_mut(|v| !is_target(v.range().table()) || shift.cellrange(v.range_mut()));
+
+        // A deleted base-cell is kept as is, there is no sensible
+        // replacement.
+        for validation in self.validations.values_mut() {
+            let mut base_cell = validation.base_cell().clone();
+            if is_target(base_cell.table()) && shift.cellref(&mut base_cell) {
+                validation.set_base_cell(base_cell);
+            }
+        }
+        let stylemaps = self
+            .cellstyles
+            .values_mut()
+            .flat_map(|v| v.stylemaps_mut().iter_mut())
+            .chain(
+                self.formats
+                    .values_mut()
+                    .flat_map(|v| v.stylemaps_mut().iter_mut()),
+            );
+        for stylemap in stylemaps {
+            let mut base_cell = stylemap.base_cell().clone();
+            if is_target(base_cell.table()) && shift.cellref(&mut base_cell) {
+                stylemap.set_base_cell(base_cell);
+            }
+        }
+    }
+
     /// Adds a default-style for all new values.
     /// This information is only used when writing the data to the ODS file.
     pub fn add_def_style(&mut self, value_type: ValueType, style: &CellStyleRef) {
@@ -591,6 +976,138 @@ impl WorkBook {
         None
     }
 
+    /// Formats the value with the ValueFormat. The stylemaps of the format
+    /// are applied too, the formats they refer to are looked up in this
+    /// workbook.
+    pub fn format_value(&self, value: &Value, format: &ValueFormat) -> String {
+        format::format_mapped(format, value, |name| self.formats.get(name))
+    }
+
+    /// Writes the sheet as CSV file. Formatted values are written as they
+    /// are displayed, using the ValueFormat of the cell-style.
+    ///
+    /// Panics
+    ///
+    /// Panics if the sheet was detached.
+    pub fn to_csv<W: Write>(
+        &self,
+        sheet: usize,
+        write: W,
+        options: &CsvOptions,
+    ) -> Result<(), OdsError> {
+        io::csv::write_csv(&self.sheets[sheet], write, options, |row, col, _| {
+            self.formatted_value(sheet, row, col)
+        })
+    }
+
+    /// Returns the text of the cell as it is displayed. Uses the
+    /// ValueFormat of the cell-style, or the default style for the value
+    /// type.
+    ///
+    /// Panics
+    ///
+    /// Panics if the sheet was detached.
+    pub fn formatted_value(&self, sheet: usize, row: u32, col: u32) -> String {
+        let sheet = &self.sheets[sheet];
+        let value = sheet.value(row, col);
+        let style = sheet
+            .cellstyle(row, col)
+            .or_else(|| self.def_style(value.value_type()));
+        match style.and_then(|v| self.find_value_format(v)) {
+            Some(format) => self.format_value(value, format),
+            None => format::format_default(value),
+        }
+    }
+
+    /// Finds all cells that match the query. Returns (sheet, row, col) in
+    /// the order of the sheets, rows and columns.
+    ///
+    /// Fails if the query is an invalid regex.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn find(&self, query: &FindQuery) -> Result<Vec<(usize, u32, u32)>, OdsError> {
+        find::find(self, query)
+    }
+
+    /// Replaces all matches of the query and returns the number of changed
+    /// cells.
+    ///
+    /// Replaces in text values, in the text nodes of TextXml values and
+    /// annotations and in formulas. The markup of rich text stays as it is,
+    /// but text that spans more than one text node is not found. Values
+    /// that are not text are found by find(), but not replaced.
+    ///
+    /// Fails if the query is an invalid regex.
+    ///
+    /// Panics
+    ///
+    /// Panics if a sheet was detached.
+    pub fn replace_all(&mut self, query: &FindQuery, replacement: &str) -> Result<usize, OdsError> {
+        self.dep_graph.take();
+        find::replace_all(self, query, replacement)
+    }
+
+    /// Sets the column widths of the sheet to an estimate of the optimal
+    /// width.
+    ///
+    /// The width is estimated from the displayed text of the cells and
+    /// the font size, font and bold state of the cell style. Wrapped
+    /// cells only need the space for the longest word. The character
+    /// widths come from built-in metrics of common fonts, other fonts
+    /// are measured like Liberation Sans.
+    ///
+    /// Columns without content are not changed. The column styles are
+    /// written with style:use-optimal-column-width. When the file is read
+    /// again these columns have Length::Default as width, like all columns
+    /// with optimal width.
+    ///
+    /// Panics
+    ///
+    /// Panics if the sheet was detached.
+    pub fn autofit_cols(&mut self, sheet: usize, range: ColRange) {
+        let widths = self.sheets[sheet]
+            .optimal_col_widths(&range, |row, col| self.autofit_measure(sheet, row, col));
+        self.sheets[sheet].set_optimal_col_widths(widths);
+    }
+
+    /// Sets the row heights of the sheet to an estimate of the optimal
+    /// height.
+    ///
+    /// Wrapped cells are broken into lines that fit the current column
+    /// width, so fit the columns first. The row styles are written with
+    /// style:use-optimal-row-height. See autofit_cols().
+    ///
+    /// Panics
+    ///
+    /// Panics if the sheet was detached.
+    pub fn autofit_rows(&mut self, sheet: usize, range: RowRange) {
+        let heights = self.sheets[sheet]
+            .optimal_row_heights(&range, |row, col| self.autofit_measure(sheet, row, col));
+        self.sheets[sheet].set_optimal_row_heights(heights);
+    }
+
+    // Displayed text and font of a cell.
+    fn autofit_measure(&self, sheet: usize, row: u32, col: u32) -> (String, CellFont) {
+        let sh = &self.sheets[sheet];
+        let style = sh
+            .cellstyle(row, col)
+            .or_else(|| sh.row_cellstyle(row))
+            .or_else(|| sh.col_cellstyle(col));
+        (
+            self.formatted_value(sheet, row, col),
+            CellFont::from_style(self, style),
+        )
+    }
+
+    /// Creates the format code for the ValueFormat. Other than
+    /// ValueFormat::to_format_code() this includes the sections for the
+    /// stylemaps, the formats they refer to are looked up in this workbook.
+    pub fn format_code(&self, format: &ValueFormat) -> String {
+        format::format_code(format, |name| self.formats.get(name))
+    }
+
     /// Adds a font.
     pub fn add_font(&mut self, font: FontFaceDecl) {
         self.fonts.insert(font.name().to_string(), font);
@@ -801,6 +1318,41 @@ impl WorkBook {
         self.graphicstyles.get_mut(name)
     }
 
+    /// Stores the image data in the package and adds it to the manifest.
+    /// The returned Image refers to this data and can be added to a sheet.
+    pub fn add_image<S: AsRef<str>>(&mut self, mime_type: S, data: Vec<u8>) -> Image {
+        let ext = match mime_type.as_ref() {
+            "image/png" => "png",
+            "image/jpeg" => "jpg",
+            "image/gif" => "gif",
+            "image/svg+xml" => "svg",
+            "image/bmp" => "bmp",
+            "image/tiff" => "tif",
+            "image/webp" => "webp",
+            _ => "bin",
+        };
+
+        let cnt = self.autonum.entry("Pictures/".to_string()).or_insert(0);
+        let href = loop {
+            *cnt += 1;
+            let href = format!("Pictures/image{}.{}", cnt, ext);
+            if !self.filebuf.contains(&href) && !self.manifest.contains(&href) {
+                break href;
+            }
+        };
+
+        self.filebuf.push_file(href.as_str(), data);
+        self.manifest
+            .push(ManifestEntry::new(href.clone(), mime_type.as_ref()));
+
+        Image::new(href)
+    }
+
+    /// Returns the image data for the href of an Image.
+    pub fn image_data(&self, href: &str) -> Option<&[u8]> {
+        self.filebuf.get(href)
+    }
+
     /// Adds a value format.
     /// Unnamed formats will be assigned an automatic name.
     pub fn add_format(&mut self, mut vstyle: ValueFormat) -> ValueFormatRef {
@@ -904,6 +1456,101 @@ impl WorkBook {
     pub fn validation_mut(&mut self, name: &str) -> Option<&mut Validation> {
         self.validations.get_mut(name)
     }
+
+    /// Global named ranges and expressions.
+    pub fn named_expressions(&self) -> &NamedExpressions {
+        &self.named_expressions
+    }
+
+    /// Global named ranges and expressions.
+    pub fn named_expressions_mut(&mut self) -> &mut NamedExpressions {
+        &mut self.named_expressions
+    }
+
+    /// Resolves the name to a range as seen from the given sheet.
+    /// The names local to the sheet take precedence over the
+    /// global ones.
+    pub fn resolve_name(&self, sheet: usize, name: &str) -> Option<CellRange> {
+        self.sheets
+            .get(sheet)
+            .and_then(|v| v.named_expressions.resolve(name))
+            .or_else(|| self.named_expressions.resolve(name))
+    }
+
+    /// Adds a database range. Replaces an existing one with the same
+    /// name. The range must contain the table-name.
+    ///
+    /// The autofilter of a sheet is set with Sheet::set_autofilter.
+    pub fn add_database_range(&mut self, database_range: DatabaseRange) {
+        if let Some(v) = self
+            .database_ranges
+            .iter_mut()
+            .find(|v| v.name() == database_range.name())
+        {
+            *v = database_range;
+        } else {
+            self.database_ranges.push(database_range);
+        }
+    }
+
+    /// Removes a database range.
+    pub fn remove_database_range(&mut self, name: &str) -> Option<DatabaseRange> {
+        let idx = self.database_ranges.iter().position(|v| v.name() == name)?;
+        Some(self.database_ranges.remove(idx))
+    }
+
+    /// Database range by name.
+    pub fn database_range(&self, name: &str) -> Option<&DatabaseRange> {
+        self.database_ranges.iter().find(|v| v.name() == name)
+    }
+
+    /// Database range by name.
+    pub fn database_range_mut(&mut self, name: &str) -> Option<&mut DatabaseRange> {
+        self.database_ranges.iter_mut().find(|v| v.name() == name)
+    }
+
+    /// All named database ranges.
+    pub fn database_ranges(&self) -> &[DatabaseRange] {
+        &self.database_ranges
+    }
+
+    /// Protects the structure of the workbook. Sheets can't be added,
+    /// removed, moved or renamed. An empty password protects without
+    /// password.
+    pub fn protect_structure(&mut self, password: &str) {
+        self.structure_protected = true;
+        self.structure_key = if password.is_empty() {
+            None
+        } else {
+            Some(ProtectionKey::from_password(password))
+        };
+    }
+
+    /// Removes the structure protection.
+    pub fn unprotect_structure(&mut self) {
+        self.structure_protected = false;
+        self.structure_key = None;
+    }
+
+    /// Structure of the workbook is protected.
+    pub fn is_structure_protected(&self) -> bool {
+        self.structure_protected
+    }
+
+    /// Password hash for the structure protection.
+    pub fn structure_protection_key(&self) -> Option<&ProtectionKey> {
+        self.structure_key.as_ref()
+    }
+
+    /// Document metadata.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Document metadata.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
 }
 
 /// Subset of the Workbook wide configurations.
@@ -955,6 +1602,113 @@ impl Display for Visibility {
     }
 }
 
+/// A group of rows or columns.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Grouped {
+    from: u32,
+    to: u32,
+    display: bool,
+}
+
+impl Grouped {
+    /// New group. The range is inclusive.
+    pub(crate) fn new(from: u32, to: u32, display: bool) -> Self {
+        assert!(from <= to);
+        Self { from, to, display }
+    }
+
+    /// First row/column.
+    pub fn from(&self) -> u32 {
+        self.from
+    }
+
+    /// Last row/column.
+    pub fn to(&self) -> u32 {
+        self.to
+    }
+
+    /// Is the group expanded? False for a collapsed group.
+    pub fn display(&self) -> bool {
+        self.display
+    }
+
+    /// Is the group expanded? This only changes the state of the group,
+    /// not the visibility of the rows/columns.
+    pub fn set_display(&mut self, display: bool) {
+        self.display = display;
+    }
+
+    /// Is the row/column in this group.
+    pub fn contains(&self, idx: u32) -> bool {
+        idx >= self.from && idx <= self.to
+    }
+}
+
+// Adds the group in sort order. Outer groups come first.
+fn add_group(groups: &mut Vec<Grouped>, group: Grouped) -> Result<(), OdsError> {
+    if let Some(v) = groups
+        .iter_mut()
+        .find(|v| v.from == group.from && v.to == group.to)
+    {
+        v.display = group.display;
+        return Ok(());
+    }
+    if let Some(v) = groups
+        .iter()
+        .find(|v| overlaps_partially((v.from, v.to), (group.from, group.to)))
+    {
+        return Err(OdsError::Ods(format!(
+            "Group {}:{} overlaps with {}:{}",
+            group.from, group.to, v.from, v.to
+        )));
+    }
+    let idx = groups
+        .iter()
+        .position(|v| (v.from, u32::MAX - v.to) > (group.from, u32::MAX - group.to))
+        .unwrap_or(groups.len());
+    groups.insert(idx, group);
+    Ok(())
+}
+
+// The ranges intersect, but neither contains the other.
+fn overlaps_partially(a: (u32, u32), b: (u32, u32)) -> bool {
+    a.0 < b.0 && b.0 <= a.1 && a.1 < b.1 || b.0 < a.0 && a.0 <= b.1 && b.1 < a.1
+}
+
+// Header rows/columns and groups must nest.
+pub(crate) fn check_headers(sheet: &Sheet) -> Result<(), OdsError> {
+    if let Some(v) = &sheet.header_rows {
+        check_header((v.row(), v.to_row()), &sheet.row_groups)?;
+    }
+    if let Some(v) = &sheet.header_cols {
+        check_header((v.col(), v.to_col()), &sheet.col_groups)?;
+    }
+    Ok(())
+}
+
+fn check_header(header: (u32, u32), groups: &[Grouped]) -> Result<(), OdsError> {
+    if let Some(v) = groups
+        .iter()
+        .find(|v| overlaps_partially((v.from, v.to), header))
+    {
+        return Err(OdsError::Ods(format!(
+            "Header {}:{} overlaps with group {}:{}",
+            header.0, header.1, v.from, v.to
+        )));
+    }
+    Ok(())
+}
+
+fn remove_group(groups: &mut Vec<Grouped>, from: u32, to: u32) -> bool {
+    let len = groups.len();
+    groups.retain(|v| v.from != from || v.to != to);
+    len != groups.len()
+}
+
+fn group_level(groups: &[Grouped], idx: u32) -> u32 {
+    groups.iter().filter(|v| v.contains(idx)).count() as u32
+}
+
 /// Row data
 #[derive(Debug, Clone, Default)]
 struct RowHeader {
@@ -963,6 +1717,8 @@ struct RowHeader {
     visible: Visibility,
     repeat: u32,
     height: Length,
+    // The height is an estimate of the optimal height.
+    optimal: bool,
 }
 
 impl RowHeader {
@@ -973,6 +1729,7 @@ impl RowHeader {
             visible: Default::default(),
             repeat: 1,
             height: Default::default(),
+            optimal: false,
         }
     }
 
@@ -1019,11 +1776,21 @@ impl RowHeader {
 
     pub(crate) fn set_height(&mut self, height: Length) {
         self.height = height;
+        self.optimal = false;
+    }
+
+    pub(crate) fn set_optimal_height(&mut self, height: Length) {
+        self.height = height;
+        self.optimal = true;
     }
 
     pub(crate) fn height(&self) -> Length {
         self.height
     }
+
+    pub(crate) fn optimal(&self) -> bool {
+        self.optimal
+    }
 }
 
 /// Column data
@@ -1033,6 +1800,8 @@ struct ColHeader {
     cellstyle: Option<String>,
     visible: Visibility,
     width: Length,
+    // The width is an estimate of the optimal width.
+    optimal: bool,
 }
 
 impl ColHeader {
@@ -1042,6 +1811,7 @@ impl ColHeader {
             cellstyle: None,
             visible: Default::default(),
             width: Default::default(),
+            optimal: false,
         }
     }
 
@@ -1079,11 +1849,21 @@ impl ColHeader {
 
     pub(crate) fn set_width(&mut self, width: Length) {
         self.width = width;
+        self.optimal = false;
+    }
+
+    pub(crate) fn set_optimal_width(&mut self, width: Length) {
+        self.width = width;
+        self.optimal = true;
     }
 
     pub(crate) fn width(&self) -> Length {
         self.width
     }
+
+    pub(crate) fn optimal(&self) -> bool {
+        self.optimal
+    }
 }
 
 /// One sheet of the spreadsheet.
@@ -1108,10 +1888,30 @@ pub struct Sheet {
     header_cols: Option<ColRange>,
     print_ranges: Option<Vec<CellRange>>,
 
+    // Sorted with the outer groups first.
+    row_groups: Vec<Grouped>,
+    col_groups: Vec<Grouped>,
+
     sheet_config: SheetConfig,
 
-    extra: Vec<XmlTag>,
-}
+    // Images anchored to the page.
+    images: Vec<Image>,
+
+    conditional_formats: Vec<ConditionalFormat>,
+
+    named_expressions: NamedExpressions,
+
+    // Anonymous database range. This is the autofilter.
+    database_range: Option<DatabaseRange>,
+
+    protection: Option<SheetProtection>,
+
+    extra: Vec<XmlTag>,
+
+    // Cells modified since the last recalculation.
+    dirty_tracking: bool,
+    dirty: BTreeSet<(u32, u32)>,
+}
 
 impl<'a> IntoIterator for &'a Sheet {
     type Item = ((u32, u32), CellContentRef<'a>);
@@ -1231,6 +2031,24 @@ impl fmt::Debug for Sheet {
         if let Some(header_cols) = &self.header_cols {
             writeln!(f, "header cols {:?}", header_cols)?;
         }
+        for group in &self.row_groups {
+            writeln!(f, "row group {:?}", group)?;
+        }
+        for group in &self.col_groups {
+            writeln!(f, "col group {:?}", group)?;
+        }
+        for cf in &self.conditional_formats {
+            writeln!(f, "conditional format {:?}", cf)?;
+        }
+        if let Some(database_range) = &self.database_range {
+            writeln!(f, "database range {:?}", database_range)?;
+        }
+        if let Some(protection) = &self.protection {
+            writeln!(f, "protection {:?}", protection)?;
+        }
+        if !self.named_expressions.is_empty() {
+            writeln!(f, "{:?}", self.named_expressions)?;
+        }
         for xtr in &self.extra {
             writeln!(f, "extras {:?}", xtr)?;
         }
@@ -1257,14 +2075,47 @@ impl Sheet {
             header_rows: None,
             header_cols: None,
             print_ranges: None,
+            row_groups: Vec::new(),
+            col_groups: Vec::new(),
             sheet_config: Default::default(),
+            images: Vec::new(),
+            conditional_formats: Vec::new(),
+            named_expressions: Default::default(),
+            database_range: None,
+            protection: None,
             extra: vec![],
             row_header: Default::default(),
             display: true,
             print: true,
+            dirty_tracking: false,
+            dirty: Default::default(),
         }
     }
 
+    /// Reads a CSV file into a new sheet.
+    ///
+    /// See CsvOptions for the supported options.
+    pub fn from_csv<S: Into<String>, R: Read>(
+        name: S,
+        read: R,
+        options: &CsvOptions,
+    ) -> Result<Self, OdsError> {
+        let mut sheet = Sheet::new(name);
+        io::csv::read_csv(&mut sheet, read, options)?;
+        Ok(sheet)
+    }
+
+    /// Writes the sheet as CSV file.
+    ///
+    /// The sheet doesn't know the styles of the workbook. Formatted values
+    /// use the default format for the value type. Use WorkBook::to_csv()
+    /// to get the display text of the cells.
+    pub fn to_csv<W: Write>(&self, write: W, options: &CsvOptions) -> Result<(), OdsError> {
+        io::csv::write_csv(self, write, options, |_, _, value| {
+            format::format_default(value)
+        })
+    }
+
     /// Copy all the attributes but not the actual data.
     pub fn clone_no_data(&self) -> Self {
         Self {
@@ -1278,8 +2129,17 @@ impl Sheet {
             header_rows: self.header_rows.clone(),
             header_cols: self.header_cols.clone(),
             print_ranges: self.print_ranges.clone(),
+            row_groups: self.row_groups.clone(),
+            col_groups: self.col_groups.clone(),
             sheet_config: Default::default(),
+            images: self.images.clone(),
+            conditional_formats: self.conditional_formats.clone(),
+            named_expressions: self.named_expressions.clone(),
+            database_range: self.database_range.clone(),
+            protection: self.protection.clone(),
             extra: self.extra.clone(),
+            dirty_tracking: self.dirty_tracking,
+            dirty: Default::default(),
         }
     }
 
@@ -1520,6 +2380,64 @@ impl Sheet {
         }
     }
 
+    // Widest cell in pt for each column. Spanned cells are not used.
+    fn optimal_col_widths<F>(&self, range: &ColRange, measure: F) -> BTreeMap<u32, f64>
+    where
+        F: Fn(u32, u32) -> (String, CellFont),
+    {
+        let mut widths = BTreeMap::new();
+        for ((row, col), cell) in self.data.iter() {
+            if !range.contains(*col) || cell.span.col_span > 1 || matches!(cell.value, Value::Empty)
+            {
+                continue;
+            }
+            let (text, font) = measure(*row, *col);
+            let width = widths.entry(*col).or_insert(0f64);
+            *width = width.max(font.width(&text));
+        }
+        widths
+    }
+
+    fn set_optimal_col_widths(&mut self, widths: BTreeMap<u32, f64>) {
+        for (col, width) in widths {
+            // creates the header if necessary.
+            self.set_col_width(col, Length::Default);
+            if let Some(ch) = self.col_header.get_mut(&col) {
+                ch.set_optimal_width(pt_length(width));
+            }
+        }
+    }
+
+    // Highest cell in pt for each row. Spanned cells are not used.
+    fn optimal_row_heights<F>(&self, range: &RowRange, measure: F) -> BTreeMap<u32, f64>
+    where
+        F: Fn(u32, u32) -> (String, CellFont),
+    {
+        let mut heights = BTreeMap::new();
+        for ((row, col), cell) in self
+            .data
+            .range((range.row(), 0)..=(range.to_row(), u32::MAX))
+        {
+            if cell.span.row_span > 1 || matches!(cell.value, Value::Empty) {
+                continue;
+            }
+            let (text, font) = measure(*row, *col);
+            let height = heights.entry(*row).or_insert(0f64);
+            *height = height.max(font.height(&text, self.col_width(*col)));
+        }
+        heights
+    }
+
+    fn set_optimal_row_heights(&mut self, heights: BTreeMap<u32, f64>) {
+        for (row, height) in heights {
+            // creates the header if necessary.
+            self.set_row_height(row, Length::Default);
+            if let Some(rh) = self.row_header.get_mut(&row) {
+                rh.set_optimal_height(pt_length(height));
+            }
+        }
+    }
+
     /// Returns the maximum used column +1 in the column header
     pub fn used_cols(&self) -> u32 {
         *self.col_header.keys().max().unwrap_or(&0) + 1
@@ -1584,39 +2502,23 @@ impl Sheet {
             formula: value.formula.clone(),
             validation_name: value.validation_name.clone(),
             span: value.span,
+            annotation: value.annotation().cloned().map(Box::new),
+            images: value.images().cloned().unwrap_or_default(),
         })
     }
 
     /// Consumes the CellContent and sets the values.
     pub fn add_cell(&mut self, row: u32, col: u32, cell: CellContent) {
-        self.add_cell_data(
-            row,
-            col,
-            CellData {
-                value: cell.value,
-                formula: cell.formula,
-                style: cell.style,
-                validation_name: cell.validation_name,
-                span: cell.span,
-            },
-        );
+        self.mark_dirty(row, col);
+        self.add_cell_data(row, col, CellData::from_content(cell));
     }
 
     /// Removes the cell and returns the values as CellContent.
     pub fn remove_cell(&mut self, row: u32, col: u32) -> Option<CellContent> {
+        self.mark_dirty(row, col);
         let value = self.data.remove(&(row, col));
 
-        if let Some(value) = value {
-            Some(CellContent {
-                value: value.value,
-                style: value.style,
-                formula: value.formula,
-                validation_name: value.validation_name,
-                span: value.span,
-            })
-        } else {
-            None
-        }
+        value.map(CellData::into_content)
     }
 
     /// Add a new cell. Main use is for reading the spreadsheet.
@@ -1632,13 +2534,18 @@ impl Sheet {
         value: V,
         style: &CellStyleRef,
     ) {
+        self.mark_dirty(row, col);
         let mut cell = self.data.entry((row, col)).or_insert_with(CellData::new);
         cell.value = value.into();
         cell.style = Some(style.to_string());
     }
 
     /// Sets a value for the specified cell. Creates a new cell if necessary.
+    ///
+    /// With dirty tracking the cell is marked as changed for
+    /// WorkBook::recalc_dirty().
     pub fn set_value<V: Into<Value>>(&mut self, row: u32, col: u32, value: V) {
+        self.mark_dirty(row, col);
         let mut cell = self.data.entry((row, col)).or_insert_with(CellData::new);
         cell.value = value.into();
     }
@@ -1654,12 +2561,14 @@ impl Sheet {
 
     /// Sets a formula for the specified cell. Creates a new cell if necessary.
     pub fn set_formula<V: Into<String>>(&mut self, row: u32, col: u32, formula: V) {
+        self.mark_dirty(row, col);
         let mut cell = self.data.entry((row, col)).or_insert_with(CellData::new);
         cell.formula = Some(formula.into());
     }
 
     /// Removes the formula.
     pub fn clear_formula(&mut self, row: u32, col: u32) {
+        self.mark_dirty(row, col);
         if let Some(cell) = self.data.get_mut(&(row, col)) {
             cell.formula = None;
         }
@@ -1674,6 +2583,42 @@ impl Sheet {
         }
     }
 
+    /// Records the changed cells for WorkBook::recalc_dirty().
+    /// Off by default.
+    pub fn set_dirty_tracking(&mut self, tracking: bool) {
+        self.dirty_tracking = tracking;
+        if !tracking {
+            self.dirty.clear();
+        }
+    }
+
+    /// Records the changed cells for WorkBook::recalc_dirty().
+    pub fn dirty_tracking(&self) -> bool {
+        self.dirty_tracking
+    }
+
+    pub(crate) fn mark_dirty(&mut self, row: u32, col: u32) {
+        if self.dirty_tracking {
+            self.dirty.insert((row, col));
+        }
+    }
+
+    /// Cells whose value or formula has been changed since the last
+    /// recalculation. Empty unless dirty tracking is on.
+    pub fn dirty_cells(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.dirty.iter().copied()
+    }
+
+    /// Has the cell been changed since the last recalculation.
+    pub fn is_dirty(&self, row: u32, col: u32) -> bool {
+        self.dirty.contains(&(row, col))
+    }
+
+    /// Forget all changes.
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+
     /// Sets the cell-style for the specified cell. Creates a new cell if necessary.
     pub fn set_cellstyle(&mut self, row: u32, col: u32, style: &CellStyleRef) {
         let mut cell = self.data.entry((row, col)).or_insert_with(CellData::new);
@@ -1718,6 +2663,182 @@ impl Sheet {
         }
     }
 
+    /// Sets the annotation for this cell.
+    pub fn set_annotation(&mut self, row: u32, col: u32, annotation: Annotation) {
+        let cell = self.data.entry((row, col)).or_default();
+        cell.extra_mut().annotation = Some(annotation);
+    }
+
+    /// Removes the annotation.
+    pub fn clear_annotation(&mut self, row: u32, col: u32) {
+        if let Some(cell) = self.data.get_mut(&(row, col)) {
+            if let Some(extra) = &mut cell.extra {
+                extra.annotation = None;
+            }
+            cell.cleanup_extra();
+        }
+    }
+
+    /// Returns the annotation for this cell.
+    pub fn annotation(&self, row: u32, col: u32) -> Option<&Annotation> {
+        if let Some(c) = self.data.get(&(row, col)) {
+            c.annotation()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the annotation for this cell.
+    pub fn annotation_mut(&mut self, row: u32, col: u32) -> Option<&mut Annotation> {
+        if let Some(c) = self.data.get_mut(&(row, col)) {
+            c.extra.as_mut().and_then(|v| v.annotation.as_mut())
+        } else {
+            None
+        }
+    }
+
+    /// Adds an image anchored to this cell.
+    pub fn add_image(&mut self, row: u32, col: u32, image: Image) {
+        let cell = self.data.entry((row, col)).or_default();
+        cell.extra_mut().images.push(image);
+    }
+
+    /// Removes all images anchored to this cell.
+    pub fn clear_images(&mut self, row: u32, col: u32) {
+        if let Some(cell) = self.data.get_mut(&(row, col)) {
+            if let Some(extra) = &mut cell.extra {
+                extra.images.clear();
+            }
+            cell.cleanup_extra();
+        }
+    }
+
+    /// Returns the images anchored to this cell.
+    pub fn images(&self, row: u32, col: u32) -> Option<&Vec<Image>> {
+        if let Some(c) = self.data.get(&(row, col)) {
+            c.images()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the images anchored to this cell.
+    pub fn images_mut(&mut self, row: u32, col: u32) -> Option<&mut Vec<Image>> {
+        if let Some(c) = self.data.get_mut(&(row, col)) {
+            c.extra
+                .as_mut()
+                .map(|v| &mut v.images)
+                .filter(|v| !v.is_empty())
+        } else {
+            None
+        }
+    }
+
+    /// Adds an image anchored to the page.
+    pub fn add_page_image(&mut self, image: Image) {
+        self.images.push(image);
+    }
+
+    /// Images anchored to the page.
+    pub fn page_images(&self) -> &Vec<Image> {
+        &self.images
+    }
+
+    /// Images anchored to the page.
+    pub fn page_images_mut(&mut self) -> &mut Vec<Image> {
+        &mut self.images
+    }
+
+    /// Adds a conditional format. The rules of earlier conditional
+    /// formats take precedence.
+    pub fn add_conditional_format(&mut self, cf: ConditionalFormat) {
+        self.conditional_formats.push(cf);
+    }
+
+    /// Conditional formats.
+    pub fn conditional_formats(&self) -> &Vec<ConditionalFormat> {
+        &self.conditional_formats
+    }
+
+    /// Conditional formats.
+    pub fn conditional_formats_mut(&mut self) -> &mut Vec<ConditionalFormat> {
+        &mut self.conditional_formats
+    }
+
+    /// Named ranges and expressions local to this sheet.
+    pub fn named_expressions(&self) -> &NamedExpressions {
+        &self.named_expressions
+    }
+
+    /// Named ranges and expressions local to this sheet.
+    pub fn named_expressions_mut(&mut self) -> &mut NamedExpressions {
+        &mut self.named_expressions
+    }
+
+    /// Sets an autofilter for the range. The first row of the range
+    /// gets the filter buttons. Replaces any existing autofilter.
+    pub fn set_autofilter(&mut self, range: CellRange) {
+        let mut database_range = DatabaseRange::new("", range);
+        database_range.set_display_filter_buttons(true);
+        self.database_range = Some(database_range);
+    }
+
+    /// Removes the autofilter.
+    pub fn clear_autofilter(&mut self) {
+        self.database_range = None;
+    }
+
+    /// The anonymous database range of the sheet. If it shows the
+    /// filter buttons this is the autofilter. It can contain a filter
+    /// and sort descriptors too.
+    pub fn autofilter(&self) -> Option<&DatabaseRange> {
+        self.database_range.as_ref()
+    }
+
+    /// The anonymous database range of the sheet.
+    pub fn autofilter_mut(&mut self) -> Option<&mut DatabaseRange> {
+        self.database_range.as_mut()
+    }
+
+    /// Sets the anonymous database range of the sheet. The name is
+    /// not used.
+    pub fn set_database_range(&mut self, database_range: Option<DatabaseRange>) {
+        self.database_range = database_range;
+    }
+
+    /// Protects the sheet. The password is stored as SHA-256 hash,
+    /// an empty password protects without password. The options
+    /// define what can still be done with the protected sheet.
+    pub fn protect(&mut self, password: &str, mut options: SheetProtection) {
+        if password.is_empty() {
+            options.set_key(None);
+        } else {
+            options.set_key(Some(ProtectionKey::from_password(password)));
+        }
+        self.protection = Some(options);
+    }
+
+    /// Removes the protection.
+    pub fn unprotect(&mut self) {
+        self.protection = None;
+    }
+
+    /// Sheet is protected.
+    pub fn is_protected(&self) -> bool {
+        self.protection.is_some()
+    }
+
+    /// Protection of the sheet.
+    pub fn protection(&self) -> Option<&SheetProtection> {
+        self.protection.as_ref()
+    }
+
+    /// Sets the protection as is. Use this to copy the protection
+    /// from another sheet.
+    pub fn set_protection(&mut self, protection: Option<SheetProtection>) {
+        self.protection = protection;
+    }
+
     /// Sets the rowspan of the cell. Must be greater than 0.
     pub fn set_row_span(&mut self, row: u32, col: u32, span: u32) {
         let mut cell = self.data.entry((row, col)).or_insert_with(CellData::new);
@@ -1750,6 +2871,9 @@ impl Sheet {
     }
 
     /// Defines a range of rows as header rows.
+    ///
+    /// The header rows must not overlap partially with a row group,
+    /// otherwise writing the sheet fails.
     pub fn set_header_rows(&mut self, row_start: u32, row_end: u32) {
         self.header_rows = Some(RowRange::new(row_start, row_end));
     }
@@ -1765,6 +2889,9 @@ impl Sheet {
     }
 
     /// Defines a range of columns as header columns.
+    ///
+    /// The header columns must not overlap partially with a column group,
+    /// otherwise writing the sheet fails.
     pub fn set_header_cols(&mut self, col_start: u32, col_end: u32) {
         self.header_cols = Some(ColRange::new(col_start, col_end));
     }
@@ -1794,6 +2921,434 @@ impl Sheet {
         self.print_ranges.as_ref()
     }
 
+    /// Groups the rows. Groups can be nested, but they must not
+    /// overlap partially, neither with each other nor with the header rows.
+    /// Grouping the same rows again only changes the collapsed state.
+    ///
+    /// A collapsed group hides its rows.
+    pub fn group_rows(&mut self, range: RowRange, collapsed: bool) -> Result<(), OdsError> {
+        if let Some(header_rows) = &self.header_rows {
+            check_header(
+                (header_rows.row(), header_rows.to_row()),
+                &[Grouped::new(range.row(), range.to_row(), true)],
+            )?;
+        }
+        add_group(
+            &mut self.row_groups,
+            Grouped::new(range.row(), range.to_row(), !collapsed),
+        )?;
+        if collapsed {
+            for row in range.row()..=range.to_row() {
+                self.set_row_visible(row, Visibility::Collapsed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the group with exactly this range. The visibility of the
+    /// rows is not changed.
+    pub fn ungroup_rows(&mut self, range: RowRange) -> bool {
+        remove_group(&mut self.row_groups, range.row(), range.to_row())
+    }
+
+    /// Row groups. Sorted by the first row, the outer groups come first.
+    pub fn row_groups(&self) -> &Vec<Grouped> {
+        &self.row_groups
+    }
+
+    /// Number of groups that contain the row.
+    pub fn row_group_level(&self, row: u32) -> u32 {
+        group_level(&self.row_groups, row)
+    }
+
+    /// Groups the columns. Groups can be nested, but they must not
+    /// overlap partially, neither with each other nor with the header
+    /// columns. Grouping the same columns again only changes the collapsed
+    /// state.
+    ///
+    /// A collapsed group hides its columns.
+    pub fn group_cols(&mut self, range: ColRange, collapsed: bool) -> Result<(), OdsError> {
+        if let Some(header_cols) = &self.header_cols {
+            check_header(
+                (header_cols.col(), header_cols.to_col()),
+                &[Grouped::new(range.col(), range.to_col(), true)],
+            )?;
+        }
+        add_group(
+            &mut self.col_groups,
+            Grouped::new(range.col(), range.to_col(), !collapsed),
+        )?;
+        if collapsed {
+            for col in range.col()..=range.to_col() {
+                self.set_col_visible(col, Visibility::Collapsed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the group with exactly this range. The visibility of the
+    /// columns is not changed.
+    pub fn ungroup_cols(&mut self, range: ColRange) -> bool {
+        remove_group(&mut self.col_groups, range.col(), range.to_col())
+    }
+
+    /// Column groups. Sorted by the first column, the outer groups come first.
+    pub fn col_groups(&self) -> &Vec<Grouped> {
+        &self.col_groups
+    }
+
+    /// Number of groups that contain the column.
+    pub fn col_group_level(&self, col: u32) -> u32 {
+        group_level(&self.col_groups, col)
+    }
+
+    /// Inserts n empty rows before the given row.
+    ///
+    /// Shifts the cells, row-headers, spans, header-rows and print-ranges.
+    /// The references in the formulas of this sheet are adjusted too.
+    /// Use WorkBook::insert_rows() to adjust the references from other
+    /// sheets, validations and stylemaps as well.
+    pub fn insert_rows(&mut self, row: u32, n: u32) {
+        self.shift(Shift::InsertRows(row, n));
+    }
+
+    /// Deletes n rows starting with the given row.
+    ///
+    /// References to the deleted cells are replaced with [.#REF!].
+    /// See insert_rows() for the details.
+    pub fn delete_rows(&mut self, row: u32, n: u32) {
+        self.shift(Shift::DeleteRows(row, n));
+    }
+
+    /// Inserts n empty columns before the given column.
+    ///
+    /// See insert_rows() for the details.
+    pub fn insert_cols(&mut self, col: u32, n: u32) {
+        self.shift(Shift::InsertCols(col, n));
+    }
+
+    /// Deletes n columns starting with the given column.
+    ///
+    /// References to the deleted cells are replaced with [.#REF!].
+    /// See insert_rows() for the details.
+    pub fn delete_cols(&mut self, col: u32, n: u32) {
+        self.shift(Shift::DeleteCols(col, n));
+    }
+
+    /// Copies the cells of the range to the destination. Values, formulas,
+    /// styles, validations and spans are copied, the destination block is
+    /// cleared first. The table-names of src and dst are ignored.
+    ///
+    /// Relative references in the formulas are translated like a paste
+    /// does, absolute references are kept.
+    ///
+    /// Fails if the range doesn't fit into the sheet at the destination.
+    pub fn copy_range(&mut self, src: CellRange, dst: CellRef) -> Result<(), OdsError> {
+        check_destination(&src, &dst)?;
+        let (cells, last_row, last_col) = self.copy_cells(&src);
+        self.paste_cells(
+            cells,
+            dst.row(),
+            dst.col(),
+            last_row,
+            last_col,
+            Some(range_offset(&src, &dst)),
+        );
+        Ok(())
+    }
+
+    /// Moves the cells of the range to the destination. The table-names of
+    /// src and dst are ignored.
+    ///
+    /// The formulas are moved unchanged, but all references of this sheet
+    /// that point into the moved block are moved too. Use
+    /// WorkBook::move_range() to adjust the references from other sheets too.
+    ///
+    /// Fails if the range doesn't fit into the sheet at the destination.
+    pub fn move_range(&mut self, src: CellRange, dst: CellRef) -> Result<(), OdsError> {
+        check_destination(&src, &dst)?;
+        let (drow, dcol) = range_offset(&src, &dst);
+        let mv = Move {
+            src: normalized(&src),
+            drow,
+            dcol,
+            table: None,
+        };
+        let name = self.name.clone();
+        self.move_refs(&mv, |table| table.is_none() || table == Some(&name));
+
+        let (cells, last_row, last_col) = self.take_cells(&src);
+        self.paste_cells(cells, dst.row(), dst.col(), last_row, last_col, None);
+        Ok(())
+    }
+
+    /// Sorts the rows of the range. The first key decides, the next keys
+    /// are used if the values are equal. Rows that are equal for all keys
+    /// keep their order. Empty rows are moved to the end. A header row
+    /// must not be part of the range.
+    ///
+    /// The cells of a row are moved with their styles, validations,
+    /// annotations and formulas. Relative references in the formulas are
+    /// translated like a paste does, absolute references are kept.
+    ///
+    /// The table-name of the range is ignored. Fails if a key column is
+    /// outside the range.
+    pub fn sort_range(&mut self, range: CellRange, keys: &[SortKey]) -> Result<(), OdsError> {
+        let (row, col, _, to_col) = normalized(&range);
+        if let Some(key) = keys.iter().find(|k| k.col() < col || k.col() > to_col) {
+            return Err(OdsError::Ods(format!(
+                "Sort key column {} is outside the range {}",
+                key.col(),
+                range
+            )));
+        }
+
+        // The cells of each row that has any, with the row and column
+        // relative to the range. Rows without cells stay empty at the end.
+        let (cells, _, _) = self.take_cells(&range);
+        let mut lines: Vec<(u32, Vec<(u32, CellData)>)> = Vec::new();
+        for ((r, c), cell) in cells {
+            match lines.last_mut() {
+                Some((last_r, line)) if *last_r == r => line.push((c, cell)),
+                _ => lines.push((r, vec![(c, cell)])),
+            }
+        }
+
+        let value = |(_, line): &(u32, Vec<(u32, CellData)>), key: &SortKey| -> Value {
+            line.iter()
+                .find(|(c, _)| *c == key.col() - col)
+                .map(|(_, cell)| cell.value.clone())
+                .unwrap_or_default()
+        };
+        let keyvalues: Vec<Vec<Value>> = lines
+            .iter()
+            .map(|line| keys.iter().map(|key| value(line, key)).collect())
+            .collect();
+        let mut order: Vec<usize> = (0..lines.len()).collect();
+        order.sort_by(|a, b| {
+            keys.iter()
+                .enumerate()
+                .map(|(i, key)| compare_values(&keyvalues[*a][i], &keyvalues[*b][i], key))
+                .find(|ord| *ord != std::cmp::Ordering::Equal)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        for (new_r, idx) in order.into_iter().enumerate() {
+            let (old_r, line) = std::mem::take(&mut lines[idx]);
+            let drow = new_r as i64 - old_r as i64;
+            for (c, mut cell) in line {
+                if let (true, Some(formula)) = (drow != 0, &cell.formula) {
+                    if let Some(formula) = translate_formula(formula, drow, 0) {
+                        cell.formula = Some(formula);
+                    }
+                }
+                let k = (row + new_r as u32, col + c);
+                self.data.insert(k, cell);
+                self.mark_dirty(k.0, k.1);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Clone of the cells in the range. Positions are relative to the start of
+    // the range. Returns the relative position of the last row and column
+    // too.
+    #[allow(clippy::type_complexity)]
+    fn copy_cells(&self, src: &CellRange) -> (Vec<((u32, u32), CellData)>, u32, u32) {
+        let (row, col, to_row, to_col) = normalized(src);
+        let cells = self
+            .data
+            .range((row, col)..=(to_row, to_col))
+            .filter(|((_, c), _)| *c >= col && *c <= to_col)
+            .map(|((r, c), d)| ((r - row, c - col), d.clone()))
+            .collect();
+        (cells, to_row - row, to_col - col)
+    }
+
+    // Removes the cells in the range. See copy_cells().
+    #[allow(clippy::type_complexity)]
+    fn take_cells(&mut self, src: &CellRange) -> (Vec<((u32, u32), CellData)>, u32, u32) {
+        let (cells, last_row, last_col) = self.copy_cells(src);
+        let (row, col, _, _) = normalized(src);
+        for ((r, c), _) in &cells {
+            self.data.remove(&(row + r, col + c));
+            self.mark_dirty(row + r, col + c);
+        }
+        (cells, last_row, last_col)
+    }
+
+    // Clears the destination and inserts the cells. Translates the formulas
+    // if an offset is given. The caller checks that the block fits with
+    // check_destination().
+    fn paste_cells(
+        &mut self,
+        cells: Vec<((u32, u32), CellData)>,
+        row: u32,
+        col: u32,
+        last_row: u32,
+        last_col: u32,
+        offset: Option<(i64, i64)>,
+    ) {
+        let to_col = col + last_col;
+        let clear: Vec<(u32, u32)> = self
+            .data
+            .range((row, col)..=(row + last_row, to_col))
+            .map(|(k, _)| *k)
+            .filter(|(_, c)| *c >= col && *c <= to_col)
+            .collect();
+        for k in clear {
+            self.data.remove(&k);
+            self.mark_dirty(k.0, k.1);
+        }
+
+        for ((r, c), mut cell) in cells {
+            if let (Some((drow, dcol)), Some(formula)) = (offset, &cell.formula) {
+                if let Some(formula) = translate_formula(formula, drow, dcol) {
+                    cell.formula = Some(formula);
+                }
+            }
+            self.data.insert((row + r, col + c), cell);
+            self.mark_dirty(row + r, col + c);
+        }
+    }
+
+    // Rewrites the references into a moved block.
+    fn move_refs<F>(&mut self, mv: &Move<'_>, is_target: F)
+    where
+        F: Fn(Option<&String>) -> bool,
+    {
+        for ((row, col), cell) in self.data.iter_mut() {
+            if let Some(formula) = &cell.formula {
+                if let Some(formula) = mv.formula(formula, &is_target) {
+                    cell.formula = Some(formula);
+                    if self.dirty_tracking {
+                        self.dirty.insert((*row, *col));
+                    }
+                }
+            }
+        }
+    }
+
+    // Moves everything around and adjusts the references to this sheet.
+    fn shift(&mut self, shift: Shift) {
+        if matches!(
+            shift,
+            Shift::InsertRows(_, 0)
+                | Shift::DeleteRows(_, 0)
+                | Shift::InsertCols(_, 0)
+                | Shift::DeleteCols(_, 0)
+        ) {
+            return;
+        }
+
+        let name = self.name.clone();
+        let is_target = |table: Option<&String>| match table {
+            None => true,
+            Some(table) => *table == name,
+        };
+
+        let data = std::mem::take(&mut self.data);
+        for ((row, col), mut cell) in data {
+            let (new_row, new_col) = match shift.cell(row, col) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            if cell.span.row_span > 1 {
+                if let Some((from, to)) = shift.rows(row, row + cell.span.row_span - 1) {
+                    cell.span.row_span = to - from + 1;
+                }
+            }
+            if cell.span.col_span > 1 {
+                if let Some((from, to)) = shift.cols(col, col + cell.span.col_span - 1) {
+                    cell.span.col_span = to - from + 1;
+                }
+            }
+
+            if let Some(formula) = &cell.formula {
+                if let Some(formula) = shift.formula(formula, is_target) {
+                    cell.formula = Some(formula);
+                    self.mark_dirty(new_row, new_col);
+                }
+            }
+
+            self.data.insert((new_row, new_col), cell);
+        }
+
+        let dirty = std::mem::take(&mut self.dirty);
+        self.dirty = dirty
+            .into_iter()
+            .filter_map(|(row, col)| shift.cell(row, col))
+            .collect();
+
+        let row_header = std::mem::take(&mut self.row_header);
+        for (row, mut header) in row_header {
+            if let Some((from, to)) = shift.rows(row, row + header.repeat.max(1) - 1) {
+                header.repeat = to - from + 1;
+                self.row_header.insert(from, header);
+            }
+        }
+        let col_header = std::mem::take(&mut self.col_header);
+        for (col, header) in col_header {
+            if let Some((col, _)) = shift.cols(col, col) {
+                self.col_header.insert(col, header);
+            }
+        }
+
+        if let Some(header_rows) = &self.header_rows {
+            self.header_rows = shift
+                .rows(header_rows.row(), header_rows.to_row())
+                .map(|(row, to_row)| RowRange::new(row, to_row));
+        }
+        if let Some(header_cols) = &self.header_cols {
+            self.header_cols = shift
+                .cols(header_cols.col(), header_cols.to_col())
+                .map(|(col, to_col)| ColRange::new(col, to_col));
+        }
+        if let Some(print_ranges) = &mut self.print_ranges {
+            print_ranges.retain_mut(|r| !is_target(r.table()) || shift.cellrange(r));
+        }
+        self.row_groups
+            .retain_mut(|g| match shift.rows(g.from, g.to) {
+                Some((from, to)) => {
+                    g.from = from;
+                    g.to = to;
+                    true
+                }
+                None => false,
+            });
+        self.col_groups
+            .retain_mut(|g| match shift.cols(g.from, g.to) {
+                Some((from, to)) => {
+                    g.from = from;
+                    g.to = to;
+                    true
+                }
+                None => false,
+            });
+        for cf in &mut self.conditional_formats {
+            cf.range_mut()
+                .retain_mut(|r| !is_target(r.table()) || shift.cellrange(r));
+            for rule in cf.rules_mut() {
+                if let CfRule::Condition(c) = rule {
+                    if let Some(mut base_cell) = c.base_cell().cloned() {
+                        if is_target(base_cell.table()) && shift.cellref(&mut base_cell) {
+                            c.set_base_cell(base_cell);
+                        }
+                    }
+                }
+            }
+        }
+        self.conditional_formats.retain(|v| !v.range().is_empty());
+        self.named_expressions.shift(&shift, is_target);
+        if let Some(database_range) = &mut self.database_range {
+            if !shift.cellrange(database_range.range_mut()) {
+                self.database_range = None;
+            }
+        }
+    }
+
     /// Split horizontally on a cell boundary. The splitting is fixed in
     /// position.
     pub fn split_col_header(&mut self, col: u32) {
@@ -1825,6 +3380,72 @@ impl Sheet {
         self.config_mut().vert_split_mode = SplitMode::Split;
         self.config_mut().vert_split_pos = col;
     }
+
+    /// Freezes the first rows and columns, like View/Freeze Cells in
+    /// LibreOffice. Sets the split modes, positions, the visible cells of
+    /// the quadrants and the active quadrant. The cursor is moved out of
+    /// the frozen cells.
+    ///
+    /// Any other split of the sheet is removed. With 0 rows and 0 columns
+    /// this is the same as unfreeze().
+    ///
+    /// The view settings of the sheet in the workbook configuration are
+    /// written from the SheetConfig when saving. WorkBook::freeze_panes()
+    /// updates them right away.
+    pub fn freeze_panes(&mut self, rows: u32, cols: u32) {
+        let config = self.config_mut();
+
+        if cols > 0 {
+            config.hor_split_mode = SplitMode::Heading;
+        } else {
+            config.hor_split_mode = SplitMode::None;
+        }
+        config.hor_split_pos = cols;
+        config.position_left = 0;
+        config.position_right = cols;
+
+        if rows > 0 {
+            config.vert_split_mode = SplitMode::Heading;
+        } else {
+            config.vert_split_mode = SplitMode::None;
+        }
+        config.vert_split_pos = rows;
+        config.position_top = 0;
+        config.position_bottom = rows;
+
+        // bottom-right if there is a right side, bottom-left otherwise.
+        config.active_split_range = if cols > 0 { 3 } else { 2 };
+
+        config.cursor_x = config.cursor_x.max(cols);
+        config.cursor_y = config.cursor_y.max(rows);
+    }
+
+    /// Removes frozen panes and any other split of the sheet.
+    pub fn unfreeze(&mut self) {
+        self.freeze_panes(0, 0);
+    }
+
+    /// Frozen rows and columns, as (rows, cols). None if nothing is frozen.
+    ///
+    /// A split that can be moved around is not frozen.
+    pub fn frozen_panes(&self) -> Option<(u32, u32)> {
+        let config = self.config();
+
+        let rows = match config.vert_split_mode {
+            SplitMode::Heading => config.vert_split_pos,
+            _ => 0,
+        };
+        let cols = match config.hor_split_mode {
+            SplitMode::Heading => config.hor_split_pos,
+            _ => 0,
+        };
+
+        if rows > 0 || cols > 0 {
+            Some((rows, cols))
+        } else {
+            None
+        }
+    }
 }
 
 /// There are two ways a sheet can be split. There are fixed column/row header
@@ -1858,18 +3479,19 @@ pub struct SheetConfig {
     pub cursor_x: u32,
     /// Active row.
     pub cursor_y: u32,
-    /// Splitting the table.
+    /// Splitting the table into a left and a right part.
     pub hor_split_mode: SplitMode,
-    /// Splitting the table.
+    /// Splitting the table into a top and a bottom part.
     pub vert_split_mode: SplitMode,
-    /// Position of the split.
+    /// Position of the split. For SplitMode::Heading the number of columns
+    /// left of the split, for SplitMode::Split a position in pixel.
     pub hor_split_pos: u32,
-    /// Position of the split.
+    /// Position of the split. For SplitMode::Heading the number of rows
+    /// above the split, for SplitMode::Split a position in pixel.
     pub vert_split_pos: u32,
-    /// SplitMode is Pixel
-    /// - 0-4 indicates the quadrant where the focus is.
-    /// SplitMode is Cell
-    /// - No real function.
+    /// The quadrant where the focus is. 0 top-left, 1 top-right,
+    /// 2 bottom-left, 3 bottom-right. Without a split the bottom quadrants
+    /// are used.
     pub active_split_range: i16,
     /// SplitMode is Pixel
     /// - First visible column in the left quadrant.
@@ -1889,7 +3511,7 @@ pub struct SheetConfig {
     ///   AND every row up from this one is simply invisible.
     pub position_top: u32,
     /// SplitMode is Pixel
-    /// - The first visible row in teh right quadrant.
+    /// - The first visible row in the bottom quadrant.
     /// SplitMode is Cell
     /// - The first visible row in the bottom quadrant.
     pub position_bottom: u32,
@@ -1997,6 +3619,26 @@ struct CellData {
     validation_name: Option<String>,
     // Row/Column span.
     span: CellSpan,
+    // Rarely used data.
+    extra: Option<Box<CellDataExt>>,
+}
+
+/// Annotation and images of a cell. Boxed to keep the CellData small.
+#[derive(Debug, Clone, Default)]
+struct CellDataExt {
+    annotation: Option<Annotation>,
+    images: Vec<Image>,
+}
+
+impl CellDataExt {
+    /// None if there is nothing to store.
+    fn new_opt(annotation: Option<Annotation>, images: Vec<Image>) -> Option<Box<Self>> {
+        if annotation.is_none() && images.is_empty() {
+            None
+        } else {
+            Some(Box::new(Self { annotation, images }))
+        }
+    }
 }
 
 impl CellData {
@@ -2008,6 +3650,56 @@ impl CellData {
             style: None,
             validation_name: None,
             span: Default::default(),
+            extra: None,
+        }
+    }
+
+    pub(crate) fn from_content(cell: CellContent) -> Self {
+        CellData {
+            value: cell.value,
+            formula: cell.formula,
+            style: cell.style,
+            validation_name: cell.validation_name,
+            span: cell.span,
+            extra: CellDataExt::new_opt(cell.annotation.map(|v| *v), cell.images),
+        }
+    }
+
+    pub(crate) fn into_content(self) -> CellContent {
+        let (annotation, images) = match self.extra {
+            Some(extra) => (extra.annotation.map(Box::new), extra.images),
+            None => (None, Vec::new()),
+        };
+        CellContent {
+            value: self.value,
+            style: self.style,
+            formula: self.formula,
+            validation_name: self.validation_name,
+            span: self.span,
+            annotation,
+            images,
+        }
+    }
+
+    fn annotation(&self) -> Option<&Annotation> {
+        self.extra.as_ref().and_then(|v| v.annotation.as_ref())
+    }
+
+    fn images(&self) -> Option<&Vec<Image>> {
+        self.extra
+            .as_ref()
+            .map(|v| &v.images)
+            .filter(|v| !v.is_empty())
+    }
+
+    fn extra_mut(&mut self) -> &mut CellDataExt {
+        self.extra.get_or_insert_with(Default::default)
+    }
+
+    // Drops the extra data if it is empty.
+    fn cleanup_extra(&mut self) {
+        if let Some(extra) = self.extra.take() {
+            self.extra = CellDataExt::new_opt(extra.annotation, extra.images);
         }
     }
 }
@@ -2026,6 +3718,10 @@ pub struct CellContentRef<'a> {
     pub validation_name: Option<&'a String>,
     /// Reference to the cellspan.
     pub span: Option<&'a CellSpan>,
+    /// Reference to the annotation.
+    pub annotation: Option<&'a Annotation>,
+    /// Reference to the images anchored to the cell.
+    pub images: Option<&'a Vec<Image>>,
 }
 
 impl<'a> From<&'a CellData> for CellContentRef<'a> {
@@ -2036,6 +3732,8 @@ impl<'a> From<&'a CellData> for CellContentRef<'a> {
             formula: cd.formula.as_ref(),
             validation_name: cd.validation_name.as_ref(),
             span: Some(&cd.span),
+            annotation: cd.annotation(),
+            images: cd.images(),
         }
     }
 }
@@ -2065,6 +3763,16 @@ impl<'a> CellContentRef<'a> {
         self.validation_name
     }
 
+    /// Returns the annotation.
+    pub fn annotation(&self) -> Option<&'a Annotation> {
+        self.annotation
+    }
+
+    /// Returns the images.
+    pub fn images(&self) -> Option<&'a Vec<Image>> {
+        self.images
+    }
+
     /// Returns the row span.
     pub fn row_span(&self) -> u32 {
         if let Some(span) = self.span {
@@ -2097,6 +3805,10 @@ pub struct CellContent {
     pub validation_name: Option<String>,
     /// Cellspan.
     pub span: CellSpan,
+    /// Annotation.
+    pub annotation: Option<Box<Annotation>>,
+    /// Images anchored to the cell.
+    pub images: Vec<Image>,
 }
 
 impl CellContent {
@@ -2108,6 +3820,8 @@ impl CellContent {
             formula: None,
             validation_name: None,
             span: Default::default(),
+            annotation: None,
+            images: Vec::new(),
         }
     }
 
@@ -2166,6 +3880,36 @@ impl CellContent {
         self.validation_name = None;
     }
 
+    /// Returns the annotation.
+    pub fn annotation(&self) -> Option<&Annotation> {
+        self.annotation.as_deref()
+    }
+
+    /// Sets the annotation.
+    pub fn set_annotation(&mut self, annotation: Annotation) {
+        self.annotation = Some(Box::new(annotation));
+    }
+
+    /// Removes the annotation.
+    pub fn clear_annotation(&mut self) {
+        self.annotation = None;
+    }
+
+    /// Returns the images.
+    pub fn images(&self) -> &Vec<Image> {
+        &self.images
+    }
+
+    /// Adds an image anchored to the cell.
+    pub fn add_image(&mut self, image: Image) {
+        self.images.push(image);
+    }
+
+    /// Removes all images.
+    pub fn clear_images(&mut self) {
+        self.images.clear();
+    }
+
     /// Sets the row span of this cell.
     /// Cells below with values will be lost when writing.
     pub fn set_row_span(&mut self, rows: u32) {
@@ -0,0 +1,323 @@
+//!
+//! Document metadata. This is the content of meta.xml.
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use spreadsheet_ods::metadata::MetaValue;
+//! use spreadsheet_ods::WorkBook;
+//!
+//! let mut wb = WorkBook::new_empty();
+//!
+//! let meta = wb.metadata_mut();
+//! meta.set_title("Quarterly report");
+//! meta.set_keywords(vec!["report".to_string(), "2022".to_string()]);
+//! meta.set_creator("accounting");
+//! meta.set_date(NaiveDate::from_ymd_opt(2022, 4, 1).and_then(|v| v.and_hms_opt(12, 0, 0)));
+//! meta.set_user_defined("Archive", MetaValue::String("A-17".to_string()));
+//! ```
+//!
+
+use crate::xmltree::XmlTag;
+use chrono::{Duration, NaiveDateTime};
+
+/// Document metadata.
+///
+/// Empty strings are not written. Any unknown elements of office:meta
+/// are kept as they are.
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub(crate) generator: String,
+    pub(crate) title: String,
+    pub(crate) description: String,
+    pub(crate) subject: String,
+    pub(crate) keywords: Vec<String>,
+    pub(crate) initial_creator: String,
+    pub(crate) creator: String,
+    pub(crate) printed_by: String,
+    pub(crate) creation_date: Option<NaiveDateTime>,
+    pub(crate) date: Option<NaiveDateTime>,
+    pub(crate) print_date: Option<NaiveDateTime>,
+    pub(crate) language: String,
+    pub(crate) editing_cycles: u32,
+    pub(crate) editing_duration: Duration,
+    pub(crate) document_statistics: MetaDocumentStatistics,
+    pub(crate) user_defined: Vec<MetaUserDefined>,
+    // Other elements of office:meta.
+    pub(crate) extra: Vec<XmlTag>,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self {
+            generator: Default::default(),
+            title: Default::default(),
+            description: Default::default(),
+            subject: Default::default(),
+            keywords: Default::default(),
+            initial_creator: Default::default(),
+            creator: Default::default(),
+            printed_by: Default::default(),
+            creation_date: None,
+            date: None,
+            print_date: None,
+            language: Default::default(),
+            editing_cycles: 0,
+            editing_duration: Duration::zero(),
+            document_statistics: Default::default(),
+            user_defined: Default::default(),
+            extra: Default::default(),
+        }
+    }
+}
+
+impl Metadata {
+    /// Metadata for a new document. Sets the generator and the
+    /// creation date.
+    pub fn new() -> Self {
+        Self {
+            generator: concat!("spreadsheet-ods ", env!("CARGO_PKG_VERSION")).to_string(),
+            creation_date: Some(chrono::Local::now().naive_local()),
+            editing_cycles: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Application that created the document.
+    pub fn generator(&self) -> &str {
+        &self.generator
+    }
+
+    /// Application that created the document.
+    pub fn set_generator<S: Into<String>>(&mut self, generator: S) {
+        self.generator = generator.into();
+    }
+
+    /// Title. Written as dc:title.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Title. Written as dc:title.
+    pub fn set_title<S: Into<String>>(&mut self, title: S) {
+        self.title = title.into();
+    }
+
+    /// Description. Written as dc:description.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Description. Written as dc:description.
+    pub fn set_description<S: Into<String>>(&mut self, description: S) {
+        self.description = description.into();
+    }
+
+    /// Subject. Written as dc:subject.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Subject. Written as dc:subject.
+    pub fn set_subject<S: Into<String>>(&mut self, subject: S) {
+        self.subject = subject.into();
+    }
+
+    /// Keywords. Each one is written as meta:keyword.
+    pub fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    /// Keywords. Each one is written as meta:keyword.
+    pub fn set_keywords(&mut self, keywords: Vec<String>) {
+        self.keywords = keywords;
+    }
+
+    /// Adds a keyword.
+    pub fn push_keyword<S: Into<String>>(&mut self, keyword: S) {
+        self.keywords.push(keyword.into());
+    }
+
+    /// Name of the person who created the document.
+    pub fn initial_creator(&self) -> &str {
+        &self.initial_creator
+    }
+
+    /// Name of the person who created the document.
+    pub fn set_initial_creator<S: Into<String>>(&mut self, initial_creator: S) {
+        self.initial_creator = initial_creator.into();
+    }
+
+    /// Name of the person who last modified the document.
+    /// Written as dc:creator.
+    pub fn creator(&self) -> &str {
+        &self.creator
+    }
+
+    /// Name of the person who last modified the document.
+    /// Written as dc:creator.
+    pub fn set_creator<S: Into<String>>(&mut self, creator: S) {
+        self.creator = creator.into();
+    }
+
+    /// Name of the person who last printed the document.
+    pub fn printed_by(&self) -> &str {
+        &self.printed_by
+    }
+
+    /// Name of the person who last printed the document.
+    pub fn set_printed_by<S: Into<String>>(&mut self, printed_by: S) {
+        self.printed_by = printed_by.into();
+    }
+
+    /// Default language. Written as dc:language.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Default language. Written as dc:language.
+    pub fn set_language<S: Into<String>>(&mut self, language: S) {
+        self.language = language.into();
+    }
+
+    /// Creation date.
+    pub fn creation_date(&self) -> Option<NaiveDateTime> {
+        self.creation_date
+    }
+
+    /// Creation date.
+    pub fn set_creation_date(&mut self, creation_date: Option<NaiveDateTime>) {
+        self.creation_date = creation_date;
+    }
+
+    /// Date of the last modification. Written as dc:date.
+    pub fn date(&self) -> Option<NaiveDateTime> {
+        self.date
+    }
+
+    /// Date of the last modification. Written as dc:date.
+    pub fn set_date(&mut self, date: Option<NaiveDateTime>) {
+        self.date = date;
+    }
+
+    /// Date of the last printout.
+    pub fn print_date(&self) -> Option<NaiveDateTime> {
+        self.print_date
+    }
+
+    /// Date of the last printout.
+    pub fn set_print_date(&mut self, print_date: Option<NaiveDateTime>) {
+        self.print_date = print_date;
+    }
+
+    /// How often the document has been edited.
+    pub fn editing_cycles(&self) -> u32 {
+        self.editing_cycles
+    }
+
+    /// How often the document has been edited.
+    pub fn set_editing_cycles(&mut self, editing_cycles: u32) {
+        self.editing_cycles = editing_cycles;
+    }
+
+    /// Total time spent editing the document.
+    pub fn editing_duration(&self) -> Duration {
+        self.editing_duration
+    }
+
+    /// Total time spent editing the document.
+    pub fn set_editing_duration(&mut self, editing_duration: Duration) {
+        self.editing_duration = editing_duration;
+    }
+
+    /// Statistics. The table, cell and object counts are updated
+    /// when the workbook is written.
+    pub fn document_statistics(&self) -> &MetaDocumentStatistics {
+        &self.document_statistics
+    }
+
+    /// Statistics.
+    pub fn document_statistics_mut(&mut self) -> &mut MetaDocumentStatistics {
+        &mut self.document_statistics
+    }
+
+    /// All user defined properties.
+    pub fn user_defined_props(&self) -> &[MetaUserDefined] {
+        &self.user_defined
+    }
+
+    /// User defined property.
+    pub fn user_defined(&self, name: &str) -> Option<&MetaValue> {
+        self.user_defined
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| &v.value)
+    }
+
+    /// Sets a user defined property. Replaces an existing one with the
+    /// same name.
+    pub fn set_user_defined<S: Into<String>>(&mut self, name: S, value: MetaValue) {
+        let name = name.into();
+        if let Some(v) = self.user_defined.iter_mut().find(|v| v.name == name) {
+            v.value = value;
+        } else {
+            self.user_defined.push(MetaUserDefined::new(name, value));
+        }
+    }
+
+    /// Removes a user defined property.
+    pub fn remove_user_defined(&mut self, name: &str) -> Option<MetaValue> {
+        let idx = self.user_defined.iter().position(|v| v.name == name)?;
+        Some(self.user_defined.remove(idx).value)
+    }
+}
+
+/// Document statistics. Written as meta:document-statistic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub struct MetaDocumentStatistics {
+    pub cell_count: u32,
+    pub character_count: u32,
+    pub draw_count: u32,
+    pub frame_count: u32,
+    pub image_count: u32,
+    pub non_whitespace_character_count: u32,
+    pub object_count: u32,
+    pub ole_object_count: u32,
+    pub page_count: u32,
+    pub paragraph_count: u32,
+    pub row_count: u32,
+    pub sentence_count: u32,
+    pub syllable_count: u32,
+    pub table_count: u32,
+    pub word_count: u32,
+}
+
+/// User defined property. Written as meta:user-defined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaUserDefined {
+    /// Name.
+    pub name: String,
+    /// Typed value.
+    pub value: MetaValue,
+}
+
+impl MetaUserDefined {
+    /// User defined property.
+    pub fn new<S: Into<String>>(name: S, value: MetaValue) -> Self {
+        Self {
+            name: name.into(),
+            value,
+        }
+    }
+}
+
+/// Value of a user defined property.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum MetaValue {
+    Boolean(bool),
+    Datetime(NaiveDateTime),
+    Float(f64),
+    TimeDuration(Duration),
+    String(String),
+}
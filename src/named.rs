@@ -0,0 +1,319 @@
+//!
+//! Named ranges and named expressions.
+//!
+//! They are defined for the whole workbook or local to one sheet.
+//! A local name hides a global one with the same name. Names are
+//! case-insensitive.
+//!
+//! ```
+//! use spreadsheet_ods::named::{NamedExpression, NamedRange};
+//! use spreadsheet_ods::{CellRange, CellRef, Sheet, WorkBook};
+//!
+//! let mut wb = WorkBook::new_empty();
+//! wb.push_sheet(Sheet::new("data"));
+//!
+//! wb.named_expressions_mut().add_range(NamedRange::new(
+//!     "Data",
+//!     CellRange::remote("data", 0, 0, 99, 3).absolute(),
+//! ));
+//! wb.named_expressions_mut().add_expression(NamedExpression::new(
+//!     "TaxRate",
+//!     "of:=0.19",
+//! ));
+//!
+//! let mut local = NamedExpression::new("Total", "of:=SUM([$data.$D$1:.$D$100])");
+//! local.set_base_cell(CellRef::remote("data", 0, 0));
+//! wb.sheet_mut(0).named_expressions_mut().add_expression(local);
+//!
+//! assert_eq!(
+//!     wb.resolve_name(0, "data"),
+//!     Some(CellRange::remote("data", 0, 0, 99, 3).absolute())
+//! );
+//! ```
+//!
+
+use crate::formula::adjust::Shift;
+use crate::formula::{parse, Expr};
+use crate::{CellRange, CellRef};
+
+/// A name for a cell range. Written as table:named-range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedRange {
+    name: String,
+    range: CellRange,
+    base_cell: Option<CellRef>,
+    usable_as: Option<String>,
+}
+
+impl NamedRange {
+    /// Name for the range.
+    pub fn new<S: Into<String>>(name: S, range: CellRange) -> Self {
+        Self {
+            name: name.into(),
+            range,
+            base_cell: None,
+            usable_as: None,
+        }
+    }
+
+    /// Name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    /// Range.
+    pub fn range(&self) -> &CellRange {
+        &self.range
+    }
+
+    /// Range.
+    pub fn set_range(&mut self, range: CellRange) {
+        self.range = range;
+    }
+
+    /// Relative references in the range are relative to this cell.
+    pub fn base_cell(&self) -> Option<&CellRef> {
+        self.base_cell.as_ref()
+    }
+
+    /// Relative references in the range are relative to this cell.
+    pub fn set_base_cell(&mut self, base_cell: CellRef) {
+        self.base_cell = Some(base_cell);
+    }
+
+    /// Resets the base cell.
+    pub fn clear_base_cell(&mut self) {
+        self.base_cell = None;
+    }
+
+    /// Additional use of the range. A whitespace separated list of
+    /// print-range, filter, repeat-row and repeat-column or none.
+    pub fn usable_as(&self) -> Option<&String> {
+        self.usable_as.as_ref()
+    }
+
+    /// Additional use of the range.
+    pub fn set_usable_as<S: Into<String>>(&mut self, usable_as: S) {
+        self.usable_as = Some(usable_as.into());
+    }
+
+    /// Resets the additional use.
+    pub fn clear_usable_as(&mut self) {
+        self.usable_as = None;
+    }
+}
+
+/// A name for a formula. Written as table:named-expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedExpression {
+    name: String,
+    expression: String,
+    base_cell: Option<CellRef>,
+}
+
+impl NamedExpression {
+    /// Name for the expression. The expression is given in the
+    /// same form as a cell formula, e.g. "of:=[.A1]*2".
+    pub fn new<S: Into<String>, T: Into<String>>(name: S, expression: T) -> Self {
+        Self {
+            name: name.into(),
+            expression: expression.into(),
+            base_cell: None,
+        }
+    }
+
+    /// Name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    /// Expression.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Expression.
+    pub fn set_expression<S: Into<String>>(&mut self, expression: S) {
+        self.expression = expression.into();
+    }
+
+    /// Relative references in the expression are relative to this cell.
+    pub fn base_cell(&self) -> Option<&CellRef> {
+        self.base_cell.as_ref()
+    }
+
+    /// Relative references in the expression are relative to this cell.
+    pub fn set_base_cell(&mut self, base_cell: CellRef) {
+        self.base_cell = Some(base_cell);
+    }
+
+    /// Resets the base cell.
+    pub fn clear_base_cell(&mut self) {
+        self.base_cell = None;
+    }
+
+    /// The range if the expression is a single reference.
+    pub fn range(&self) -> Option<CellRange> {
+        match parse(&self.expression).ok()? {
+            Expr::CellRange(r) => Some(r),
+            Expr::CellRef(c) => {
+                let mut r = match c.table() {
+                    Some(table) => CellRange::remote(table, c.row(), c.col(), c.row(), c.col()),
+                    None => CellRange::local(c.row(), c.col(), c.row(), c.col()),
+                };
+                r.set_row_abs(c.row_abs());
+                r.set_col_abs(c.col_abs());
+                r.set_to_row_abs(c.row_abs());
+                r.set_to_col_abs(c.col_abs());
+                Some(r)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The named ranges and named expressions of a workbook or a sheet.
+#[derive(Debug, Clone, Default)]
+pub struct NamedExpressions {
+    ranges: Vec<NamedRange>,
+    expressions: Vec<NamedExpression>,
+}
+
+impl NamedExpressions {
+    /// Empty.
+    pub fn new() -> Self {
+        Self {
+            ranges: Vec::new(),
+            expressions: Vec::new(),
+        }
+    }
+
+    /// Nothing defined.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty() && self.expressions.is_empty()
+    }
+
+    /// Number of names.
+    pub fn len(&self) -> usize {
+        self.ranges.len() + self.expressions.len()
+    }
+
+    /// Adds a named range. Replaces any range or expression with
+    /// the same name.
+    pub fn add_range(&mut self, range: NamedRange) {
+        self.remove(range.name());
+        self.ranges.push(range);
+    }
+
+    /// Adds a named expression. Replaces any range or expression with
+    /// the same name.
+    pub fn add_expression(&mut self, expression: NamedExpression) {
+        self.remove(expression.name());
+        self.expressions.push(expression);
+    }
+
+    /// Removes the name. Returns false if there was no such name.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.len();
+        self.ranges.retain(|v| !v.name.eq_ignore_ascii_case(name));
+        self.expressions
+            .retain(|v| !v.name.eq_ignore_ascii_case(name));
+        len != self.len()
+    }
+
+    /// Is the name defined?
+    pub fn contains(&self, name: &str) -> bool {
+        self.range(name).is_some() || self.expression(name).is_some()
+    }
+
+    /// Named range.
+    pub fn range(&self, name: &str) -> Option<&NamedRange> {
+        self.ranges
+            .iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Named range.
+    pub fn range_mut(&mut self, name: &str) -> Option<&mut NamedRange> {
+        self.ranges
+            .iter_mut()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Named expression.
+    pub fn expression(&self, name: &str) -> Option<&NamedExpression> {
+        self.expressions
+            .iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Named expression.
+    pub fn expression_mut(&mut self, name: &str) -> Option<&mut NamedExpression> {
+        self.expressions
+            .iter_mut()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    /// All named ranges.
+    pub fn ranges(&self) -> &Vec<NamedRange> {
+        &self.ranges
+    }
+
+    /// All named expressions.
+    pub fn expressions(&self) -> &Vec<NamedExpression> {
+        &self.expressions
+    }
+
+    /// The range for the name. This works for named ranges and for
+    /// named expressions that consist of a single reference.
+    pub fn resolve(&self, name: &str) -> Option<CellRange> {
+        if let Some(range) = self.range(name) {
+            Some(range.range.clone())
+        } else {
+            self.expression(name).and_then(|v| v.range())
+        }
+    }
+
+    // Adjusts the references to a changed sheet. A deleted range
+    // is kept as is, there is no sensible replacement.
+    pub(crate) fn shift<F>(&mut self, shift: &Shift, is_target: F)
+    where
+        F: Fn(Option<&String>) -> bool,
+    {
+        for range in &mut self.ranges {
+            let mut new_range = range.range.clone();
+            if is_target(new_range.table()) && shift.cellrange(&mut new_range) {
+                range.range = new_range;
+            }
+            shift_base_cell(&mut range.base_cell, shift, &is_target);
+        }
+        for expression in &mut self.expressions {
+            if let Some(v) = shift.formula(&expression.expression, &is_target) {
+                expression.expression = v;
+            }
+            shift_base_cell(&mut expression.base_cell, shift, &is_target);
+        }
+    }
+}
+
+fn shift_base_cell<F>(base_cell: &mut Option<CellRef>, shift: &Shift, is_target: F)
+where
+    F: Fn(Option<&String>) -> bool,
+{
+    if let Some(base_cell) = base_cell {
+        let mut new_cell = base_cell.clone();
+        if is_target(new_cell.table()) && shift.cellref(&mut new_cell) {
+            *base_cell = new_cell;
+        }
+    }
+}
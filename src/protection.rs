@@ -0,0 +1,204 @@
+//!
+//! Protection for sheets and for the structure of the workbook.
+//!
+//! The password is stored as a SHA-256 hash in the same way
+//! LibreOffice does. Which cells can be edited in a protected sheet
+//! is defined by the cell-style, see
+//! [CellStyle::set_cell_protect](crate::CellStyle::set_cell_protect).
+//!
+//! ```
+//! use spreadsheet_ods::protection::SheetProtection;
+//! use spreadsheet_ods::style::units::CellProtect;
+//! use spreadsheet_ods::{CellStyle, Sheet, ValueFormatRef, WorkBook};
+//!
+//! let mut wb = WorkBook::new_empty();
+//!
+//! let mut input = CellStyle::new("input", &ValueFormatRef::from(""));
+//! input.set_cell_protect(CellProtect::None);
+//! let input = wb.add_cellstyle(input);
+//!
+//! let mut sh = Sheet::new("form");
+//! sh.set_styled_value(1, 1, "", &input);
+//!
+//! let mut options = SheetProtection::new();
+//! options.set_select_protected_cells(false);
+//! sh.protect("secret", options);
+//! assert!(sh.protection().map(|v| v.verify("secret")) == Some(true));
+//!
+//! wb.push_sheet(sh);
+//! wb.protect_structure("secret");
+//! ```
+//!
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+/// Digest algorithm used for the password hash.
+pub const SHA256_DIGEST: &str = "http://www.w3.org/2000/09/xmldsig#sha256";
+
+/// Hashed password.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectionKey {
+    key: String,
+    digest_algorithm: Option<String>,
+}
+
+impl ProtectionKey {
+    /// Hashes the password with SHA-256.
+    pub fn from_password(password: &str) -> Self {
+        Self {
+            key: sha256_key(password),
+            digest_algorithm: Some(SHA256_DIGEST.to_string()),
+        }
+    }
+
+    /// Key as it is stored in the file. The key is the base64 encoded
+    /// hash of the password. If no digest algorithm is given, SHA-1
+    /// is assumed.
+    pub fn new<S: Into<String>>(key: S, digest_algorithm: Option<String>) -> Self {
+        Self {
+            key: key.into(),
+            digest_algorithm,
+        }
+    }
+
+    /// Base64 encoded hash.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Digest algorithm as URI.
+    pub fn digest_algorithm(&self) -> Option<&String> {
+        self.digest_algorithm.as_ref()
+    }
+
+    /// Checks the password. Only SHA-256 hashes can be verified,
+    /// for any other algorithm this returns false.
+    pub fn verify(&self, password: &str) -> bool {
+        match self.digest_algorithm.as_deref() {
+            Some(SHA256_DIGEST) => self.key == sha256_key(password),
+            _ => false,
+        }
+    }
+}
+
+fn sha256_key(password: &str) -> String {
+    STANDARD.encode(Sha256::digest(password.as_bytes()))
+}
+
+/// Protection of a sheet. Besides the password this defines what
+/// can still be done with a protected sheet.
+///
+/// The options are written as loext:table-protection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetProtection {
+    key: Option<ProtectionKey>,
+    select_protected_cells: bool,
+    select_unprotected_cells: bool,
+    insert_columns: bool,
+    insert_rows: bool,
+    delete_columns: bool,
+    delete_rows: bool,
+}
+
+impl Default for SheetProtection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SheetProtection {
+    /// Default protection. All cells can be selected, no columns or
+    /// rows can be inserted or deleted.
+    pub fn new() -> Self {
+        Self {
+            key: None,
+            select_protected_cells: true,
+            select_unprotected_cells: true,
+            insert_columns: false,
+            insert_rows: false,
+            delete_columns: false,
+            delete_rows: false,
+        }
+    }
+
+    /// Password hash. Without a key the sheet is protected without
+    /// password.
+    pub fn key(&self) -> Option<&ProtectionKey> {
+        self.key.as_ref()
+    }
+
+    /// Password hash.
+    pub fn set_key(&mut self, key: Option<ProtectionKey>) {
+        self.key = key;
+    }
+
+    /// Checks the password. Without a key any password is ok.
+    pub fn verify(&self, password: &str) -> bool {
+        match &self.key {
+            Some(key) => key.verify(password),
+            None => true,
+        }
+    }
+
+    /// Protected cells can be selected.
+    pub fn select_protected_cells(&self) -> bool {
+        self.select_protected_cells
+    }
+
+    /// Protected cells can be selected.
+    pub fn set_select_protected_cells(&mut self, select: bool) {
+        self.select_protected_cells = select;
+    }
+
+    /// Unprotected cells can be selected.
+    pub fn select_unprotected_cells(&self) -> bool {
+        self.select_unprotected_cells
+    }
+
+    /// Unprotected cells can be selected.
+    pub fn set_select_unprotected_cells(&mut self, select: bool) {
+        self.select_unprotected_cells = select;
+    }
+
+    /// Columns can be inserted.
+    pub fn insert_columns(&self) -> bool {
+        self.insert_columns
+    }
+
+    /// Columns can be inserted.
+    pub fn set_insert_columns(&mut self, insert: bool) {
+        self.insert_columns = insert;
+    }
+
+    /// Rows can be inserted.
+    pub fn insert_rows(&self) -> bool {
+        self.insert_rows
+    }
+
+    /// Rows can be inserted.
+    pub fn set_insert_rows(&mut self, insert: bool) {
+        self.insert_rows = insert;
+    }
+
+    /// Columns can be deleted.
+    pub fn delete_columns(&self) -> bool {
+        self.delete_columns
+    }
+
+    /// Columns can be deleted.
+    pub fn set_delete_columns(&mut self, delete: bool) {
+        self.delete_columns = delete;
+    }
+
+    /// Rows can be deleted.
+    pub fn delete_rows(&self) -> bool {
+        self.delete_rows
+    }
+
+    /// Rows can be deleted.
+    pub fn set_delete_rows(&mut self, delete: bool) {
+        self.delete_rows = delete;
+    }
+}
@@ -0,0 +1,1129 @@
+//!
+//! Typed row mapping with serde. Needs the feature `serde`.
+//!
+//! Rows are deserialized into structs, either by the names in a header
+//! row or by the position of the columns. Serializing writes a header
+//! row with the field names and one row per value.
+//!
+//! Booleans, numbers, strings, options and unit enum variants map
+//! directly to cell values. Numbers stored as text are accepted too.
+//! Dates, durations and decimals need the helper modules of this
+//! module with `#[serde(with = "...")]`, otherwise they end up as
+//! text cells.
+//!
+//! ```
+//! use chrono::{Duration, NaiveDate};
+//! use serde::{Deserialize, Serialize};
+//! use spreadsheet_ods::serde::{from_sheet, to_sheet};
+//! use spreadsheet_ods::{OdsError, Sheet};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Task {
+//!     name: String,
+//!     #[serde(with = "spreadsheet_ods::serde::date")]
+//!     due: NaiveDate,
+//!     #[serde(with = "spreadsheet_ods::serde::duration::option")]
+//!     spent: Option<Duration>,
+//!     done: bool,
+//! }
+//!
+//! let tasks = vec![Task {
+//!     name: "Review".to_string(),
+//!     due: NaiveDate::from_ymd_opt(2022, 5, 1).expect("date"),
+//!     spent: Some(Duration::minutes(90)),
+//!     done: false,
+//! }];
+//!
+//! let mut sheet = Sheet::new("tasks");
+//! to_sheet(&mut sheet, &tasks)?;
+//! assert_eq!(sheet.value(0, 1).as_str_or(""), "due");
+//!
+//! let read: Vec<Task> = from_sheet(&sheet)?;
+//! assert_eq!(read, tasks);
+//! # Ok::<(), OdsError>(())
+//! ```
+//!
+
+use crate::io::parse::parse_duration;
+use crate::io::write::duration_string;
+use crate::{CellRange, CellRef, OdsError, Sheet, Value};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+#[cfg(feature = "use_decimal")]
+use rust_decimal::Decimal;
+use serde::de::{DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::ser::{
+    Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple, SerializeTupleStruct,
+};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::fmt::Formatter;
+#[cfg(feature = "use_decimal")]
+use std::str::FromStr;
+
+// Names of the newtype structs used by the helper modules. They tell
+// the cell (de)serializer which kind of value is needed. Any other
+// (de)serializer only sees a string.
+const DATE: &str = "$spreadsheet_ods::date";
+const DATETIME: &str = "$spreadsheet_ods::datetime";
+const DURATION: &str = "$spreadsheet_ods::duration";
+#[cfg(feature = "use_decimal")]
+const DECIMAL: &str = "$spreadsheet_ods::decimal";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// How the columns are mapped to the fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Header {
+    /// The first row contains the field names.
+    Names,
+    /// There is no header row. The fields are mapped in column order.
+    Positions,
+}
+
+/// Deserializes all rows of the sheet. The first row contains the
+/// field names. Empty rows are skipped.
+pub fn from_sheet<'de, T: Deserialize<'de>>(sheet: &'de Sheet) -> Result<Vec<T>, OdsError> {
+    let (rows, cols) = sheet.used_grid_size();
+    from_range(
+        sheet,
+        &CellRange::local(0, 0, rows - 1, cols - 1),
+        Header::Names,
+    )
+}
+
+/// Deserializes the rows of the range. Empty rows are skipped.
+///
+/// With Header::Names the first row of the range contains the field
+/// names, columns without a name are ignored. With Header::Positions
+/// the fields are filled in column order.
+pub fn from_range<'de, T: Deserialize<'de>>(
+    sheet: &'de Sheet,
+    range: &CellRange,
+    header: Header,
+) -> Result<Vec<T>, OdsError> {
+    let (names, first_row) = match header {
+        Header::Names => {
+            let names: Vec<_> = (range.col()..=range.to_col())
+                .filter_map(|col| {
+                    let name = sheet.value(range.row(), col).as_cow_str_or("");
+                    let name = name.trim();
+                    if name.is_empty() {
+                        None
+                    } else {
+                        Some((col, name.to_string()))
+                    }
+                })
+                .collect();
+            (Some(names), range.row() + 1)
+        }
+        Header::Positions => (None, range.row()),
+    };
+
+    let mut result = Vec::new();
+    for row in first_row..=range.to_row() {
+        let empty =
+            (range.col()..=range.to_col()).all(|col| matches!(sheet.value(row, col), Value::Empty));
+        if empty {
+            continue;
+        }
+
+        result.push(T::deserialize(RowDeserializer {
+            sheet,
+            row,
+            col: range.col(),
+            to_col: range.to_col(),
+            names: names.as_deref(),
+        })?);
+    }
+
+    Ok(result)
+}
+
+/// Writes a header row with the field names starting at cell A1 and
+/// one row for each value below. Values that are sequences or tuples
+/// are written without a header row.
+///
+/// Existing cells are overwritten, empty values and None are skipped.
+pub fn to_sheet<T: Serialize>(sheet: &mut Sheet, rows: &[T]) -> Result<(), OdsError> {
+    let rows = rows
+        .iter()
+        .map(|v| v.serialize(RowSerializer))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut names: Vec<String> = Vec::new();
+    let has_header = rows.iter().flatten().any(|(name, _)| name.is_some());
+    let first_row = if has_header { 1 } else { 0 };
+
+    for (row, cells) in rows.into_iter().enumerate() {
+        for (idx, (name, value)) in cells.into_iter().enumerate() {
+            let col = match name {
+                Some(name) => match names.iter().position(|v| *v == name) {
+                    Some(col) => col,
+                    None => {
+                        names.push(name);
+                        names.len() - 1
+                    }
+                },
+                None => idx,
+            };
+            if !matches!(value, Value::Empty) {
+                sheet.set_value(first_row + row as u32, col as u32, value);
+            }
+        }
+    }
+
+    for (col, name) in names.into_iter().enumerate() {
+        sheet.set_value(0, col as u32, name);
+    }
+
+    Ok(())
+}
+
+// Types that are written with a special newtype name.
+trait CellType: Sized {
+    const NAME: &'static str;
+
+    fn to_text(&self) -> String;
+
+    fn from_text(s: &str) -> Result<Self, String>;
+}
+
+impl CellType for NaiveDate {
+    const NAME: &'static str = DATE;
+
+    fn to_text(&self) -> String {
+        self.format(DATE_FORMAT).to_string()
+    }
+
+    fn from_text(s: &str) -> Result<Self, String> {
+        NaiveDate::parse_from_str(s, DATE_FORMAT)
+            .or_else(|_| NaiveDateTime::from_text(s).map(|v| v.date()))
+            .map_err(|_| format!("invalid date {}", s))
+    }
+}
+
+impl CellType for NaiveDateTime {
+    const NAME: &'static str = DATETIME;
+
+    fn to_text(&self) -> String {
+        self.format(DATETIME_FORMAT).to_string()
+    }
+
+    fn from_text(s: &str) -> Result<Self, String> {
+        NaiveDateTime::parse_from_str(s, DATETIME_FORMAT)
+            .or_else(|_| {
+                NaiveDate::parse_from_str(s, DATE_FORMAT)
+                    .map(|v| v.and_hms_opt(0, 0, 0).expect("midnight"))
+            })
+            .map_err(|_| format!("invalid date {}", s))
+    }
+}
+
+impl CellType for Duration {
+    const NAME: &'static str = DURATION;
+
+    fn to_text(&self) -> String {
+        duration_string(self)
+    }
+
+    fn from_text(s: &str) -> Result<Self, String> {
+        parse_duration(s.as_bytes()).map_err(|_| format!("invalid duration {}", s))
+    }
+}
+
+#[cfg(feature = "use_decimal")]
+impl CellType for Decimal {
+    const NAME: &'static str = DECIMAL;
+
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_text(s: &str) -> Result<Self, String> {
+        Decimal::from_str(s).map_err(|_| format!("invalid decimal {}", s))
+    }
+}
+
+fn serialize_cell<T: CellType, S: Serializer>(v: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(T::NAME, &v.to_text())
+}
+
+fn deserialize_cell<'de, T: CellType, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    let s = deserializer.deserialize_newtype_struct(T::NAME, TextVisitor)?;
+    T::from_text(&s).map_err(serde::de::Error::custom)
+}
+
+fn serialize_cell_option<T: CellType, S: Serializer>(
+    v: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    struct Some<'a, T>(&'a T);
+    impl<T: CellType> Serialize for Some<'_, T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_cell(self.0, serializer)
+        }
+    }
+
+    match v {
+        Option::Some(v) => serializer.serialize_some(&Some(v)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_cell_option<'de, T: CellType, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    struct OptionVisitor<T>(std::marker::PhantomData<T>);
+    impl<'de, T: CellType> Visitor<'de> for OptionVisitor<T> {
+        type Value = Option<T>;
+
+        fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "an optional {}", T::NAME)
+        }
+
+        fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+            deserialize_cell(d).map(Some)
+        }
+    }
+
+    deserializer.deserialize_option(OptionVisitor(std::marker::PhantomData))
+}
+
+macro_rules! cell_type_module {
+    ($name:ident, $ty:ty, $doc:literal) => {
+        #[doc = $doc]
+        pub mod $name {
+            use serde::{Deserializer, Serializer};
+
+            #[allow(missing_docs)]
+            pub fn serialize<S: Serializer>(v: &$ty, serializer: S) -> Result<S::Ok, S::Error> {
+                super::serialize_cell(v, serializer)
+            }
+
+            #[allow(missing_docs)]
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<$ty, D::Error> {
+                super::deserialize_cell(deserializer)
+            }
+
+            /// Same for an Option.
+            pub mod option {
+                use serde::{Deserializer, Serializer};
+
+                #[allow(missing_docs)]
+                pub fn serialize<S: Serializer>(
+                    v: &Option<$ty>,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    super::super::serialize_cell_option(v, serializer)
+                }
+
+                #[allow(missing_docs)]
+                pub fn deserialize<'de, D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Option<$ty>, D::Error> {
+                    super::super::deserialize_cell_option(deserializer)
+                }
+            }
+        }
+    };
+}
+
+cell_type_module!(
+    date,
+    chrono::NaiveDate,
+    "NaiveDate as date cell. Other serializers see an ISO date string."
+);
+cell_type_module!(
+    datetime,
+    chrono::NaiveDateTime,
+    "NaiveDateTime as date cell. Other serializers see an ISO datetime string."
+);
+cell_type_module!(
+    duration,
+    chrono::Duration,
+    "Duration as time duration cell. Other serializers see an ISO duration string."
+);
+#[cfg(feature = "use_decimal")]
+cell_type_module!(
+    decimal,
+    rust_decimal::Decimal,
+    "Decimal as number cell, values with more digits than a f64 fail. Other serializers see a string."
+);
+
+// Accepts the text of a cell, or whatever another deserializer gives
+// for the helper modules.
+struct TextVisitor;
+
+impl<'de> Visitor<'de> for TextVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a string")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_string(self)
+    }
+}
+
+// Adds the cell reference to an error.
+fn cell_error(row: u32, col: u32, e: OdsError) -> OdsError {
+    let msg = match e {
+        OdsError::Ods(msg) => msg,
+        e => e.to_string(),
+    };
+    OdsError::Ods(format!("{}: {}", CellRef::local(row, col), msg))
+}
+
+// One row as struct, map or sequence.
+struct RowDeserializer<'a, 'de> {
+    sheet: &'de Sheet,
+    row: u32,
+    col: u32,
+    to_col: u32,
+    names: Option<&'a [(u32, String)]>,
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_, 'de> {
+    type Error = OdsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.names {
+            Some(names) => visitor.visit_map(RowMapAccess {
+                sheet: self.sheet,
+                row: self.row,
+                names: names.iter(),
+                col: None,
+            }),
+            None => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(RowSeqAccess {
+            sheet: self.sheet,
+            row: self.row,
+            col: self.col,
+            to_col: self.to_col,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+struct RowMapAccess<'a, 'de> {
+    sheet: &'de Sheet,
+    row: u32,
+    names: std::slice::Iter<'a, (u32, String)>,
+    col: Option<u32>,
+}
+
+impl<'de> MapAccess<'de> for RowMapAccess<'_, 'de> {
+    type Error = OdsError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.names.next() {
+            Some((col, name)) => {
+                self.col = Some(*col);
+                seed.deserialize(name.as_str().into_deserializer())
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let col = self.col.take().expect("next_key_seed");
+        seed.deserialize(CellDeserializer {
+            value: self.sheet.value(self.row, col),
+        })
+        .map_err(|e| cell_error(self.row, col, e))
+    }
+}
+
+struct RowSeqAccess<'de> {
+    sheet: &'de Sheet,
+    row: u32,
+    col: u32,
+    to_col: u32,
+}
+
+impl<'de> SeqAccess<'de> for RowSeqAccess<'de> {
+    type Error = OdsError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.col > self.to_col {
+            return Ok(None);
+        }
+        let col = self.col;
+        self.col += 1;
+        seed.deserialize(CellDeserializer {
+            value: self.sheet.value(self.row, col),
+        })
+        .map(Some)
+        .map_err(|e| cell_error(self.row, col, e))
+    }
+}
+
+// One cell.
+struct CellDeserializer<'de> {
+    value: &'de Value,
+}
+
+impl CellDeserializer<'_> {
+    fn number(&self) -> Option<f64> {
+        match self.value {
+            Value::Number(v) | Value::Percentage(v) | Value::Currency(v, _) => Some(*v),
+            Value::Text(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self.value {
+            Value::Empty => Unexpected::Unit,
+            Value::Boolean(v) => Unexpected::Bool(*v),
+            Value::Number(v) | Value::Percentage(v) | Value::Currency(v, _) => {
+                Unexpected::Float(*v)
+            }
+            Value::Text(v) => Unexpected::Str(v),
+            Value::TextXml(_) => Unexpected::Other("formatted text"),
+            Value::DateTime(_) => Unexpected::Other("date"),
+            Value::TimeDuration(_) => Unexpected::Other("time duration"),
+        }
+    }
+
+    fn deserialize_integer<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, OdsError>
+    where
+        Self: Deserializer<'de, Error = OdsError>,
+    {
+        match self.number() {
+            Some(v) if v.fract() == 0.0 && v >= i64::MIN as f64 && v <= i64::MAX as f64 => {
+                visitor.visit_i64(v as i64)
+            }
+            Some(v) => visitor.visit_f64(v),
+            None => self.deserialize_any(visitor),
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.deserialize_integer(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for CellDeserializer<'de> {
+    type Error = OdsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Empty => visitor.visit_unit(),
+            Value::Boolean(v) => visitor.visit_bool(*v),
+            Value::Number(v) | Value::Percentage(v) | Value::Currency(v, _) => {
+                visitor.visit_f64(*v)
+            }
+            Value::Text(v) => visitor.visit_borrowed_str(v),
+            _ => self.deserialize_string(visitor),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.number() {
+            Some(v) => visitor.visit_f64(v),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Empty => visitor.visit_borrowed_str(""),
+            Value::Boolean(v) => visitor.visit_string(v.to_string()),
+            Value::Number(v) | Value::Percentage(v) | Value::Currency(v, _) => {
+                visitor.visit_string(v.to_string())
+            }
+            Value::Text(v) => visitor.visit_borrowed_str(v),
+            Value::TextXml(_) => visitor.visit_string(self.value.as_cow_str_or("").into_owned()),
+            Value::DateTime(v) => visitor.visit_string(v.to_text()),
+            Value::TimeDuration(v) => visitor.visit_string(v.to_text()),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Empty => visitor.visit_none(),
+            Value::Text(v) if v.is_empty() => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match name {
+            DATE | DATETIME => match self.value {
+                Value::DateTime(_) | Value::Text(_) => self.deserialize_string(visitor),
+                _ => Err(serde::de::Error::invalid_type(self.unexpected(), &"a date")),
+            },
+            DURATION => match self.value {
+                Value::TimeDuration(_) | Value::Text(_) => self.deserialize_string(visitor),
+                _ => Err(serde::de::Error::invalid_type(
+                    self.unexpected(),
+                    &"a time duration",
+                )),
+            },
+            #[cfg(feature = "use_decimal")]
+            DECIMAL => match self.value {
+                Value::Number(_)
+                | Value::Percentage(_)
+                | Value::Currency(_, _)
+                | Value::Text(_) => self.deserialize_string(visitor),
+                _ => Err(serde::de::Error::invalid_type(
+                    self.unexpected(),
+                    &"a number",
+                )),
+            },
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Text(v) => visitor.visit_enum(v.as_str().into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+// Number cells hold a f64. A decimal that doesn't come back the same
+// is rejected instead of silently losing digits.
+#[cfg(feature = "use_decimal")]
+fn decimal_number(text: &str) -> Result<Value, String> {
+    let d = Decimal::from_str(text).map_err(|_| format!("invalid decimal {}", text))?;
+    let n = text.parse::<f64>().map_err(|e| e.to_string())?;
+    match Decimal::from_str(&n.to_string()) {
+        Ok(v) if v == d => Ok(Value::Number(n)),
+        _ => Err(format!(
+            "decimal {} can't be stored as a number without loss",
+            text
+        )),
+    }
+}
+
+fn unsupported(what: &str) -> OdsError {
+    OdsError::Ods(format!("Unsupported value for a cell: {}", what))
+}
+
+// One cell.
+struct CellSerializer;
+
+impl Serializer for CellSerializer {
+    type Ok = Value;
+    type Error = OdsError;
+    type SerializeSeq = Impossible<Value, OdsError>;
+    type SerializeTuple = Impossible<Value, OdsError>;
+    type SerializeTupleStruct = Impossible<Value, OdsError>;
+    type SerializeTupleVariant = Impossible<Value, OdsError>;
+    type SerializeMap = Impossible<Value, OdsError>;
+    type SerializeStruct = Impossible<Value, OdsError>;
+    type SerializeStructVariant = Impossible<Value, OdsError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, OdsError> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, OdsError> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, OdsError> {
+        Ok(Value::Number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, OdsError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, OdsError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value, OdsError> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Value, OdsError> {
+        Ok(Value::Empty)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, OdsError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, OdsError> {
+        Ok(Value::Empty)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, OdsError> {
+        Ok(Value::Empty)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, OdsError> {
+        Ok(Value::Text(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, OdsError> {
+        let value = value.serialize(self)?;
+        let text = match (name, &value) {
+            (DATE | DATETIME | DURATION, Value::Text(v)) => v.as_str(),
+            #[cfg(feature = "use_decimal")]
+            (DECIMAL, Value::Text(v)) => v.as_str(),
+            _ => return Ok(value),
+        };
+        match name {
+            DATE | DATETIME => NaiveDateTime::from_text(text).map(Value::DateTime),
+            DURATION => Duration::from_text(text).map(Value::TimeDuration),
+            #[cfg(feature = "use_decimal")]
+            DECIMAL => decimal_number(text),
+            _ => Ok(Value::Text(text.to_string())),
+        }
+        .map_err(OdsError::Ods)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<Value, OdsError> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, OdsError> {
+        Err(unsupported("sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, OdsError> {
+        Err(unsupported("tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, OdsError> {
+        Err(unsupported("tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, OdsError> {
+        Err(unsupported("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, OdsError> {
+        Err(unsupported("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, OdsError> {
+        Err(unsupported("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, OdsError> {
+        Err(unsupported("struct variant"))
+    }
+}
+
+type RowCells = Vec<(Option<String>, Value)>;
+
+fn unsupported_row() -> OdsError {
+    OdsError::Ods("A row must be a struct, a map, a tuple or a sequence".to_string())
+}
+
+// One row. Collects the cells with their field names.
+struct RowSerializer;
+
+macro_rules! unsupported_row {
+    ($($method:ident: $ty:ty)*) => {
+        $(
+            fn $method(self, _v: $ty) -> Result<RowCells, OdsError> {
+                Err(unsupported_row())
+            }
+        )*
+    };
+}
+
+impl Serializer for RowSerializer {
+    type Ok = RowCells;
+    type Error = OdsError;
+    type SerializeSeq = RowCollect;
+    type SerializeTuple = RowCollect;
+    type SerializeTupleStruct = RowCollect;
+    type SerializeTupleVariant = Impossible<RowCells, OdsError>;
+    type SerializeMap = RowCollect;
+    type SerializeStruct = RowCollect;
+    type SerializeStructVariant = Impossible<RowCells, OdsError>;
+
+    unsupported_row! {
+        serialize_bool: bool
+        serialize_i8: i8
+        serialize_i16: i16
+        serialize_i32: i32
+        serialize_i64: i64
+        serialize_u8: u8
+        serialize_u16: u16
+        serialize_u32: u32
+        serialize_u64: u64
+        serialize_f32: f32
+        serialize_f64: f64
+        serialize_char: char
+        serialize_str: &str
+        serialize_bytes: &[u8]
+        serialize_unit_struct: &'static str
+    }
+
+    fn serialize_none(self) -> Result<RowCells, OdsError> {
+        Err(unsupported_row())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<RowCells, OdsError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<RowCells, OdsError> {
+        Err(unsupported_row())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<RowCells, OdsError> {
+        Err(unsupported_row())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<RowCells, OdsError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<RowCells, OdsError> {
+        Err(unsupported_row())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, OdsError> {
+        Ok(RowCollect::default())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, OdsError> {
+        Ok(RowCollect::default())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, OdsError> {
+        Ok(RowCollect::default())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, OdsError> {
+        Err(unsupported_row())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, OdsError> {
+        Ok(RowCollect::default())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, OdsError> {
+        Ok(RowCollect::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, OdsError> {
+        Err(unsupported_row())
+    }
+}
+
+#[derive(Default)]
+struct RowCollect {
+    cells: RowCells,
+    key: Option<String>,
+}
+
+impl RowCollect {
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        name: Option<String>,
+        value: &T,
+    ) -> Result<(), OdsError> {
+        let col = self.cells.len();
+        let value = value.serialize(CellSerializer).map_err(|e| match &name {
+            Some(name) => OdsError::Ods(format!("{}: {}", name, e)),
+            None => OdsError::Ods(format!("column {}: {}", col, e)),
+        })?;
+        self.cells.push((name, value));
+        Ok(())
+    }
+}
+
+impl SerializeSeq for RowCollect {
+    type Ok = RowCells;
+    type Error = OdsError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), OdsError> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<RowCells, OdsError> {
+        Ok(self.cells)
+    }
+}
+
+impl SerializeTuple for RowCollect {
+    type Ok = RowCells;
+    type Error = OdsError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), OdsError> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<RowCells, OdsError> {
+        Ok(self.cells)
+    }
+}
+
+impl SerializeTupleStruct for RowCollect {
+    type Ok = RowCells;
+    type Error = OdsError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), OdsError> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<RowCells, OdsError> {
+        Ok(self.cells)
+    }
+}
+
+impl SerializeMap for RowCollect {
+    type Ok = RowCells;
+    type Error = OdsError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), OdsError> {
+        let key = match key.serialize(CellSerializer)? {
+            Value::Text(v) => v,
+            Value::Number(v) => v.to_string(),
+            Value::Boolean(v) => v.to_string(),
+            _ => return Err(unsupported("map key")),
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), OdsError> {
+        let key = self.key.take();
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<RowCells, OdsError> {
+        Ok(self.cells)
+    }
+}
+
+impl SerializeStruct for RowCollect {
+    type Ok = RowCells;
+    type Error = OdsError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), OdsError> {
+        self.push(Some(key.to_string()), value)
+    }
+
+    fn end(self) -> Result<RowCells, OdsError> {
+        Ok(self.cells)
+    }
+}
@@ -0,0 +1,230 @@
+//!
+//! Sorting of cell ranges, see [Sheet::sort_range](crate::Sheet::sort_range).
+//!
+//! Values are ordered like LibreOffice does. Numbers come before text,
+//! dates, durations and booleans count as numbers. Empty cells are
+//! always sorted to the end, in both directions.
+//!
+//! ```
+//! use spreadsheet_ods::database::SortOrder;
+//! use spreadsheet_ods::sort::SortKey;
+//! use spreadsheet_ods::{CellRange, Sheet};
+//!
+//! let mut sh = Sheet::new("parts");
+//! sh.set_value(0, 0, "bolt");
+//! sh.set_value(0, 1, 12);
+//! sh.set_value(1, 0, "nut");
+//! sh.set_value(1, 1, 40);
+//! sh.set_value(2, 0, "washer");
+//! sh.set_value(2, 1, 12);
+//!
+//! let mut name = SortKey::new(0, SortOrder::Descending);
+//! name.set_natural(true);
+//! sh.sort_range(
+//!     CellRange::local(0, 0, 2, 1),
+//!     &[SortKey::new(1, SortOrder::Ascending), name],
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(sh.value(0, 0).as_str_or(""), "washer");
+//! assert_eq!(sh.value(2, 0).as_str_or(""), "nut");
+//! ```
+//!
+
+use crate::database::SortOrder;
+use crate::format::base_date;
+use crate::Value;
+use std::cmp::Ordering;
+
+/// One key for sorting the rows of a range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    col: u32,
+    order: SortOrder,
+    case_sensitive: bool,
+    natural: bool,
+}
+
+impl SortKey {
+    /// Sorts by the given column of the sheet. Text is compared case
+    /// insensitive.
+    pub fn new(col: u32, order: SortOrder) -> Self {
+        Self {
+            col,
+            order,
+            case_sensitive: false,
+            natural: false,
+        }
+    }
+
+    /// Column of the sheet.
+    pub fn col(&self) -> u32 {
+        self.col
+    }
+
+    /// Column of the sheet.
+    pub fn set_col(&mut self, col: u32) {
+        self.col = col;
+    }
+
+    /// Sort order.
+    pub fn order(&self) -> SortOrder {
+        self.order
+    }
+
+    /// Sort order.
+    pub fn set_order(&mut self, order: SortOrder) {
+        self.order = order;
+    }
+
+    /// Compare text case sensitive. Lowercase comes before uppercase.
+    pub fn case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// Compare text case sensitive. Lowercase comes before uppercase.
+    pub fn set_case_sensitive(&mut self, case_sensitive: bool) {
+        self.case_sensitive = case_sensitive;
+    }
+
+    /// Natural sort order. Numbers within the text are compared by
+    /// their value, "item2" comes before "item10".
+    pub fn natural(&self) -> bool {
+        self.natural
+    }
+
+    /// Natural sort order. Numbers within the text are compared by
+    /// their value, "item2" comes before "item10".
+    pub fn set_natural(&mut self, natural: bool) {
+        self.natural = natural;
+    }
+}
+
+/// Compares two values with the rules of the sort key.
+pub fn compare_values(a: &Value, b: &Value, key: &SortKey) -> Ordering {
+    let (a, b) = match (sort_value(a), sort_value(b)) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (Some(a), Some(b)) => (a, b),
+    };
+
+    let ord = match (a, b) {
+        (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(&b),
+        (SortValue::Number(_), SortValue::Text(_)) => Ordering::Less,
+        (SortValue::Text(_), SortValue::Number(_)) => Ordering::Greater,
+        (SortValue::Text(a), SortValue::Text(b)) => {
+            if key.natural {
+                compare_natural(&a, &b).then_with(|| {
+                    if key.case_sensitive {
+                        compare_text(&a, &b, true)
+                    } else {
+                        Ordering::Equal
+                    }
+                })
+            } else {
+                compare_text(&a, &b, key.case_sensitive)
+            }
+        }
+    };
+
+    match key.order {
+        SortOrder::Ascending => ord,
+        SortOrder::Descending => ord.reverse(),
+    }
+}
+
+enum SortValue {
+    Number(f64),
+    Text(String),
+}
+
+// None for empty cells.
+fn sort_value(v: &Value) -> Option<SortValue> {
+    match v {
+        Value::Empty => None,
+        Value::Boolean(b) => Some(SortValue::Number(if *b { 1.0 } else { 0.0 })),
+        Value::Number(n) | Value::Percentage(n) | Value::Currency(n, _) => {
+            Some(SortValue::Number(*n))
+        }
+        Value::DateTime(d) => Some(SortValue::Number(
+            (*d - base_date()).num_milliseconds() as f64 / 86_400_000.0,
+        )),
+        Value::TimeDuration(d) => Some(SortValue::Number(
+            d.num_milliseconds() as f64 / 86_400_000.0,
+        )),
+        Value::Text(_) | Value::TextXml(_) => {
+            let s = v.as_cow_str_or("");
+            if s.is_empty() {
+                None
+            } else {
+                Some(SortValue::Text(s.to_string()))
+            }
+        }
+    }
+}
+
+// Compares ignoring the case first. If case matters, lowercase
+// breaks the tie.
+fn compare_text(a: &str, b: &str, case_sensitive: bool) -> Ordering {
+    let ord = a.to_lowercase().cmp(&b.to_lowercase());
+    if ord != Ordering::Equal || !case_sensitive {
+        return ord;
+    }
+    for (ca, cb) in a.chars().zip(b.chars()) {
+        if ca != cb {
+            return match (ca.is_lowercase(), cb.is_lowercase()) {
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => ca.cmp(&cb),
+            };
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+// Compares runs of digits by their value and everything else as text,
+// ignoring the case.
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let a = split_digits(a);
+    let b = split_digits(b);
+    for (pa, pb) in a.iter().zip(b.iter()) {
+        let ord = match (
+            pa.as_bytes()[0].is_ascii_digit(),
+            pb.as_bytes()[0].is_ascii_digit(),
+        ) {
+            (true, true) => {
+                let na = pa.trim_start_matches('0');
+                let nb = pb.trim_start_matches('0');
+                na.len()
+                    .cmp(&nb.len())
+                    .then_with(|| na.cmp(nb))
+                    .then_with(|| pa.len().cmp(&pb.len()))
+            }
+            _ => compare_text(pa, pb, false),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+// Splits into runs of ascii digits and the rest.
+fn split_digits(s: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut start = 0;
+    let mut digit = None;
+    for (i, c) in s.char_indices() {
+        let d = c.is_ascii_digit();
+        if digit.is_some() && digit != Some(d) {
+            res.push(&s[start..i]);
+            start = i;
+        }
+        digit = Some(d);
+    }
+    if start < s.len() {
+        res.push(&s[start..]);
+    }
+    res
+}
@@ -2,9 +2,9 @@ use crate::attrmap2::AttrMap2;
 use crate::format::ValueFormatRef;
 use crate::style::stylemap::StyleMap;
 use crate::style::units::{
-    Angle, Border, CellAlignVertical, FontStyle, FontWeight, Length, LineMode, LineStyle, LineType,
-    LineWidth, PageBreak, ParaAlignVertical, RotationAlign, TextAlign, TextAlignSource, TextKeep,
-    TextPosition, TextRelief, TextTransform, WrapOption, WritingMode,
+    Angle, Border, CellAlignVertical, CellProtect, FontStyle, FontWeight, Length, LineMode,
+    LineStyle, LineType, LineWidth, PageBreak, ParaAlignVertical, RotationAlign, TextAlign,
+    TextAlignSource, TextKeep, TextPosition, TextRelief, TextTransform, WrapOption, WritingMode,
 };
 use crate::style::{
     border_line_width_string, border_string, color_string, percent_string, shadow_string,
@@ -221,7 +221,6 @@ impl CellStyle {
     text!(textstyle_mut);
 
     // missing:
-    // style:cell-protect 20.253,
     // style:decimal-places 20.258,
     // style:direction 20.263,
     // style:glyph-orientation-vertical 20.297,
@@ -232,6 +231,12 @@ impl CellStyle {
     style_shadow!(cellstyle_mut);
     style_writing_mode!(cellstyle_mut);
 
+    /// Protection of the cell if the sheet is protected.
+    pub fn set_cell_protect(&mut self, protect: CellProtect) {
+        self.cellstyle
+            .set_attr("style:cell-protect", protect.to_string());
+    }
+
     /// Wrap text.
     pub fn set_wrap_option(&mut self, wrap: WrapOption) {
         self.cellstyle.set_attr("fo:wrap-option", wrap.to_string());
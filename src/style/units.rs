@@ -245,6 +245,33 @@ impl Display for RotationAlign {
     }
 }
 
+/// Cell protection. Only has an effect if the sheet is protected.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CellProtect {
+    /// The cell can be edited.
+    None,
+    /// The cell can not be edited and the content is hidden.
+    HiddenAndProtected,
+    /// The cell can not be edited.
+    Protected,
+    /// The formula is hidden, the cell can be edited.
+    FormulaHidden,
+    /// The cell can not be edited and the formula is hidden.
+    ProtectedFormulaHidden,
+}
+
+impl Display for CellProtect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            CellProtect::None => write!(f, "none"),
+            CellProtect::HiddenAndProtected => write!(f, "hidden-and-protected"),
+            CellProtect::Protected => write!(f, "protected"),
+            CellProtect::FormulaHidden => write!(f, "formula-hidden"),
+            CellProtect::ProtectedFormulaHidden => write!(f, "protected formula-hidden"),
+        }
+    }
+}
+
 /// Vertical alignment.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 #[allow(missing_docs)]
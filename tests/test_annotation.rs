@@ -0,0 +1,52 @@
+use chrono::NaiveDate;
+use spreadsheet_ods::annotation::Annotation;
+use spreadsheet_ods::{read_ods_buf, write_ods_buf, Length, OdsError, Sheet, WorkBook};
+
+#[test]
+fn write_read_annotation() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("1");
+
+    let date = NaiveDate::from_ymd_opt(2022, 3, 1)
+        .unwrap()
+        .and_hms_opt(10, 30, 0)
+        .unwrap();
+
+    sh.set_value(0, 0, "value");
+    let mut note = Annotation::new("first line");
+    note.push_text_str("second <line>");
+    note.set_author("someone");
+    note.set_date(date);
+    note.set_display(true);
+    note.set_size(Length::Cm(4.0), Length::Cm(2.5));
+    note.set_position(Length::Cm(1.0), Length::Cm(1.5));
+    sh.set_annotation(0, 0, note);
+
+    // annotation without a value
+    sh.set_annotation(2, 1, Annotation::new("lonely"));
+
+    wb.push_sheet(sh);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    let sh = wb.sheet(0);
+
+    assert_eq!(sh.value(0, 0).as_str_or(""), "value");
+    let note = sh.annotation(0, 0).expect("annotation");
+    assert_eq!(note.text_str(), "first line\nsecond <line>");
+    assert_eq!(note.author().map(|v| v.as_str()), Some("someone"));
+    assert_eq!(note.date(), Some(date));
+    assert!(note.display());
+    assert_eq!(note.size(), Some((Length::Cm(4.0), Length::Cm(2.5))));
+    assert_eq!(note.position(), Some((Length::Cm(1.0), Length::Cm(1.5))));
+
+    assert!(sh.value(2, 1).as_str_opt().is_none());
+    let note = sh.annotation(2, 1).expect("annotation");
+    assert_eq!(note.text_str(), "lonely");
+    assert!(!note.display());
+    assert!(note.author().is_none());
+
+    assert!(sh.annotation(1, 1).is_none());
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use spreadsheet_ods::style::units::{FontWeight, WrapOption};
+use spreadsheet_ods::style::CellStyle;
+use spreadsheet_ods::{
+    cm, pt, read_ods_buf, write_ods_buf, ColRange, Length, OdsError, RowRange, Sheet, WorkBook,
+};
+
+fn cm(l: Length) -> f64 {
+    match l {
+        Length::Cm(v) => v,
+        v => panic!("unexpected length {:?}", v),
+    }
+}
+
+#[test]
+fn autofit_sheet() {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "short");
+    sh.set_value(1, 0, "a much longer text");
+    sh.set_value(0, 1, 1);
+    sh.set_value(0, 2, "spans over two columns");
+    sh.set_col_span(0, 2, 2);
+    sh.set_value(0, 5, "first\nsecond\nthird");
+    sh.set_col_width(4, cm!(5));
+    wb.push_sheet(sh);
+
+    wb.autofit_cols(0, ColRange::new(0, 5));
+    let sh = wb.sheet(0);
+    assert!(cm(sh.col_width(0)) > cm(sh.col_width(1)));
+    assert!(cm(sh.col_width(0)) > 2.0);
+    assert!(cm(sh.col_width(1)) < 1.0);
+    // only spanned content or no content at all.
+    assert_eq!(sh.col_width(2), Length::Default);
+    assert_eq!(sh.col_width(3), Length::Default);
+    assert_eq!(sh.col_width(4), cm!(5));
+
+    wb.autofit_rows(0, RowRange::new(0, 2));
+    let sh = wb.sheet(0);
+    let single = cm(sh.row_height(1));
+    assert!((single - 0.452).abs() < 0.01);
+    assert!((cm(sh.row_height(0)) - 3.0 * single).abs() < 0.01);
+    assert_eq!(sh.row_height(2), Length::Default);
+}
+
+#[test]
+fn autofit_styles() {
+    let mut wb = WorkBook::new_empty();
+
+    let mut big = CellStyle::empty();
+    big.set_font_size(pt!(20));
+    big.set_font_weight(FontWeight::Bold);
+    let big = wb.add_cellstyle(big);
+
+    let mut mono = CellStyle::empty();
+    mono.set_font_name("Liberation Mono");
+    let mono = wb.add_cellstyle(mono);
+
+    let mut wrap = CellStyle::empty();
+    wrap.set_wrap_option(WrapOption::Wrap);
+    let wrap = wb.add_cellstyle(wrap);
+
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "iiiii");
+    sh.set_styled_value(0, 1, "iiiii", &big);
+    sh.set_styled_value(0, 2, "iiiii", &mono);
+    sh.set_styled_value(1, 3, "some words that wrap around", &wrap);
+    sh.set_value(0, 3, "words");
+    wb.push_sheet(sh);
+
+    wb.autofit_cols(0, ColRange::new(0, 3));
+    let sh = wb.sheet(0);
+    assert!(cm(sh.col_width(1)) > 2.0 * cm(sh.col_width(0)) - 0.2);
+    assert!(cm(sh.col_width(2)) > cm(sh.col_width(0)));
+    // the longest word.
+    let words = cm(sh.col_width(3));
+    assert!(words < 1.5);
+
+    wb.autofit_rows(0, RowRange::new(0, 1));
+    let sh = wb.sheet(0);
+    assert!(cm(sh.row_height(0)) > 0.8);
+    // 5 lines with the narrow column.
+    assert!(cm(sh.row_height(1)) > 4.0 * 0.452);
+}
+
+#[test]
+fn autofit_write() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "text");
+    sh.set_value(0, 1, "text");
+    sh.set_value(0, 2, "text");
+    sh.set_col_width(2, cm!(3));
+    wb.push_sheet(sh);
+
+    wb.autofit_cols(0, ColRange::new(0, 1));
+    wb.autofit_rows(0, RowRange::new(0, 0));
+    let width = wb.sheet(0).col_width(0);
+    wb.sheet_mut(0).set_col_width(1, width);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+
+    let style = wb.sheet(0).colstyle(0).expect("style").clone();
+    let style = wb.colstyle(&style).expect("style");
+    assert!(style.use_optimal_col_width()?);
+    assert_eq!(style.col_width()?, width);
+    let style = wb.sheet(0).colstyle(1).expect("style").clone();
+    assert!(!wb
+        .colstyle(&style)
+        .expect("style")
+        .use_optimal_col_width()?);
+    let style = wb.sheet(0).rowstyle(0).expect("style").clone();
+    assert!(wb
+        .rowstyle(&style)
+        .expect("style")
+        .use_optimal_row_height()?);
+
+    // the estimated width survives read and write.
+    let mut wb = read_ods_buf(&buf)?;
+    assert_eq!(wb.sheet(0).col_width(0), width);
+    assert_eq!(wb.sheet(0).col_width(1), width);
+    assert_eq!(wb.sheet(0).col_width(2), cm!(3));
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let style = wb.sheet(0).colstyle(0).expect("style").clone();
+    let style = wb.colstyle(&style).expect("style");
+    assert!(style.use_optimal_col_width()?);
+    assert_eq!(style.col_width()?, width);
+
+    let wb = read_ods_buf(&buf)?;
+    assert_eq!(wb.sheet(0).col_width(0), width);
+    assert_eq!(wb.sheet(0).col_width(1), width);
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use color::Rgb;
+use spreadsheet_ods::conditional::{
+    CfCondition, CfRule, CfTest, CfValue, CfValueType, ColorScale, ConditionalFormat, DataBar,
+    IconSet, IconSetType,
+};
+use spreadsheet_ods::style::CellStyle;
+use spreadsheet_ods::{
+    read_ods_buf, write_ods_buf, CellRange, CellRef, OdsError, Sheet, ValueFormatRef, WorkBook,
+};
+
+#[test]
+fn write_read_conditional() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let alert = wb.add_cellstyle(CellStyle::new("alert", &ValueFormatRef::from("")));
+
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, 1);
+
+    let mut cf = ConditionalFormat::new(CellRange::remote("one", 0, 0, 9, 0));
+    cf.add_range(CellRange::remote("one", 0, 2, 9, 2));
+    cf.push_condition(CfCondition::new(CfTest::greater(100), &alert));
+    cf.push_condition(CfCondition::new(CfTest::between(1, 10), &alert));
+    cf.push_condition(CfCondition::new(CfTest::contains_text("a,\"b\""), &alert));
+    cf.push_condition(CfCondition::new(CfTest::TopElements(3), &alert));
+    cf.push_condition(CfCondition::new(CfTest::Duplicate, &alert));
+    let mut cond = CfCondition::new(CfTest::formula("ISEVEN([.A1])"), &alert);
+    cond.set_base_cell(CellRef::remote("one", 0, 0));
+    cf.push_condition(cond);
+    sh.add_conditional_format(cf);
+
+    let mut cf = ConditionalFormat::new(CellRange::remote("one", 0, 1, 9, 1));
+    cf.push_color_scale(ColorScale::new_3(
+        Rgb::new(255, 0, 0),
+        Rgb::new(255, 255, 0),
+        Rgb::new(0, 255, 0),
+    ));
+    let mut bar = DataBar::new(Rgb::new(0x63, 0x8e, 0xc6));
+    bar.set_negative_color(Rgb::new(255, 0, 0));
+    bar.set_gradient(false);
+    bar.set_max(CfValue::number(50.0));
+    cf.push_data_bar(bar);
+    let mut icons = IconSet::new(IconSetType::TrafficLights3);
+    icons.set_show_value(false);
+    cf.push_icon_set(icons);
+    sh.add_conditional_format(cf);
+
+    wb.push_sheet(sh);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    let sh = wb.sheet(0);
+
+    assert_eq!(sh.conditional_formats().len(), 2);
+
+    let cf = &sh.conditional_formats()[0];
+    assert_eq!(cf.range().len(), 2);
+    assert_eq!(cf.range()[1], CellRange::remote("one", 0, 2, 9, 2));
+    let tests: Vec<_> = cf
+        .rules()
+        .iter()
+        .map(|v| match v {
+            CfRule::Condition(c) => {
+                assert_eq!(c.style(), "alert");
+                c.test().clone()
+            }
+            _ => panic!("condition expected"),
+        })
+        .collect();
+    assert_eq!(
+        tests,
+        vec![
+            CfTest::Greater("100".to_string()),
+            CfTest::Between("1".to_string(), "10".to_string()),
+            CfTest::ContainsText("\"a,\"\"b\"\"\"".to_string()),
+            CfTest::TopElements(3),
+            CfTest::Duplicate,
+            CfTest::Formula("ISEVEN([.A1])".to_string()),
+        ]
+    );
+
+    let cf = &sh.conditional_formats()[1];
+    match &cf.rules()[0] {
+        CfRule::ColorScale(scale) => {
+            assert_eq!(scale.entries().len(), 3);
+            assert_eq!(scale.entries()[1].0.value_type(), CfValueType::Percentile);
+            assert_eq!(scale.entries()[2].1, Rgb::new(0, 255, 0));
+        }
+        _ => panic!("color scale expected"),
+    }
+    match &cf.rules()[1] {
+        CfRule::DataBar(bar) => {
+            assert_eq!(bar.positive_color(), Rgb::new(0x63, 0x8e, 0xc6));
+            assert_eq!(bar.negative_color(), Some(Rgb::new(255, 0, 0)));
+            assert!(!bar.gradient());
+            assert_eq!(bar.min().value_type(), CfValueType::AutoMinimum);
+            assert_eq!(bar.max(), &CfValue::number(50.0));
+        }
+        _ => panic!("data bar expected"),
+    }
+    match &cf.rules()[2] {
+        CfRule::IconSet(icons) => {
+            assert_eq!(icons.icon_set_type(), IconSetType::TrafficLights3);
+            assert!(!icons.show_value());
+            assert_eq!(icons.entries().len(), 3);
+        }
+        _ => panic!("icon set expected"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn conditional_insert_rows() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("one");
+    let mut cf = ConditionalFormat::new(CellRange::remote("one", 2, 0, 5, 0));
+    cf.push_condition(CfCondition::new(CfTest::Error, &"alert".into()));
+    sh.add_conditional_format(cf);
+    wb.push_sheet(sh);
+
+    wb.insert_rows(0, 1, 2);
+
+    let cf = &wb.sheet(0).conditional_formats()[0];
+    assert_eq!(cf.range()[0], CellRange::remote("one", 4, 0, 7, 0));
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use chrono::NaiveDate;
+use icu_locid::locale;
+use spreadsheet_ods::format::ValueFormat;
+use spreadsheet_ods::style::CellStyle;
+use spreadsheet_ods::{CsvOptions, CsvQuote, OdsError, Sheet, Value, ValueType, WorkBook};
+
+#[test]
+fn read_csv() -> Result<(), OdsError> {
+    let csv = "id,name,price,share,date,flag\n\
+               1,Apple,\"1,234.50\",12.5%,2021-12-24,TRUE\n\
+               2,\"Pear, green\",$3.20,-1e2%,12/31/2021 10:15,false\n\
+               3,,€-4,,2021-12-24T10:15:30,x 1\n";
+    let sheet = Sheet::from_csv("csv", csv.as_bytes(), &CsvOptions::new().header(true))?;
+
+    assert_eq!(sheet.value(0, 0).as_str_or(""), "id");
+    assert_eq!(sheet.header_rows().as_ref().unwrap().row(), 0);
+    assert_eq!(sheet.value(1, 0).as_f64_or(0.0), 1.0);
+    assert_eq!(sheet.value(1, 2).as_f64_or(0.0), 1234.5);
+    assert_eq!(sheet.value(1, 3).value_type(), ValueType::Percentage);
+    assert_eq!(sheet.value(1, 3).as_f64_or(0.0), 0.125);
+    assert_eq!(
+        sheet.value(1, 4).as_datetime_opt(),
+        NaiveDate::from_ymd_opt(2021, 12, 24)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+    );
+    assert!(sheet.value(1, 5).as_bool_or(false));
+
+    assert_eq!(sheet.value(2, 1).as_str_or(""), "Pear, green");
+    assert_eq!(sheet.value(2, 2).value_type(), ValueType::Currency);
+    assert_eq!(sheet.value(2, 2).currency(), "USD");
+    assert_eq!(sheet.value(2, 2).as_f64_or(0.0), 3.2);
+    assert_eq!(sheet.value(2, 3).as_f64_or(0.0), -1.0);
+    // no locale, only ISO dates.
+    assert_eq!(sheet.value(2, 4).value_type(), ValueType::Text);
+    assert!(!sheet.value(2, 5).as_bool_or(true));
+
+    assert_eq!(sheet.value(3, 1).value_type(), ValueType::Empty);
+    assert_eq!(sheet.value(3, 2).currency(), "EUR");
+    assert_eq!(sheet.value(3, 2).as_f64_or(0.0), -4.0);
+    assert_eq!(
+        sheet.value(3, 4).as_datetime_opt(),
+        NaiveDate::from_ymd_opt(2021, 12, 24)
+            .unwrap()
+            .and_hms_opt(10, 15, 30)
+    );
+    assert_eq!(sheet.value(3, 5).as_str_or(""), "x 1");
+
+    // no detection
+    let sheet = Sheet::from_csv("csv", csv.as_bytes(), &CsvOptions::new().detect(false))?;
+    assert_eq!(sheet.value(1, 0).as_str_or(""), "1");
+    assert!(sheet.header_rows().is_none());
+
+    // leading zeros stay text, empty lines are empty rows.
+    let csv = "00123,0,0.5,-007\n\n\"a\nb\"\n\nx\n";
+    let sheet = Sheet::from_csv("csv", csv.as_bytes(), &CsvOptions::new())?;
+    assert_eq!(sheet.value(0, 0).as_str_or(""), "00123");
+    assert_eq!(sheet.value(0, 1).as_f64_or(1.0), 0.0);
+    assert_eq!(sheet.value(0, 2).as_f64_or(0.0), 0.5);
+    assert_eq!(sheet.value(0, 3).as_str_or(""), "-007");
+    assert!(sheet.is_empty(1, 0));
+    assert_eq!(sheet.value(2, 0).as_str_or(""), "a\nb");
+    assert!(sheet.is_empty(3, 0));
+    assert_eq!(sheet.value(4, 0).as_str_or(""), "x");
+
+    let mut buf = Vec::new();
+    sheet.to_csv(&mut buf, &CsvOptions::new())?;
+    assert_eq!(String::from_utf8_lossy(&buf), csv);
+
+    Ok(())
+}
+
+#[test]
+fn read_csv_locale() -> Result<(), OdsError> {
+    let csv = "1.234,5;1,5 €;24.12.2021;3,5%;1.23;12/31/2021\n";
+    let options = CsvOptions::new().delimiter(b';').locale(locale!("de-AT"));
+    let sheet = Sheet::from_csv("csv", csv.as_bytes(), &options)?;
+    assert_eq!(sheet.value(0, 0).as_f64_or(0.0), 1234.5);
+    assert_eq!(sheet.value(0, 1).currency(), "EUR");
+    assert_eq!(sheet.value(0, 1).as_f64_or(0.0), 1.5);
+    assert_eq!(
+        sheet.value(0, 2).as_datetime_opt(),
+        NaiveDate::from_ymd_opt(2021, 12, 24)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+    );
+    assert_eq!(sheet.value(0, 3).as_f64_or(0.0), 0.035);
+    // invalid grouping
+    assert_eq!(sheet.value(0, 4).value_type(), ValueType::Text);
+    assert_eq!(sheet.value(0, 5).value_type(), ValueType::Text);
+
+    let csv = "12/31/2021\t1,234\n";
+    let options = CsvOptions::new().delimiter(b'\t').locale(locale!("en-US"));
+    let sheet = Sheet::from_csv("tsv", csv.as_bytes(), &options)?;
+    assert_eq!(
+        sheet.value(0, 0).as_datetime_opt(),
+        NaiveDate::from_ymd_opt(2021, 12, 31)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+    );
+    assert_eq!(sheet.value(0, 1).as_f64_or(0.0), 1234.0);
+
+    Ok(())
+}
+
+#[test]
+fn read_csv_encoding() -> Result<(), OdsError> {
+    let csv = b"Gr\xfc\xdfe;\"a \"\"quoted\"\" text\"\n";
+    let options = CsvOptions::new()
+        .delimiter(b';')
+        .encoding(encoding_rs::WINDOWS_1252);
+    let sheet = Sheet::from_csv("csv", &csv[..], &options)?;
+    assert_eq!(sheet.value(0, 0).as_str_or(""), "Grüße");
+    assert_eq!(sheet.value(0, 1).as_str_or(""), "a \"quoted\" text");
+
+    let mut buf = Vec::new();
+    sheet.to_csv(&mut buf, &options)?;
+    assert_eq!(buf.as_slice(), &csv[..]);
+
+    let options = CsvOptions::new().quote_style(CsvQuote::Never);
+    let sheet = Sheet::from_csv("csv", "\"a\",b".as_bytes(), &options)?;
+    assert_eq!(sheet.value(0, 0).as_str_or(""), "\"a\"");
+
+    Ok(())
+}
+
+#[test]
+fn write_csv() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut v1 = ValueFormat::new_named("f1", ValueType::Number);
+    v1.part_number().fixed_decimal_places(2).push();
+    let v1 = wb.add_format(v1);
+    let f1 = wb.add_cellstyle(CellStyle::new("f1", &v1));
+
+    let mut sheet = Sheet::new("csv");
+    sheet.set_value(0, 0, "a,b");
+    sheet.set_styled_value(0, 1, 1.5, &f1);
+    sheet.set_value(0, 2, true);
+    sheet.set_value(1, 3, Value::new_percentage(0.25));
+    sheet.set_value(
+        3,
+        0,
+        NaiveDate::from_ymd_opt(2021, 12, 24)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap(),
+    );
+    sheet.set_value(4, 1, "x");
+    sheet.set_row_repeat(4, 2);
+    wb.push_sheet(sheet);
+
+    let mut buf = Vec::new();
+    wb.sheet(0).to_csv(&mut buf, &CsvOptions::new())?;
+    assert_eq!(
+        String::from_utf8_lossy(&buf),
+        "\"a,b\",1.5,TRUE\n,,,0.25\n\n2021-12-24\n,x\n,x\n"
+    );
+
+    let mut buf = Vec::new();
+    wb.to_csv(
+        0,
+        &mut buf,
+        &CsvOptions::new()
+            .formatted(true)
+            .delimiter(b';')
+            .quote_style(CsvQuote::Always),
+    )?;
+    assert_eq!(
+        String::from_utf8_lossy(&buf),
+        "\"a,b\";\"1.50\";\"TRUE\"\n\"\";\"\";\"\";\"25%\"\n\n\"2021-12-24\"\n\"\";\"x\"\n\"\";\"x\"\n"
+    );
+
+    // round trip
+    let sheet = Sheet::from_csv("csv", buf.as_slice(), &CsvOptions::new().delimiter(b';'))?;
+    assert_eq!(sheet.value(0, 1).as_f64_or(0.0), 1.5);
+    assert_eq!(sheet.value(5, 1).as_str_or(""), "x");
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use spreadsheet_ods::database::{
+    DatabaseRange, Filter, FilterCondition, FilterDataType, FilterOperator, SortBy, SortDataType,
+    SortOrder,
+};
+use spreadsheet_ods::{
+    read_fods_buf, read_ods_buf, write_fods_buf, write_ods_buf, CellRange, OdsError, Sheet,
+    WorkBook,
+};
+
+#[test]
+fn autofilter() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "name");
+    sh.set_value(0, 1, "count");
+    sh.set_autofilter(CellRange::local(0, 0, 10, 1));
+    wb.push_sheet(sh);
+    let mut sh = Sheet::new("two");
+    sh.set_autofilter(CellRange::local(2, 2, 5, 4));
+    let mut cond = FilterCondition::one_of(1, vec!["a".to_string(), "b".to_string()]);
+    cond.set_case_sensitive(true);
+    sh.autofilter_mut()
+        .expect("autofilter")
+        .set_filter(Some(Filter::Condition(cond)));
+    wb.push_sheet(sh);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let mut wb = read_ods_buf(&buf)?;
+
+    let af = wb.sheet(0).autofilter().expect("autofilter");
+    assert_eq!(af.range(), &CellRange::remote("one", 0, 0, 10, 1));
+    assert!(af.display_filter_buttons());
+    assert!(af.contains_header());
+    assert!(af.filter().is_none());
+
+    let af = wb.sheet(1).autofilter().expect("autofilter");
+    assert_eq!(af.range(), &CellRange::remote("two", 2, 2, 5, 4));
+    match af.filter() {
+        Some(Filter::Condition(cond)) => {
+            assert_eq!(cond.field(), 1);
+            assert!(cond.case_sensitive());
+            assert_eq!(cond.set_items(), &["a".to_string(), "b".to_string()]);
+        }
+        v => panic!("unexpected filter {:?}", v),
+    }
+    assert!(wb.database_ranges().is_empty());
+
+    wb.sheet_mut(0).clear_autofilter();
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    assert!(wb.sheet(0).autofilter().is_none());
+    assert!(wb.sheet(1).autofilter().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn database_range() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    wb.push_sheet(Sheet::new("data"));
+
+    let mut amount = FilterCondition::new(3, FilterOperator::GreaterEqual, "100");
+    amount.set_data_type(FilterDataType::Number);
+    let filter = Filter::Or(vec![
+        Filter::Condition(amount),
+        Filter::And(vec![
+            Filter::Condition(FilterCondition::new(0, FilterOperator::BeginsWith, "A&B")),
+            Filter::Condition(FilterCondition::new(1, FilterOperator::NotEmpty, "")),
+        ]),
+    ]);
+
+    let mut dr = DatabaseRange::new("Orders", CellRange::remote("data", 0, 0, 20, 3));
+    dr.set_contains_header(false);
+    dr.set_filter(Some(filter.clone()));
+    dr.push_sort(SortBy::new(3, SortOrder::Descending));
+    let mut by_name = SortBy::new(0, SortOrder::Ascending);
+    by_name.set_data_type(SortDataType::Text);
+    dr.push_sort(by_name);
+    wb.add_database_range(dr);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+
+    let dr = wb.database_range("Orders").expect("database range");
+    assert_eq!(dr.range(), &CellRange::remote("data", 0, 0, 20, 3));
+    assert!(!dr.contains_header());
+    assert!(!dr.display_filter_buttons());
+    assert_eq!(dr.filter(), Some(&filter));
+    assert_eq!(dr.sort().len(), 2);
+    assert_eq!(dr.sort()[0].order(), SortOrder::Descending);
+    assert_eq!(dr.sort()[0].data_type(), &SortDataType::Automatic);
+    assert_eq!(dr.sort()[1].field(), 0);
+    assert_eq!(dr.sort()[1].data_type(), &SortDataType::Text);
+    assert!(wb.sheet(0).autofilter().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn database_range_shift() {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("data");
+    sh.set_autofilter(CellRange::local(2, 0, 10, 3));
+    wb.push_sheet(sh);
+    wb.add_database_range(DatabaseRange::new(
+        "Range",
+        CellRange::remote("data", 2, 0, 10, 3),
+    ));
+
+    wb.insert_rows(0, 0, 2);
+    assert_eq!(
+        wb.sheet(0).autofilter().expect("autofilter").range(),
+        &CellRange::local(4, 0, 12, 3)
+    );
+    assert_eq!(
+        wb.database_range("Range").expect("range").range(),
+        &CellRange::remote("data", 4, 0, 12, 3)
+    );
+
+    wb.remove_database_range("Range");
+    assert!(wb.database_ranges().is_empty());
+}
+
+#[test]
+fn database_range_lossless() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    wb.push_sheet(Sheet::new("data"));
+    wb.add_database_range(DatabaseRange::new(
+        "Orders",
+        CellRange::remote("data", 0, 0, 20, 3),
+    ));
+    wb.add_database_range(DatabaseRange::new(
+        "Other",
+        CellRange::remote("data", 0, 5, 20, 6),
+    ));
+    let buf = String::from_utf8(write_fods_buf(&mut wb, Vec::new())?).expect("utf8");
+
+    // Attributes and content as LibreOffice writes them.
+    let ranges = r#"<table:database-ranges>
+<table:database-range table:name="Orders" table:target-range-address="data.A1:data.D21" table:is-selection="true" table:on-update-keep-styles="true" table:has-persistent-data="false" table:refresh-delay="PT1M" table:orientation="column">
+<table:filter table:condition-source="self" table:target-range-address="data.F1:data.I1" table:display-duplicates="false">
+<table:filter-condition table:field-number="1" table:value="x" table:operator="="/>
+</table:filter>
+<table:sort table:bind-styles-to-content="false" table:case-sensitive="true" table:algorithm="alphanumeric" table:language="de" table:country="AT">
+<table:sort-by table:field-number="0" table:data-type="UserList1" table:order="descending"/>
+</table:sort>
+</table:database-range>
+<table:database-range table:name="Other" table:target-range-address="data.F1:data.G21">
+<table:filter>
+<table:filter-condition table:field-number="1" table:value="x" table:operator="unknown"/>
+</table:filter>
+<table:sort>
+<table:sort-by table:field-number="0" table:order="sideways"/>
+</table:sort>
+</table:database-range>
+</table:database-ranges>"#;
+    let start = buf.find("<table:database-ranges>").expect("ranges");
+    let end = buf.find("</table:database-ranges>").expect("ranges") + 24;
+    let buf = format!("{}{}{}", &buf[..start], ranges, &buf[end..]);
+
+    let mut wb = read_fods_buf(buf.as_bytes())?;
+    let dr = wb.database_range("Orders").expect("database range");
+    assert_eq!(
+        dr.attrmap().attr("table:is-selection"),
+        Some(&"true".into())
+    );
+    assert_eq!(
+        dr.attrmap().attr("table:refresh-delay"),
+        Some(&"PT1M".into())
+    );
+    assert_eq!(
+        dr.filter_attrmap().attr("table:condition-source"),
+        Some(&"self".into())
+    );
+    assert_eq!(dr.sort_attrmap().attr("table:language"), Some(&"de".into()));
+    assert_eq!(
+        dr.sort()[0].data_type(),
+        &SortDataType::UserList("UserList1".into())
+    );
+    let dr = wb.database_range("Other").expect("database range");
+    assert!(dr.filter().is_none());
+    assert!(dr.sort().is_empty());
+
+    let buf = String::from_utf8(write_fods_buf(&mut wb, Vec::new())?).expect("utf8");
+    for v in [
+        r#"table:is-selection="true""#,
+        r#"table:on-update-keep-styles="true""#,
+        r#"table:has-persistent-data="false""#,
+        r#"table:refresh-delay="PT1M""#,
+        r#"table:orientation="column""#,
+        r#"table:condition-source="self""#,
+        r#"table:target-range-address="data.F1:data.I1""#,
+        r#"table:display-duplicates="false""#,
+        r#"table:bind-styles-to-content="false""#,
+        r#"table:case-sensitive="true""#,
+        r#"table:algorithm="alphanumeric""#,
+        r#"table:language="de""#,
+        r#"table:country="AT""#,
+        r#"table:data-type="UserList1""#,
+        r#"table:operator="unknown""#,
+        r#"table:order="sideways""#,
+    ] {
+        assert!(buf.contains(v), "missing {}", v);
+    }
+    // the filter is written before the sort.
+    let other = &buf[buf.find(r#"table:name="Other""#).expect("other")..];
+    assert!(
+        other.find("<table:filter").expect("filter") < other.find("<table:sort").expect("sort")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::io::{Cursor, Read};
+
+use spreadsheet_ods::style::GraphicStyle;
+use spreadsheet_ods::{read_ods_buf, write_ods_buf, Length, OdsError, Sheet, WorkBook};
+
+#[test]
+fn write_read_image() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let gs = wb.add_graphicstyle(GraphicStyle::new("gr1"));
+
+    let png = b"\x89PNG not really".to_vec();
+    let mut logo = wb.add_image("image/png", png.clone());
+    logo.set_name("Logo");
+    logo.set_style(&gs);
+    logo.set_position(Length::Mm(1.0), Length::Mm(2.0));
+    logo.set_size(Length::Cm(4.0), Length::Cm(2.0));
+
+    let mut page = wb.add_image("image/jpeg", vec![1, 2, 3]);
+    page.set_position(Length::Cm(10.0), Length::Cm(5.0));
+    page.set_size(Length::Cm(1.0), Length::Cm(1.0));
+    page.set_z_index(2);
+    assert_ne!(logo.href(), page.href());
+
+    let mut sh = Sheet::new("1");
+    sh.set_value(1, 1, "value");
+    sh.add_image(1, 1, logo);
+    sh.add_image(3, 0, page.clone());
+    sh.add_page_image(page);
+    wb.push_sheet(sh);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+
+    let mut zip = zip::ZipArchive::new(Cursor::new(&buf))?;
+    let mut manifest = String::new();
+    zip.by_name("META-INF/manifest.xml")?
+        .read_to_string(&mut manifest)?;
+    assert!(manifest
+        .contains(r#"manifest:full-path="Pictures/image1.png" manifest:media-type="image/png""#));
+    assert!(manifest.contains(r#"manifest:full-path="content.xml""#));
+
+    let mut wb = read_ods_buf(&buf)?;
+    let sh = wb.sheet(0);
+
+    assert_eq!(sh.value(1, 1).as_str_or(""), "value");
+    let images = sh.images(1, 1).expect("images");
+    assert_eq!(images.len(), 1);
+    let logo = &images[0];
+    assert_eq!(logo.name(), "Logo");
+    assert_eq!(logo.style().map(|v| v.as_str()), Some("gr1"));
+    assert_eq!(logo.position(), Some((Length::Mm(1.0), Length::Mm(2.0))));
+    assert_eq!(logo.size(), Some((Length::Cm(4.0), Length::Cm(2.0))));
+    assert_eq!(wb.image_data(logo.href()), Some(png.as_slice()));
+
+    assert!(sh.value(3, 0).as_str_opt().is_none());
+    assert_eq!(sh.images(3, 0).map(|v| v.len()), Some(1));
+
+    assert_eq!(sh.page_images().len(), 1);
+    let page = &sh.page_images()[0];
+    assert_eq!(page.z_index(), Some(2));
+    assert_eq!(wb.image_data(page.href()), Some(&[1u8, 2, 3][..]));
+
+    // a second round keeps the files and the manifest.
+    let mut more = wb.add_image("image/png", vec![4]);
+    more.set_name("more");
+    assert_eq!(more.href(), "Pictures/image2.png");
+    wb.sheet_mut(0).add_image(0, 0, more);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let mut zip = zip::ZipArchive::new(Cursor::new(&buf))?;
+    let mut manifest = String::new();
+    zip.by_name("META-INF/manifest.xml")?
+        .read_to_string(&mut manifest)?;
+    assert_eq!(manifest.matches("Pictures/image1.png").count(), 1);
+    assert!(manifest.contains("Pictures/image2.png"));
+    assert_eq!(
+        manifest
+            .matches(r#"manifest:full-path="content.xml""#)
+            .count(),
+        1
+    );
+
+    let wb = read_ods_buf(&buf)?;
+    let sh = wb.sheet(0);
+    assert_eq!(sh.images(0, 0).map(|v| v[0].name()), Some("more"));
+    assert_eq!(sh.images(1, 1).map(|v| v.len()), Some(1));
+    assert_eq!(wb.image_data("Pictures/image2.png"), Some(&[4u8][..]));
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use spreadsheet_ods::{
+    read_ods, read_ods_buf, read_ods_buf_with_password, read_ods_with_password, write_ods,
+    write_ods_buf_with_password, write_ods_with_password, OdsError, OdsStreamReader, Sheet,
+    WorkBook,
+};
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn book() -> WorkBook {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("secret");
+    sh.set_value(0, 0, "confidential");
+    sh.set_value(1, 0, 4711);
+    wb.push_sheet(sh);
+    wb.metadata_mut().set_title("Encrypted");
+    wb
+}
+
+#[test]
+fn write_read_encrypted() -> Result<(), OdsError> {
+    let mut wb = book();
+    let buf = write_ods_buf_with_password(&mut wb, Vec::new(), "password")?;
+
+    let mut zip = ZipArchive::new(Cursor::new(buf.as_slice()))?;
+    {
+        let ze = zip.by_name("mimetype")?;
+        assert_eq!(ze.compression(), CompressionMethod::Stored);
+    }
+    {
+        let ze = zip.by_name("content.xml")?;
+        assert_eq!(ze.compression(), CompressionMethod::Stored);
+    }
+    let mut manifest = String::new();
+    zip.by_name("META-INF/manifest.xml")?
+        .read_to_string(&mut manifest)?;
+    assert!(manifest.contains("manifest:encryption-data"));
+    assert!(manifest.contains("aes256-cbc"));
+
+    let wb = read_ods_buf_with_password(&buf, "password")?;
+    assert_eq!(wb.sheet(0).name(), "secret");
+    assert_eq!(wb.sheet(0).value(0, 0).as_str_or(""), "confidential");
+    assert_eq!(wb.sheet(0).value(1, 0).as_i32_or(0), 4711);
+    assert_eq!(wb.metadata().title(), "Encrypted");
+
+    assert!(read_ods_buf_with_password(&buf, "wrong").is_err());
+    assert!(read_ods_buf(&buf).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn write_read_encrypted_file() -> Result<(), OdsError> {
+    std::fs::create_dir_all("test_out")?;
+
+    let mut wb = book();
+    write_ods_with_password(&mut wb, "test_out/encrypted.ods", "password")?;
+    let mut wb = read_ods_with_password("test_out/encrypted.ods", "password")?;
+    assert_eq!(wb.sheet(0).value(1, 0).as_i32_or(0), 4711);
+
+    // written again without password.
+    write_ods(&mut wb, "test_out/decrypted.ods")?;
+    let wb = read_ods("test_out/decrypted.ods")?;
+    assert_eq!(wb.sheet(0).value(1, 0).as_i32_or(0), 4711);
+
+    // a plain file works with a password too.
+    let wb = read_ods_with_password("test_out/decrypted.ods", "password")?;
+    assert_eq!(wb.sheet(0).value(1, 0).as_i32_or(0), 4711);
+
+    Ok(())
+}
+
+#[test]
+fn stream_encrypted() -> Result<(), OdsError> {
+    let mut wb = book();
+    let buf = write_ods_buf_with_password(&mut wb, Vec::new(), "password")?;
+
+    assert!(OdsStreamReader::new(Cursor::new(buf.as_slice())).is_err());
+    assert!(OdsStreamReader::new_with_password(Cursor::new(buf.as_slice()), "wrong").is_err());
+
+    let mut ods = OdsStreamReader::new_with_password(Cursor::new(buf.as_slice()), "password")?;
+    assert_eq!(ods.book().metadata().title(), "Encrypted");
+    let mut rows = ods.rows()?;
+    let sheet = rows.next_sheet()?.expect("sheet");
+    assert_eq!(sheet.name(), "secret");
+    let row = rows.next_row()?.expect("row");
+    let cells: Vec<_> = row.into_iter().collect();
+    assert_eq!(cells[0].1.value().as_str_or(""), "confidential");
+
+    Ok(())
+}
+
+// The same content as text.ods. The files follow the layout LibreOffice
+// writes: each stream is deflated and encrypted on its own, with its own
+// salt and initialisation vector.
+fn check_text(wb: &WorkBook) -> Result<(), OdsError> {
+    let plain = read_ods("tests/text.ods")?;
+    assert_eq!(wb.num_sheets(), plain.num_sheets());
+    for idx in 0..plain.num_sheets() {
+        let sh = wb.sheet(idx);
+        let plain = plain.sheet(idx);
+        assert_eq!(sh.name(), plain.name());
+        assert_eq!(sh.iter().count(), plain.iter().count());
+        for ((row, col), cell) in plain.iter() {
+            assert_eq!(
+                sh.value(row, col).as_cow_str_or(""),
+                cell.value().as_cow_str_or("")
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn read_aes256() -> Result<(), OdsError> {
+    let wb = read_ods_with_password("tests/encrypted_aes.ods", "secret")?;
+    check_text(&wb)?;
+    assert!(read_ods_with_password("tests/encrypted_aes.ods", "Secret").is_err());
+    assert!(read_ods("tests/encrypted_aes.ods").is_err());
+    Ok(())
+}
+
+#[test]
+fn read_blowfish() -> Result<(), OdsError> {
+    let wb = read_ods_with_password("tests/encrypted_blowfish.ods", "secret")?;
+    check_text(&wb)?;
+    assert!(read_ods_with_password("tests/encrypted_blowfish.ods", "Secret").is_err());
+    Ok(())
+}
+
+// Copy of the file with a changed manifest.
+fn patch_manifest(path: &str, from: &str, to: &str) -> Result<Vec<u8>, OdsError> {
+    let buf = std::fs::read(path)?;
+    let mut zip = ZipArchive::new(Cursor::new(buf.as_slice()))?;
+
+    let mut out = ZipWriter::new(Cursor::new(Vec::new()));
+    for idx in 0..zip.len() {
+        let mut ze = zip.by_index(idx)?;
+        let mut data = Vec::new();
+        ze.read_to_end(&mut data)?;
+        if ze.name() == "META-INF/manifest.xml" {
+            data = String::from_utf8_lossy(&data)
+                .replace(from, to)
+                .into_bytes();
+        }
+        out.start_file(
+            ze.name(),
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        out.write_all(&data)?;
+    }
+    Ok(out.finish()?.into_inner())
+}
+
+#[test]
+fn invalid_key_size() -> Result<(), OdsError> {
+    let buf = patch_manifest(
+        "tests/encrypted_aes.ods",
+        "manifest:key-derivation-name=\"PBKDF2\" manifest:key-size=\"32\"",
+        "manifest:key-derivation-name=\"PBKDF2\" manifest:key-size=\"4000000000\"",
+    )?;
+    match read_ods_buf_with_password(&buf, "secret") {
+        Err(e) => assert!(e.to_string().contains("Invalid key size")),
+        Ok(_) => panic!("key size not checked"),
+    }
+    Ok(())
+}
+
+#[test]
+fn invalid_iteration_count() -> Result<(), OdsError> {
+    let buf = patch_manifest(
+        "tests/encrypted_aes.ods",
+        "manifest:iteration-count=\"100000\"",
+        "manifest:iteration-count=\"4000000000\"",
+    )?;
+    match read_ods_buf_with_password(&buf, "secret") {
+        Err(e) => assert!(e.to_string().contains("Invalid iteration count")),
+        Ok(_) => panic!("iteration count not checked"),
+    }
+    Ok(())
+}
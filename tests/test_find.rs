@@ -0,0 +1,166 @@
+use spreadsheet_ods::annotation::Annotation;
+use spreadsheet_ods::find::{FindMode, FindQuery, SearchIn};
+use spreadsheet_ods::text::{TextP, TextSpan};
+use spreadsheet_ods::{OdsError, Sheet, Value, WorkBook};
+
+fn book() -> WorkBook {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "PX-100 bolt");
+    sh.set_value(1, 0, "px-100");
+    sh.set_value(2, 0, 100);
+    sh.set_formula(3, 0, "of:=CONCATENATE(\"PX-100\";[.A1])");
+    sh.set_value(3, 0, "PX-100PX-100 bolt");
+    sh.set_annotation(4, 0, Annotation::new("see PX-100"));
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("two");
+    sh.set_value(
+        0,
+        1,
+        TextP::new()
+            .text("Part ")
+            .tag(TextSpan::new().text("PX-100"))
+            .text(" in stock")
+            .into_xmltag(),
+    );
+    wb.push_sheet(sh);
+
+    wb
+}
+
+#[test]
+fn find() -> Result<(), OdsError> {
+    let wb = book();
+
+    let query = FindQuery::new("PX-100");
+    assert_eq!(
+        wb.find(&query)?,
+        vec![(0, 0, 0), (0, 1, 0), (0, 3, 0), (1, 0, 1)]
+    );
+    assert_eq!(
+        wb.find(&query.clone().case_sensitive(true))?,
+        vec![(0, 0, 0), (0, 3, 0), (1, 0, 1)]
+    );
+    assert_eq!(
+        wb.find(&query.clone().mode(FindMode::WholeCell))?,
+        vec![(0, 1, 0)]
+    );
+    assert_eq!(
+        wb.find(&query.clone().search_in(SearchIn::Formulas))?,
+        vec![(0, 0, 0), (0, 1, 0), (0, 3, 0), (1, 0, 1)]
+    );
+    assert_eq!(
+        wb.find(&query.clone().search_in(SearchIn::Annotations))?,
+        vec![(0, 4, 0)]
+    );
+
+    // numbers as displayed.
+    assert_eq!(
+        wb.find(&FindQuery::new("100").mode(FindMode::WholeCell))?,
+        vec![(0, 2, 0)]
+    );
+    // formulas as stored.
+    assert_eq!(
+        wb.find(&FindQuery::new("[.A1]").search_in(SearchIn::Formulas))?,
+        vec![(0, 3, 0)]
+    );
+
+    #[cfg(feature = "regex")]
+    {
+        let query = FindQuery::new(r"^px-\d+$").mode(FindMode::Regex);
+        assert_eq!(wb.find(&query)?, vec![(0, 1, 0)]);
+
+        assert!(wb
+            .find(&FindQuery::new("PX-(").mode(FindMode::Regex))
+            .is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn replace_all() -> Result<(), OdsError> {
+    let mut wb = book();
+
+    let query = FindQuery::new("PX-100").case_sensitive(true);
+    assert_eq!(wb.replace_all(&query, "QX-$1")?, 3);
+    assert_eq!(wb.sheet(0).value(0, 0).as_str_or(""), "QX-$1 bolt");
+    assert_eq!(wb.sheet(0).value(1, 0).as_str_or(""), "px-100");
+    // the formula is not touched when searching values.
+    assert_eq!(
+        wb.sheet(0).formula(3, 0).expect("formula"),
+        "of:=CONCATENATE(\"PX-100\";[.A1])"
+    );
+
+    // structure of the rich text is kept.
+    match wb.sheet(1).value(0, 1) {
+        Value::TextXml(tags) => {
+            assert_eq!(
+                tags[0].to_string(),
+                "<text:p>\nPart \n<text:span>\nQX-$1\n</text:span>\n in stock\n</text:p>\n"
+            );
+        }
+        v => panic!("unexpected value {:?}", v),
+    }
+
+    #[cfg(feature = "regex")]
+    {
+        let query = FindQuery::new(r"(\d{3})").mode(FindMode::Regex);
+        assert_eq!(
+            wb.replace_all(&query.search_in(SearchIn::Formulas), "<$1>")?,
+            2
+        );
+        assert_eq!(
+            wb.sheet(0).formula(3, 0).expect("formula"),
+            "of:=CONCATENATE(\"PX-<100>\";[.A1])"
+        );
+        assert_eq!(wb.sheet(0).value(1, 0).as_str_or(""), "px-<100>");
+        // numbers are not replaced.
+        assert_eq!(wb.sheet(0).value(2, 0).as_i32_or(0), 100);
+    }
+
+    let query = FindQuery::new("px-100").search_in(SearchIn::Annotations);
+    assert_eq!(wb.replace_all(&query, "PX-200")?, 1);
+    assert_eq!(
+        wb.sheet(0).annotation(4, 0).expect("annotation").text_str(),
+        "see PX-200"
+    );
+
+    let query = FindQuery::new("Part QX-$1 in stock").mode(FindMode::WholeCell);
+    assert_eq!(wb.replace_all(&query, "sold out")?, 1);
+    assert_eq!(wb.sheet(1).value(0, 1).as_cow_str_or(""), "sold out");
+
+    // the emptied paragraphs are dropped.
+    let mut sh = Sheet::new("three");
+    sh.set_value(
+        0,
+        0,
+        Value::TextXml(vec![
+            TextP::new().text("first").into_xmltag(),
+            TextP::new()
+                .tag(TextSpan::new().text("second"))
+                .into_xmltag(),
+        ]),
+    );
+    wb.push_sheet(sh);
+    let query = FindQuery::new("First\nSecond").mode(FindMode::WholeCell);
+    assert_eq!(wb.replace_all(&query, "both")?, 1);
+    match wb.sheet(2).value(0, 0) {
+        Value::TextXml(tags) => {
+            assert_eq!(tags.len(), 1);
+            assert_eq!(tags[0].to_string(), "<text:p>\nboth\n</text:p>\n");
+        }
+        v => panic!("unexpected value {:?}", v),
+    }
+
+    // literal replacement ignores the case and doesn't expand groups.
+    let mut sh = Sheet::new("four");
+    sh.set_value(0, 0, "Äb äB");
+    wb.push_sheet(sh);
+    assert_eq!(wb.replace_all(&FindQuery::new("äb"), "$0")?, 1);
+    assert_eq!(wb.sheet(3).value(0, 0).as_str_or(""), "$0 $0");
+
+    Ok(())
+}
@@ -1,9 +1,14 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use icu_locid::locale;
 
+use spreadsheet_ods::condition::ValueCondition;
 use spreadsheet_ods::format::{FormatCalendarStyle, FormatNumberStyle};
+use spreadsheet_ods::style::stylemap::StyleMap;
 use spreadsheet_ods::style::CellStyle;
-use spreadsheet_ods::{write_ods, OdsError, Sheet, ValueFormat, ValueType, WorkBook};
+use spreadsheet_ods::{
+    currency, percent, read_ods_buf, write_ods, write_ods_buf, CellRef, OdsError, Sheet, Value,
+    ValueFormat, ValueType, WorkBook,
+};
 
 #[test]
 fn write_format() -> Result<(), OdsError> {
@@ -111,3 +116,241 @@ fn write_format() -> Result<(), OdsError> {
         write_ods(&mut wb, path)
     }
 }
+
+#[test]
+fn test_format_value() {
+    let mut wb = WorkBook::new_empty();
+
+    let mut v = ValueFormat::new_named("n", ValueType::Number);
+    v.part_number()
+        .decimal_places(3)
+        .min_decimal_places(1)
+        .grouping()
+        .push();
+    assert_eq!(v.format_value(&Value::Number(1234567.5)), "1,234,567.5");
+    assert_eq!(v.format_value(&Value::Number(-0.12345)), "-0.123");
+    assert_eq!(v.format_value(&Value::Number(-0.0001)), "0.0");
+    assert_eq!(v.format_value(&Value::Text("abc".into())), "abc");
+
+    let mut v = ValueFormat::new_localized("n", locale!("de_AT"), ValueType::Number);
+    v.part_number()
+        .fixed_decimal_places(2)
+        .grouping()
+        .display_factor(1000.0)
+        .push();
+    v.part_text(" k");
+    assert_eq!(v.format_value(&Value::Number(1234567.0)), "1.234,57 k");
+
+    let mut v = ValueFormat::new_named("s", ValueType::Number);
+    v.part_scientific().decimal_places(2).push();
+    assert_eq!(v.format_value(&Value::Number(12345.0)), "1.23E+04");
+    assert_eq!(v.format_value(&Value::Number(0.00012)), "1.20E-04");
+
+    let mut v = ValueFormat::new_named("f", ValueType::Number);
+    v.part_fraction().max_denominator(9).push();
+    v.parts_mut()[0].set_attr("number:min-integer-digits", "0".to_string());
+    assert_eq!(v.format_value(&Value::Number(2.75)), "2 3/4");
+    assert_eq!(v.format_value(&Value::Number(0.5)), "1/2");
+
+    let mut v = ValueFormat::new_named("c", ValueType::Currency);
+    v.part_number().fixed_decimal_places(2).grouping().push();
+    v.part_text(" ");
+    v.part_currency().symbol("€").push();
+    assert_eq!(v.format_value(&currency!("EUR", 1234.5)), "1,234.50 €");
+
+    let mut v = ValueFormat::new_named("p", ValueType::Percentage);
+    v.part_number().fixed_decimal_places(1).push();
+    v.part_text("%");
+    assert_eq!(v.format_value(&percent!(0.125)), "12.5%");
+
+    let mut v = ValueFormat::new_named("d", ValueType::DateTime);
+    v.part_day_of_week().short_style().push();
+    v.part_text(", ");
+    v.part_day().push();
+    v.part_text(". ");
+    v.part_month().textual().long_style().push();
+    v.part_text(" ");
+    v.part_year().long_style().push();
+    v.part_text(" ");
+    v.part_hours().push();
+    v.part_text(":");
+    v.part_minutes().long_style().push();
+    v.part_text(" ");
+    v.part_am_pm();
+    let dt = NaiveDate::from_ymd_opt(2023, 3, 7)
+        .unwrap()
+        .and_hms_opt(15, 4, 0)
+        .unwrap();
+    assert_eq!(
+        v.format_value(&Value::DateTime(dt)),
+        "Tue, 7. March 2023 3:04 PM"
+    );
+    // serial date
+    assert_eq!(
+        v.format_value(&Value::Number(45000.5)),
+        "Wed, 15. March 2023 12:00 PM"
+    );
+
+    let mut v = ValueFormat::new_named("t", ValueType::TimeDuration);
+    v.set_truncate_on_overflow(false);
+    v.part_hours().long_style().push();
+    v.part_text(":");
+    v.part_minutes().long_style().push();
+    v.part_text(":");
+    v.part_seconds().long_style().decimal_places(1).push();
+    assert_eq!(
+        v.format_value(&Value::TimeDuration(Duration::milliseconds(
+            (26 * 3600 + 5 * 60 + 7) * 1000 + 250
+        ))),
+        "26:05:07.3"
+    );
+
+    let mut v = ValueFormat::new_named("b", ValueType::Boolean);
+    v.part_boolean();
+    assert_eq!(v.format_value(&Value::Boolean(true)), "TRUE");
+
+    let mut v = ValueFormat::new_named("x", ValueType::Text);
+    v.part_text("<");
+    v.part_text_content();
+    v.part_text(">");
+    assert_eq!(v.format_value(&Value::Text("abc".into())), "<abc>");
+
+    // multi-section format with stylemap.
+    let mut pos = ValueFormat::new_named("m0", ValueType::Number);
+    pos.part_number().fixed_decimal_places(2).push();
+    let pos = wb.add_format(pos);
+    let mut neg = ValueFormat::new_named("m", ValueType::Number);
+    neg.part_text("(");
+    neg.part_number().fixed_decimal_places(2).push();
+    neg.part_text(")");
+    neg.push_stylemap(StyleMap::new(
+        ValueCondition::content_gte(0),
+        pos.to_string(),
+        CellRef::new(),
+    ));
+    let neg = wb.add_format(neg);
+    let style = wb.add_cellstyle(CellStyle::new("m", &neg));
+
+    let mut sh = Sheet::new("1");
+    sh.set_styled_value(0, 0, 1.5, &style);
+    sh.set_styled_value(1, 0, -1.5, &style);
+    sh.set_value(2, 0, 0.1 + 0.2);
+    wb.push_sheet(sh);
+
+    assert_eq!(wb.formatted_value(0, 0, 0), "1.50");
+    assert_eq!(wb.formatted_value(0, 1, 0), "(1.50)");
+    assert_eq!(wb.formatted_value(0, 2, 0), "0.3");
+    assert_eq!(wb.formatted_value(0, 3, 0), "");
+}
+
+#[test]
+fn test_format_code() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    // round trip
+    for code in [
+        "#,##0.00",
+        "0.000",
+        "General",
+        "0.00E+00",
+        "# ?/?",
+        "0%",
+        "#,##0,",
+        "DD.MM.YYYY HH:MM",
+        "YYYY-MM-DD",
+        "DDDD, MMMM D",
+        "HH:MM:SS AM/PM",
+        "[HH]:MM:SS",
+        "#,##0.00 \"EUR\"",
+        "[$€]#,##0.00",
+        "BOOLEAN",
+        "\"Total\"@",
+    ] {
+        let f = ValueFormat::from_format_code("f", code, locale!("en-US"))?;
+        assert_eq!(f.len(), 1);
+        assert_eq!(f[0].to_format_code(), code);
+    }
+
+    let f = ValueFormat::from_format_code("f", "DD.MM.YYYY", locale!("de"))?;
+    assert_eq!(f[0].value_type(), ValueType::DateTime);
+    assert_eq!(f[0].locale(), Some(locale!("de")));
+    let f = ValueFormat::from_format_code("f", "[HH]:MM", locale!("de"))?;
+    assert_eq!(f[0].value_type(), ValueType::TimeDuration);
+    assert_eq!(
+        f[0].format_value(&Value::TimeDuration(Duration::minutes(1501))),
+        "25:01"
+    );
+    let f = ValueFormat::from_format_code("f", "0.0%", locale!("en"))?;
+    assert_eq!(f[0].value_type(), ValueType::Percentage);
+    assert_eq!(f[0].format_value(&Value::Percentage(0.1234)), "12.3%");
+    let f = ValueFormat::from_format_code("f", "M/D/YY", locale!("en"))?;
+    let d = NaiveDate::from_ymd_opt(2023, 3, 5).unwrap();
+    assert_eq!(f[0].format_value(&Value::from(d)), "3/5/23");
+
+    // sections
+    let f = ValueFormat::from_format_code("m", "#,##0.00 \"EUR\";[RED]-#,##0.00", locale!("en"))?;
+    assert_eq!(f.len(), 2);
+    assert_eq!(f[0].name(), "mP0");
+    assert_eq!(f[1].name(), "m");
+    assert_eq!(f[1].textstyle().attr("fo:color").unwrap(), "#ff0000");
+    let stylemaps = f[1].stylemaps().unwrap();
+    assert_eq!(stylemaps.len(), 1);
+    assert_eq!(stylemaps[0].condition(), "value()>=0");
+    assert_eq!(stylemaps[0].applied_style(), "mP0");
+    for v in f {
+        wb.add_format(v);
+    }
+    let m = wb.format("m").unwrap();
+    assert_eq!(wb.format_value(&Value::Number(1234.5), m), "1,234.50 EUR");
+    assert_eq!(wb.format_value(&Value::Number(-1234.5), m), "-1,234.50");
+    assert_eq!(wb.format_code(m), "#,##0.00 \"EUR\";[RED]-#,##0.00");
+
+    let f = ValueFormat::from_format_code("z", "0.0;(0.0);\"zero\"", locale!("en"))?;
+    assert_eq!(f.len(), 3);
+    for v in f {
+        wb.add_format(v);
+    }
+    let z = wb.format("z").unwrap();
+    assert_eq!(wb.format_value(&Value::Number(1.0), z), "1.0");
+    assert_eq!(wb.format_value(&Value::Number(-1.0), z), "(1.0)");
+    assert_eq!(wb.format_value(&Value::Number(0.0), z), "zero");
+    assert_eq!(wb.format_code(z), "0.0;(0.0);\"zero\"");
+
+    let f = ValueFormat::from_format_code("c", "[>=100]\"big\";[<>0]0.0;0", locale!("en"))?;
+    assert_eq!(f.len(), 3);
+    assert_eq!(f[2].stylemaps().unwrap()[1].condition(), "value()!=0");
+    for v in f {
+        wb.add_format(v);
+    }
+    let c = wb.format("c").unwrap();
+    assert_eq!(wb.format_value(&Value::Number(150.0), c), "big");
+    assert_eq!(wb.format_value(&Value::Number(1.26), c), "1.3");
+    assert_eq!(wb.format_value(&Value::Number(0.0), c), "0");
+    assert_eq!(wb.format_code(c), "[>=100]\"big\";[<>0]0.0;0");
+
+    assert!(ValueFormat::from_format_code("e", "\"open", locale!("en")).is_err());
+    assert!(ValueFormat::from_format_code("e", "0;0;0;@;0", locale!("en")).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_text_format_roundtrip() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut f = ValueFormat::from_format_code("t", "\"<\"@\">\"", locale!("en"))?;
+    let t = wb.add_format(f.remove(0));
+    let t = wb.add_cellstyle(CellStyle::new("t", &t));
+
+    let mut sh = Sheet::new("1");
+    sh.set_styled_value(0, 0, "abc", &t);
+    wb.push_sheet(sh);
+    assert_eq!(wb.formatted_value(0, 0, 0), "<abc>");
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    assert_eq!(wb.sheet(0).value(0, 0).as_str_or(""), "abc");
+    assert_eq!(wb.formatted_value(0, 0, 0), "<abc>");
+
+    Ok(())
+}
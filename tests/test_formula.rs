@@ -1 +1,454 @@
+use chrono::NaiveDate;
+use spreadsheet_ods::formula::{check_formula, eval, parse, BinaryOp, Expr, UnaryOp};
+use spreadsheet_ods::{CellRange, CellRef, OdsError, Sheet, Value, WorkBook};
 
+#[test]
+fn test_parse() -> Result<(), OdsError> {
+    let f = parse("of:=SUM([.A1:.B3])*2")?;
+    assert_eq!(
+        f,
+        Expr::Binary(
+            BinaryOp::Mul,
+            Box::new(Expr::Function(
+                "SUM".to_string(),
+                vec![Expr::CellRange(CellRange::local(0, 0, 2, 1))]
+            )),
+            Box::new(Expr::Number(2.0))
+        )
+    );
+
+    let f = parse("=1+2*3")?;
+    assert_eq!(
+        f,
+        Expr::Binary(
+            BinaryOp::Add,
+            Box::new(Expr::Number(1.0)),
+            Box::new(Expr::Binary(
+                BinaryOp::Mul,
+                Box::new(Expr::Number(2.0)),
+                Box::new(Expr::Number(3.0))
+            ))
+        )
+    );
+
+    let f = parse("of:=-[$'Sheet 2'.$A$1]%")?;
+    assert_eq!(
+        f,
+        Expr::Unary(
+            UnaryOp::Neg,
+            Box::new(Expr::Unary(
+                UnaryOp::Percent,
+                Box::new(Expr::CellRef(CellRef::remote("Sheet 2", 0, 0).absolute()))
+            ))
+        )
+    );
+
+    let f = parse("of:=IF([.A1]>=10;;\"a\"\"b\")")?;
+    assert_eq!(
+        f,
+        Expr::Function(
+            "IF".to_string(),
+            vec![
+                Expr::Binary(
+                    BinaryOp::Ge,
+                    Box::new(Expr::CellRef(CellRef::local(0, 0))),
+                    Box::new(Expr::Number(10.0))
+                ),
+                Expr::Missing,
+                Expr::Text("a\"b".to_string())
+            ]
+        )
+    );
+
+    let f = parse("of:=[.#REF!]+TaxRate")?;
+    assert_eq!(
+        f,
+        Expr::Binary(
+            BinaryOp::Add,
+            Box::new(Expr::InvalidRef(".#REF!".to_string())),
+            Box::new(Expr::Name("TaxRate".to_string()))
+        )
+    );
+    assert_eq!(f.to_formula(), "of:=[.#REF!]+TaxRate");
+
+    // reference operators bind tighter than the prefix operators.
+    let f = parse("of:=-[.A1]:[.B2]")?;
+    assert_eq!(
+        f,
+        Expr::Unary(
+            UnaryOp::Neg,
+            Box::new(Expr::Binary(
+                BinaryOp::Range,
+                Box::new(Expr::CellRef(CellRef::local(0, 0))),
+                Box::new(Expr::CellRef(CellRef::local(1, 1)))
+            ))
+        )
+    );
+    let f = parse("of:=-2^2")?;
+    assert_eq!(
+        f,
+        Expr::Binary(
+            BinaryOp::Pow,
+            Box::new(Expr::Unary(UnaryOp::Neg, Box::new(Expr::Number(2.0)))),
+            Box::new(Expr::Number(2.0))
+        )
+    );
+
+    assert!(parse("of:=SUM([.A1:.B3]").is_err());
+    assert!(parse("of:=1+").is_err());
+    assert!(parse("of:=\"abc").is_err());
+    assert!(parse("of:=[.A1").is_err());
+    assert!(parse("of:=1 2").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_print() -> Result<(), OdsError> {
+    for f in [
+        "of:=SUM([.A1:.B3])*2",
+        "of:=(1+2)*3",
+        "of:=1-(2-3)",
+        "of:=-2^2",
+        "of:=[Sheet1.$A$1:.$B$4]",
+        "of:=['a b'.C7]&\"x\"\"y\"",
+        "of:=IF([.A1]<>0;1;)",
+        "of:={1;2|3;4}",
+        "of:=PI()",
+        "of:=ORG.OPENOFFICE.WEEKS(1;2;3)",
+        "of:=1.5E+20",
+        "of:=#DIV/0!",
+        "of:=50%",
+        "of:=-[.A1]:[.B2]",
+        "of:=SUM([.A1]:[.B2]~[.C3])%",
+        "of:=[Sheet1.#REF!]*2",
+    ] {
+        let e = parse(f)?;
+        assert_eq!(parse(&e.to_formula())?, e, "{}", f);
+    }
+
+    assert_eq!(parse("of:= 1 +  2")?.to_formula(), "of:=1+2");
+    assert_eq!(parse("of:=(1+2)*3")?.to_string(), "(1+2)*3");
+
+    // precedence is restored for constructed trees.
+    let e = Expr::Binary(
+        BinaryOp::Mul,
+        Box::new(Expr::Binary(
+            BinaryOp::Add,
+            Box::new(Expr::Number(1.0)),
+            Box::new(Expr::Number(2.0)),
+        )),
+        Box::new(Expr::Number(3.0)),
+    );
+    assert_eq!(e.to_formula(), "of:=(1+2)*3");
+
+    let e = Expr::Binary(
+        BinaryOp::Range,
+        Box::new(Expr::Unary(
+            UnaryOp::Neg,
+            Box::new(Expr::CellRef(CellRef::local(0, 0))),
+        )),
+        Box::new(Expr::CellRef(CellRef::local(1, 1))),
+    );
+    assert_eq!(e.to_formula(), "of:=(-[.A1]):[.B2]");
+
+    Ok(())
+}
+
+#[test]
+fn test_rewrite() -> Result<(), OdsError> {
+    let mut f = parse("of:=SUM([.A1:.B3])+[.C1]")?;
+    let mut n = 0;
+    f.visit(&mut |e| {
+        if matches!(e, Expr::CellRef(_) | Expr::CellRange(_)) {
+            n += 1;
+        }
+    });
+    assert_eq!(n, 2);
+
+    f.visit_mut(&mut |e| {
+        if let Expr::CellRef(c) = e {
+            c.set_table("Other");
+        }
+    });
+    assert_eq!(f.to_formula(), "of:=SUM([.A1:.B3])+[Other.C1]");
+
+    Ok(())
+}
+
+#[test]
+fn test_recalc() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("One");
+    sh.set_value(0, 0, 1);
+    sh.set_value(1, 0, 2);
+    sh.set_value(2, 0, 3);
+    sh.set_formula(3, 0, "of:=SUM([.A1:.A3])");
+    sh.set_formula(4, 0, "of:=AVERAGE([.A1:.A4])*2");
+    sh.set_formula(5, 0, "of:=IF([.A4]>5;\"big\";\"small\")&\"!\"");
+    sh.set_formula(6, 0, "of:=[Two.A1]+1");
+    sh.set_formula(7, 0, "of:=ROUND(2/3;2)");
+    sh.set_formula(8, 0, "of:=1/0");
+    sh.set_formula(9, 0, "of:=DATE(2022;14;1)");
+    sh.set_formula(10, 0, "of:=TEXT(1234.5;\"#,##0.00\")");
+    sh.set_formula(11, 0, "of:=TEXT([.A10];\"DD.MM.YYYY\")");
+    sh.set_formula(12, 0, "of:=-2^2");
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Two");
+    sh.set_formula(0, 0, "of:=[One.A4]*10");
+    sh.set_value(0, 2, "a");
+    sh.set_value(0, 3, 10);
+    sh.set_value(1, 2, "b");
+    sh.set_value(1, 3, 20);
+    sh.set_value(2, 2, "c");
+    sh.set_value(2, 3, 30);
+    sh.set_formula(3, 0, "of:=VLOOKUP(\"B\";[.C1:.D3];2;0)");
+    sh.set_formula(4, 0, "of:=VLOOKUP(\"bb\";[.C1:.D3];2)");
+    sh.set_formula(5, 0, "of:=VLOOKUP(\"x\";[.C1:.D2];2;0)");
+    wb.push_sheet(sh);
+
+    wb.recalc()?;
+
+    let sh = wb.sheet(0);
+    assert_eq!(sh.value(3, 0).as_f64_or(0.0), 6.0);
+    assert_eq!(sh.value(4, 0).as_f64_or(0.0), 6.0);
+    assert_eq!(sh.value(5, 0).as_str_or(""), "big!");
+    assert_eq!(sh.value(6, 0).as_f64_or(0.0), 61.0);
+    assert_eq!(sh.value(7, 0).as_f64_or(0.0), 0.67);
+    assert_eq!(sh.value(8, 0).as_str_or(""), "#DIV/0!");
+    assert_eq!(
+        sh.value(9, 0).as_date_opt(),
+        NaiveDate::from_ymd_opt(2023, 2, 1)
+    );
+    assert_eq!(sh.value(10, 0).as_str_or(""), "1,234.50");
+    assert_eq!(sh.value(11, 0).as_str_or(""), "01.02.2023");
+    assert_eq!(sh.value(12, 0).as_f64_or(0.0), 4.0);
+
+    let sh = wb.sheet(1);
+    assert_eq!(sh.value(0, 0).as_f64_or(0.0), 60.0);
+    assert_eq!(sh.value(3, 0).as_f64_or(0.0), 20.0);
+    assert_eq!(sh.value(4, 0).as_f64_or(0.0), 20.0);
+    assert_eq!(sh.value(5, 0).as_str_or(""), "#N/A");
+
+    let v = eval(&wb, 0, "of:=[.A4]+[Two.D3]")?;
+    assert!(matches!(v, Value::Number(n) if n == 36.0));
+
+    Ok(())
+}
+
+#[test]
+fn test_recalc_errors() {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("One");
+    sh.set_formula(0, 0, "of:=[.B1]+1");
+    sh.set_formula(0, 1, "of:=[.C1]+1");
+    sh.set_formula(0, 2, "of:=SUM([.A1:.A3])");
+    wb.push_sheet(sh);
+
+    match wb.recalc() {
+        Err(OdsError::Ods(msg)) => assert!(msg.contains("Circular"), "{}", msg),
+        r => panic!("{:?}", r),
+    }
+    assert!(matches!(wb.sheet(0).value(0, 0), Value::Empty));
+
+    // in-cell errors like LibreOffice.
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("One");
+    sh.set_formula(0, 0, "of:=NOSUCHFUNCTION(1)");
+    sh.set_formula(0, 1, "of:=MOD(1)");
+    sh.set_formula(0, 2, "of:=IF(TRUE();1;2;3)");
+    sh.set_formula(0, 3, "of:=ISERROR([.B1])");
+    sh.set_formula(0, 4, "of:=IFERROR([.A1];7)+1");
+    wb.push_sheet(sh);
+    wb.recalc().expect("recalc");
+    assert_eq!(wb.sheet(0).value(0, 0).as_str_or(""), "#NAME?");
+    assert_eq!(wb.sheet(0).value(0, 1).as_str_or(""), "#N/A");
+    assert_eq!(wb.sheet(0).value(0, 2).as_str_or(""), "#N/A");
+    assert!(wb.sheet(0).value(0, 3).as_bool_or(false));
+    assert_eq!(wb.sheet(0).value(0, 4).as_f64_or(0.0), 8.0);
+
+    assert!(check_formula("of:=IF([.A1]>1;SUM([.A1:.A3]);VLOOKUP(1;[.A1:.B3];2))").is_ok());
+    assert!(check_formula("of:=1+SUM(MOD(1))").is_err());
+    assert!(check_formula("of:=nosuchfunction()").is_err());
+}
+
+#[test]
+fn test_recalc_references() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("One");
+    sh.set_value(0, 0, 10);
+    sh.set_value(1, 0, 5);
+    sh.set_value(2, 0, "text");
+    // single references skip empty and text cells like ranges do.
+    sh.set_formula(0, 1, "of:=AVERAGE([.A1];[.A4])");
+    sh.set_formula(1, 1, "of:=SUM([.A1];[.A3])");
+    sh.set_formula(2, 1, "of:=COUNTA([.A4])");
+    sh.set_formula(3, 1, "of:=SUM(\"text\")");
+    // range operator.
+    sh.set_formula(4, 1, "of:=SUM([.A1]:[.A3])");
+    sh.set_formula(5, 1, "of:=SUM([.A2]:([.A1]:[.A1]))");
+    sh.set_formula(6, 1, "of:=SUM([.A1]~[.A2])");
+    wb.push_sheet(sh);
+
+    wb.recalc()?;
+    let sh = wb.sheet(0);
+    assert_eq!(sh.value(0, 1).as_f64_or(0.0), 10.0);
+    assert_eq!(sh.value(1, 1).as_f64_or(0.0), 10.0);
+    assert_eq!(sh.value(2, 1).as_f64_or(1.0), 0.0);
+    assert_eq!(sh.value(3, 1).as_str_or(""), "#VALUE!");
+    assert_eq!(sh.value(4, 1).as_f64_or(0.0), 15.0);
+    assert_eq!(sh.value(5, 1).as_f64_or(0.0), 15.0);
+    assert_eq!(sh.value(6, 1).as_str_or(""), "#VALUE!");
+
+    // the cells between both ends are precedents too.
+    assert_eq!(
+        wb.dependents_of(&CellRef::remote("One", 1, 0))?,
+        vec![
+            CellRef::remote("One", 4, 1),
+            CellRef::remote("One", 5, 1),
+            CellRef::remote("One", 6, 1)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_dependencies() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("One");
+    sh.set_dirty_tracking(true);
+    sh.set_value(0, 0, 1);
+    sh.set_value(1, 0, 2);
+    sh.set_formula(2, 0, "of:=SUM([.A1:.A2])");
+    sh.set_formula(3, 0, "of:=[.A3]*2");
+    sh.set_formula(4, 0, "of:=[.A1]");
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Two");
+    sh.set_dirty_tracking(true);
+    sh.set_formula(0, 0, "of:=[One.A4]+[One.B9]");
+    wb.push_sheet(sh);
+
+    assert_eq!(
+        wb.dependents_of(&CellRef::remote("One", 1, 0))?,
+        vec![
+            CellRef::remote("One", 2, 0),
+            CellRef::remote("One", 3, 0),
+            CellRef::remote("Two", 0, 0),
+        ]
+    );
+    assert_eq!(
+        wb.precedents_of(&CellRef::remote("Two", 0, 0))?,
+        vec![
+            CellRange::remote("One", 3, 0, 3, 0),
+            CellRange::remote("One", 8, 1, 8, 1),
+        ]
+    );
+    assert!(wb.dependents_of(&CellRef::remote("Two", 0, 0))?.is_empty());
+    assert!(wb.circular_references()?.is_empty());
+
+    // dirty tracking
+    wb.recalc()?;
+    assert_eq!(wb.sheet(1).value(0, 0).as_f64_or(0.0), 6.0);
+    assert!(wb.dirty_dependents()?.is_empty());
+
+    wb.sheet_mut(0).set_value(0, 0, 11);
+    assert!(wb.sheet(0).is_dirty(0, 0));
+    assert_eq!(
+        wb.dirty_dependents()?,
+        vec![
+            CellRef::remote("One", 2, 0),
+            CellRef::remote("One", 3, 0),
+            CellRef::remote("One", 4, 0),
+            CellRef::remote("Two", 0, 0),
+        ]
+    );
+    wb.recalc_dirty()?;
+    assert!(!wb.sheet(0).is_dirty(0, 0));
+    assert_eq!(wb.sheet(0).value(4, 0).as_f64_or(0.0), 11.0);
+    assert_eq!(wb.sheet(1).value(0, 0).as_f64_or(0.0), 26.0);
+
+    // the cached graph follows the changes.
+    wb.sheet_mut(0).set_formula(5, 0, "of:=[.A2]");
+    assert_eq!(
+        wb.dependents_of(&CellRef::remote("One", 1, 0))?,
+        vec![
+            CellRef::remote("One", 2, 0),
+            CellRef::remote("One", 3, 0),
+            CellRef::remote("One", 5, 0),
+            CellRef::remote("Two", 0, 0),
+        ]
+    );
+
+    // without tracking everything is recalculated.
+    wb.sheet_mut(0).set_dirty_tracking(false);
+    wb.sheet_mut(0).set_value(0, 0, 1);
+    assert!(!wb.sheet(0).is_dirty(0, 0));
+    wb.recalc_dirty()?;
+    assert_eq!(wb.sheet(0).value(4, 0).as_f64_or(0.0), 1.0);
+    assert_eq!(wb.sheet(1).value(0, 0).as_f64_or(0.0), 6.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_dependencies_ranges() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("One");
+    sh.set_formula(0, 0, "of:=SUM([.O1:.R5])");
+    sh.set_formula(1, 0, "of:=SUM([.A10:.AMJ10])");
+    sh.set_formula(2, 0, "of:=SUM([.Q1:.Q1048576])");
+    wb.push_sheet(sh);
+
+    assert_eq!(
+        wb.dependents_of(&CellRef::remote("One", 4, 14))?,
+        vec![CellRef::remote("One", 0, 0)]
+    );
+    assert_eq!(
+        wb.dependents_of(&CellRef::remote("One", 2, 16))?,
+        vec![CellRef::remote("One", 0, 0), CellRef::remote("One", 2, 0)]
+    );
+    assert_eq!(
+        wb.dependents_of(&CellRef::remote("One", 9, 1000))?,
+        vec![CellRef::remote("One", 1, 0)]
+    );
+    assert!(wb.dependents_of(&CellRef::remote("One", 5, 17))?.is_empty());
+    assert!(wb.dependents_of(&CellRef::remote("One", 0, 18))?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_cycles() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("One");
+    sh.set_formula(0, 0, "of:=[.B1]");
+    sh.set_formula(0, 1, "of:=[Two.A1]");
+    sh.set_formula(0, 2, "of:=[.C1]+1");
+    sh.set_formula(0, 3, "of:=[.A1]");
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Two");
+    sh.set_formula(0, 0, "of:=SUM([One.A1:.A5])");
+    wb.push_sheet(sh);
+
+    let cycles = wb.circular_references()?;
+    assert_eq!(
+        cycles,
+        vec![
+            vec![
+                CellRef::remote("One", 0, 0),
+                CellRef::remote("One", 0, 1),
+                CellRef::remote("Two", 0, 0),
+            ],
+            vec![CellRef::remote("One", 0, 2)],
+        ]
+    );
+
+    Ok(())
+}
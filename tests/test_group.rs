@@ -0,0 +1,142 @@
+use spreadsheet_ods::{
+    read_ods_buf, write_ods_buf, ColRange, OdsError, OdsStreamWriter, RowRange, Sheet, Visibility,
+    WorkBook,
+};
+
+#[test]
+fn write_read_groups() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("one");
+    for row in 0..20 {
+        if row % 3 == 0 {
+            sh.set_value(row, 0, row);
+        }
+    }
+    sh.set_header_rows(0, 1);
+
+    sh.group_rows(RowRange::new(2, 15), false)?;
+    sh.group_rows(RowRange::new(4, 7), true)?;
+    sh.group_rows(RowRange::new(9, 10), false)?;
+    // same range as the header
+    sh.group_rows(RowRange::new(0, 1), false)?;
+    // beyond the data
+    sh.group_rows(RowRange::new(18, 25), false)?;
+    assert!(sh.group_rows(RowRange::new(6, 9), false).is_err());
+
+    sh.group_cols(ColRange::new(1, 3), true)?;
+    sh.group_cols(ColRange::new(1, 2), false)?;
+
+    assert_eq!(sh.row_group_level(5), 2);
+    assert_eq!(sh.row_group_level(8), 1);
+    assert_eq!(sh.row_visible(5), Visibility::Collapsed);
+    assert_eq!(sh.col_visible(3), Visibility::Collapsed);
+    wb.push_sheet(sh);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    let sh = wb.sheet(0);
+
+    let groups: Vec<_> = sh
+        .row_groups()
+        .iter()
+        .map(|v| (v.from(), v.to(), v.display()))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            (0, 1, true),
+            (2, 15, true),
+            (4, 7, false),
+            (9, 10, true),
+            (18, 25, true)
+        ]
+    );
+    assert_eq!(sh.header_rows(), &Some(RowRange::new(0, 1)));
+    assert_eq!(sh.row_visible(6), Visibility::Collapsed);
+    assert_eq!(sh.value(18, 0).as_i32_or(0), 18);
+
+    let groups: Vec<_> = sh
+        .col_groups()
+        .iter()
+        .map(|v| (v.from(), v.to(), v.display()))
+        .collect();
+    assert_eq!(groups, vec![(1, 3, false), (1, 2, true)]);
+    assert_eq!(sh.col_visible(2), Visibility::Collapsed);
+
+    Ok(())
+}
+
+#[test]
+fn groups_header_overlap() -> Result<(), OdsError> {
+    let mut sh = Sheet::new("one");
+    sh.group_rows(RowRange::new(2, 6), false)?;
+    // nested is fine.
+    sh.set_header_rows(3, 4);
+    assert!(sh.group_rows(RowRange::new(4, 8), false).is_err());
+    sh.group_rows(RowRange::new(0, 8), false)?;
+
+    sh.set_header_cols(1, 3);
+    assert!(sh.group_cols(ColRange::new(2, 5), true).is_err());
+    assert!(sh.col_groups().is_empty());
+    sh.group_cols(ColRange::new(1, 5), true)?;
+
+    let mut wb = WorkBook::new_empty();
+    sh.set_value(9, 9, 1);
+    wb.push_sheet(sh);
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let mut wb = read_ods_buf(&buf)?;
+    assert_eq!(wb.sheet(0).header_rows(), &Some(RowRange::new(3, 4)));
+    assert_eq!(wb.sheet(0).row_groups().len(), 2);
+
+    // a partial overlap is found when writing.
+    wb.sheet_mut(0).set_header_rows(4, 9);
+    assert!(write_ods_buf(&mut wb, Vec::new()).is_err());
+    wb.sheet_mut(0).set_header_rows(3, 4);
+    wb.sheet_mut(0).set_header_cols(0, 1);
+    assert!(write_ods_buf(&mut wb, Vec::new()).is_err());
+
+    let mut sh = Sheet::new("two");
+    sh.group_rows(RowRange::new(2, 6), false)?;
+    sh.set_header_rows(0, 2);
+    let mut writer = OdsStreamWriter::new_buf(Vec::new(), WorkBook::new_empty())?;
+    assert!(writer.start_sheet_from(&sh).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn groups_insert_rows() -> Result<(), OdsError> {
+    let mut sh = Sheet::new("one");
+    sh.group_rows(RowRange::new(2, 5), false)?;
+    sh.group_rows(RowRange::new(8, 9), false)?;
+    sh.insert_rows(3, 2);
+    sh.delete_rows(8, 5);
+
+    let groups: Vec<_> = sh.row_groups().iter().map(|v| (v.from(), v.to())).collect();
+    assert_eq!(groups, vec![(2, 7)]);
+    assert!(sh.ungroup_rows(RowRange::new(2, 7)));
+    assert!(sh.row_groups().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn stream_groups() -> Result<(), OdsError> {
+    let mut sh = Sheet::new("one");
+    sh.group_rows(RowRange::new(1, 4), false)?;
+
+    let mut ods = OdsStreamWriter::new_buf(Vec::new(), WorkBook::new_empty())?;
+    ods.start_sheet_from(&sh)?;
+    ods.write_values([1, 2])?;
+    ods.write_values([3, 4])?;
+    let buf = ods.finish()?.into_inner();
+
+    let wb = read_ods_buf(&buf)?;
+    let sh = wb.sheet(0);
+    assert_eq!(sh.row_groups().len(), 1);
+    assert_eq!(sh.row_groups()[0].from(), 1);
+    assert_eq!(sh.row_groups()[0].to(), 4);
+    assert_eq!(sh.value(1, 1).as_i32_or(0), 4);
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use chrono::{Duration, NaiveDate};
+use spreadsheet_ods::metadata::MetaValue;
+use spreadsheet_ods::{
+    read_fods_buf, read_ods_buf, write_fods_buf, write_ods_buf, OdsError, OdsStreamReader, Sheet,
+    WorkBook,
+};
+use std::io::Cursor;
+
+fn metadata_book() -> WorkBook {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, 1);
+    sh.set_value(0, 1, 2);
+    wb.push_sheet(sh);
+    wb.push_sheet(Sheet::new("two"));
+
+    let meta = wb.metadata_mut();
+    meta.set_title("Report <1>");
+    meta.set_subject("Sales");
+    meta.set_description("Sales per region");
+    meta.set_keywords(vec!["sales".to_string()]);
+    meta.push_keyword("2022");
+    meta.set_initial_creator("alice");
+    meta.set_creator("bob");
+    meta.set_date(NaiveDate::from_ymd_opt(2022, 4, 1).and_then(|v| v.and_hms_opt(12, 30, 0)));
+    meta.set_editing_cycles(3);
+    // milliseconds need three digits, 0.005S not 0.5S.
+    meta.set_editing_duration(Duration::milliseconds(3_723_005));
+    meta.set_user_defined("Archive", MetaValue::String("A-17".to_string()));
+    meta.set_user_defined("Checked", MetaValue::Boolean(true));
+    meta.set_user_defined("Amount", MetaValue::Float(12.5));
+    meta.set_user_defined(
+        "Due",
+        MetaValue::Datetime(
+            NaiveDate::from_ymd_opt(2022, 5, 1)
+                .and_then(|v| v.and_hms_opt(0, 0, 0))
+                .expect("date"),
+        ),
+    );
+    meta.set_user_defined(
+        "Spent",
+        MetaValue::TimeDuration(Duration::minutes(90) + Duration::milliseconds(50)),
+    );
+    wb
+}
+
+fn check_metadata(wb: &WorkBook) {
+    let meta = wb.metadata();
+    assert_eq!(meta.title(), "Report <1>");
+    assert_eq!(meta.subject(), "Sales");
+    assert_eq!(meta.description(), "Sales per region");
+    assert_eq!(meta.keywords(), ["sales", "2022"]);
+    assert_eq!(meta.initial_creator(), "alice");
+    assert_eq!(meta.creator(), "bob");
+    assert_eq!(
+        meta.date(),
+        NaiveDate::from_ymd_opt(2022, 4, 1).and_then(|v| v.and_hms_opt(12, 30, 0))
+    );
+    assert!(meta.creation_date().is_some());
+    assert!(meta.generator().starts_with("spreadsheet-ods"));
+    assert_eq!(meta.editing_cycles(), 3);
+    assert_eq!(meta.editing_duration(), Duration::milliseconds(3_723_005));
+    assert_eq!(meta.document_statistics().table_count, 2);
+    assert_eq!(meta.document_statistics().cell_count, 2);
+
+    assert_eq!(meta.user_defined_props().len(), 5);
+    assert_eq!(
+        meta.user_defined("Archive"),
+        Some(&MetaValue::String("A-17".to_string()))
+    );
+    assert_eq!(
+        meta.user_defined("Checked"),
+        Some(&MetaValue::Boolean(true))
+    );
+    assert_eq!(meta.user_defined("Amount"), Some(&MetaValue::Float(12.5)));
+    assert_eq!(
+        meta.user_defined("Spent"),
+        Some(&MetaValue::TimeDuration(
+            Duration::minutes(90) + Duration::milliseconds(50)
+        ))
+    );
+    assert!(matches!(
+        meta.user_defined("Due"),
+        Some(MetaValue::Datetime(_))
+    ));
+}
+
+#[test]
+fn write_read_metadata() -> Result<(), OdsError> {
+    let mut wb = metadata_book();
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let mut wb = read_ods_buf(&buf)?;
+    check_metadata(&wb);
+
+    let mut ods = OdsStreamReader::new(Cursor::new(buf))?;
+    check_metadata(ods.book());
+    assert!(ods.rows()?.next_sheet()?.is_some());
+
+    // changes are written again
+    wb.metadata_mut().set_title("Other");
+    wb.metadata_mut().remove_user_defined("Archive");
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    assert_eq!(wb.metadata().title(), "Other");
+    assert!(wb.metadata().user_defined("Archive").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn write_read_metadata_fods() -> Result<(), OdsError> {
+    let mut wb = metadata_book();
+    let buf = write_fods_buf(&mut wb, Vec::new())?;
+    let wb = read_fods_buf(&buf)?;
+    check_metadata(&wb);
+
+    Ok(())
+}
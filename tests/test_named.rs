@@ -0,0 +1,99 @@
+use spreadsheet_ods::named::{NamedExpression, NamedRange};
+use spreadsheet_ods::{read_ods_buf, write_ods_buf, CellRange, CellRef, OdsError, Sheet, WorkBook};
+
+#[test]
+fn write_read_named() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    wb.push_sheet(Sheet::new("one"));
+    wb.push_sheet(Sheet::new("two"));
+
+    let mut data = NamedRange::new("Data", CellRange::remote("one", 0, 0, 9, 2).absolute());
+    data.set_usable_as("print-range");
+    wb.named_expressions_mut().add_range(data);
+    wb.named_expressions_mut()
+        .add_expression(NamedExpression::new("TaxRate", "of:=0.19"));
+    wb.named_expressions_mut()
+        .add_expression(NamedExpression::new("Cell", "of:=[$one.$B$2]"));
+
+    let mut local = NamedRange::new("Data", CellRange::remote("two", 1, 1, 2, 2).absolute());
+    local.set_base_cell(CellRef::remote("two", 0, 0));
+    wb.sheet_mut(1).named_expressions_mut().add_range(local);
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let mut wb = read_ods_buf(&buf)?;
+
+    let global = wb.named_expressions();
+    assert_eq!(global.len(), 3);
+    assert!(global.contains("taxrate"));
+    assert_eq!(
+        global.expression("TaxRate").map(|v| v.expression()),
+        Some("of:=0.19")
+    );
+    assert_eq!(
+        global
+            .range("data")
+            .and_then(|v| v.usable_as())
+            .map(|v| v.as_str()),
+        Some("print-range")
+    );
+    assert_eq!(
+        global.resolve("Cell"),
+        Some(CellRange::remote("one", 1, 1, 1, 1).absolute())
+    );
+    assert_eq!(global.resolve("TaxRate"), None);
+
+    let local = wb
+        .sheet(1)
+        .named_expressions()
+        .range("Data")
+        .expect("local");
+    assert_eq!(local.base_cell(), Some(&CellRef::remote("two", 0, 0)));
+
+    // local before global
+    assert_eq!(
+        wb.resolve_name(0, "Data"),
+        Some(CellRange::remote("one", 0, 0, 9, 2).absolute())
+    );
+    assert_eq!(
+        wb.resolve_name(1, "Data"),
+        Some(CellRange::remote("two", 1, 1, 2, 2).absolute())
+    );
+    assert_eq!(wb.resolve_name(1, "Other"), None);
+
+    // replaces and removes
+    wb.named_expressions_mut().add_range(NamedRange::new(
+        "TAXRATE",
+        CellRange::remote("one", 0, 0, 0, 0),
+    ));
+    assert_eq!(wb.named_expressions().len(), 3);
+    assert!(wb.named_expressions().expression("TaxRate").is_none());
+    assert!(wb.named_expressions_mut().remove("taxrate"));
+    assert!(!wb.named_expressions_mut().remove("taxrate"));
+
+    Ok(())
+}
+
+#[test]
+fn named_insert_rows() {
+    let mut wb = WorkBook::new_empty();
+    wb.push_sheet(Sheet::new("one"));
+    wb.named_expressions_mut().add_range(NamedRange::new(
+        "Data",
+        CellRange::remote("one", 2, 0, 4, 0).absolute(),
+    ));
+    wb.named_expressions_mut()
+        .add_expression(NamedExpression::new("Cell", "of:=[$one.$A$3]*2"));
+
+    wb.insert_rows(0, 1, 2);
+
+    assert_eq!(
+        wb.resolve_name(0, "Data"),
+        Some(CellRange::remote("one", 4, 0, 6, 0).absolute())
+    );
+    assert_eq!(
+        wb.named_expressions()
+            .expression("Cell")
+            .map(|v| v.expression()),
+        Some("of:=[$one.$A$5]*2")
+    );
+}
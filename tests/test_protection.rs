@@ -0,0 +1,89 @@
+use spreadsheet_ods::protection::{ProtectionKey, SheetProtection, SHA256_DIGEST};
+use spreadsheet_ods::style::units::CellProtect;
+use spreadsheet_ods::{
+    read_ods_buf, write_ods_buf, CellStyle, OdsError, OdsStreamReader, Sheet, ValueFormatRef,
+    WorkBook,
+};
+use std::io::Cursor;
+
+#[test]
+fn protection_key() {
+    let key = ProtectionKey::from_password("secret");
+    assert_eq!(key.key(), "K7gNU3sdo+OL0wNhqoVWhr3g6s1xYv72ol/pe/Unols=");
+    assert_eq!(
+        key.digest_algorithm().map(|v| v.as_str()),
+        Some(SHA256_DIGEST)
+    );
+    assert!(key.verify("secret"));
+    assert!(!key.verify("Secret"));
+
+    // sha1 can't be checked.
+    let key = ProtectionKey::new("5en6G6MezRroT3XKqkdPOmY/BfQ=", None);
+    assert!(!key.verify("secret"));
+}
+
+#[test]
+fn write_read_protection() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut input = CellStyle::new("input", &ValueFormatRef::from(""));
+    input.set_cell_protect(CellProtect::None);
+    let input = wb.add_cellstyle(input);
+
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "name");
+    sh.set_styled_value(0, 1, "", &input);
+    let mut options = SheetProtection::new();
+    options.set_select_protected_cells(false);
+    options.set_insert_rows(true);
+    sh.protect("secret", options);
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("two");
+    sh.protect("", SheetProtection::new());
+    wb.push_sheet(sh);
+
+    wb.push_sheet(Sheet::new("three"));
+
+    wb.protect_structure("other");
+
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+
+    assert!(wb.is_structure_protected());
+    assert!(wb
+        .structure_protection_key()
+        .map(|v| v.verify("other"))
+        .unwrap_or(false));
+
+    let protection = wb.sheet(0).protection().expect("protection");
+    assert!(protection.verify("secret"));
+    assert!(!protection.verify("other"));
+    assert!(!protection.select_protected_cells());
+    assert!(protection.select_unprotected_cells());
+    assert!(protection.insert_rows());
+    assert!(!protection.insert_columns());
+
+    let protection = wb.sheet(1).protection().expect("protection");
+    assert!(protection.key().is_none());
+    assert!(protection.verify("anything"));
+
+    assert!(!wb.sheet(2).is_protected());
+
+    let style = wb.cellstyle("input").expect("style");
+    assert_eq!(
+        style
+            .cellstyle()
+            .attr("style:cell-protect")
+            .map(|v| v.as_str()),
+        Some("none")
+    );
+
+    // streaming
+    let mut ods = OdsStreamReader::new(Cursor::new(buf))?;
+    let mut rows = ods.rows()?;
+    let sheet = rows.next_sheet()?.expect("sheet");
+    assert!(sheet.protection().map(|v| v.insert_rows()).unwrap_or(false));
+
+    Ok(())
+}
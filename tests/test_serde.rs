@@ -0,0 +1,178 @@
+#![cfg(all(feature = "serde", feature = "use_decimal"))]
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use spreadsheet_ods::serde::{from_range, from_sheet, to_sheet, Header};
+use spreadsheet_ods::{read_ods_buf, write_ods_buf, CellRange, OdsError, Sheet, Value, WorkBook};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Status {
+    Open,
+    Closed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Order {
+    id: u32,
+    customer: String,
+    #[serde(with = "spreadsheet_ods::serde::date")]
+    ordered: NaiveDate,
+    #[serde(with = "spreadsheet_ods::serde::datetime::option")]
+    shipped: Option<NaiveDateTime>,
+    #[serde(with = "spreadsheet_ods::serde::duration")]
+    handling: Duration,
+    #[serde(with = "spreadsheet_ods::serde::decimal")]
+    amount: Decimal,
+    discount: Option<f64>,
+    paid: bool,
+    status: Status,
+}
+
+fn orders() -> Vec<Order> {
+    vec![
+        Order {
+            id: 1,
+            customer: "Acme".to_string(),
+            ordered: NaiveDate::from_ymd_opt(2022, 4, 1).expect("date"),
+            shipped: NaiveDate::from_ymd_opt(2022, 4, 3).and_then(|v| v.and_hms_opt(14, 30, 0)),
+            handling: Duration::minutes(45),
+            amount: dec!(1200.50),
+            discount: Some(0.1),
+            paid: true,
+            status: Status::Closed,
+        },
+        Order {
+            id: 2,
+            customer: "Globex".to_string(),
+            ordered: NaiveDate::from_ymd_opt(2022, 4, 2).expect("date"),
+            shipped: None,
+            handling: Duration::milliseconds(90_005),
+            amount: dec!(99.99),
+            discount: None,
+            paid: false,
+            status: Status::Open,
+        },
+    ]
+}
+
+#[test]
+fn serialize_deserialize() -> Result<(), OdsError> {
+    let mut sheet = Sheet::new("orders");
+    to_sheet(&mut sheet, &orders())?;
+
+    assert_eq!(sheet.value(0, 0).as_str_or(""), "id");
+    assert_eq!(sheet.value(0, 8).as_str_or(""), "status");
+    assert!(matches!(sheet.value(1, 2), Value::DateTime(_)));
+    assert!(matches!(sheet.value(1, 4), Value::TimeDuration(_)));
+    assert_eq!(sheet.value(1, 5).as_f64_or(0.0), 1200.5);
+    assert_eq!(sheet.value(2, 8).as_str_or(""), "Open");
+    assert!(matches!(sheet.value(2, 3), Value::Empty));
+
+    let read: Vec<Order> = from_sheet(&sheet)?;
+    assert_eq!(read, orders());
+
+    // through a file
+    let mut wb = WorkBook::new_empty();
+    wb.push_sheet(sheet);
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let wb = read_ods_buf(&buf)?;
+    let read: Vec<Order> = from_sheet(wb.sheet(0))?;
+    assert_eq!(read, orders());
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Item {
+    name: String,
+    count: i32,
+    note: Option<String>,
+}
+
+#[test]
+fn deserialize_by_name_and_position() -> Result<(), OdsError> {
+    let mut sheet = Sheet::new("items");
+    // other order, unknown column, missing column.
+    sheet.set_value(0, 0, "count");
+    sheet.set_value(0, 1, "ignored");
+    sheet.set_value(0, 2, "name");
+    sheet.set_value(1, 0, 3);
+    sheet.set_value(1, 1, true);
+    sheet.set_value(1, 2, "bolt");
+    // empty row is skipped
+    sheet.set_value(3, 0, "7");
+    sheet.set_value(3, 2, 4711);
+
+    let read: Vec<Item> = from_sheet(&sheet)?;
+    assert_eq!(
+        read,
+        vec![
+            Item {
+                name: "bolt".to_string(),
+                count: 3,
+                note: None
+            },
+            Item {
+                name: "4711".to_string(),
+                count: 7,
+                note: None
+            }
+        ]
+    );
+
+    let read: Vec<(i32, bool, String)> =
+        from_range(&sheet, &CellRange::local(1, 0, 1, 2), Header::Positions)?;
+    assert_eq!(read, vec![(3, true, "bolt".to_string())]);
+
+    Ok(())
+}
+
+#[test]
+fn deserialize_error() {
+    let mut sheet = Sheet::new("items");
+    sheet.set_value(0, 0, "name");
+    sheet.set_value(0, 1, "count");
+    sheet.set_value(1, 0, "nut");
+    sheet.set_value(1, 1, "many");
+
+    let err = from_sheet::<Item>(&sheet).expect_err("invalid count");
+    assert!(err.to_string().contains("B2"));
+}
+
+#[test]
+fn serialize_tuples() -> Result<(), OdsError> {
+    let mut sheet = Sheet::new("tuples");
+    to_sheet(&mut sheet, &[("a", 1), ("b", 2)])?;
+    assert_eq!(sheet.value(0, 0).as_str_or(""), "a");
+    assert_eq!(sheet.value(1, 1).as_i32_or(0), 2);
+
+    assert!(to_sheet(&mut sheet, &[vec![vec![1]]]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn serialize_decimal_precision() -> Result<(), OdsError> {
+    #[derive(Serialize)]
+    struct Amount {
+        #[serde(with = "spreadsheet_ods::serde::decimal")]
+        amount: Decimal,
+    }
+
+    let mut sheet = Sheet::new("amounts");
+    to_sheet(&mut sheet, &[Amount { amount: dec!(0.10) }])?;
+    assert_eq!(sheet.value(1, 0).as_f64_or(0.0), 0.1);
+
+    let err = to_sheet(
+        &mut sheet,
+        &[Amount {
+            amount: dec!(12345678901234567.89),
+        }],
+    )
+    .expect_err("too many digits");
+    assert!(err.to_string().contains("without loss"), "{}", err);
+
+    Ok(())
+}
@@ -1,6 +1,10 @@
+use std::io::{Cursor, Read};
+
+use spreadsheet_ods::formula::{parse, Expr};
+use spreadsheet_ods::validation::Validation;
 use spreadsheet_ods::{
-    cm, currency, percent, read_ods, write_ods, CellRange, ColRange, Length, OdsError, RowRange,
-    Sheet, Value, ValueType, WorkBook,
+    cm, currency, percent, read_ods, read_ods_buf, write_ods, write_ods_buf, CellRange, CellRef,
+    ColRange, Length, OdsError, RowRange, Sheet, Value, ValueType, WorkBook,
 };
 
 #[test]
@@ -252,6 +256,154 @@ fn split_table() -> Result<(), OdsError> {
     Ok(())
 }
 
+#[test]
+fn freeze_panes() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("Freeze0");
+    sh.set_value(0, 0, 1);
+    sh.freeze_panes(2, 1);
+    assert_eq!(sh.frozen_panes(), Some((2, 1)));
+    assert_eq!(sh.config().position_right, 1);
+    assert_eq!(sh.config().position_bottom, 2);
+    assert_eq!(sh.config().active_split_range, 3);
+    assert_eq!((sh.config().cursor_y, sh.config().cursor_x), (2, 1));
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Freeze1");
+    sh.set_value(0, 0, 1);
+    sh.freeze_panes(1, 0);
+    assert_eq!(sh.frozen_panes(), Some((1, 0)));
+    assert_eq!(sh.config().active_split_range, 2);
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Freeze2");
+    sh.set_value(0, 0, 1);
+    sh.freeze_panes(3, 3);
+    sh.unfreeze();
+    assert_eq!(sh.frozen_panes(), None);
+    sh.split_horizontal(250);
+    assert_eq!(sh.frozen_panes(), None);
+    wb.push_sheet(sh);
+
+    write_ods(&mut wb, "test_out/freeze_panes.ods")?;
+    let wb = read_ods("test_out/freeze_panes.ods")?;
+
+    assert_eq!(wb.sheet(0).frozen_panes(), Some((2, 1)));
+    assert_eq!(wb.sheet(0).config().position_right, 1);
+    assert_eq!(wb.sheet(0).config().position_bottom, 2);
+    assert_eq!(wb.sheet(1).frozen_panes(), Some((1, 0)));
+    assert_eq!(wb.sheet(2).frozen_panes(), None);
+
+    // split by LibreOffice.
+    let wb = read_ods("tests/orders.ods")?;
+    let sh = wb.sheet(0);
+    assert_eq!(sh.frozen_panes(), None);
+    assert_eq!(sh.config().position_right, 2);
+    assert_eq!(sh.config().position_bottom, 3);
+
+    // frozen by LibreOffice, the same as freeze_panes(2, 1).
+    let mut wb = read_ods("tests/freeze.ods")?;
+    let mut frozen = Sheet::new("Frozen");
+    frozen.freeze_panes(2, 1);
+    for _ in 0..2 {
+        let sh = wb.sheet(0);
+        assert_eq!(sh.frozen_panes(), Some((2, 1)));
+        let (c, f) = (sh.config(), frozen.config());
+        assert_eq!(
+            (c.hor_split_pos, c.vert_split_pos),
+            (f.hor_split_pos, f.vert_split_pos)
+        );
+        assert_eq!(c.active_split_range, f.active_split_range);
+        assert_eq!(
+            (
+                c.position_left,
+                c.position_right,
+                c.position_top,
+                c.position_bottom
+            ),
+            (
+                f.position_left,
+                f.position_right,
+                f.position_top,
+                f.position_bottom
+            )
+        );
+        assert_eq!((c.cursor_x, c.cursor_y), (f.cursor_x, f.cursor_y));
+
+        write_ods(&mut wb, "test_out/freeze_panes_lo.ods")?;
+        wb = read_ods("test_out/freeze_panes_lo.ods")?;
+    }
+
+    // the settings written back are the ones LibreOffice wrote.
+    let lo_settings = split_settings(&std::fs::read("tests/freeze.ods")?, "Sheet1");
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    assert_eq!(split_settings(&buf, "Sheet1"), lo_settings);
+
+    wb.unfreeze(0);
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    let settings = split_settings(&buf, "Sheet1");
+    assert_eq!(
+        settings[0],
+        ("HorizontalSplitMode".to_string(), "0".to_string())
+    );
+    assert_eq!(
+        settings[1],
+        ("VerticalSplitMode".to_string(), "0".to_string())
+    );
+    assert_eq!(read_ods_buf(&buf)?.sheet(0).frozen_panes(), None);
+
+    wb.freeze_panes(0, 2, 1);
+    let buf = write_ods_buf(&mut wb, Vec::new())?;
+    assert_eq!(split_settings(&buf, "Sheet1"), lo_settings);
+    assert_eq!(read_ods_buf(&buf)?.sheet(0).frozen_panes(), Some((2, 1)));
+
+    Ok(())
+}
+
+// The split settings of a table in settings.xml.
+fn split_settings(buf: &[u8], table: &str) -> Vec<(String, String)> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(buf)).expect("zip");
+    let mut settings = String::new();
+    zip.by_name("settings.xml")
+        .expect("settings.xml")
+        .read_to_string(&mut settings)
+        .expect("settings.xml");
+
+    let start = settings
+        .find(&format!(
+            "<config:config-item-map-entry config:name=\"{}\">",
+            table
+        ))
+        .expect("table");
+    let end = start
+        + settings[start..]
+            .find("</config:config-item-map-entry>")
+            .expect("table");
+
+    let mut items = Vec::new();
+    for name in [
+        "HorizontalSplitMode",
+        "VerticalSplitMode",
+        "HorizontalSplitPosition",
+        "VerticalSplitPosition",
+        "ActiveSplitRange",
+        "PositionLeft",
+        "PositionRight",
+        "PositionTop",
+        "PositionBottom",
+        "CursorPositionX",
+        "CursorPositionY",
+    ] {
+        let tag = format!("<config:config-item config:name=\"{}\"", name);
+        let pos = start + settings[start..end].find(&tag).expect("item");
+        let value = settings[pos..].split_once('>').expect("item").1;
+        let value = value.split_once('<').expect("item").0;
+        items.push((name.to_string(), value.to_string()));
+    }
+    items
+}
+
 #[test]
 fn test_iterator() {
     let mut sh = Sheet::new("1");
@@ -272,3 +424,172 @@ fn test_iterator() {
         }
     }
 }
+
+#[test]
+fn test_insert_delete() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("One");
+    sh.set_value(0, 0, 1);
+    sh.set_value(1, 0, 2);
+    sh.set_value(2, 0, 3);
+    sh.set_formula(3, 0, "of:=SUM([.A1:.A3])");
+    sh.set_formula(4, 0, "of:=[.A2]*[.$B$1]");
+    sh.set_col_span(5, 0, 2);
+    sh.set_row_span(6, 0, 3);
+    sh.set_header_rows(0, 1);
+    sh.add_print_range(CellRange::local(0, 0, 9, 1));
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Two");
+    sh.set_formula(0, 0, "of:=[One.A2]+[One.B1]");
+    sh.set_formula(1, 0, "of:=[.A2]");
+    wb.push_sheet(sh);
+
+    let mut valid = Validation::new();
+    valid.set_base_cell(CellRef::remote("One", 2, 0));
+    let valid = wb.add_validation(valid);
+
+    wb.insert_rows(0, 1, 2);
+    let sh = wb.sheet(0);
+    assert_eq!(sh.value(0, 0).as_i32_or(0), 1);
+    assert_eq!(sh.value(3, 0).as_i32_or(0), 2);
+    assert_eq!(sh.formula(5, 0).unwrap(), "of:=SUM([.A1:.A5])");
+    assert_eq!(sh.formula(6, 0).unwrap(), "of:=[.A4]*[.$B$1]");
+    assert_eq!(sh.col_span(7, 0), 2);
+    assert_eq!(sh.row_span(8, 0), 3);
+    assert_eq!(sh.header_rows(), &Some(RowRange::new(0, 3)));
+    assert_eq!(sh.print_ranges().unwrap()[0], CellRange::local(0, 0, 11, 1));
+    let sh = wb.sheet(1);
+    assert_eq!(sh.formula(0, 0).unwrap(), "of:=[One.A4]+[One.B1]");
+    assert_eq!(sh.formula(1, 0).unwrap(), "of:=[.A2]");
+    assert_eq!(
+        wb.validation(valid.as_str()).unwrap().base_cell(),
+        &CellRef::remote("One", 4, 0)
+    );
+
+    wb.delete_rows(0, 2, 2);
+    let sh = wb.sheet(0);
+    assert_eq!(sh.formula(3, 0).unwrap(), "of:=SUM([.A1:.A3])");
+    assert_eq!(sh.formula(4, 0).unwrap(), "of:=[.#REF!]*[.$B$1]");
+    assert_eq!(
+        wb.sheet(1).formula(0, 0).unwrap(),
+        "of:=[One.#REF!]+[One.B1]"
+    );
+    let mut refs = Vec::new();
+    parse(wb.sheet(1).formula(0, 0).unwrap())?.visit(&mut |e| {
+        if let Expr::InvalidRef(r) = e {
+            refs.push(r.clone());
+        }
+    });
+    assert_eq!(refs, vec!["One.#REF!".to_string()]);
+
+    wb.insert_cols(0, 0, 1);
+    wb.delete_cols(0, 2, 1);
+    let sh = wb.sheet(0);
+    assert_eq!(sh.value(0, 1).as_i32_or(0), 1);
+    assert_eq!(sh.formula(3, 1).unwrap(), "of:=SUM([.B1:.B3])");
+    assert_eq!(sh.formula(4, 1).unwrap(), "of:=[.#REF!]*[.#REF!]");
+    assert_eq!(sh.col_span(5, 1), 1);
+
+    let mut sh = Sheet::new("Three");
+    sh.set_value(0, 0, 1);
+    sh.set_formula(0, 1, "of:=[.A1]+[Other.A1]");
+    sh.insert_cols(0, 1);
+    assert_eq!(sh.formula(0, 2).unwrap(), "of:=[.B1]+[Other.A1]");
+
+    Ok(())
+}
+
+#[test]
+fn test_copy_move() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+
+    let mut sh = Sheet::new("One");
+    sh.set_value(0, 0, 1);
+    sh.set_value(1, 0, 2);
+    sh.set_formula(2, 0, "of:=SUM([.A1:.A2])*[.$D$1]");
+    sh.set_formula(2, 1, "of:=[.A3]+[.$A1]");
+    sh.set_col_span(1, 1, 2);
+    sh.set_value(4, 5, "x");
+    wb.push_sheet(sh);
+
+    let mut sh = Sheet::new("Two");
+    sh.set_formula(0, 0, "of:=[One.A1]+[One.B3]");
+    wb.push_sheet(sh);
+
+    let sh = wb.sheet_mut(0);
+    sh.copy_range(CellRange::local(0, 0, 2, 1), CellRef::local(4, 4))?;
+    assert_eq!(sh.value(4, 4).as_i32_or(0), 1);
+    assert_eq!(sh.formula(6, 4).unwrap(), "of:=SUM([.E5:.E6])*[.$D$1]");
+    assert_eq!(sh.formula(6, 5).unwrap(), "of:=[.E7]+[.$A5]");
+    assert_eq!(sh.col_span(5, 5), 2);
+    // cleared
+    assert!(sh.is_empty(4, 5));
+
+    let sh = wb.sheet_mut(0);
+    sh.copy_range(CellRange::local(2, 1, 2, 1), CellRef::local(1, 3))?;
+    assert_eq!(sh.formula(1, 3).unwrap(), "of:=[.C2]+[.#REF!]");
+
+    wb.copy_range(
+        CellRange::remote("One", 4, 4, 6, 5),
+        CellRef::remote("Two", 1, 0),
+    )?;
+    assert_eq!(
+        wb.sheet(1).formula(3, 0).unwrap(),
+        "of:=SUM([.A2:.A3])*[.$D$1]"
+    );
+
+    wb.move_range(
+        CellRange::remote("One", 4, 4, 6, 5),
+        CellRef::remote("One", 10, 0),
+    )?;
+    let sh = wb.sheet(0);
+    assert!(sh.is_empty(4, 4));
+    assert_eq!(sh.value(10, 0).as_i32_or(0), 1);
+    assert_eq!(sh.formula(12, 0).unwrap(), "of:=SUM([.A11:.A12])*[.$D$1]");
+    assert_eq!(sh.formula(12, 1).unwrap(), "of:=[.A13]+[.$A5]");
+
+    wb.move_range(
+        CellRange::remote("One", 0, 0, 2, 0),
+        CellRef::remote("Two", 5, 5),
+    )?;
+    assert!(wb.sheet(0).is_empty(1, 0));
+    assert_eq!(wb.sheet(1).value(6, 5).as_i32_or(0), 2);
+    assert_eq!(
+        wb.sheet(1).formula(7, 5).unwrap(),
+        "of:=SUM([Two.F6:.F7])*[$One.$D$1]"
+    );
+    assert_eq!(wb.sheet(1).formula(0, 0).unwrap(), "of:=[Two.F6]+[One.B3]");
+    assert_eq!(
+        wb.sheet(0).formula(2, 1).unwrap(),
+        "of:=[Two.F8]+[$Two.$F6]"
+    );
+
+    // the destination must fit into the sheet.
+    let mut sh = Sheet::new("Three");
+    sh.set_value(0, 0, 1);
+    sh.set_value(1, 0, 2);
+    assert!(sh
+        .copy_range(CellRange::local(0, 0, 1, 0), CellRef::local(u32::MAX, 0))
+        .is_err());
+    assert!(sh
+        .move_range(CellRange::local(0, 0, 1, 1), CellRef::local(0, u32::MAX))
+        .is_err());
+    assert!(sh
+        .move_range(
+            CellRange::local(0, 0, 1, 0),
+            CellRef::local(u32::MAX - 1, 0)
+        )
+        .is_ok());
+    assert_eq!(sh.value(u32::MAX, 0).as_i32_or(0), 2);
+    wb.push_sheet(sh);
+    assert!(wb
+        .copy_range(
+            CellRange::remote("Three", u32::MAX - 1, 0, u32::MAX, 0),
+            CellRef::remote("One", u32::MAX, 0),
+        )
+        .is_err());
+
+    Ok(())
+}
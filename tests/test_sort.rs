@@ -0,0 +1,143 @@
+use chrono::NaiveDate;
+use spreadsheet_ods::database::SortOrder;
+use spreadsheet_ods::sort::SortKey;
+use spreadsheet_ods::style::CellStyleRef;
+use spreadsheet_ods::{CellRange, Sheet, Value};
+
+fn column(sh: &Sheet, col: u32, rows: u32) -> Vec<String> {
+    (0..rows)
+        .map(|row| match sh.value(row, col) {
+            Value::Empty => "-".to_string(),
+            Value::DateTime(_) => "date".to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            v => v.as_cow_str_or("").to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn sort_mixed() {
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, "b");
+    sh.set_value(1, 0, 10);
+    sh.set_value(3, 0, "A");
+    sh.set_value(4, 0, true);
+    sh.set_value(5, 0, 2.5);
+    sh.set_value(
+        6,
+        0,
+        NaiveDate::from_ymd_opt(1900, 1, 1)
+            .and_then(|v| v.and_hms_opt(0, 0, 0))
+            .expect("date"),
+    );
+
+    sh.sort_range(
+        CellRange::local(0, 0, 6, 0),
+        &[SortKey::new(0, SortOrder::Ascending)],
+    )
+    .expect("sort");
+    assert_eq!(
+        column(&sh, 0, 7),
+        vec!["true", "date", "2.5", "10", "A", "b", "-"]
+    );
+
+    sh.sort_range(
+        CellRange::local(0, 0, 6, 0),
+        &[SortKey::new(0, SortOrder::Descending)],
+    )
+    .expect("sort");
+    assert_eq!(
+        column(&sh, 0, 7),
+        vec!["b", "A", "10", "2.5", "date", "true", "-"]
+    );
+}
+
+#[test]
+fn sort_text() {
+    let mut sh = Sheet::new("one");
+    for (row, v) in ["item10", "Item2", "item2", "item1"].iter().enumerate() {
+        sh.set_value(row as u32, 0, *v);
+    }
+    let range = CellRange::local(0, 0, 3, 0);
+
+    sh.sort_range(range.clone(), &[SortKey::new(0, SortOrder::Ascending)])
+        .expect("sort");
+    assert_eq!(column(&sh, 0, 4), vec!["item1", "item10", "Item2", "item2"]);
+
+    let mut key = SortKey::new(0, SortOrder::Ascending);
+    key.set_natural(true);
+    key.set_case_sensitive(true);
+    sh.sort_range(range, &[key]).expect("sort");
+    assert_eq!(column(&sh, 0, 4), vec!["item1", "item2", "Item2", "item10"]);
+}
+
+#[test]
+fn sort_rows() {
+    let mut sh = Sheet::new("one");
+    let style = CellStyleRef::from("red");
+    // header
+    sh.set_value(0, 0, "name");
+    sh.set_value(0, 1, "group");
+    sh.set_value(0, 2, "count");
+    sh.set_value(0, 3, "double");
+
+    let data = [("c", 1, 3), ("a", 2, 1), ("b", 1, 2), ("d", 2, 1)];
+    for (i, (name, group, count)) in data.iter().enumerate() {
+        let row = i as u32 + 1;
+        sh.set_value(row, 0, *name);
+        sh.set_value(row, 1, *group);
+        sh.set_value(row, 2, *count);
+        sh.set_formula(row, 3, format!("of:=[.C{}]*2+[.$C$1]", row + 1));
+    }
+    sh.set_cellstyle(2, 0, &style);
+    // outside the range
+    sh.set_value(1, 5, "x");
+
+    let mut count = SortKey::new(2, SortOrder::Descending);
+    count.set_natural(true);
+    sh.sort_range(
+        CellRange::local(1, 0, 4, 3),
+        &[SortKey::new(1, SortOrder::Ascending), count],
+    )
+    .expect("sort");
+
+    // a and d are equal and keep their order.
+    assert_eq!(column(&sh, 0, 5), vec!["name", "c", "b", "a", "d"]);
+    assert_eq!(sh.formula(1, 3).expect("formula"), "of:=[.C2]*2+[.$C$1]");
+    assert_eq!(sh.formula(2, 3).expect("formula"), "of:=[.C3]*2+[.$C$1]");
+    assert_eq!(sh.formula(4, 3).expect("formula"), "of:=[.C5]*2+[.$C$1]");
+    assert_eq!(sh.cellstyle(3, 0), Some(&"red".to_string()));
+    assert_eq!(sh.cellstyle(2, 0), None);
+    assert_eq!(sh.value(1, 5).as_str_or(""), "x");
+
+    assert!(sh
+        .sort_range(
+            CellRange::local(1, 0, 4, 3),
+            &[SortKey::new(4, SortOrder::Ascending)]
+        )
+        .is_err());
+}
+
+#[test]
+fn sort_sparse() {
+    let mut sh = Sheet::new("one");
+    sh.set_value(0, 0, 3);
+    sh.set_value(2, 0, 1);
+    sh.set_value(3, 1, "x");
+    sh.set_value(5, 0, 2);
+    sh.set_value(1_000_000, 0, 0);
+    let range = CellRange::local(0, 0, u32::MAX - 1, 1);
+
+    // empty rows go to the end, the row without a key value before them.
+    sh.sort_range(range.clone(), &[SortKey::new(0, SortOrder::Ascending)])
+        .expect("sort");
+    assert_eq!(column(&sh, 0, 6), vec!["0", "1", "2", "3", "-", "-"]);
+    assert_eq!(column(&sh, 1, 6), vec!["-", "-", "-", "-", "x", "-"]);
+    assert!(sh.is_empty(1_000_000, 0));
+
+    sh.sort_range(range, &[SortKey::new(0, SortOrder::Descending)])
+        .expect("sort");
+    assert_eq!(column(&sh, 0, 6), vec!["3", "2", "1", "0", "-", "-"]);
+    assert_eq!(column(&sh, 1, 6), vec!["-", "-", "-", "-", "x", "-"]);
+}
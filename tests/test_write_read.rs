@@ -2,9 +2,13 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 
+use spreadsheet_ods::format::ValueFormat;
+use spreadsheet_ods::named::NamedRange;
+use spreadsheet_ods::style::{CellStyle, FontFaceDecl};
 use spreadsheet_ods::{
-    read_ods, read_ods_buf, write_ods, write_ods_buf, OdsError, Sheet, SplitMode, ValueType,
-    WorkBook,
+    read_fods, read_fods_buf, read_ods, read_ods_buf, write_fods, write_fods_buf, write_ods,
+    write_ods_buf, CellContent, CellRange, Length, OdsError, OdsStreamReader, OdsStreamWriter,
+    Sheet, SplitMode, ValueFormatRef, ValueType, WorkBook,
 };
 use std::time::Instant;
 
@@ -144,3 +148,246 @@ fn test_read_buf() -> Result<(), OdsError> {
     write_ods(&mut wb, "test_out/orders.ods")?;
     Ok(())
 }
+
+#[test]
+fn read_stream() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("1");
+    sh.set_value(0, 0, "A");
+    sh.set_value(0, 2, 1.5);
+    sh.set_formula(1, 1, "of:=[.C1]*2");
+    sh.set_value(5, 0, "x");
+    sh.set_row_repeat(5, 3);
+    wb.push_sheet(sh);
+    let mut sh = Sheet::new("2");
+    sh.set_value(2, 3, true);
+    sh.set_autofilter(CellRange::local(0, 0, 2, 3));
+    wb.push_sheet(sh);
+    wb.named_expressions_mut()
+        .add_range(NamedRange::new("first", CellRange::remote("1", 0, 0, 1, 2)));
+    write_ods(&mut wb, "test_out/read_stream.ods")?;
+
+    let mut ods = OdsStreamReader::open("test_out/read_stream.ods")?;
+    assert_eq!(ods.book().num_sheets(), 0);
+    // after the sheets in content.xml.
+    assert!(ods.book().named_expressions().range("first").is_some());
+    assert_eq!(ods.book().database_ranges().len(), 1);
+    let mut rows = ods.rows()?;
+
+    let sheet = rows.next_sheet()?.expect("sheet");
+    assert_eq!(sheet.name(), "1");
+    let row = rows.next_row()?.expect("row");
+    assert_eq!(row.row(), 0);
+    let cells: Vec<_> = row.into_iter().collect();
+    assert_eq!(cells.len(), 2);
+    assert_eq!(cells[0].0, 0);
+    assert_eq!(cells[0].1.value().as_str_or(""), "A");
+    assert_eq!(cells[1].0, 2);
+    assert_eq!(cells[1].1.value().as_f64_or(0.0), 1.5);
+    let row = rows.next_row()?.expect("row");
+    assert_eq!(row.row(), 1);
+    let cells: Vec<_> = row.into_iter().collect();
+    assert_eq!(cells[0].1.formula().unwrap(), "of:=[.C1]*2");
+    for i in 5..8 {
+        let row = rows.next_row()?.expect("row");
+        assert_eq!(row.row(), i);
+        assert_eq!(row.len(), 1);
+    }
+    assert!(rows.next_row()?.is_none());
+
+    let sheet = rows.next_sheet()?.expect("sheet");
+    assert_eq!(sheet.name(), "2");
+    assert!(rows.next_sheet()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn read_stream_same() -> Result<(), OdsError> {
+    for path in ["tests/orders.ods", "tests/rw.ods", "tests/text.ods"] {
+        let wb = read_ods(path)?;
+        let mut ods = OdsStreamReader::open(path)?;
+        let mut rows = ods.rows()?;
+
+        let mut idx = 0;
+        while let Some(sheet) = rows.next_sheet()? {
+            let sh = wb.sheet(idx);
+            assert_eq!(sheet.name(), sh.name());
+
+            let mut count = 0;
+            let mut base = 0;
+            while let Some(row) = rows.next_row()? {
+                // repeated rows only exist once in the sheet.
+                let r = row.row();
+                if r > base && r < base + sh.row_repeat(base) {
+                    continue;
+                }
+                base = r;
+                for (col, cell) in row {
+                    let value = sh.value(r, col);
+                    assert_eq!(cell.value().value_type(), value.value_type());
+                    if value.value_type() == ValueType::TextXml {
+                        // attribute order is not stable.
+                        assert_eq!(cell.value().as_cow_str_or(""), value.as_cow_str_or(""));
+                    } else {
+                        assert_eq!(format!("{:?}", cell.value()), format!("{:?}", value));
+                    }
+                    assert_eq!(cell.formula(), sh.formula(r, col));
+                    assert_eq!(cell.style(), sh.cellstyle(r, col));
+                    if let Some(style) = cell.style() {
+                        assert!(rows.book().cellstyle(style).is_some());
+                    }
+                    count += 1;
+                }
+            }
+            assert_eq!(count, sh.iter().count());
+            idx += 1;
+        }
+        assert_eq!(idx, wb.num_sheets());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn write_stream() -> Result<(), OdsError> {
+    let mut wb = WorkBook::new_empty();
+    let mut v1 = ValueFormat::new_named("f1", ValueType::Number);
+    v1.part_number().fixed_decimal_places(2).push();
+    let v1 = wb.add_format(v1);
+    let f1 = wb.add_cellstyle(CellStyle::new("f1", &v1));
+
+    let mut ods = OdsStreamWriter::new("test_out/write_stream.ods", wb)?;
+    assert!(ods.write_values(["x"]).is_err());
+
+    ods.start_sheet("1")?;
+    ods.write_values(["id", "value"])?;
+    for i in 0..10 {
+        let mut cell = CellContent::new();
+        cell.set_value(i as f64 * 1.5);
+        cell.set_style(&f1);
+        ods.write_row([(3, cell), (0, CellContent::new())])?;
+    }
+    ods.write_row([])?;
+    let mut cell = CellContent::new();
+    cell.set_value("span");
+    cell.set_col_span(2);
+    ods.write_row([(1, cell)])?;
+    assert!(ods
+        .write_row([(1, CellContent::new()), (1, CellContent::new())])
+        .is_err());
+
+    let mut sh = Sheet::new("2");
+    sh.set_col_width(0, Length::Cm(3.0));
+    sh.set_row_repeat(1, 3);
+    sh.set_row_repeat(4, 2);
+    ods.start_sheet_from(&sh)?;
+    ods.write_values([true])?;
+    ods.write_values(["repeated"])?;
+    ods.write_values(["after"])?;
+    ods.finish()?;
+
+    let wb = read_ods("test_out/write_stream.ods")?;
+    assert_eq!(wb.num_sheets(), 2);
+    let sh = wb.sheet(0);
+    assert_eq!(sh.name(), "1");
+    assert_eq!(sh.value(0, 1).as_str_or(""), "value");
+    assert_eq!(sh.value(4, 3).as_f64_or(0.0), 4.5);
+    assert_eq!(sh.cellstyle(4, 3).unwrap().as_str(), "f1");
+    assert_eq!(sh.value(11, 1).as_str_or(""), "");
+    assert_eq!(sh.value(12, 1).as_str_or(""), "span");
+    assert_eq!(sh.col_span(12, 1), 2);
+    assert!(wb.cellstyle("f1").is_some());
+    let sh = wb.sheet(1);
+    assert_eq!(sh.name(), "2");
+    assert!(sh.value(0, 0).as_bool_or(false));
+    assert_eq!(sh.value(1, 0).as_str_or(""), "repeated");
+    assert_eq!(sh.row_repeat(1), 3);
+    assert_eq!(sh.value(4, 0).as_str_or(""), "after");
+    assert_eq!(sh.row_repeat(4), 2);
+
+    Ok(())
+}
+
+#[test]
+fn write_read_fods() -> Result<(), OdsError> {
+    for path in ["tests/orders.ods", "tests/rw.ods", "tests/text.ods"] {
+        let mut wb = read_ods(path)?;
+        let buf = write_fods_buf(&mut wb, Vec::new())?;
+        assert!(String::from_utf8_lossy(&buf).contains("<office:document "));
+
+        let mut wb2 = read_fods_buf(&buf)?;
+        assert_eq!(wb.num_sheets(), wb2.num_sheets());
+        for idx in 0..wb.num_sheets() {
+            let sh = wb.sheet(idx);
+            let sh2 = wb2.sheet(idx);
+            assert_eq!(sh.name(), sh2.name());
+            assert_eq!(sh.iter().count(), sh2.iter().count());
+            for ((row, col), cell) in sh.iter() {
+                assert_eq!(
+                    sh.value(row, col).value_type(),
+                    sh2.value(row, col).value_type()
+                );
+                assert_eq!(cell.formula, sh2.formula(row, col));
+                assert_eq!(cell.style, sh2.cellstyle(row, col));
+                if let Some(style) = cell.style {
+                    assert!(wb2.cellstyle(style).is_some());
+                }
+            }
+        }
+        assert_eq!(wb.config().active_table, wb2.config().active_table);
+
+        // and back to ods.
+        let buf = write_ods_buf(&mut wb2, Vec::new())?;
+        let wb3 = read_ods_buf(&buf)?;
+        assert_eq!(wb.num_sheets(), wb3.num_sheets());
+    }
+
+    let mut wb = WorkBook::new_empty();
+    let mut sh = Sheet::new("fods");
+    sh.set_value(0, 0, "fods");
+    sh.set_value(1, 1, 42);
+    wb.push_sheet(sh);
+    write_fods(&mut wb, "test_out/write_read.fods")?;
+    let wb = read_fods("test_out/write_read.fods")?;
+    assert_eq!(wb.sheet(0).name(), "fods");
+    assert_eq!(wb.sheet(0).value(0, 0).as_str_or(""), "fods");
+    assert_eq!(wb.sheet(0).value(1, 1).as_i32_or(0), 42);
+
+    Ok(())
+}
+
+#[test]
+fn write_fods_deterministic() -> Result<(), OdsError> {
+    fn build() -> WorkBook {
+        let mut wb = WorkBook::new_empty();
+        let mut sh = Sheet::new("fods");
+        for i in 0..20 {
+            let mut ff = FontFaceDecl::new_with_name(format!("Font{}", i));
+            ff.set_font_family(format!("Font{}", i).as_str());
+            wb.add_font(ff);
+
+            let mut st = CellStyle::new(format!("st{}", i), &ValueFormatRef::from(""));
+            st.set_font_name(format!("Font{}", i).as_str());
+            st.set_font_bold();
+            st.attrmap_mut()
+                .set_attr(format!("loext:attr{}", i).as_str(), i.to_string());
+            let st = wb.add_cellstyle(st);
+            sh.set_styled_value(i, 0, i, &st);
+        }
+        wb.push_sheet(sh);
+        // not part of the comparison.
+        wb.metadata_mut().set_creation_date(None);
+        wb
+    }
+
+    let buf = write_fods_buf(&mut build(), Vec::new())?;
+    let buf2 = write_fods_buf(&mut build(), Vec::new())?;
+    assert!(buf == buf2);
+
+    let buf = write_fods_buf(&mut read_ods("tests/orders.ods")?, Vec::new())?;
+    let buf2 = write_fods_buf(&mut read_ods("tests/orders.ods")?, Vec::new())?;
+    assert!(buf == buf2);
+
+    Ok(())
+}